@@ -0,0 +1,131 @@
+//! Per-request costs after generation: pulling the JSON out of model output,
+//! compiling the word contract schema and validating an entry against it.
+//!
+//! `cargo bench --bench validation`; compare runs with `-- --save-baseline <name>`
+//! and `-- --baseline <name>`.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use jsonschema::{Draft, JSONSchema};
+use lingua_fast::model::prompt::extract_json_bytes;
+use lingua_fast::validate::Validator;
+use serde_json::{json, Value};
+
+const SCHEMA_SRC: &str = include_str!("../schema/word_contract.schema.json");
+
+fn meaning(rank: u64, part_of_speech: &str, definition: &str) -> Value {
+    json!({
+        "senseRank": rank,
+        "partOfSpeech": part_of_speech,
+        "definition": definition,
+        "exampleSentence": "They had to run to catch the last train home.",
+        "grammarTip": "Irregular: run, ran, run. Takes an object when it means to manage something.",
+        "synonyms": ["sprint", "dash", "race"],
+        "antonyms": ["walk", "stroll"],
+        "translations": {
+            "es": "correr", "fr": "courir", "de": "laufen", "zh": "跑", "ja": "走る",
+            "it": "correre", "pt": "correr", "ru": "бежать", "ar": "يركض"
+        }
+    })
+}
+
+/// A three-sense entry shaped like real model output.
+fn entry() -> Value {
+    json!({
+        "word": "run",
+        "baseForm": "run",
+        "phonetic": "/rʌn/",
+        "difficulty": "beginner",
+        "language": "english",
+        "meanings": [
+            meaning(1, "verb", "To move swiftly on foot so that both feet leave the ground during each stride."),
+            meaning(2, "noun", "An act or spell of running, especially as exercise or in a race."),
+            meaning(3, "adjective", "Of a liquid: having flowed or melted, as in run honey."),
+        ]
+    })
+}
+
+/// Valid in substance but needing the validator's fixes: ranks out of order,
+/// differently cased difficulty and keys outside the contract.
+fn entry_needing_fixes() -> Value {
+    let mut entry = entry();
+    entry["difficulty"] = json!("Beginner");
+    entry["notes"] = json!("generated by a small model");
+    for (idx, meaning) in entry["meanings"]
+        .as_array_mut()
+        .unwrap()
+        .iter_mut()
+        .enumerate()
+    {
+        meaning["senseRank"] = json!(3 - idx);
+        meaning["register"] = json!("neutral");
+    }
+    entry
+}
+
+/// Rejected by the schema after every fix.
+fn invalid_entry() -> Value {
+    let mut entry = entry();
+    entry["meanings"][0]["translations"] = json!(["correr", "courir"]);
+    entry["meanings"][1]["synonyms"] = json!("sprint, dash");
+    entry
+}
+
+fn validate(c: &mut Criterion) {
+    let validator = Validator::new(SCHEMA_SRC).unwrap();
+    let mut group = c.benchmark_group("validate_and_fix");
+    for (name, payload) in [
+        ("valid", entry()),
+        ("needs_fixes", entry_needing_fixes()),
+        ("invalid", invalid_entry()),
+    ] {
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || payload.clone(),
+                |v| validator.validate_and_fix(v, black_box("run")),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+/// Compiling the schema against checking an entry with a compiled one: what
+/// `validate_and_fix` would save by not recompiling on every call.
+fn compile_schema(c: &mut Criterion) {
+    let schema: Value = serde_json::from_str(SCHEMA_SRC).unwrap();
+    let compile = || {
+        JSONSchema::options()
+            .with_draft(Draft::Draft202012)
+            .compile(black_box(&schema))
+            .unwrap()
+    };
+    c.bench_function("schema_compile", |b| b.iter(compile));
+
+    let compiled = compile();
+    let entry = entry();
+    c.bench_function("schema_check_precompiled", |b| {
+        b.iter(|| compiled.is_valid(black_box(&entry)))
+    });
+}
+
+fn extract(c: &mut Criterion) {
+    let json = entry().to_string();
+    let mut group = c.benchmark_group("extract_json_bytes");
+    for (name, output) in [
+        ("bare", json.clone()),
+        (
+            "fenced_with_prose",
+            format!("Sure! Here is the entry for \"run\":\n\n```json\n{json}\n```\n\nLet me know if you need anything else."),
+        ),
+        (
+            "after_brace_aside",
+            format!("Note: {{this aside is not JSON}} and neither is {{ \"half\": }}.\n{json}"),
+        ),
+    ] {
+        group.bench_function(name, |b| b.iter(|| extract_json_bytes(black_box(&output))));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, validate, compile_schema, extract);
+criterion_main!(benches);
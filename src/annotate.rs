@@ -0,0 +1,210 @@
+use serde::Serialize;
+
+/// Most distinct content words one request may look up.
+pub const MAX_DISTINCT_WORDS: usize = 200;
+
+/// Function words readers never need explained; everything else is a content word.
+const FUNCTION_WORDS: &[&str] = &[
+    "a",
+    "about",
+    "above",
+    "after",
+    "again",
+    "against",
+    "all",
+    "am",
+    "an",
+    "and",
+    "any",
+    "are",
+    "as",
+    "at",
+    "be",
+    "because",
+    "been",
+    "before",
+    "being",
+    "below",
+    "between",
+    "both",
+    "but",
+    "by",
+    "can",
+    "could",
+    "did",
+    "do",
+    "does",
+    "doing",
+    "down",
+    "during",
+    "each",
+    "few",
+    "for",
+    "from",
+    "further",
+    "had",
+    "has",
+    "have",
+    "having",
+    "he",
+    "her",
+    "here",
+    "hers",
+    "herself",
+    "him",
+    "himself",
+    "his",
+    "how",
+    "i",
+    "if",
+    "in",
+    "into",
+    "is",
+    "it",
+    "its",
+    "itself",
+    "just",
+    "me",
+    "more",
+    "most",
+    "my",
+    "myself",
+    "no",
+    "nor",
+    "not",
+    "now",
+    "of",
+    "off",
+    "on",
+    "once",
+    "only",
+    "or",
+    "other",
+    "our",
+    "ours",
+    "ourselves",
+    "out",
+    "over",
+    "own",
+    "same",
+    "she",
+    "should",
+    "so",
+    "some",
+    "such",
+    "than",
+    "that",
+    "the",
+    "their",
+    "theirs",
+    "them",
+    "themselves",
+    "then",
+    "there",
+    "these",
+    "they",
+    "this",
+    "those",
+    "through",
+    "to",
+    "too",
+    "under",
+    "until",
+    "up",
+    "very",
+    "was",
+    "we",
+    "were",
+    "what",
+    "when",
+    "where",
+    "which",
+    "while",
+    "who",
+    "whom",
+    "why",
+    "will",
+    "with",
+    "would",
+    "you",
+    "your",
+    "yours",
+    "yourself",
+    "yourselves",
+];
+
+/// One content word of a text. `start` and `end` are character (Unicode
+/// scalar value) offsets into the text, end exclusive; `word` is the form
+/// looked up: lowercased, without a possessive `'s`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub word: String,
+}
+
+/// The content words of `text`, in order. Words are runs of letters, which
+/// may be joined by an apostrophe or hyphen ("don't", "well-known");
+/// function words and single letters are skipped.
+pub fn content_words(text: &str) -> Vec<Span> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut spans = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if !chars[i].is_alphabetic() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < chars.len()
+            && (chars[i].is_alphabetic()
+                || (is_joiner(chars[i]) && chars.get(i + 1).is_some_and(|c| c.is_alphabetic())))
+        {
+            i += 1;
+        }
+        let surface: String = chars[start..i].iter().collect::<String>().to_lowercase();
+        let word = surface
+            .strip_suffix("'s")
+            .or_else(|| surface.strip_suffix("’s"))
+            .unwrap_or(&surface)
+            .to_string();
+        if word.chars().count() > 1 && !FUNCTION_WORDS.contains(&word.as_str()) {
+            spans.push(Span {
+                start,
+                end: i,
+                word,
+            });
+        }
+    }
+    spans
+}
+
+fn is_joiner(c: char) -> bool {
+    matches!(c, '\'' | '’' | '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_content_words_with_char_offsets() {
+        let spans = content_words("The café's well-known owner didn't — wait — I see.");
+        let words: Vec<(&str, usize, usize)> = spans
+            .iter()
+            .map(|s| (s.word.as_str(), s.start, s.end))
+            .collect();
+        assert_eq!(
+            words,
+            [
+                ("café", 4, 10),
+                ("well-known", 11, 21),
+                ("owner", 22, 27),
+                ("didn't", 28, 34),
+                ("wait", 37, 41),
+                ("see", 46, 49),
+            ]
+        );
+        assert!(content_words("-- 42 a I").is_empty());
+    }
+}
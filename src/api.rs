@@ -1,340 +1,2475 @@
 use crate::{
-    model::{InferParams, LlmBackend, PromptParts},
-    validate::Validator,
+    annotate::{self, MAX_DISTINCT_WORDS},
+    batch,
+    cache::{PurgeFilter, WordCache},
+    cefr,
+    config::CefrMode,
+    daily::{DailyWords, Date},
+    dashboard,
+    examples::{DEFAULT_EXAMPLES, MAX_EXAMPLES},
+    error::ErrorCode,
+    extract::{request_schema, ValidJson},
+    fair::{FairScheduler, Scheduled},
+    fewshot::FewShotLibrary,
+    health::Readiness,
+    lists::{ListError, WordLists, MAX_LISTS_PER_OWNER, MAX_LIST_WORDS},
+    model::{Granularity, InferParams, LlmBackend, PromptParts, PromptTask},
+    patch,
+    profile::{Profile, Profiles},
+    quota::{Rejection, Remaining, UsageTracker},
+    service::{
+        load_persisted, persist, AnalyzeError, AnalyzeOptions, Attempts, EntrySource, Persisted,
+        RetryBudget, RetryReason, WordEntry, WordService,
+    },
+    signing::{EntrySigner, KEY_ID_HEADER, SIGNATURE_HEADER},
+    stats::{self, Stats},
+    store::{EntryFlags, EntryStore, CURATED_MODEL},
+    tenant::{Tenants, TENANT_HEADER},
+    validate::{Validator, Violation, SCHEMA_VERSION},
+    variants,
+};
+use anyhow::Result;
+use axum::{
+    extract::{FromRequestParts, Path, Query, Request, State},
+    http::{header, request::Parts, HeaderMap, HeaderValue, StatusCode},
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Response},
+    routing::{delete, get, patch as patch_route, post},
+    Json, Router,
 };
-use anyhow::{Context, Result};
-use axum::{http::StatusCode, response::IntoResponse, routing::post, Json, Router};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::{sync::Arc, time::Duration};
+use std::any::Any;
+use std::sync::Arc;
+use tower_http::catch_panic::CatchPanicLayer;
 use tracing::{debug, error, info, warn};
 
 #[derive(Debug, Deserialize)]
 pub struct WordReq {
     pub word: String,
+    /// Replaces the configured system prompt; only honored when overrides are allowed.
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    /// Sentence the word was met in, to put the sense used there first.
+    #[serde(default)]
+    pub context: Option<String>,
+    /// Only the meaning with this part of speech; `?pos=` takes precedence.
+    #[serde(default)]
+    pub pos: Option<String>,
+    /// At most this many meanings (1-4), highest ranked first.
+    #[serde(default)]
+    pub max_meanings: Option<usize>,
+    /// Split the word's uses into fewer, broader senses or more, finer ones.
+    #[serde(default)]
+    pub granularity: Option<Granularity>,
+}
+
+request_schema!(
+    WordReq,
+    r#"{
+        "type": "object",
+        "required": ["word"],
+        "properties": {
+            "word": { "type": "string" },
+            "system_prompt": { "type": ["string", "null"] },
+            "context": { "type": ["string", "null"], "maxLength": 500 },
+            "pos": { "type": ["string", "null"] },
+            "max_meanings": { "type": ["integer", "null"], "minimum": 1, "maximum": 4 },
+            "granularity": { "enum": ["coarse", "fine", null] }
+        }
+    }"#
+);
+
+/// Query options shaping how an entry is presented to the client.
+#[derive(Debug, Default, Deserialize)]
+pub struct PresentationQuery {
+    /// `accepted` drops translations for languages not listed in `Accept-Language`.
+    pub translations: Option<String>,
+    /// Comma-separated dotted paths to keep, e.g. `word,phonetic,meanings.definition`.
+    pub fields: Option<String>,
+    /// Add each entry's provenance under `_meta`.
+    #[serde(default)]
+    pub meta: bool,
+}
+
+/// Query options for the single-word endpoint.
+#[derive(Debug, Default, Deserialize)]
+pub struct SenseQuery {
+    /// Part of speech of the one meaning to return, e.g. `verb`.
+    pub pos: Option<String>,
+}
+
+/// Query options for the batch endpoint.
+#[derive(Debug, Default, Deserialize)]
+pub struct BatchQuery {
+    #[serde(default)]
+    pub result_format: ResultFormat,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResultFormat {
+    /// Per-word results in input order
+    #[default]
+    Array,
+    /// Also a `by_word` object mapping each successful word to its entry
+    Map,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct BatchReq {
     pub words: Vec<String>,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    /// Words analyzed at once, capped at the server's `batch_concurrency`.
+    #[serde(default)]
+    pub concurrency: Option<usize>,
+    /// As for `/v1/word`, applied to every word.
+    #[serde(default)]
+    pub max_meanings: Option<usize>,
+    #[serde(default)]
+    pub granularity: Option<Granularity>,
+}
+
+request_schema!(
+    BatchReq,
+    r#"{
+        "type": "object",
+        "required": ["words"],
+        "properties": {
+            "words": { "type": "array", "items": { "type": "string" } },
+            "system_prompt": { "type": ["string", "null"] },
+            "concurrency": { "type": ["integer", "null"], "minimum": 0 },
+            "max_meanings": { "type": ["integer", "null"], "minimum": 1, "maximum": 4 },
+            "granularity": { "enum": ["coarse", "fine", null] }
+        }
+    }"#
+);
+
+/// One word's outcome within a batch; `data` on success, the error fields otherwise.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchItem {
+    pub word: String,
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<ErrorCode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub numeric_code: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_suggested: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<Vec<Violation>>,
+    /// Ed25519 signature of `data`, when the server signs entries; the key ID
+    /// is in the response's `X-Signature-Key-Id` header.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    /// Inferences the word took, when it was generated now (on success or failure)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attempts: Option<usize>,
+    /// Why each attempt but the last was retried
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_reasons: Option<Vec<RetryReason>>,
+    /// Hashes of the attempts' raw outputs, for `GET /admin/raw/{hash}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_outputs: Option<Vec<String>>,
+}
+
+impl BatchItem {
+    fn success(word: &str, data: Value) -> Self {
+        Self {
+            word: word.to_string(),
+            ok: true,
+            data: Some(data),
+            error: None,
+            code: None,
+            numeric_code: None,
+            error_type: None,
+            retry_suggested: None,
+            details: None,
+            signature: None,
+            attempts: None,
+            retry_reasons: None,
+            raw_outputs: None,
+        }
+    }
+
+    fn failure(word: &str, code: ErrorCode, error: String, retry_suggested: bool) -> Self {
+        Self {
+            word: word.to_string(),
+            ok: false,
+            data: None,
+            error: Some(error),
+            code: Some(code),
+            numeric_code: Some(code.numeric()),
+            error_type: Some(code.as_legacy_str().to_string()),
+            retry_suggested: Some(retry_suggested),
+            details: None,
+            signature: None,
+            attempts: None,
+            retry_reasons: None,
+            raw_outputs: None,
+        }
+    }
+
+    /// With the attempts behind this outcome, when it was generated now.
+    fn with_attempts(self, attempts: Option<&Attempts>) -> Self {
+        Self {
+            attempts: attempts.map(|a| a.attempts),
+            retry_reasons: attempts.map(|a| a.retry_reasons.clone()),
+            raw_outputs: attempts
+                .filter(|a| !a.raw_outputs.is_empty())
+                .map(|a| a.raw_outputs.clone()),
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchSummary {
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub duration_ms: u64,
+    /// The batch's shared retries ran out, so later words were skipped
+    #[serde(default)]
+    pub retry_budget_exhausted: bool,
+}
+
+/// Body of `/v1/words`, whatever the status code.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchResponse {
+    pub results: Vec<BatchItem>,
+    pub summary: BatchSummary,
+    /// Present with `?result_format=map`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub by_word: Option<serde_json::Map<String, Value>>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ErrorResponse {
     pub error: String,
+    pub code: ErrorCode,
+    pub numeric_code: u16,
+    /// Lowercase alias of `code`, kept for clients written before error codes existed
     pub error_type: String,
     pub word: Option<String>,
     pub retry_suggested: bool,
+    /// Each contract violation, for validation failures
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<Vec<Violation>>,
+    /// Inferences made before giving up, for failed generations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attempts: Option<usize>,
+    /// Why each attempt but the last was retried
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_reasons: Option<Vec<RetryReason>>,
+    /// Hashes of the attempts' raw outputs, for `GET /admin/raw/{hash}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_outputs: Option<Vec<String>>,
 }
 
-#[derive(Debug, Clone)]
-enum ApiErrorType {
-    Validation(String),
-    Inference(String),
-    JsonParse(String),
-    Internal(String),
-}
-
-impl ApiErrorType {
-    fn should_retry(&self) -> bool {
-        matches!(self, Self::Inference(_) | Self::Internal(_))
+impl ErrorResponse {
+    pub fn new(code: ErrorCode, error: impl Into<String>, word: Option<String>) -> Self {
+        Self {
+            error: error.into(),
+            code,
+            numeric_code: code.numeric(),
+            error_type: code.as_legacy_str().to_string(),
+            word,
+            retry_suggested: code.is_retryable(),
+            details: None,
+            attempts: None,
+            retry_reasons: None,
+            raw_outputs: None,
+        }
     }
+}
 
+impl AnalyzeError {
     fn status_code(&self) -> StatusCode {
         match self {
-            Self::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::InvalidInput(_) => StatusCode::BAD_REQUEST,
+            Self::NotAWord(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::InvalidCharacters(_) => StatusCode::BAD_REQUEST,
+            Self::Deleted => StatusCode::NOT_FOUND,
+            Self::SenseNotFound(_) => StatusCode::NOT_FOUND,
+            Self::NotCached(_) => StatusCode::NOT_FOUND,
+            Self::Validation { .. } => StatusCode::UNPROCESSABLE_ENTITY,
             Self::JsonParse(_) => StatusCode::UNPROCESSABLE_ENTITY,
             Self::Inference(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
             Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::Generation { error, .. } => error.status_code(),
         }
     }
 
-    fn error_type_str(&self) -> &'static str {
-        match self {
-            Self::Validation(_) => "validation_error",
-            Self::JsonParse(_) => "json_parse_error",
-            Self::Inference(_) => "inference_error",
-            Self::Internal(_) => "internal_error",
+    fn into_response_for(self, word: &str) -> Response {
+        let mut error_response = ErrorResponse::new(
+            self.code(),
+            self.message(),
+            Some(word.to_string()),
+        );
+        error_response.retry_suggested = self.retry_suggested();
+        error_response.details = self.details();
+        if let Some(attempts) = self.attempts() {
+            error_response.attempts = Some(attempts.attempts);
+            error_response.retry_reasons = Some(attempts.retry_reasons.clone());
+            if !attempts.raw_outputs.is_empty() {
+                error_response.raw_outputs = Some(attempts.raw_outputs.clone());
+            }
         }
+        (self.status_code(), Json(error_response)).into_response()
     }
+}
 
-    fn message(&self) -> &str {
-        match self {
-            Self::Validation(msg) | Self::JsonParse(msg) |
-            Self::Inference(msg) | Self::Internal(msg) => msg,
+/// Shared state handed to every handler through axum's `State` extractor.
+#[derive(Clone)]
+pub struct AppState {
+    pub backend: Arc<dyn LlmBackend>,
+    pub validator: Arc<Validator>,
+    pub params: InferParams,
+    pub cache: Arc<WordCache>,
+    pub store: Option<Arc<EntryStore>>,
+    /// Bearer token for `/admin` endpoints; `None` disables them entirely.
+    pub admin_token: Option<Arc<str>>,
+    pub readiness: Arc<Readiness>,
+    pub system_prompt: Arc<str>,
+    /// Whether requests may carry their own `system_prompt`.
+    pub allow_system_prompt_override: bool,
+    pub few_shot: Arc<FewShotLibrary>,
+    /// Examples offered per prompt; the budgeter may send fewer.
+    pub few_shot_count: usize,
+    /// Prefer cached entries resembling the requested word over the fixed library.
+    pub few_shot_from_cache: bool,
+    /// Most words of one batch analyzed at once; requests may ask for fewer.
+    pub batch_concurrency: usize,
+    /// Share of failed words at or above which a batch answers 502 instead of 207.
+    pub batch_failure_threshold: f64,
+    /// Retries all words of one batch may make between them; 0 leaves each
+    /// word its own retries.
+    pub batch_retry_budget: usize,
+    /// Longest accepted word, in characters.
+    pub max_word_chars: usize,
+    /// Spot-check generated entries' translations by back-translation.
+    pub back_translation_check: bool,
+    /// Longest one word's generation may take, retries included; `None`
+    /// leaves it to the retry count.
+    pub word_time_budget: Option<std::time::Duration>,
+    /// Schema file `/admin/reload-schema` rereads; `None` when the embedded
+    /// schema is in use.
+    pub schema_file: Option<Arc<str>>,
+    /// Per-API-key defaults; empty when no profiles file is configured.
+    pub profiles: Arc<Profiles>,
+    /// Request and token counts behind each profile's limits.
+    pub usage: Arc<UsageTracker>,
+    /// How responses carry CEFR levels.
+    pub cefr: CefrMode,
+    /// Rotation behind `GET /v1/word-of-the-day`.
+    pub daily_words: Arc<DailyWords>,
+    /// Signs served entries; unsigned when no key is configured.
+    pub signer: Option<Arc<EntrySigner>>,
+    /// Namespaces declared in the profiles file; empty without tenants.
+    pub tenants: Arc<Tenants>,
+    /// Whether keyless callers and keys bound to no tenant may pick one with
+    /// `X-Tenant`; otherwise only the admin token may.
+    pub trust_tenant_header: bool,
+    /// Tenant whose cache, store and contract this state holds; `None` for
+    /// the server's own.
+    pub tenant: Option<Arc<str>>,
+    /// Shares inference slots out in turn among API keys; `None` serves
+    /// requests in arrival order.
+    pub scheduler: Option<Arc<FairScheduler>>,
+    /// Word lists built through `/v1/lists`.
+    pub lists: Arc<WordLists>,
+}
+
+impl AppState {
+    /// The word pipeline over this state's backend, cache and store.
+    pub fn words(&self) -> WordService {
+        WordService {
+            backend: self.backend.clone(),
+            validator: self.validator.clone(),
+            params: self.params.clone(),
+            cache: self.cache.clone(),
+            store: self.store.clone(),
+            system_prompt: self.system_prompt.clone(),
+            few_shot: self.few_shot.clone(),
+            few_shot_count: self.few_shot_count,
+            few_shot_from_cache: self.few_shot_from_cache,
+            max_word_chars: self.max_word_chars,
+            back_translation_check: self.back_translation_check,
+            word_time_budget: self.word_time_budget,
         }
     }
-}
 
-pub fn routes<B: LlmBackend + Clone + 'static>(
-    backend: B,
-    validator: Arc<Validator>,
-    params: InferParams,
-) -> Router {
-    let backend_single = backend.clone();
-    let validator_single = validator.clone();
-    let params_single = params.clone();
-    let backend_batch = backend.clone();
-    let validator_batch = validator.clone();
-    let params_batch = params.clone();
+    /// Charge a freshly generated entry's output tokens to the caller's quota.
+    /// Cache and store hits cost no inference and are free.
+    fn charge(&self, headers: &HeaderMap, found: &WordEntry) {
+        if found.source == EntrySource::Generated {
+            self.charge_output(headers, &found.entry);
+        }
+    }
 
+    /// Charge model output generated for the caller, whatever its shape.
+    fn charge_output(&self, headers: &HeaderMap, output: &Value) {
+        let Some(key) = api_key(headers) else {
+            return;
+        };
+        let text = output.to_string();
+        let tokens = self
+            .backend
+            .count_tokens(&text)
+            .unwrap_or_else(|| text.chars().count().div_ceil(4));
+        self.usage
+            .record_tokens(&usage_key(self.tenant.as_deref(), key), tokens as u64);
+    }
+
+    /// This state with the cache, store and contract of the request's tenant
+    /// swapped in. Requests naming no tenant get the server's own.
+    fn for_tenant(&self, headers: &HeaderMap) -> Result<AppState, TenantRejection> {
+        let Some(name) = request_tenant(self, headers)? else {
+            return Ok(self.clone());
+        };
+        let Some(namespace) = self.tenants.get(&name) else {
+            warn!(tenant = %name, "Rejected request for unknown tenant");
+            return Err(TenantRejection::Unknown(name));
+        };
+        Ok(AppState {
+            validator: namespace.validator.clone(),
+            cache: namespace.cache.clone(),
+            store: namespace.store.clone(),
+            schema_file: namespace.schema_file.clone(),
+            tenant: Some(Arc::from(name)),
+            ..self.clone()
+        })
+    }
+
+    /// This state with each inference waiting for the requester's turn at a
+    /// slot, when slots are shared out fairly. Keyless requests take turns as
+    /// one requester.
+    fn scheduled(mut self, headers: &HeaderMap) -> AppState {
+        if let Some(scheduler) = &self.scheduler {
+            self.backend = Arc::new(Scheduled::new(
+                self.backend.clone(),
+                scheduler.clone(),
+                requester(&self, headers),
+            ));
+        }
+        self
+    }
+
+    /// The word pipeline with `profile`'s sampling overrides applied.
+    fn words_for(&self, profile: Option<&Profile>) -> WordService {
+        let mut words = self.words();
+        if let Some(profile) = profile {
+            words.params = profile.params(&self.params);
+        }
+        words
+    }
+}
+
+pub fn router(state: AppState) -> Router {
     Router::new()
-        .route("/v1/word", post(move |Json(req): Json<WordReq>| {
-            let backend = backend_single.clone();
-            let validator = validator_single.clone();
-            let params = params_single.clone();
-            async move {
-                info!("Processing single word request: {}", req.word);
-
-                // Input validation
-                if req.word.trim().is_empty() {
-                    let error_response = ErrorResponse {
-                        error: "Word cannot be empty".to_string(),
-                        error_type: "validation_error".to_string(),
-                        word: Some(req.word.clone()),
-                        retry_suggested: false,
-                    };
-                    return (StatusCode::BAD_REQUEST, Json(error_response)).into_response();
-                }
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/metrics", get(metrics_text))
+        .route("/v1/word", post(analyze_word))
+        .route("/v1/words", post(analyze_batch))
+        .route("/v1/family", post(word_family))
+        .route("/v1/pronunciation", post(word_pronunciation))
+        .route("/v1/word-of-the-day", get(word_of_the_day))
+        .route("/v1/lists", post(create_list))
+        .route("/v1/lists/:id", get(list_bundle))
+        .route("/v1/lists/:id/words", post(add_list_words))
+        .route("/v1/text/annotate", post(annotate_text))
+        .route("/v1/signing-key", get(signing_key))
+        .route("/v1/tokenize", post(tokenize))
+        .route(CHAT_COMPLETIONS_PATH, post(chat_completions))
+        .route("/v1/word/:word/regenerate", post(regenerate_word))
+        .route("/v1/word/:word/fields", post(regenerate_fields))
+        .route("/v1/word/:word/examples", post(word_examples))
+        .route("/v1/word/:word/history", get(word_history))
+        .route("/v1/word/:word/history/:version", get(word_version))
+        .route("/admin/cache/:word", delete(evict_cached))
+        .route("/admin/cache/purge", post(purge_cache))
+        .route("/admin/entries/:word/rollback/:version", post(rollback_entry))
+        .route("/admin/entries/:word", patch_route(edit_entry))
+        .route("/admin/raw", post(raw_generate))
+        .route("/admin/raw/:hash", get(raw_output))
+        .route("/admin/reload-schema", post(reload_schema))
+        .route("/admin/diff", get(model_diff))
+        .route("/admin/dashboard", get(dashboard_page))
+        .route("/admin/dashboard/stats", get(dashboard_stats))
+        .route("/admin/stats", get(process_stats))
+        .layer(middleware::from_fn_with_state(state.clone(), meter_api_key))
+        .layer(middleware::from_fn(openai_bearer_key))
+        .layer(middleware::from_fn(dashboard::track))
+        .layer(CatchPanicLayer::custom(handle_panic))
+        .with_state(state)
+}
 
-                if req.word.len() > 100 {
-                    let error_response = ErrorResponse {
-                        error: "Word too long (max 100 characters)".to_string(),
-                        error_type: "validation_error".to_string(),
-                        word: Some(req.word.clone()),
-                        retry_suggested: false,
-                    };
-                    return (StatusCode::BAD_REQUEST, Json(error_response)).into_response();
-                }
+/// A handler panicked: answer with a 500 instead of dropping the connection.
+fn handle_panic(payload: Box<dyn Any + Send + 'static>) -> Response {
+    let message = crate::health::panic_message(&*payload);
+    error!("Handler panicked: {}", message);
+    crate::telemetry::record_panic("handler");
+    let error_response = ErrorResponse::new(
+        ErrorCode::InternalError,
+        "Internal error while handling the request",
+        None,
+    );
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response)).into_response()
+}
 
-                // Attempt inference with retry logic
-                let result = attempt_word_inference(backend, validator, params, &req.word).await;
+/// Liveness: the process is up and serving HTTP.
+pub async fn healthz() -> Response {
+    Json(json!({ "status": "ok" })).into_response()
+}
 
-                match result {
-                    Ok(json_value) => {
-                        info!("Successfully processed word: {}", req.word);
-                        Json(json_value).into_response()
-                    }
-                    Err(api_error) => {
-                        error!("Failed to process word '{}': {}", req.word, api_error.message());
-                        let error_response = ErrorResponse {
-                            error: api_error.message().to_string(),
-                            error_type: api_error.error_type_str().to_string(),
-                            word: Some(req.word.clone()),
-                            retry_suggested: api_error.should_retry(),
-                        };
-                        (api_error.status_code(), Json(error_response)).into_response()
-                    }
-                }
+/// Prometheus scrape endpoint; 404 when the process installed no recorder.
+pub async fn metrics_text() -> Response {
+    match crate::telemetry::render() {
+        Some(text) => (
+            [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+            text,
+        )
+            .into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// Readiness: 503 while the backend canary is failing or has not yet passed.
+pub async fn readyz(State(state): State<AppState>) -> Response {
+    let ready = state.readiness.is_ready();
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    let body = json!({
+        "ready": ready,
+        "model": state.backend.model_name(),
+        "canary": state.readiness.last_canary(),
+    });
+    (status, Json(body)).into_response()
+}
+
+pub async fn analyze_word(
+    Tenanted(state): Tenanted,
+    headers: HeaderMap,
+    Query(query): Query<PresentationQuery>,
+    Query(sense): Query<SenseQuery>,
+    ValidJson(req): ValidJson<WordReq>,
+) -> Response {
+    info!("Processing single word request: {}", req.word);
+    let profile = match request_profile(&state, &headers) {
+        Ok(profile) => profile,
+        Err(rejection) => return rejection.into_response(),
+    };
+    let presentation = Presentation::from_request(&state, &headers, &query, profile.as_deref());
+
+    if let Some(res) = reject_system_override(&state, req.system_prompt.as_deref(), &req.word) {
+        return res;
+    }
+    let opts = AnalyzeOptions {
+        system_prompt: req.system_prompt,
+        context: req.context,
+        part_of_speech: sense.pos.or(req.pos),
+        max_meanings: req.max_meanings,
+        granularity: req.granularity,
+        ..AnalyzeOptions::default()
+    };
+    match state.words_for(profile.as_deref()).analyze(&req.word, &opts).await {
+        Ok(found) => {
+            state.charge(&headers, &found);
+            let attempts = found.attempts.clone();
+            let entry = presentation.apply(found);
+            let signature = state.signer.as_ref().map(|signer| signer.sign(&entry));
+            let mut res = Json(entry).into_response();
+            add_signature_headers(&state, &mut res, signature);
+            if let Some(attempts) = attempts {
+                add_attempt_headers(&mut res, &attempts);
             }
-        }))
-        .route("/v1/words", post(move |Json(req): Json<BatchReq>| {
-            let backend = backend_batch.clone();
-            let validator = validator_batch.clone();
-            let params = params_batch.clone();
-            async move {
-                let n = req.words.len();
-                let mut results: Vec<Option<Value>> = vec![None; n];
-
-                // Concurrency with order preservation
-                let mut set = tokio::task::JoinSet::new();
-                // Allow overriding batch concurrency via INFER_CONCURRENCY to avoid GPU thrash
-                let concurrency_limit = std::env::var("INFER_CONCURRENCY")
-                    .ok()
-                    .and_then(|s| s.parse::<usize>().ok())
-                    .filter(|&v| v > 0)
-                    .unwrap_or_else(|| usize::min(8, num_cpus::get()));
-                for (idx, word) in req.words.iter().cloned().enumerate() {
-                    let backend = backend.clone();
-                    let validator = validator.clone();
-                    let params = params.clone();
-                    set.spawn(async move {
-                        let result = attempt_word_inference(backend.clone(), validator.clone(), params.clone(), &word).await;
-                        Ok::<(usize, Result<Value, ApiErrorType>), anyhow::Error>((idx, result))
-                    });
-
-                    // Backpressure to cap concurrency
-                    if set.len() >= concurrency_limit {
-                        if let Some(res) = set.join_next().await {
-                            match res {
-                                Ok(Ok((idx, inner))) => {
-                                    match inner {
-                                        Ok(v) => {
-                                            results[idx] = Some(json!({
-                                                "word": req.words[idx].clone(),
-                                                "ok": true,
-                                                "data": v,
-                                            }));
-                                        }
-                                        Err(api_error) => {
-                                            results[idx] = Some(json!({
-                                                "word": req.words[idx].clone(),
-                                                "ok": false,
-                                                "error": api_error.message(),
-                                                "error_type": api_error.error_type_str(),
-                                                "retry_suggested": api_error.should_retry(),
-                                            }));
-                                        }
-                                    }
-                                }
-                                Ok(Err(e)) => {
-                                    if let Some(i) = results.iter().position(|x| x.is_none()) {
-                                        results[i] = Some(json!({
-                                            "word": req.words[i].clone(),
-                                            "ok": false,
-                                            "error": e.to_string(),
-                                        }));
-                                    }
-                                }
-                                Err(join_err) => {
-                                    if let Some(i) = results.iter().position(|x| x.is_none()) {
-                                        results[i] = Some(json!({
-                                            "word": req.words[i].clone(),
-                                            "ok": false,
-                                            "error": join_err.to_string(),
-                                        }));
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+            res
+        }
+        Err(api_error) => api_error.into_response_for(&req.word),
+    }
+}
 
-                while let Some(res) = set.join_next().await {
-                    match res {
-                        Ok(Ok((idx, inner))) => {
-                            match inner {
-                                Ok(v) => {
-                                    results[idx] = Some(json!({
-                                        "word": req.words[idx].clone(),
-                                        "ok": true,
-                                        "data": v,
-                                    }));
-                                }
-                                Err(api_error) => {
-                                    results[idx] = Some(json!({
-                                        "word": req.words[idx].clone(),
-                                        "ok": false,
-                                        "error": api_error.message(),
-                                        "error_type": api_error.error_type_str(),
-                                        "retry_suggested": api_error.should_retry(),
-                                    }));
-                                }
-                            }
-                        }
-                        Ok(Err(e)) => {
-                            if let Some(i) = results.iter().position(|x| x.is_none()) {
-                                results[i] = Some(json!({
-                                    "word": req.words[i].clone(),
-                                    "ok": false,
-                                    "error": e.to_string(),
-                                }));
-                            }
-                        }
-                        Err(join_err) => {
-                            // Task join error; include message
-                            if let Some(i) = results.iter().position(|x| x.is_none()) {
-                                results[i] = Some(json!({
-                                    "word": req.words[i].clone(),
-                                    "ok": false,
-                                    "error": join_err.to_string(),
-                                }));
-                            }
-                        }
-                    }
+/// The key ID header, plus the entry's signature when there is one. No-op
+/// unless the server signs entries.
+fn add_signature_headers(state: &AppState, res: &mut Response, signature: Option<String>) {
+    let Some(signer) = &state.signer else {
+        return;
+    };
+    let headers = res.headers_mut();
+    if let Ok(key_id) = HeaderValue::from_str(signer.key_id()) {
+        headers.insert(KEY_ID_HEADER, key_id);
+    }
+    if let Some(Ok(signature)) = signature.map(|s| HeaderValue::from_str(&s)) {
+        headers.insert(SIGNATURE_HEADER, signature);
+    }
+}
+
+/// `X-Attempts` and, when any attempt was retried, `X-Retry-Reasons` for an
+/// entry generated by this request.
+fn add_attempt_headers(res: &mut Response, attempts: &Attempts) {
+    let headers = res.headers_mut();
+    headers.insert("x-attempts", HeaderValue::from(attempts.attempts));
+    if !attempts.retry_reasons.is_empty() {
+        let reasons: Vec<&str> = attempts.retry_reasons.iter().map(|r| r.as_str()).collect();
+        if let Ok(value) = HeaderValue::from_str(&reasons.join(", ")) {
+            headers.insert("x-retry-reasons", value);
+        }
+    }
+}
+
+/// The public key entry signatures verify against, with its ID.
+pub async fn signing_key(State(state): State<AppState>) -> Response {
+    let Some(signer) = &state.signer else {
+        let error_response =
+            ErrorResponse::new(ErrorCode::NotFound, "This server does not sign entries", None);
+        return (StatusCode::NOT_FOUND, Json(error_response)).into_response();
+    };
+    Json(json!({
+        "algorithm": "Ed25519",
+        "keyId": signer.key_id(),
+        "publicKey": signer.public_key(),
+    }))
+    .into_response()
+}
+
+pub async fn analyze_batch(
+    Tenanted(state): Tenanted,
+    headers: HeaderMap,
+    Query(query): Query<PresentationQuery>,
+    Query(batch_query): Query<BatchQuery>,
+    ValidJson(req): ValidJson<BatchReq>,
+) -> Response {
+    let profile = match request_profile(&state, &headers) {
+        Ok(profile) => profile,
+        Err(rejection) => return rejection.into_response(),
+    };
+    let presentation = Presentation::from_request(&state, &headers, &query, profile.as_deref());
+    if let Some(res) = reject_system_override(&state, req.system_prompt.as_deref(), "") {
+        return res;
+    }
+    if req.concurrency == Some(0) {
+        let error_response =
+            ErrorResponse::new(ErrorCode::InvalidInput, "concurrency must be at least 1", None);
+        return (StatusCode::BAD_REQUEST, Json(error_response)).into_response();
+    }
+    // Clients may ask for less parallelism, never more than the server allows
+    let concurrency_limit = req
+        .concurrency
+        .map_or(state.batch_concurrency, |c| c.min(state.batch_concurrency));
+
+    let (out, summary) = run_batch(
+        &state,
+        &headers,
+        &presentation,
+        state.words_for(profile.as_deref()),
+        req.words,
+        concurrency_limit,
+        AnalyzeOptions {
+            system_prompt: req.system_prompt,
+            max_meanings: req.max_meanings,
+            granularity: req.granularity,
+            ..AnalyzeOptions::default()
+        },
+    )
+    .await;
+    let failed = summary.failed;
+    let status = batch_status(failed, out.len(), state.batch_failure_threshold);
+    if status == StatusCode::BAD_GATEWAY {
+        warn!(failed, total = out.len(), "Batch failure rate reached threshold");
+    }
+    // Repeated words map to the last result; the array keeps them all
+    let by_word = (batch_query.result_format == ResultFormat::Map).then(|| {
+        out.iter()
+            .filter_map(|item| Some((item.word.clone(), item.data.clone()?)))
+            .collect()
+    });
+    let body = BatchResponse {
+        results: out,
+        summary,
+        by_word,
+    };
+    let mut res = (status, Json(body)).into_response();
+    add_signature_headers(&state, &mut res, None);
+    res
+}
+
+/// Analyze `list` with `opts`, `concurrency` words at a time, into one item
+/// per word, charging generated entries to the caller.
+async fn run_batch(
+    state: &AppState,
+    headers: &HeaderMap,
+    presentation: &Presentation,
+    words: WordService,
+    list: Vec<String>,
+    concurrency: usize,
+    opts: AnalyzeOptions,
+) -> (Vec<BatchItem>, BatchSummary) {
+    let started = std::time::Instant::now();
+    let retry_budget = (state.batch_retry_budget > 0)
+        .then(|| Arc::new(RetryBudget::new(state.batch_retry_budget)));
+    let opts = Arc::new(AnalyzeOptions {
+        retry_budget: retry_budget.clone(),
+        ..opts
+    });
+    let outcomes = batch::run_indexed(list.clone(), concurrency, |word| {
+        let words = words.clone();
+        let opts = opts.clone();
+        async move { words.analyze(&word, &opts).await }
+    })
+    .await;
+
+    let out: Vec<BatchItem> = list
+        .iter()
+        .zip(outcomes)
+        .map(|(word, outcome)| match outcome {
+            Ok(Ok(found)) => {
+                state.charge(headers, &found);
+                let attempts = found.attempts.clone();
+                let entry = presentation.apply(found);
+                BatchItem {
+                    signature: state.signer.as_ref().map(|signer| signer.sign(&entry)),
+                    ..BatchItem::success(word, entry)
                 }
+                .with_attempts(attempts.as_ref())
+            }
+            Ok(Err(api_error)) => BatchItem {
+                details: api_error.details(),
+                ..BatchItem::failure(
+                    word,
+                    api_error.code(),
+                    api_error.message(),
+                    api_error.retry_suggested(),
+                )
+            }
+            .with_attempts(api_error.attempts()),
+            Err(join_err) => {
+                error!("Batch task for '{}' failed: {}", word, join_err);
+                let code = ErrorCode::InternalError;
+                BatchItem::failure(word, code, join_err.to_string(), code.is_retryable())
+            }
+        })
+        .collect();
 
-                // Convert to Vec<Value>, safe to unwrap as all Some on success
-                let out: Vec<Value> = results
-                    .into_iter()
-                    .map(|v| v.expect("batch item missing"))
-                    .collect();
-                Json(out).into_response()
+    let failed = out.iter().filter(|item| !item.ok).count();
+    let summary = BatchSummary {
+        total: out.len(),
+        succeeded: out.len() - failed,
+        failed,
+        duration_ms: started.elapsed().as_millis() as u64,
+        retry_budget_exhausted: retry_budget.is_some_and(|b| b.is_exhausted()),
+    };
+    if summary.retry_budget_exhausted {
+        warn!(failed, total = out.len(), "Batch used up its retry budget");
+    }
+    (out, summary)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateListReq {
+    pub name: String,
+}
+
+request_schema!(
+    CreateListReq,
+    r#"{
+        "type": "object",
+        "required": ["name"],
+        "properties": {
+            "name": { "type": "string", "minLength": 1, "maxLength": 200 }
+        }
+    }"#
+);
+
+#[derive(Debug, Deserialize)]
+pub struct ListWordsReq {
+    pub words: Vec<String>,
+}
+
+request_schema!(
+    ListWordsReq,
+    r#"{
+        "type": "object",
+        "required": ["words"],
+        "properties": {
+            "words": { "type": "array", "items": { "type": "string" } }
+        }
+    }"#
+);
+
+/// Body of `GET /v1/lists/{id}`: the list with an entry or error per word.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListBundle {
+    pub id: String,
+    pub name: String,
+    pub results: Vec<BatchItem>,
+    pub summary: BatchSummary,
+}
+
+pub async fn create_list(
+    Tenanted(state): Tenanted,
+    headers: HeaderMap,
+    ValidJson(req): ValidJson<CreateListReq>,
+) -> Response {
+    if let Err(rejection) = request_profile(&state, &headers) {
+        return rejection.into_response();
+    }
+    match state.lists.create(&requester(&state, &headers), &req.name) {
+        Ok(list) => {
+            info!(id = %list.id, name = %list.name, "Created word list");
+            (StatusCode::CREATED, Json(list)).into_response()
+        }
+        Err(e) => list_error(e, ""),
+    }
+}
+
+pub async fn add_list_words(
+    Tenanted(state): Tenanted,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    ValidJson(req): ValidJson<ListWordsReq>,
+) -> Response {
+    if let Err(rejection) = request_profile(&state, &headers) {
+        return rejection.into_response();
+    }
+    match state.lists.add_words(&requester(&state, &headers), &id, &req.words) {
+        Ok(list) => Json(list).into_response(),
+        Err(e) => list_error(e, &id),
+    }
+}
+
+/// The list as a bundle, generating entries for words that have none yet.
+pub async fn list_bundle(
+    Tenanted(state): Tenanted,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Query(query): Query<PresentationQuery>,
+) -> Response {
+    let profile = match request_profile(&state, &headers) {
+        Ok(profile) => profile,
+        Err(rejection) => return rejection.into_response(),
+    };
+    let list = match state.lists.get(&requester(&state, &headers), &id) {
+        Ok(list) => list,
+        Err(e) => return list_error(e, &id),
+    };
+    let presentation = Presentation::from_request(&state, &headers, &query, profile.as_deref());
+    let (results, summary) = run_batch(
+        &state,
+        &headers,
+        &presentation,
+        state.words_for(profile.as_deref()),
+        list.words,
+        state.batch_concurrency,
+        AnalyzeOptions::default(),
+    )
+    .await;
+    let status = batch_status(summary.failed, results.len(), state.batch_failure_threshold);
+    let body = ListBundle {
+        id: list.id,
+        name: list.name,
+        results,
+        summary,
+    };
+    let mut res = (status, Json(body)).into_response();
+    add_signature_headers(&state, &mut res, None);
+    res
+}
+
+fn list_error(error: ListError, id: &str) -> Response {
+    let (status, message) = match error {
+        ListError::NotFound => (StatusCode::NOT_FOUND, format!("No word list '{}'", id)),
+        ListError::TooManyWords => (
+            StatusCode::BAD_REQUEST,
+            format!("A word list holds at most {} words", MAX_LIST_WORDS),
+        ),
+        ListError::TooManyLists => (
+            StatusCode::BAD_REQUEST,
+            format!(
+                "At most {} word lists may be kept at once; unused ones expire after 24 hours",
+                MAX_LISTS_PER_OWNER
+            ),
+        ),
+    };
+    let code = match status {
+        StatusCode::NOT_FOUND => ErrorCode::NotFound,
+        _ => ErrorCode::InvalidInput,
+    };
+    (status, Json(ErrorResponse::new(code, message, None))).into_response()
+}
+
+/// 200 when every word succeeded, 502 when the failed share reaches
+/// `threshold` (the model is likely broken, not the inputs), 207 otherwise.
+fn batch_status(failed: usize, total: usize, threshold: f64) -> StatusCode {
+    if failed == 0 {
+        StatusCode::OK
+    } else if failed as f64 >= threshold * total as f64 {
+        StatusCode::BAD_GATEWAY
+    } else {
+        StatusCode::MULTI_STATUS
+    }
+}
+
+pub async fn regenerate_word(
+    Tenanted(state): Tenanted,
+    headers: HeaderMap,
+    Path(word): Path<String>,
+) -> Response {
+    // Rewrites the shared cache and history, so it is a curator tool
+    if let Some(res) = reject_unauthorized(&headers, state.admin_token.as_deref()) {
+        return res;
+    }
+    info!("Regenerating entry for word: {}", word);
+    let profile = match request_profile(&state, &headers) {
+        Ok(profile) => profile,
+        Err(rejection) => return rejection.into_response(),
+    };
+    let persisted = match load_persisted(state.store.as_deref(), &word) {
+        Persisted::Found { locked: true, .. } => {
+            let error_response = ErrorResponse::new(
+                ErrorCode::EntryLocked,
+                "Entry is curated and locked against regeneration",
+                Some(word),
+            );
+            return (StatusCode::CONFLICT, Json(error_response)).into_response();
+        }
+        Persisted::Found { stored, .. } => Some(stored),
+        Persisted::Deleted => return not_found(&word, "Entry has been removed"),
+        Persisted::Missing => None,
+    };
+    let previous = state
+        .cache
+        .get(&word)
+        .map(|e| (e.value, e.model, e.schema_version))
+        .or_else(|| persisted.map(|v| (v.entry, v.model, v.schema_version)));
+    let model_name = state.backend.model_name();
+
+    let words = state.words_for(profile.as_deref());
+    match words.generate(&word, &state.system_prompt, None).await {
+        Ok((entry, attempts)) => {
+            state.charge_output(&headers, &entry);
+            state.cache.insert(&word, entry.clone(), &model_name, SCHEMA_VERSION);
+            let version = persist(
+                state.store.as_deref(),
+                &word,
+                &entry,
+                &model_name,
+                None,
+                Some(&attempts),
+            );
+            words.spawn_back_translation(&word, &entry, version);
+            let patch = match &previous {
+                Some((prev, _, _)) => patch::diff(prev, &entry),
+                None => vec![json!({ "op": "add", "path": "", "value": entry })],
+            };
+            Json(json!({
+                "word": word,
+                "previous": previous.map(|(_, model, schema_version)| json!({
+                    "model": model,
+                    "schema_version": schema_version,
+                })),
+                "model": model_name,
+                "version": version,
+                "patch": patch,
+                "entry": entry,
+            }))
+            .into_response()
+        }
+        Err(api_error) => {
+            error!("Failed to regenerate word '{}': {}", word, api_error.message());
+            api_error.into_response_for(&word)
+        }
+    }
+}
+
+/// Fields of an existing entry to regenerate, e.g.
+/// `["phonetic", "meanings[0].exampleSentence"]`.
+#[derive(Debug, Deserialize)]
+pub struct FieldsReq {
+    pub fields: Vec<String>,
+}
+
+request_schema!(
+    FieldsReq,
+    r#"{
+        "type": "object",
+        "required": ["fields"],
+        "properties": {
+            "fields": { "type": "array", "items": { "type": "string" } }
+        }
+    }"#
+);
+
+/// Regenerate only the named fields of an existing entry, one focused prompt
+/// per field, and merge the results into it. Everything else is kept as is,
+/// so curators can fix a weak example without losing the rest of the entry.
+pub async fn regenerate_fields(
+    Tenanted(state): Tenanted,
+    headers: HeaderMap,
+    Path(word): Path<String>,
+    ValidJson(req): ValidJson<FieldsReq>,
+) -> Response {
+    // Rewrites the shared cache and history, like regenerating the whole entry
+    if let Some(res) = reject_unauthorized(&headers, state.admin_token.as_deref()) {
+        return res;
+    }
+    info!("Regenerating fields {:?} for word: {}", req.fields, word);
+    let profile = match request_profile(&state, &headers) {
+        Ok(profile) => profile,
+        Err(rejection) => return rejection.into_response(),
+    };
+    let bad_request = |message: String| {
+        let error_response = ErrorResponse::new(ErrorCode::InvalidInput, &message, Some(word.clone()));
+        (StatusCode::BAD_REQUEST, Json(error_response)).into_response()
+    };
+    if req.fields.is_empty() {
+        return bad_request("At least one field is required".to_string());
+    }
+    let mut targets = Vec::with_capacity(req.fields.len());
+    for field in &req.fields {
+        match patch::field_pointer(field) {
+            Some(pointer) => targets.push((field.as_str(), pointer)),
+            None => return bad_request(format!("Field '{}' cannot be regenerated", field)),
+        }
+    }
+
+    let persisted = match load_persisted(state.store.as_deref(), &word) {
+        Persisted::Found { locked: true, .. } => {
+            let error_response = ErrorResponse::new(
+                ErrorCode::EntryLocked,
+                "Entry is curated and locked against regeneration",
+                Some(word),
+            );
+            return (StatusCode::CONFLICT, Json(error_response)).into_response();
+        }
+        Persisted::Found { stored, .. } => Some(stored),
+        Persisted::Deleted => return not_found(&word, "Entry has been removed"),
+        Persisted::Missing => None,
+    };
+    let Some(current) = state
+        .cache
+        .get(&word)
+        .map(|e| e.value)
+        .or_else(|| persisted.map(|v| v.entry))
+    else {
+        return not_found(&word, "No entry to update; analyze the word first");
+    };
+    if let Some((field, _)) = targets.iter().find(|(_, ptr)| current.pointer(ptr).is_none()) {
+        return bad_request(format!("Entry has no field '{}'", field));
+    }
+
+    // Sequential, so each prompt sees the fields regenerated before it
+    let words = state.words_for(profile.as_deref());
+    let mut updated = current.clone();
+    let mut attempts = None;
+    for (field, pointer) in &targets {
+        match words.generate_field(&word, field, &updated).await {
+            Ok((value, tried)) => {
+                state.charge_output(&headers, &value);
+                *updated.pointer_mut(pointer).expect("checked above") = value;
+                attempts = Some(tried);
+            }
+            Err(api_error) => {
+                error!("Failed to regenerate '{}' of '{}': {}", field, word, api_error.message());
+                return api_error.into_response_for(&word);
             }
-        }))
+        }
+    }
+    let entry = match state.validator.validate_and_fix(updated, &word) {
+        Ok(entry) => entry,
+        Err(error) => return AnalyzeError::Validation { error, attempts: 1 }.into_response_for(&word),
+    };
+
+    let model_name = state.backend.model_name();
+    state.cache.insert(&word, entry.clone(), &model_name, SCHEMA_VERSION);
+    // The version points at the last field's raw output; every field's is kept
+    let version =
+        persist(state.store.as_deref(), &word, &entry, &model_name, None, attempts.as_ref());
+    Json(json!({
+        "word": word,
+        "fields": req.fields,
+        "model": model_name,
+        "version": version,
+        "patch": patch::diff(&current, &entry),
+        "entry": entry,
+    }))
+    .into_response()
 }
 
-/// Attempt word inference with retry logic and enhanced error handling
-async fn attempt_word_inference<B: LlmBackend>(
-    backend: B,
-    validator: Arc<Validator>,
-    params: InferParams,
-    word: &str,
-) -> Result<Value, ApiErrorType> {
-    const MAX_RETRIES: usize = 2;
-    const RETRY_DELAY: Duration = Duration::from_millis(500);
+/// Longest text accepted by `/v1/tokenize`, in bytes.
+const MAX_TOKENIZE_BYTES: usize = 256 * 1024;
 
-    let system = "You are an expert linguist and lexicographer. Produce a single valid JSON object only.".to_string();
-    let prompt = PromptParts {
-        system,
-        user_word: word.to_string()
-    };
-
-    for attempt in 0..=MAX_RETRIES {
-        debug!("Inference attempt {} for word: {}", attempt + 1, word);
-
-        let inference_result = async {
-            let bytes = backend.infer_json(prompt.clone(), &params).await
-                .context("LLM inference failed")?;
-            Ok::<Vec<u8>, anyhow::Error>(bytes)
-        }.await;
-
-        let bytes = match inference_result {
-            Ok(bytes) => bytes,
-            Err(e) => {
-                warn!("Inference attempt {} failed for '{}': {}", attempt + 1, word, e);
-                if attempt < MAX_RETRIES {
-                    tokio::time::sleep(RETRY_DELAY).await;
-                    continue;
-                }
-                return Err(ApiErrorType::Inference(
-                    format!("LLM inference failed after {} attempts: {}", MAX_RETRIES + 1, e)
-                ));
+/// Body of the focused per-word endpoints (`/v1/family`, `/v1/pronunciation`).
+#[derive(Debug, Deserialize)]
+pub struct FocusedReq {
+    pub word: String,
+}
+
+request_schema!(
+    FocusedReq,
+    r#"{
+        "type": "object",
+        "required": ["word"],
+        "properties": {
+            "word": { "type": "string" }
+        }
+    }"#
+);
+
+/// Derivationally related words sharing the word's stem, each with its part
+/// of speech. Generated on every call; nothing is cached or stored.
+pub async fn word_family(
+    Tenanted(state): Tenanted,
+    headers: HeaderMap,
+    ValidJson(req): ValidJson<FocusedReq>,
+) -> Response {
+    let profile = match request_profile(&state, &headers) {
+        Ok(profile) => profile,
+        Err(rejection) => return rejection.into_response(),
+    };
+    match state.words_for(profile.as_deref()).family(&req.word).await {
+        Ok(found) => {
+            state.charge(&headers, &found);
+            Json(found.entry).into_response()
+        }
+        Err(api_error) => api_error.into_response_for(&req.word),
+    }
+}
+
+/// IPA with syllable boundaries, the written syllables, the stressed one and
+/// 2-3 minimal pairs, for pronunciation practice. Generated on every call.
+pub async fn word_pronunciation(
+    Tenanted(state): Tenanted,
+    headers: HeaderMap,
+    ValidJson(req): ValidJson<FocusedReq>,
+) -> Response {
+    let profile = match request_profile(&state, &headers) {
+        Ok(profile) => profile,
+        Err(rejection) => return rejection.into_response(),
+    };
+    match state.words_for(profile.as_deref()).pronunciation(&req.word).await {
+        Ok(found) => {
+            state.charge(&headers, &found);
+            Json(found.entry).into_response()
+        }
+        Err(api_error) => api_error.into_response_for(&req.word),
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ExamplesQuery {
+    /// Sentences wanted, 1 to `MAX_EXAMPLES`; `DEFAULT_EXAMPLES` when absent.
+    pub count: Option<usize>,
+}
+
+/// More example sentences for a word that already has an entry, from a short
+/// prompt instead of a full regeneration. The entry itself is left as it is.
+pub async fn word_examples(
+    Tenanted(state): Tenanted,
+    headers: HeaderMap,
+    Path(word): Path<String>,
+    Query(query): Query<ExamplesQuery>,
+) -> Response {
+    let count = query.count.unwrap_or(DEFAULT_EXAMPLES);
+    if !(1..=MAX_EXAMPLES).contains(&count) {
+        let error_response = ErrorResponse::new(
+            ErrorCode::InvalidInput,
+            format!("count must be between 1 and {}", MAX_EXAMPLES),
+            Some(word),
+        );
+        return (StatusCode::BAD_REQUEST, Json(error_response)).into_response();
+    }
+    let profile = match request_profile(&state, &headers) {
+        Ok(profile) => profile,
+        Err(rejection) => return rejection.into_response(),
+    };
+    let current = state.cache.get(&word).map(|e| e.value).or_else(|| {
+        match load_persisted(state.store.as_deref(), &word) {
+            Persisted::Found { stored, .. } => Some(stored.entry),
+            Persisted::Deleted | Persisted::Missing => None,
+        }
+    });
+    let Some(current) = current else {
+        return not_found(&word, "No entry for this word; analyze it first");
+    };
+    match state
+        .words_for(profile.as_deref())
+        .examples(&word, &current, count)
+        .await
+    {
+        Ok(found) => {
+            state.charge(&headers, &found);
+            Json(found.entry).into_response()
+        }
+        Err(api_error) => api_error.into_response_for(&word),
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct DailyQuery {
+    /// `YYYY-MM-DD`; today (UTC) when absent.
+    pub date: Option<String>,
+}
+
+/// The word for a date from the configured rotation, with its full entry,
+/// generated and cached like any `POST /v1/word` on the first request.
+pub async fn word_of_the_day(
+    Tenanted(state): Tenanted,
+    headers: HeaderMap,
+    Query(query): Query<PresentationQuery>,
+    Query(daily): Query<DailyQuery>,
+) -> Response {
+    let date = match daily.date.as_deref() {
+        None => Date::today(),
+        Some(raw) => match Date::parse(raw) {
+            Some(date) => date,
+            None => {
+                let error_response = ErrorResponse::new(
+                    ErrorCode::InvalidInput,
+                    format!("date must be a calendar date as YYYY-MM-DD, got '{}'", raw),
+                    None,
+                );
+                return (StatusCode::BAD_REQUEST, Json(error_response)).into_response();
             }
-        };
+        },
+    };
+    let word = state.daily_words.pick(date);
+    let profile = match request_profile(&state, &headers) {
+        Ok(profile) => profile,
+        Err(rejection) => return rejection.into_response(),
+    };
+    let presentation = Presentation::from_request(&state, &headers, &query, profile.as_deref());
+    match state
+        .words_for(profile.as_deref())
+        .analyze(word, &AnalyzeOptions::default())
+        .await
+    {
+        Ok(found) => {
+            state.charge(&headers, &found);
+            let body = json!({
+                "date": date.to_string(),
+                "word": word,
+                "entry": presentation.apply(found),
+            });
+            Json(body).into_response()
+        }
+        Err(api_error) => api_error.into_response_for(word),
+    }
+}
 
-        // Parse JSON
-        let json_value = match serde_json::from_slice::<Value>(&bytes) {
-            Ok(v) => v,
-            Err(e) => {
-                warn!("JSON parsing failed for '{}' on attempt {}: {}", word, attempt + 1, e);
-                if attempt < MAX_RETRIES {
-                    tokio::time::sleep(RETRY_DELAY).await;
-                    continue;
-                }
-                return Err(ApiErrorType::JsonParse(
-                    format!("Failed to parse JSON response: {}", e)
-                ));
+#[derive(Debug, Deserialize)]
+pub struct AnnotateReq {
+    pub text: String,
+    /// Also return each word's entry under `entries`, keyed by the spans' `word`.
+    #[serde(default)]
+    pub inline: bool,
+}
+
+request_schema!(
+    AnnotateReq,
+    r#"{
+        "type": "object",
+        "required": ["text"],
+        "properties": {
+            "text": { "type": "string", "maxLength": 5000 },
+            "inline": { "type": "boolean" }
+        }
+    }"#
+);
+
+/// Span offsets and difficulty for every content word of a text, so reader
+/// apps can underline the hard ones. Each distinct word is analyzed like a
+/// batch item, so cached words cost nothing and new ones are generated and
+/// cached. Words that fail carry their error `code` instead of a difficulty.
+pub async fn annotate_text(
+    Tenanted(state): Tenanted,
+    headers: HeaderMap,
+    Query(query): Query<PresentationQuery>,
+    ValidJson(req): ValidJson<AnnotateReq>,
+) -> Response {
+    let profile = match request_profile(&state, &headers) {
+        Ok(profile) => profile,
+        Err(rejection) => return rejection.into_response(),
+    };
+    let presentation = Presentation::from_request(&state, &headers, &query, profile.as_deref());
+
+    let spans = annotate::content_words(&req.text);
+    let mut words: Vec<String> = Vec::new();
+    for span in &spans {
+        if !words.contains(&span.word) {
+            words.push(span.word.clone());
+        }
+    }
+    if words.len() > MAX_DISTINCT_WORDS {
+        let error_response = ErrorResponse::new(
+            ErrorCode::InvalidInput,
+            format!(
+                "Text has {} distinct content words; at most {} per request",
+                words.len(),
+                MAX_DISTINCT_WORDS
+            ),
+            None,
+        );
+        return (StatusCode::BAD_REQUEST, Json(error_response)).into_response();
+    }
+
+    let service = state.words_for(profile.as_deref());
+    let outcomes = batch::run_indexed(words.clone(), state.batch_concurrency, |word| {
+        let service = service.clone();
+        async move { service.analyze(&word, &AnalyzeOptions::default()).await }
+    })
+    .await;
+    let mut entries = serde_json::Map::new();
+    let mut failures = std::collections::HashMap::new();
+    for (word, outcome) in words.into_iter().zip(outcomes) {
+        match outcome {
+            Ok(Ok(found)) => {
+                state.charge(&headers, &found);
+                entries.insert(word, presentation.apply(found));
             }
-        };
+            Ok(Err(api_error)) => {
+                failures.insert(word, api_error.code());
+            }
+            Err(join_err) => {
+                error!("Annotation task for '{}' failed: {}", word, join_err);
+                failures.insert(word, ErrorCode::InternalError);
+            }
+        }
+    }
 
-        // Validate and fix
-        match validator.validate_and_fix(json_value, word) {
-            Ok(validated) => {
-                debug!("Successfully processed '{}' on attempt {}", word, attempt + 1);
-                return Ok(validated);
+    let spans: Vec<Value> = spans
+        .into_iter()
+        .map(|span| {
+            let mut out = json!(span);
+            match entries.get(&span.word) {
+                Some(entry) => out["difficulty"] = entry["difficulty"].clone(),
+                None => out["code"] = json!(failures.get(&span.word)),
             }
-            Err(e) => {
-                // Check if it's a validation error we shouldn't retry
-                let error_msg = e.to_string();
-                if error_msg.contains("Missing required field") ||
-                   error_msg.contains("Invalid value") ||
-                   error_msg.contains("duplicate partOfSpeech") {
-                    warn!("Validation failed for '{}': {}", word, e);
-                    return Err(ApiErrorType::Validation(error_msg));
+            out
+        })
+        .collect();
+    let mut body = json!({ "text": req.text, "spans": spans });
+    if req.inline {
+        body["entries"] = Value::Object(entries);
+    }
+    Json(body).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TokenizeReq {
+    pub text: String,
+    /// Also return each token's id and text, not just the count
+    #[serde(default)]
+    pub with_tokens: bool,
+}
+
+request_schema!(
+    TokenizeReq,
+    r#"{
+        "type": "object",
+        "required": ["text"],
+        "properties": {
+            "text": { "type": "string" },
+            "with_tokens": { "type": "boolean" }
+        }
+    }"#
+);
+
+/// Token count of arbitrary text under the loaded model, so clients can
+/// budget their own prompts. 501 when the backend has no local tokenizer.
+pub async fn tokenize(State(state): State<AppState>, ValidJson(req): ValidJson<TokenizeReq>) -> Response {
+    if req.text.len() > MAX_TOKENIZE_BYTES {
+        let error_response = ErrorResponse::new(
+            ErrorCode::InvalidInput,
+            format!("Text too long (max {} bytes)", MAX_TOKENIZE_BYTES),
+            None,
+        );
+        return (StatusCode::BAD_REQUEST, Json(error_response)).into_response();
+    }
+    let backend = state.backend.clone();
+    let text = req.text;
+    // Long texts take real CPU time; keep it off the async workers
+    let tokens = match tokio::task::spawn_blocking(move || backend.tokenize(&text)).await {
+        Ok(Some(Ok(tokens))) => tokens,
+        Ok(None) => {
+            let error_response = ErrorResponse::new(
+                ErrorCode::NotSupported,
+                format!("Backend '{}' has no local tokenizer", state.backend.model_name()),
+                None,
+            );
+            return (StatusCode::NOT_IMPLEMENTED, Json(error_response)).into_response();
+        }
+        Ok(Some(Err(e))) => {
+            error!("Tokenization failed: {:#}", e);
+            let error_response = ErrorResponse::new(ErrorCode::InternalError, format!("{:#}", e), None);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response)).into_response();
+        }
+        Err(e) => {
+            error!("Tokenization task failed: {}", e);
+            let error_response = ErrorResponse::new(ErrorCode::InternalError, "Tokenization failed", None);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response)).into_response();
+        }
+    };
+
+    let mut body = json!({
+        "model": state.backend.model_name(),
+        "count": tokens.len(),
+    });
+    if req.with_tokens {
+        body["tokens"] = json!(tokens);
+    }
+    Json(body).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+}
+
+/// The subset of an OpenAI chat completion request the facade reads; sampling
+/// fields and the rest are accepted and ignored.
+#[derive(Debug, Deserialize)]
+pub struct ChatCompletionReq {
+    pub messages: Vec<ChatMessage>,
+    #[serde(default)]
+    pub stream: bool,
+}
+
+request_schema!(
+    ChatCompletionReq,
+    r#"{
+        "type": "object",
+        "required": ["messages"],
+        "properties": {
+            "messages": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["role", "content"],
+                    "properties": {
+                        "role": { "type": "string" },
+                        "content": { "type": "string" }
+                    }
                 }
+            },
+            "stream": { "type": "boolean" }
+        }
+    }"#
+);
+
+/// OpenAI-shaped `/v1/chat/completions` over word analysis, so OpenAI clients
+/// and gateways can call the service unchanged. The last user message is the
+/// word, the configured system prompt always applies, and the validated entry
+/// comes back as the assistant message's JSON content.
+pub async fn chat_completions(
+    Tenanted(state): Tenanted,
+    headers: HeaderMap,
+    ValidJson(req): ValidJson<ChatCompletionReq>,
+) -> Response {
+    let profile = match request_profile(&state, &headers) {
+        Ok(profile) => profile,
+        Err(_) => {
+            return openai_error(StatusCode::UNAUTHORIZED, ErrorCode::Unauthorized, "Unknown API key")
+        }
+    };
+    if req.stream {
+        return openai_error(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::NotSupported,
+            "Streaming is not supported; set stream to false",
+        );
+    }
+    let Some(word) = req
+        .messages
+        .iter()
+        .rev()
+        .find(|m| m.role == "user")
+        .map(|m| m.content.trim().to_string())
+    else {
+        return openai_error(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidInput,
+            "messages must include a user message holding the word",
+        );
+    };
+
+    let words = state.words_for(profile.as_deref());
+    let found = match words.analyze(&word, &AnalyzeOptions::default()).await {
+        Ok(found) => {
+            state.charge(&headers, &found);
+            found
+        }
+        Err(api_error) => {
+            return openai_error(api_error.status_code(), api_error.code(), &api_error.message())
+        }
+    };
+    let presentation = Presentation::from_request(
+        &state,
+        &HeaderMap::new(),
+        &PresentationQuery::default(),
+        profile.as_deref(),
+    );
+    let content = presentation.apply(found).to_string();
+    let prompt_tokens = state.backend.count_tokens(&word).unwrap_or(0);
+    let completion_tokens = state.backend.count_tokens(&content).unwrap_or(0);
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    Json(json!({
+        "id": format!("chatcmpl-{:x}", now.as_nanos()),
+        "object": "chat.completion",
+        "created": now.as_secs(),
+        "model": state.backend.model_name(),
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": content },
+            "finish_reason": "stop",
+        }],
+        "usage": {
+            "prompt_tokens": prompt_tokens,
+            "completion_tokens": completion_tokens,
+            "total_tokens": prompt_tokens + completion_tokens,
+        },
+    }))
+    .into_response()
+}
+
+/// Error in OpenAI's envelope, which OpenAI clients know how to surface.
+fn openai_error(status: StatusCode, code: ErrorCode, message: &str) -> Response {
+    let kind = if status.is_client_error() {
+        "invalid_request_error"
+    } else {
+        "server_error"
+    };
+    let body = json!({
+        "error": { "message": message, "type": kind, "param": null, "code": code },
+    });
+    (status, Json(body)).into_response()
+}
+
+pub async fn word_history(Tenanted(state): Tenanted, Path(word): Path<String>) -> Response {
+    let Some(store) = state.store else {
+        return persistence_disabled(&word);
+    };
+    match store.history(&word) {
+        Ok(versions) if versions.is_empty() => not_found(&word, "No stored versions for word"),
+        Ok(versions) => Json(json!({ "word": word, "versions": versions })).into_response(),
+        Err(e) => {
+            error!("Failed to read history for '{}': {:#}", word, e);
+            AnalyzeError::Internal(e.to_string()).into_response_for(&word)
+        }
+    }
+}
+
+pub async fn word_version(
+    Tenanted(state): Tenanted,
+    Path((word, version)): Path<(String, u32)>,
+) -> Response {
+    let Some(store) = state.store else {
+        return persistence_disabled(&word);
+    };
+    match store.version(&word, version) {
+        Ok(Some(stored)) => Json(stored).into_response(),
+        Ok(None) => not_found(&word, "No such version for word"),
+        Err(e) => {
+            error!("Failed to read version {} of '{}': {:#}", version, word, e);
+            AnalyzeError::Internal(e.to_string()).into_response_for(&word)
+        }
+    }
+}
+
+fn not_found(word: &str, message: &str) -> Response {
+    let error_response = ErrorResponse::new(ErrorCode::NotFound, message, Some(word.to_string()));
+    (StatusCode::NOT_FOUND, Json(error_response)).into_response()
+}
+
+fn persistence_disabled(word: &str) -> Response {
+    let error_response = ErrorResponse::new(
+        ErrorCode::PersistenceDisabled,
+        "Persistence is not enabled on this instance",
+        Some(word.to_string()),
+    );
+    (StatusCode::NOT_IMPLEMENTED, Json(error_response)).into_response()
+}
+
+/// Post-validation shaping of entries: translation ordering, CEFR level,
+/// alternative spellings, field projection, then provenance.
+struct Presentation {
+    translations: TranslationPrefs,
+    cefr: CefrMode,
+    fields: Option<FieldSelection>,
+    meta: bool,
+}
+
+impl Presentation {
+    fn from_request(
+        state: &AppState,
+        headers: &HeaderMap,
+        query: &PresentationQuery,
+        profile: Option<&Profile>,
+    ) -> Self {
+        Self {
+            translations: TranslationPrefs::from_request(headers, query, profile),
+            cefr: state.cefr,
+            fields: query.fields.as_deref().map(FieldSelection::parse),
+            meta: query.meta,
+        }
+    }
+
+    fn apply(&self, found: WordEntry) -> Value {
+        let entry = cefr::apply(self.translations.apply(found.entry), self.cefr);
+        let entry = variants::apply(entry);
+        let mut entry = match &self.fields {
+            Some(fields) => fields.project(&entry),
+            None => entry,
+        };
+        if let (true, Some(obj)) = (self.meta, entry.as_object_mut()) {
+            let provenance = found.provenance;
+            let mut meta = json!({
+                "model": provenance.model,
+                "schemaVersion": provenance.schema_version,
+                "generatedAt": provenance.generated_at,
+                "source": found.source,
+            });
+            // Only known for entries generated by this request
+            if let Some(attempts) = found.attempts {
+                meta["validatorFixes"] = attempts.fixes.into();
+            }
+            obj.insert("_meta".to_string(), meta);
+        }
+        entry
+    }
+}
+
+/// A set of dotted field paths forming a tree; a node without children keeps
+/// the whole subtree. Paths traverse arrays transparently, so `meanings.definition`
+/// keeps the definition of every meaning.
+#[derive(Debug, Default)]
+pub struct FieldSelection {
+    children: std::collections::BTreeMap<String, FieldSelection>,
+}
+
+impl FieldSelection {
+    pub fn parse(spec: &str) -> Self {
+        let mut root = Self::default();
+        for path in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let mut node = &mut root;
+            for segment in path.split('.') {
+                node = node.children.entry(segment.to_string()).or_default();
+            }
+        }
+        root
+    }
+
+    pub fn project(&self, value: &Value) -> Value {
+        if self.children.is_empty() {
+            return value.clone();
+        }
+        match value {
+            Value::Object(obj) => Value::Object(
+                obj.iter()
+                    .filter_map(|(k, v)| self.children.get(k).map(|sel| (k.clone(), sel.project(v))))
+                    .collect(),
+            ),
+            Value::Array(items) => Value::Array(items.iter().map(|v| self.project(v)).collect()),
+            other => other.clone(),
+        }
+    }
+}
+
+/// Client language preferences used to reorder each meaning's translations.
+struct TranslationPrefs {
+    languages: Vec<String>,
+    only_accepted: bool,
+}
+
+impl TranslationPrefs {
+    fn from_request(headers: &HeaderMap, query: &PresentationQuery, profile: Option<&Profile>) -> Self {
+        let languages = headers
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|v| v.to_str().ok())
+            .map(parse_accept_language)
+            .unwrap_or_default();
+        // A profile's languages stand in for a missing Accept-Language and are exclusive
+        if let Some(profile_languages) = profile.and_then(|p| p.languages.as_ref()) {
+            if languages.is_empty() {
+                return Self {
+                    languages: profile_languages.clone(),
+                    only_accepted: true,
+                };
+            }
+        }
+        Self {
+            languages,
+            only_accepted: query.translations.as_deref() == Some("accepted"),
+        }
+    }
 
-                warn!("Validation attempt {} failed for '{}': {}", attempt + 1, word, e);
-                if attempt < MAX_RETRIES {
-                    tokio::time::sleep(RETRY_DELAY).await;
-                    continue;
+    /// Put preferred languages first (optionally dropping the rest) and add a
+    /// `primaryTranslation` for the most preferred language the entry covers.
+    fn apply(&self, mut entry: Value) -> Value {
+        if self.languages.is_empty() {
+            return entry;
+        }
+        let Some(meanings) = entry.get_mut("meanings").and_then(|m| m.as_array_mut()) else {
+            return entry;
+        };
+        for meaning in meanings.iter_mut().filter_map(|m| m.as_object_mut()) {
+            let Some(translations) = meaning.get_mut("translations").and_then(|t| t.as_object_mut()) else {
+                continue;
+            };
+            let mut ordered = serde_json::Map::new();
+            for lang in &self.languages {
+                if let Some(text) = translations.shift_remove(lang) {
+                    ordered.insert(lang.clone(), text);
                 }
-                return Err(ApiErrorType::Validation(
-                    format!("Validation failed after {} attempts: {}", MAX_RETRIES + 1, e)
-                ));
+            }
+            if ordered.is_empty() {
+                // None of the preferred languages are available; leave as-is
+                ordered.append(translations);
+                *translations = ordered;
+                continue;
+            }
+            let primary = ordered.values().next().cloned();
+            if !self.only_accepted {
+                ordered.append(translations);
+            }
+            *translations = ordered;
+            if let Some(primary) = primary {
+                meaning.insert("primaryTranslation".to_string(), primary);
             }
         }
+        entry
     }
+}
+
+/// Parse an `Accept-Language` header into primary language subtags, most preferred first.
+fn parse_accept_language(header: &str) -> Vec<String> {
+    let mut langs: Vec<(String, f32)> = header
+        .split(',')
+        .filter_map(|part| {
+            let mut pieces = part.trim().split(';');
+            let tag = pieces.next()?.trim();
+            let q = pieces
+                .find_map(|p| p.trim().strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            let primary = tag.split('-').next()?.to_lowercase();
+            (!primary.is_empty() && primary != "*" && q > 0.0).then_some((primary, q))
+        })
+        .collect();
+    // Stable sort keeps header order for equal weights
+    langs.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let mut seen = std::collections::HashSet::new();
+    langs
+        .into_iter()
+        .filter(|(l, _)| seen.insert(l.clone()))
+        .map(|(l, _)| l)
+        .collect()
+}
 
-    Err(ApiErrorType::Internal("Unexpected end of retry loop".to_string()))
+/// A prompt to run verbatim, with optional overrides of the configured sampling.
+#[derive(Debug, Deserialize)]
+pub struct RawReq {
+    pub prompt: String,
+    pub max_tokens: Option<i32>,
+    pub temp: Option<f32>,
+    pub top_p: Option<f32>,
+    pub min_p: Option<f32>,
+    pub repeat_penalty: Option<f32>,
+    pub repeat_last_n: Option<i32>,
+    pub frequency_penalty: Option<f32>,
+    pub presence_penalty: Option<f32>,
+}
+
+request_schema!(
+    RawReq,
+    r#"{
+        "type": "object",
+        "required": ["prompt"],
+        "properties": {
+            "prompt": { "type": "string" },
+            "max_tokens": { "type": ["integer", "null"], "minimum": 1 },
+            "temp": { "type": ["number", "null"], "minimum": 0 },
+            "top_p": { "type": ["number", "null"], "minimum": 0, "maximum": 1 },
+            "min_p": { "type": ["number", "null"], "minimum": 0, "maximum": 1 },
+            "repeat_penalty": { "type": ["number", "null"], "minimum": 0 },
+            "repeat_last_n": { "type": ["integer", "null"], "minimum": -1 },
+            "frequency_penalty": { "type": ["number", "null"] },
+            "presence_penalty": { "type": ["number", "null"] }
+        }
+    }"#
+);
+
+/// Operator edits to a stored entry. `entry` is an RFC 7396 merge patch applied
+/// to the latest version; the result is validated and stored as a curated version.
+#[derive(Debug, Deserialize)]
+pub struct EntryEdit {
+    pub locked: Option<bool>,
+    pub deleted: Option<bool>,
+    pub entry: Option<Value>,
+}
+
+request_schema!(
+    EntryEdit,
+    r#"{
+        "type": "object",
+        "properties": {
+            "locked": { "type": ["boolean", "null"] },
+            "deleted": { "type": ["boolean", "null"] },
+            "entry": { "type": ["object", "null"] }
+        }
+    }"#
+);
+
+request_schema!(
+    PurgeFilter,
+    r#"{
+        "type": "object",
+        "properties": {
+            "model": { "type": ["string", "null"] },
+            "schema_version": { "type": ["string", "null"] },
+            "older_than_secs": { "type": ["integer", "null"], "minimum": 0 }
+        }
+    }"#
+);
+
+// Operator endpoints; every request must carry `Authorization: Bearer <admin token>`.
+// When no admin token is configured the endpoints reject all requests.
+
+pub async fn evict_cached(
+    Tenanted(state): Tenanted,
+    headers: HeaderMap,
+    Path(word): Path<String>,
+) -> Response {
+    if let Some(res) = reject_unauthorized(&headers, state.admin_token.as_deref()) {
+        return res;
+    }
+    match state.cache.remove(&word) {
+        Some(_) => {
+            info!("Evicted cached entry for word: {}", word);
+            Json(json!({ "purged": 1 })).into_response()
+        }
+        None => not_found(&word, "Word is not cached"),
+    }
+}
+
+pub async fn purge_cache(
+    Tenanted(state): Tenanted,
+    headers: HeaderMap,
+    ValidJson(filter): ValidJson<PurgeFilter>,
+) -> Response {
+    if let Some(res) = reject_unauthorized(&headers, state.admin_token.as_deref()) {
+        return res;
+    }
+    let purged = state.cache.purge(&filter);
+    info!(?filter, purged, "Purged cache entries");
+    Json(json!({ "purged": purged, "remaining": state.cache.entry_count() })).into_response()
+}
+
+/// Reread SCHEMA_FILE and validate against it from now on. A file that does
+/// not parse or compile is rejected and the current schema stays in force.
+pub async fn reload_schema(Tenanted(state): Tenanted, headers: HeaderMap) -> Response {
+    if let Some(res) = reject_unauthorized(&headers, state.admin_token.as_deref()) {
+        return res;
+    }
+    let Some(path) = state.schema_file else {
+        let error_response = ErrorResponse::new(
+            ErrorCode::NotSupported,
+            "SCHEMA_FILE is not set; the embedded schema cannot be reloaded",
+            None,
+        );
+        return (StatusCode::NOT_IMPLEMENTED, Json(error_response)).into_response();
+    };
+    match crate::schema::reload(&state.validator, std::path::Path::new(&*path)) {
+        Ok(changed) => {
+            let hash = state.validator.schema_hash();
+            info!(%path, changed, %hash, "Reloaded word contract schema");
+            Json(json!({ "changed": changed, "schema_hash": hash })).into_response()
+        }
+        Err(e) => {
+            warn!("Keeping the current schema: {:#}", e);
+            let error_response = ErrorResponse::new(
+                ErrorCode::ValidationError,
+                format!("{:#}; the current schema stays in force", e),
+                None,
+            );
+            (StatusCode::UNPROCESSABLE_ENTITY, Json(error_response)).into_response()
+        }
+    }
+}
+
+pub async fn rollback_entry(
+    Tenanted(state): Tenanted,
+    headers: HeaderMap,
+    Path((word, version)): Path<(String, u32)>,
+) -> Response {
+    if let Some(res) = reject_unauthorized(&headers, state.admin_token.as_deref()) {
+        return res;
+    }
+    let Some(store) = state.store else {
+        return persistence_disabled(&word);
+    };
+    // Restoring appends the old content as a new version so history stays linear
+    let restored = store.version(&word, version).and_then(|found| {
+        found
+            .map(|old| {
+                store
+                    .append(&word, &old.entry, &old.model, &old.schema_version)
+                    .map(|new_version| (old, new_version))
+            })
+            .transpose()
+    });
+    match restored {
+        Ok(Some((old, new_version))) => {
+            info!("Rolled back '{}' to version {} as version {}", word, version, new_version);
+            state.cache.insert(&word, old.entry, &old.model, &old.schema_version);
+            Json(json!({ "word": word, "restored_from": version, "version": new_version }))
+                .into_response()
+        }
+        Ok(None) => not_found(&word, "No such version for word"),
+        Err(e) => {
+            error!("Failed to roll back '{}': {:#}", word, e);
+            AnalyzeError::Internal(e.to_string()).into_response_for(&word)
+        }
+    }
+}
+
+pub async fn edit_entry(
+    Tenanted(state): Tenanted,
+    headers: HeaderMap,
+    Path(word): Path<String>,
+    ValidJson(edit): ValidJson<EntryEdit>,
+) -> Response {
+    if let Some(res) = reject_unauthorized(&headers, state.admin_token.as_deref()) {
+        return res;
+    }
+    let Some(store) = state.store else {
+        return persistence_disabled(&word);
+    };
+    let (flags, version) = match apply_entry_edit(&store, &state.validator, &word, &edit) {
+        Ok(applied) => applied,
+        Err(api_error) => {
+            warn!("Rejected edit of '{}': {}", word, api_error.message());
+            return api_error.into_response_for(&word);
+        }
+    };
+    info!(?flags, ?version, "Updated curated entry for word: {}", word);
+
+    // Keep the cache in line with what persistence will now serve
+    match load_persisted(Some(&store), &word) {
+        Persisted::Found { stored, .. } => {
+            state.cache.insert(&word, stored.entry, &stored.model, &stored.schema_version)
+        }
+        Persisted::Deleted | Persisted::Missing => {
+            state.cache.remove(&word);
+        }
+    }
+    Json(json!({
+        "word": word,
+        "locked": flags.locked,
+        "deleted": flags.deleted,
+        "version": version,
+    }))
+    .into_response()
+}
+
+/// Run an arbitrary prompt and return the model's output untouched: no JSON
+/// extraction, validation, caching or persistence. For debugging prompts and
+/// templates against the production model.
+pub async fn raw_generate(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    ValidJson(req): ValidJson<RawReq>,
+) -> Response {
+    if let Some(res) = reject_unauthorized(&headers, state.admin_token.as_deref()) {
+        return res;
+    }
+    if req.prompt.trim().is_empty() {
+        let error_response = ErrorResponse::new(ErrorCode::InvalidInput, "Prompt must not be empty", None);
+        return (StatusCode::BAD_REQUEST, Json(error_response)).into_response();
+    }
+    let params = InferParams {
+        max_tokens: req.max_tokens.unwrap_or(state.params.max_tokens),
+        temp: req.temp.unwrap_or(state.params.temp),
+        top_p: req.top_p.unwrap_or(state.params.top_p),
+        min_p: req.min_p.unwrap_or(state.params.min_p),
+        repeat_penalty: req.repeat_penalty.unwrap_or(state.params.repeat_penalty),
+        repeat_last_n: req.repeat_last_n.unwrap_or(state.params.repeat_last_n),
+        frequency_penalty: req.frequency_penalty.unwrap_or(state.params.frequency_penalty),
+        presence_penalty: req.presence_penalty.unwrap_or(state.params.presence_penalty),
+        ..state.params.clone()
+    };
+    let prompt_tokens = state.backend.count_tokens(&req.prompt);
+    let prompt = PromptParts {
+        task: PromptTask::Raw { prompt: req.prompt },
+        ..PromptParts::default()
+    };
+
+    let started = std::time::Instant::now();
+    let bytes = match state.backend.infer_json(prompt, &params).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error!("Raw generation failed: {:#}", e);
+            let error_response = ErrorResponse::new(ErrorCode::InferenceError, format!("{:#}", e), None);
+            return (StatusCode::SERVICE_UNAVAILABLE, Json(error_response)).into_response();
+        }
+    };
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    let output = String::from_utf8_lossy(&bytes).into_owned();
+    info!(elapsed_ms, output_len = output.len(), "Ran raw generation");
+
+    Json(json!({
+        "model": state.backend.model_name(),
+        "output": output,
+        "output_is_json": serde_json::from_slice::<Value>(&bytes).is_ok(),
+        "timing": {
+            "elapsed_ms": elapsed_ms,
+            "prompt_tokens": prompt_tokens,
+            "output_tokens": state.backend.count_tokens(&output),
+        },
+        "params": {
+            "max_tokens": params.max_tokens,
+            "temp": params.temp,
+            "top_p": params.top_p,
+            "min_p": params.min_p,
+            "repeat_penalty": params.repeat_penalty,
+            "repeat_last_n": params.repeat_last_n,
+            "frequency_penalty": params.frequency_penalty,
+            "presence_penalty": params.presence_penalty,
+        },
+    }))
+    .into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiffQuery {
+    pub model_a: String,
+    pub model_b: String,
+}
+
+/// Raw model output kept by persistence, byte for byte, as found in an entry
+/// version's `raw_output` or a failure's `raw_outputs`.
+pub async fn raw_output(
+    Tenanted(state): Tenanted,
+    headers: HeaderMap,
+    Path(hash): Path<String>,
+) -> Response {
+    if let Some(res) = reject_unauthorized(&headers, state.admin_token.as_deref()) {
+        return res;
+    }
+    let Some(store) = state.store else {
+        let error_response = ErrorResponse::new(
+            ErrorCode::PersistenceDisabled,
+            "Persistence is not enabled on this instance",
+            None,
+        );
+        return (StatusCode::NOT_IMPLEMENTED, Json(error_response)).into_response();
+    };
+    match store.raw(&hash) {
+        Ok(Some(bytes)) => {
+            let content_type = match std::str::from_utf8(&bytes) {
+                Ok(_) => "text/plain; charset=utf-8",
+                Err(_) => "application/octet-stream",
+            };
+            ([(header::CONTENT_TYPE, content_type)], bytes).into_response()
+        }
+        Ok(None) => {
+            let error_response =
+                ErrorResponse::new(ErrorCode::NotFound, "No raw output with this hash", None);
+            (StatusCode::NOT_FOUND, Json(error_response)).into_response()
+        }
+        Err(e) => {
+            error!("Failed to read raw output {}: {:#}", hash, e);
+            let error_response = ErrorResponse::new(ErrorCode::InternalError, format!("{:#}", e), None);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response)).into_response()
+        }
+    }
+}
+
+/// How many stored entries changed between two models, by content hash, for
+/// signing off a model upgrade.
+pub async fn model_diff(
+    Tenanted(state): Tenanted,
+    headers: HeaderMap,
+    Query(query): Query<DiffQuery>,
+) -> Response {
+    if let Some(res) = reject_unauthorized(&headers, state.admin_token.as_deref()) {
+        return res;
+    }
+    let Some(store) = state.store else {
+        let error_response = ErrorResponse::new(
+            ErrorCode::PersistenceDisabled,
+            "Persistence is not enabled on this instance",
+            None,
+        );
+        return (StatusCode::NOT_IMPLEMENTED, Json(error_response)).into_response();
+    };
+    // Reads every entry file; keep it off the async workers
+    let diff = tokio::task::spawn_blocking(move || store.diff_models(&query.model_a, &query.model_b))
+        .await
+        .unwrap_or_else(|e| Err(e.into()));
+    match diff {
+        Ok(diff) => Json(diff).into_response(),
+        Err(e) => {
+            error!("Failed to diff models: {:#}", e);
+            let error_response = ErrorResponse::new(ErrorCode::InternalError, format!("{:#}", e), None);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response)).into_response()
+        }
+    }
+}
+
+/// Operator dashboard. The page carries no data and is served without a
+/// token; it asks for one and polls [`dashboard_stats`] with it.
+pub async fn dashboard_page() -> Html<&'static str> {
+    Html(dashboard::PAGE)
+}
+
+/// Request rates, latency percentiles, queue depth, cache hit rate and recent
+/// errors, for the dashboard page.
+pub async fn dashboard_stats(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Some(res) = reject_unauthorized(&headers, state.admin_token.as_deref()) {
+        return res;
+    }
+    Json(dashboard::snapshot()).into_response()
+}
+
+/// Process and GPU memory, model and KV cache accounting, inference slots
+/// and cache size, for spotting leaks and sizing deployments.
+pub async fn process_stats(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Some(res) = reject_unauthorized(&headers, state.admin_token.as_deref()) {
+        return res;
+    }
+    let cache_entries = state.cache.entry_count();
+    let backend = state.backend.stats();
+    // nvidia-smi can take a while to answer
+    match tokio::task::spawn_blocking(stats::process).await {
+        Ok(process) => Json(Stats { process, cache_entries, backend }).into_response(),
+        Err(e) => {
+            error!("Failed to collect process stats: {}", e);
+            let error_response = ErrorResponse::new(ErrorCode::InternalError, e.to_string(), None);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response)).into_response()
+        }
+    }
+}
+
+fn apply_entry_edit(
+    store: &EntryStore,
+    validator: &Validator,
+    word: &str,
+    edit: &EntryEdit,
+) -> Result<(EntryFlags, Option<u32>), AnalyzeError> {
+    let internal = |e: anyhow::Error| AnalyzeError::Internal(format!("{:#}", e));
+
+    let mut version = None;
+    if let Some(changes) = &edit.entry {
+        let mut merged = store
+            .latest(word)
+            .map_err(internal)?
+            .map(|v| v.entry)
+            .unwrap_or_else(|| json!({}));
+        patch::merge(&mut merged, changes);
+        let validated = validator
+            .validate_and_fix(merged, word)
+            .map_err(|error| AnalyzeError::Validation { error, attempts: 1 })?;
+        version = Some(
+            store
+                .append(word, &validated, CURATED_MODEL, SCHEMA_VERSION)
+                .map_err(internal)?,
+        );
+    }
+
+    let flags = store
+        .update_flags(word, |flags| {
+            if let Some(locked) = edit.locked {
+                flags.locked = locked;
+            }
+            if let Some(deleted) = edit.deleted {
+                flags.deleted = deleted;
+            }
+        })
+        .map_err(internal)?;
+    Ok((flags, version))
+}
+
+/// Returns the rejection response when a request carries a system prompt but
+/// this instance does not allow overriding it.
+fn reject_system_override(state: &AppState, requested: Option<&str>, word: &str) -> Option<Response> {
+    if requested.is_none() || state.allow_system_prompt_override {
+        return None;
+    }
+    warn!("Rejected system prompt override");
+    let error_response = ErrorResponse::new(
+        ErrorCode::Forbidden,
+        "Overriding the system prompt is not enabled on this instance",
+        (!word.is_empty()).then(|| word.to_string()),
+    );
+    Some((StatusCode::FORBIDDEN, Json(error_response)).into_response())
+}
+
+/// Header carrying a client's API key, which selects its profile.
+const API_KEY_HEADER: &str = "x-api-key";
+
+/// The profile bound to the request's API key. Requests without a key get the
+/// server defaults; an unknown key is rejected rather than silently defaulted.
+/// Keys are ignored entirely when no profiles are configured.
+fn request_profile(state: &AppState, headers: &HeaderMap) -> Result<Option<Arc<Profile>>, UnknownApiKey> {
+    if state.profiles.is_empty() {
+        return Ok(None);
+    }
+    let Some(key) = api_key(headers) else {
+        return Ok(None);
+    };
+    match state.profiles.for_key(key) {
+        Some((name, profile)) => {
+            debug!(profile = name, "applying API key profile");
+            Ok(Some(profile.clone()))
+        }
+        None => {
+            warn!("Rejected unknown API key");
+            Err(UnknownApiKey)
+        }
+    }
+}
+
+fn api_key(headers: &HeaderMap) -> Option<&str> {
+    headers.get(API_KEY_HEADER).and_then(|v| v.to_str().ok())
+}
+
+/// The tenant a request is for: the one its API key's profile names. A key
+/// bound to one tenant may not name another in `X-Tenant`, and other callers
+/// may only name one with the admin token or `TRUST_TENANT_HEADER`.
+fn request_tenant(
+    state: &AppState,
+    headers: &HeaderMap,
+) -> Result<Option<String>, TenantRejection> {
+    let named = headers
+        .get(TENANT_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_string());
+    let bound = api_key(headers)
+        .and_then(|key| state.profiles.for_key(key))
+        .and_then(|(_, profile)| profile.tenant.clone());
+    match (bound, named) {
+        (Some(bound), Some(named)) if bound != named => {
+            warn!(tenant = %named, "Rejected API key naming another tenant");
+            Err(TenantRejection::OtherTenant { bound })
+        }
+        (Some(bound), _) => Ok(Some(bound)),
+        (None, None) => Ok(None),
+        (None, Some(named))
+            if state.trust_tenant_header
+                || is_admin(headers, state.admin_token.as_deref()) =>
+        {
+            Ok(Some(named))
+        }
+        (None, Some(named)) => {
+            warn!(tenant = %named, "Rejected X-Tenant without a key bound to it");
+            Err(TenantRejection::Unbound)
+        }
+    }
+}
+
+/// Who a request is from: its tenant and API key, with keyless requests
+/// sharing one identity.
+fn requester(state: &AppState, headers: &HeaderMap) -> String {
+    usage_key(state.tenant.as_deref(), api_key(headers).unwrap_or(""))
+}
+
+/// Usage counters are kept per tenant, so one key's use under one tenant
+/// never draws on its quota under another.
+fn usage_key(tenant: Option<&str>, key: &str) -> String {
+    match tenant {
+        Some(tenant) => format!("{}/{}", tenant, key),
+        None => key.to_string(),
+    }
+}
+
+/// [`AppState`] scoped to the request's tenant, for handlers that read or
+/// write entries.
+pub struct Tenanted(pub AppState);
+
+#[axum::async_trait]
+impl FromRequestParts<AppState> for Tenanted {
+    type Rejection = TenantRejection;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, TenantRejection> {
+        let state = state.for_tenant(&parts.headers)?;
+        Ok(Tenanted(state.scheduled(&parts.headers)))
+    }
+}
+
+pub enum TenantRejection {
+    /// `X-Tenant` names no configured tenant
+    Unknown(String),
+    /// The API key is bound to a tenant other than the one `X-Tenant` names
+    OtherTenant { bound: String },
+    /// `X-Tenant` came without a key bound to it or the admin token
+    Unbound,
+}
+
+impl IntoResponse for TenantRejection {
+    fn into_response(self) -> Response {
+        let (status, error_response) = match self {
+            Self::Unknown(name) => (
+                StatusCode::BAD_REQUEST,
+                ErrorResponse::new(
+                    ErrorCode::InvalidInput,
+                    format!("Unknown tenant '{}'", name),
+                    None,
+                ),
+            ),
+            Self::OtherTenant { bound } => (
+                StatusCode::FORBIDDEN,
+                ErrorResponse::new(
+                    ErrorCode::Forbidden,
+                    format!("This API key belongs to tenant '{}'", bound),
+                    None,
+                ),
+            ),
+            Self::Unbound => (
+                StatusCode::FORBIDDEN,
+                ErrorResponse::new(
+                    ErrorCode::Forbidden,
+                    "X-Tenant needs an API key bound to that tenant",
+                    None,
+                ),
+            ),
+        };
+        (status, Json(error_response)).into_response()
+    }
+}
+
+struct UnknownApiKey;
+
+/// Where the OpenAI-compatible facade is served.
+const CHAT_COMPLETIONS_PATH: &str = "/v1/chat/completions";
+
+/// OpenAI SDKs send their key as `Authorization: Bearer <key>`. On the chat
+/// completions facade it stands in for a missing `X-API-Key`, so those
+/// clients get their profile and are metered like any other; elsewhere the
+/// bearer token stays the admin token.
+async fn openai_bearer_key(mut req: Request, next: Next) -> Response {
+    if req.uri().path() == CHAT_COMPLETIONS_PATH && !req.headers().contains_key(API_KEY_HEADER) {
+        let bearer = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .and_then(|key| HeaderValue::from_str(key.trim()).ok());
+        if let Some(key) = bearer {
+            req.headers_mut().insert(API_KEY_HEADER, key);
+        }
+    }
+    next.run(req).await
+}
+
+/// Enforce the calling key's rate limit and token quota before the request
+/// runs, and report what is left in `X-RateLimit-Remaining` and
+/// `X-Quota-Remaining-Tokens` on the response. Keyless requests pass
+/// untouched; unknown keys are left for the endpoints to reject.
+async fn meter_api_key(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let Some((key, profile)) = api_key(req.headers())
+        .and_then(|key| Some((key.to_string(), state.profiles.for_key(key)?.1.clone())))
+    else {
+        return next.run(req).await;
+    };
+    // A tenant the request may not use is rejected by the handler
+    let tenant = request_tenant(&state, req.headers()).ok().flatten();
+    let key = usage_key(tenant.as_deref(), &key);
+    let rejection = match state.usage.admit(&key, &profile) {
+        Ok(_) => {
+            let mut res = next.run(req).await;
+            usage_headers(&mut res, state.usage.remaining(&key, &profile));
+            return res;
+        }
+        Err(rejection) => rejection,
+    };
+
+    let (status, code, message, retry_after) = match rejection {
+        Rejection::RateLimited { retry_after } => (
+            StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::RateLimited,
+            "Rate limit exceeded for this API key",
+            retry_after,
+        ),
+        Rejection::QuotaExceeded { retry_after } => (
+            StatusCode::PAYMENT_REQUIRED,
+            ErrorCode::QuotaExceeded,
+            "Daily token quota exhausted for this API key",
+            retry_after,
+        ),
+    };
+    warn!(%code, "Rejected API key request over its limits");
+    let mut res = (status, Json(ErrorResponse::new(code, message, None))).into_response();
+    // Whole seconds, rounded up so clients never retry a moment too early
+    let secs = retry_after.as_millis().div_ceil(1000).max(1);
+    res.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(secs as u64));
+    usage_headers(&mut res, state.usage.remaining(&key, &profile));
+    res
+}
+
+fn usage_headers(res: &mut Response, remaining: Remaining) {
+    let headers = res.headers_mut();
+    if let Some(requests) = remaining.requests {
+        headers.insert("x-ratelimit-remaining", HeaderValue::from(requests));
+    }
+    if let Some(tokens) = remaining.tokens {
+        headers.insert("x-quota-remaining-tokens", HeaderValue::from(tokens));
+    }
+}
+
+impl IntoResponse for UnknownApiKey {
+    fn into_response(self) -> Response {
+        let error_response = ErrorResponse::new(ErrorCode::Unauthorized, "Unknown API key", None);
+        (StatusCode::UNAUTHORIZED, Json(error_response)).into_response()
+    }
+}
+
+/// Whether the request carries the admin token; never without one configured.
+fn is_admin(headers: &HeaderMap, admin_token: Option<&str>) -> bool {
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+
+    match (admin_token, provided) {
+        (Some(expected), Some(given)) => same_secret(expected, given),
+        _ => false,
+    }
+}
+
+/// Compare secrets without the time taken giving away how much of `given`
+/// matched: both are hashed to equal-length digests, which are then compared
+/// in full.
+fn same_secret(expected: &str, given: &str) -> bool {
+    use sha2::{Digest, Sha256};
+    let (expected, given) = (Sha256::digest(expected), Sha256::digest(given));
+    expected.iter().zip(given.iter()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Returns the rejection response when the request lacks a valid admin token.
+fn reject_unauthorized(headers: &HeaderMap, admin_token: Option<&str>) -> Option<Response> {
+    if is_admin(headers, admin_token) {
+        return None;
+    }
+    warn!("Rejected unauthorized admin request");
+    let error_response = ErrorResponse::new(
+        ErrorCode::Unauthorized,
+        "Missing or invalid admin token",
+        None,
+    );
+    Some((StatusCode::UNAUTHORIZED, Json(error_response)).into_response())
 }
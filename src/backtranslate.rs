@@ -0,0 +1,159 @@
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// Translations checked per entry; a spot-check, not a review.
+const SAMPLE_SIZE: usize = 2;
+
+/// Words too common in definitions to say anything about the sense.
+const FILLER: [&str; 14] = [
+    "that",
+    "with",
+    "which",
+    "from",
+    "into",
+    "something",
+    "someone",
+    "somebody",
+    "being",
+    "having",
+    "their",
+    "there",
+    "thing",
+    "used",
+];
+
+/// Translations of one sense picked for back-translation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    /// Index into `meanings`
+    pub meaning: usize,
+    pub part_of_speech: String,
+    /// `(language code, translation)`
+    pub items: Vec<(String, String)>,
+}
+
+/// Up to two translations of the entry's first sense. Which languages is
+/// fixed per word but varies between words, so over many entries every
+/// language gets checked.
+pub fn sample(entry: &Value, word: &str) -> Option<Sample> {
+    let meaning = entry["meanings"].get(0)?;
+    let mut translations: Vec<(&String, &str)> = meaning["translations"]
+        .as_object()?
+        .iter()
+        .filter_map(|(lang, text)| Some((lang, text.as_str()?.trim())))
+        .filter(|(_, text)| !text.is_empty())
+        .collect();
+    if translations.is_empty() {
+        return None;
+    }
+    translations.sort();
+    let start = word
+        .bytes()
+        .fold(0usize, |h, b| h.wrapping_mul(31).wrapping_add(b as usize))
+        % translations.len();
+    let items = translations
+        .iter()
+        .cycle()
+        .skip(start)
+        .take(SAMPLE_SIZE.min(translations.len()))
+        .map(|(lang, text)| (lang.to_string(), text.to_string()))
+        .collect();
+    Some(Sample {
+        meaning: 0,
+        part_of_speech: meaning["partOfSpeech"].as_str().unwrap_or("").to_string(),
+        items,
+    })
+}
+
+/// Quality warnings for the sampled translations whose back-translation
+/// (`{"<lang>": ["english", ...]}`) shares nothing with the sense: not the
+/// word, its base form, a synonym or a content word of the definition.
+/// Languages missing from `answer` are not judged.
+pub fn warnings(answer: &Value, entry: &Value, word: &str, sample: &Sample) -> Vec<String> {
+    let meaning = &entry["meanings"][sample.meaning];
+    let mut reference = HashSet::new();
+    let texts = [word, entry["baseForm"].as_str().unwrap_or("")]
+        .into_iter()
+        .chain(
+            meaning["synonyms"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str),
+        );
+    for text in texts {
+        reference.extend(stems(text, 3));
+    }
+    reference.extend(
+        stems(meaning["definition"].as_str().unwrap_or(""), 4)
+            .filter(|s| !FILLER.iter().any(|f| f.starts_with(s.as_str()))),
+    );
+
+    sample
+        .items
+        .iter()
+        .filter_map(|(lang, text)| {
+            let english: Vec<&str> = answer[lang].as_array()?.iter().filter_map(Value::as_str).collect();
+            if english.is_empty() || english.iter().flat_map(|e| stems(e, 3)).any(|s| reference.contains(&s)) {
+                return None;
+            }
+            Some(format!(
+                "translation \"{}\" ({}) of the {} sense back-translates to \"{}\", unrelated to the sense",
+                text,
+                lang,
+                sample.part_of_speech,
+                english.join(", ")
+            ))
+        })
+        .collect()
+}
+
+/// Lowercase words of `text` at least `min_len` letters long, cut to four
+/// letters so inflections ("lends", "lending") meet their stem.
+fn stems(text: &str, min_len: usize) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphabetic())
+        .filter(move |w| w.chars().count() >= min_len)
+        .map(|w| w.to_lowercase().chars().take(4).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn flags_translations_that_lost_the_sense() {
+        let entry = json!({
+            "baseForm": "bank",
+            "meanings": [{
+                "partOfSpeech": "noun",
+                "definition": "An institution that keeps and lends money.",
+                "synonyms": ["lender"],
+                "translations": { "de": "Bank", "es": "banco", "fr": "rive", "it": "" }
+            }]
+        });
+        let sample = sample(&entry, "bank").unwrap();
+        assert_eq!(sample.items.len(), 2);
+        assert!(sample.items.iter().all(|(lang, _)| lang != "it"));
+
+        let sample = Sample {
+            meaning: 0,
+            part_of_speech: "noun".to_string(),
+            items: vec![
+                ("es".to_string(), "banco".to_string()),
+                ("fr".to_string(), "rive".to_string()),
+            ],
+        };
+        let answer = json!({ "es": ["bank", "bench"], "fr": ["shore", "riverside"] });
+        assert_eq!(
+            warnings(&answer, &entry, "bank", &sample),
+            vec!["translation \"rive\" (fr) of the noun sense back-translates to \"shore, riverside\", unrelated to the sense"]
+        );
+
+        // Definition words count too, and inflections meet their stem
+        let answer = json!({ "es": ["financial institution"], "fr": ["lending house"] });
+        assert!(warnings(&answer, &entry, "bank", &sample).is_empty());
+        // No answer for a language is no verdict
+        assert!(warnings(&json!({}), &entry, "bank", &sample).is_empty());
+    }
+}
@@ -0,0 +1,85 @@
+use futures_util::stream::{self, StreamExt};
+use std::future::Future;
+use tokio::task::JoinError;
+
+/// Run `task` for every item with at most `limit` in flight, returning the
+/// outcomes in input order.
+///
+/// Each item runs on its own tokio task so a panic is contained and reported
+/// in that item's slot, never attributed to a neighbour.
+pub async fn run_indexed<I, T, F, Fut>(
+    items: Vec<I>,
+    limit: usize,
+    task: F,
+) -> Vec<Result<T, JoinError>>
+where
+    F: Fn(I) -> Fut,
+    Fut: Future<Output = T> + Send + 'static,
+    T: Send + 'static,
+{
+    let n = items.len();
+    let mut slots: Vec<Option<Result<T, JoinError>>> = (0..n).map(|_| None).collect();
+
+    let mut finished = stream::iter(items.into_iter().enumerate())
+        .map(|(idx, item)| {
+            let handle = tokio::spawn(task(item));
+            async move { (idx, handle.await) }
+        })
+        .buffer_unordered(limit.max(1));
+
+    while let Some((idx, outcome)) = finished.next().await {
+        slots[idx] = Some(outcome);
+    }
+
+    slots
+        .into_iter()
+        .map(|slot| slot.expect("every index is yielded exactly once"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn keeps_input_order_and_bounds_concurrency() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let items: Vec<u64> = vec![30, 5, 20, 1, 10];
+
+        let out = run_indexed(items.clone(), 2, |delay| {
+            let in_flight = in_flight.clone();
+            let peak = peak.clone();
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                delay * 2
+            }
+        })
+        .await;
+
+        let values: Vec<u64> = out.into_iter().map(Result::unwrap).collect();
+        assert_eq!(values, vec![60, 10, 40, 2, 20]);
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[tokio::test]
+    async fn panic_lands_on_its_own_item() {
+        let out = run_indexed(vec!["a", "boom", "c"], 3, |word| async move {
+            if word == "boom" {
+                panic!("task failed");
+            }
+            word.to_uppercase()
+        })
+        .await;
+
+        assert_eq!(out[0].as_ref().unwrap(), "A");
+        assert!(out[1].as_ref().unwrap_err().is_panic());
+        assert_eq!(out[2].as_ref().unwrap(), "C");
+    }
+}
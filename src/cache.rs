@@ -0,0 +1,395 @@
+use crate::config::CasePolicy;
+use parking_lot::{Mutex, RwLock};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Most inputs the negative cache tracks at once, so a stream of distinct bad
+/// inputs can't grow it without bound.
+const NEGATIVE_CAPACITY: usize = 10_000;
+
+/// A validated entry plus the provenance needed to decide when to evict it.
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    pub value: Value,
+    pub model: String,
+    pub schema_version: String,
+    pub created_at: SystemTime,
+}
+
+/// Filters for bulk eviction; every field that is set must match.
+#[derive(Debug, Default, Deserialize)]
+pub struct PurgeFilter {
+    pub model: Option<String>,
+    pub schema_version: Option<String>,
+    pub older_than_secs: Option<u64>,
+}
+
+impl PurgeFilter {
+    fn matches(&self, entry: &CacheEntry, now: SystemTime) -> bool {
+        if let Some(model) = &self.model {
+            if &entry.model != model {
+                return false;
+            }
+        }
+        if let Some(version) = &self.schema_version {
+            if &entry.schema_version != version {
+                return false;
+            }
+        }
+        if let Some(secs) = self.older_than_secs {
+            let age = now.duration_since(entry.created_at).unwrap_or_default();
+            if age < Duration::from_secs(secs) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Failure history for an input that could not be turned into a valid entry.
+#[derive(Debug, Clone)]
+struct NegativeEntry {
+    failures: u32,
+    last_failure: Instant,
+}
+
+/// Result of a cache lookup, distinguishing expired entries that may still be served.
+#[derive(Debug)]
+pub enum Lookup {
+    Fresh(CacheEntry),
+    Stale(CacheEntry),
+    Miss,
+}
+
+/// In-memory cache of validated word entries keyed by surface word.
+pub struct WordCache {
+    entries: RwLock<HashMap<String, CacheEntry>>,
+    refreshing: Mutex<HashSet<String>>,
+    capacity: usize,
+    ttl: Option<Duration>,
+    serve_stale: bool,
+    negative: Mutex<HashMap<String, NegativeEntry>>,
+    negative_ttl: Duration,
+    negative_threshold: u32,
+    negative_capacity: usize,
+    case_policy: CasePolicy,
+}
+
+impl WordCache {
+    /// A capacity of 0 disables caching entirely; a `ttl` of `None` never expires entries.
+    /// With `serve_stale`, expired entries are still returned (as [`Lookup::Stale`]) so the
+    /// caller can answer immediately and refresh in the background.
+    pub fn new(capacity: usize, ttl: Option<Duration>, serve_stale: bool) -> Self {
+        Self {
+            entries: RwLock::new(HashMap::new()),
+            refreshing: Mutex::new(HashSet::new()),
+            capacity,
+            ttl,
+            serve_stale,
+            negative: Mutex::new(HashMap::new()),
+            negative_ttl: Duration::ZERO,
+            negative_threshold: 0,
+            negative_capacity: NEGATIVE_CAPACITY,
+            case_policy: CasePolicy::default(),
+        }
+    }
+
+    /// Remember inputs that failed `threshold` times in a row for `ttl`, so repeated
+    /// requests for them are rejected without another inference. A threshold of 0 disables it.
+    pub fn with_negative_caching(mut self, ttl: Duration, threshold: u32) -> Self {
+        self.negative_ttl = ttl;
+        self.negative_threshold = threshold;
+        self
+    }
+
+    /// Key entries by `policy`, so differently cased requests can share one.
+    pub fn with_case_policy(mut self, policy: CasePolicy) -> Self {
+        self.case_policy = policy;
+        self
+    }
+
+    pub fn key(&self, word: &str) -> String {
+        self.case_policy.key(word)
+    }
+
+    pub fn get(&self, word: &str) -> Option<CacheEntry> {
+        self.entries.read().get(&self.key(word)).cloned()
+    }
+
+    /// Look up an entry, reporting whether it has outlived the configured TTL.
+    pub fn lookup(&self, word: &str) -> Lookup {
+        match self.get(word) {
+            Some(entry) if self.is_expired(&entry) => {
+                if self.serve_stale {
+                    Lookup::Stale(entry)
+                } else {
+                    Lookup::Miss
+                }
+            }
+            Some(entry) => Lookup::Fresh(entry),
+            None => Lookup::Miss,
+        }
+    }
+
+    fn is_expired(&self, entry: &CacheEntry) -> bool {
+        match self.ttl {
+            Some(ttl) => entry
+                .created_at
+                .elapsed()
+                .map(|age| age >= ttl)
+                .unwrap_or(false),
+            None => false,
+        }
+    }
+
+    /// Claim the background refresh for a word. Returns false if one is already running.
+    pub fn begin_refresh(&self, word: &str) -> bool {
+        self.refreshing.lock().insert(self.key(word))
+    }
+
+    pub fn end_refresh(&self, word: &str) {
+        self.refreshing.lock().remove(&self.key(word));
+    }
+
+    pub fn insert(&self, word: &str, value: Value, model: &str, schema_version: &str) {
+        if self.capacity == 0 {
+            return;
+        }
+        let key = self.key(word);
+        let mut entries = self.entries.write();
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            // Evict the oldest entry to stay within capacity
+            if let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, e)| e.created_at)
+                .map(|(k, _)| k.clone())
+            {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(
+            key,
+            CacheEntry {
+                value,
+                model: model.to_string(),
+                schema_version: schema_version.to_string(),
+                created_at: SystemTime::now(),
+            },
+        );
+    }
+
+    pub fn remove(&self, word: &str) -> Option<CacheEntry> {
+        self.negative.lock().remove(&self.key(word));
+        self.entries.write().remove(&self.key(word))
+    }
+
+    /// Record that analysing this input produced an unusable result.
+    pub fn record_failure(&self, word: &str) {
+        if self.negative_threshold == 0 {
+            return;
+        }
+        let key = self.key(word);
+        let mut negative = self.negative.lock();
+        let now = Instant::now();
+        if negative.len() >= self.negative_capacity && !negative.contains_key(&key) {
+            // Make room: first forget inputs whose failures have expired, then the stalest
+            negative.retain(|_, e| now.duration_since(e.last_failure) < self.negative_ttl);
+            if negative.len() >= self.negative_capacity {
+                if let Some(stalest) = negative
+                    .iter()
+                    .min_by_key(|(_, e)| e.last_failure)
+                    .map(|(k, _)| k.clone())
+                {
+                    negative.remove(&stalest);
+                }
+            }
+        }
+        let entry = negative.entry(key).or_insert(NegativeEntry {
+            failures: 0,
+            last_failure: now,
+        });
+        if now.duration_since(entry.last_failure) >= self.negative_ttl {
+            entry.failures = 0;
+        }
+        entry.failures += 1;
+        entry.last_failure = now;
+    }
+
+    /// Whether the input has failed often enough, recently enough, to skip inference.
+    pub fn is_known_bad(&self, word: &str) -> bool {
+        if self.negative_threshold == 0 {
+            return false;
+        }
+        let mut negative = self.negative.lock();
+        let key = self.key(word);
+        match negative.get(&key) {
+            Some(e) if e.last_failure.elapsed() >= self.negative_ttl => {
+                negative.remove(&key);
+                false
+            }
+            Some(e) => e.failures >= self.negative_threshold,
+            None => false,
+        }
+    }
+
+    /// Remove every entry matching the filter, returning how many were evicted.
+    pub fn purge(&self, filter: &PurgeFilter) -> usize {
+        let now = SystemTime::now();
+        let mut entries = self.entries.write();
+        let before = entries.len();
+        entries.retain(|_, e| !filter.matches(e, now));
+        self.negative.lock().clear();
+        before - entries.len()
+    }
+
+    /// Every cached entry with its key, e.g. to persist what is only in memory.
+    pub fn snapshot(&self) -> Vec<(String, CacheEntry)> {
+        self.entries
+            .read()
+            .iter()
+            .map(|(k, e)| (k.clone(), e.clone()))
+            .collect()
+    }
+
+    pub fn entry_count(&self) -> usize {
+        self.entries.read().len()
+    }
+
+    /// The `limit` entries scoring highest under `score`, best first. Entries
+    /// scoring 0 are never returned; only the winners are cloned.
+    pub fn best_matches(
+        &self,
+        limit: usize,
+        score: impl Fn(&str, &CacheEntry) -> u32,
+    ) -> Vec<(String, Value)> {
+        let entries = self.entries.read();
+        let mut scored: Vec<(u32, &String)> = entries
+            .iter()
+            .map(|(k, e)| (score(k, e), k))
+            .filter(|(s, _)| *s > 0)
+            .collect();
+        // Key order breaks ties so the choice doesn't depend on hash order
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+        scored
+            .into_iter()
+            .take(limit)
+            .map(|(_, k)| (k.clone(), entries[k].value.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn purge_filters_by_model_and_version() {
+        let cache = WordCache::new(10, None, false);
+        cache.insert("a", json!({}), "old-model", "1");
+        cache.insert("b", json!({}), "new-model", "1");
+        cache.insert("c", json!({}), "old-model", "2");
+
+        let filter = PurgeFilter {
+            model: Some("old-model".into()),
+            schema_version: Some("1".into()),
+            older_than_secs: None,
+        };
+        assert_eq!(cache.purge(&filter), 1);
+        assert!(cache.get("a").is_none());
+        assert_eq!(cache.entry_count(), 2);
+
+        assert_eq!(cache.purge(&PurgeFilter::default()), 2);
+        assert_eq!(cache.entry_count(), 0);
+    }
+
+    #[test]
+    fn capacity_evicts_oldest() {
+        let cache = WordCache::new(2, None, false);
+        cache.insert("a", json!(1), "m", "1");
+        std::thread::sleep(Duration::from_millis(2));
+        cache.insert("b", json!(2), "m", "1");
+        cache.insert("c", json!(3), "m", "1");
+        assert_eq!(cache.entry_count(), 2);
+        assert!(cache.get("a").is_none());
+    }
+
+    #[test]
+    fn expired_entries_are_stale_and_refresh_is_exclusive() {
+        let cache = WordCache::new(10, Some(Duration::ZERO), true);
+        cache.insert("a", json!(1), "m", "1");
+        assert!(matches!(cache.lookup("a"), Lookup::Stale(_)));
+        let strict = WordCache::new(10, Some(Duration::ZERO), false);
+        strict.insert("a", json!(1), "m", "1");
+        assert!(matches!(strict.lookup("a"), Lookup::Miss));
+        assert!(matches!(cache.lookup("b"), Lookup::Miss));
+
+        assert!(cache.begin_refresh("a"));
+        assert!(!cache.begin_refresh("a"));
+        cache.end_refresh("a");
+        assert!(cache.begin_refresh("a"));
+    }
+
+    #[test]
+    fn negative_cache_trips_after_threshold() {
+        let cache =
+            WordCache::new(10, None, false).with_negative_caching(Duration::from_secs(60), 2);
+        cache.record_failure("zzxq");
+        assert!(!cache.is_known_bad("zzxq"));
+        cache.record_failure("zzxq");
+        assert!(cache.is_known_bad("zzxq"));
+        cache.remove("zzxq");
+        assert!(!cache.is_known_bad("zzxq"));
+
+        let expired = WordCache::new(10, None, false).with_negative_caching(Duration::ZERO, 1);
+        expired.record_failure("zzxq");
+        assert!(!expired.is_known_bad("zzxq"));
+    }
+
+    #[test]
+    fn negative_cache_stays_bounded() {
+        let mut cache =
+            WordCache::new(10, None, false).with_negative_caching(Duration::from_secs(60), 1);
+        cache.negative_capacity = 3;
+        for i in 0..50 {
+            cache.record_failure(&format!("bad{i}"));
+        }
+        assert_eq!(cache.negative.lock().len(), 3);
+        // The most recent failures are the ones kept
+        assert!(cache.is_known_bad("bad49"));
+        assert!(!cache.is_known_bad("bad0"));
+
+        let mut expired = WordCache::new(10, None, false).with_negative_caching(Duration::ZERO, 1);
+        expired.negative_capacity = 3;
+        for i in 0..4 {
+            expired.record_failure(&format!("bad{i}"));
+        }
+        // Expired failures are swept out before anything recent is evicted
+        assert_eq!(expired.negative.lock().len(), 1);
+    }
+
+    #[test]
+    fn case_policy_decides_which_casings_share_an_entry() {
+        let distinct = WordCache::new(10, None, false);
+        distinct.insert("Polish", json!(1), "m", "1");
+        assert!(distinct.get("polish").is_none());
+
+        let folded = WordCache::new(10, None, false).with_case_policy(CasePolicy::Fold);
+        folded.insert("Polish", json!(1), "m", "1");
+        assert!(folded.get(" polish ").is_some());
+        assert!(folded.begin_refresh("POLISH"));
+        assert!(!folded.begin_refresh("polish"));
+
+        assert_eq!(CasePolicy::Fold.surface(Some("Polish"), "polish"), "polish");
+        assert_eq!(
+            CasePolicy::Preserve.surface(Some("Polish"), "polish"),
+            "Polish"
+        );
+        assert_eq!(
+            CasePolicy::Preserve.surface(Some("Poland"), "polish"),
+            "polish"
+        );
+    }
+}
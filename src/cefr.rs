@@ -0,0 +1,136 @@
+use crate::config::CefrMode;
+use clap::ValueEnum;
+use once_cell::sync::Lazy;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// A Common European Framework of Reference level.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    #[value(name = "A1")]
+    A1,
+    #[value(name = "A2")]
+    A2,
+    #[value(name = "B1")]
+    B1,
+    #[value(name = "B2")]
+    B2,
+    #[value(name = "C1")]
+    C1,
+    #[value(name = "C2")]
+    C2,
+}
+
+impl Level {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_uppercase().as_str() {
+            "A1" => Some(Self::A1),
+            "A2" => Some(Self::A2),
+            "B1" => Some(Self::B1),
+            "B2" => Some(Self::B2),
+            "C1" => Some(Self::C1),
+            "C2" => Some(Self::C2),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::A1 => "A1",
+            Self::A2 => "A2",
+            Self::B1 => "B1",
+            Self::B2 => "B2",
+            Self::C1 => "C1",
+            Self::C2 => "C2",
+        }
+    }
+
+    /// The contract's three-level `difficulty` this level falls under.
+    pub fn difficulty(self) -> &'static str {
+        match self {
+            Self::A1 | Self::A2 => "beginner",
+            Self::B1 | Self::B2 => "intermediate",
+            Self::C1 | Self::C2 => "advanced",
+        }
+    }
+
+    /// Lowest level of a `difficulty` band, for words the list does not cover.
+    pub fn from_difficulty(difficulty: &str) -> Option<Self> {
+        match difficulty {
+            "beginner" => Some(Self::A1),
+            "intermediate" => Some(Self::B1),
+            "advanced" => Some(Self::C1),
+            _ => None,
+        }
+    }
+}
+
+static LISTED: Lazy<HashMap<&'static str, Level>> = Lazy::new(|| {
+    include_str!("../data/cefr_words.tsv")
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let (word, level) = line.split_once('\t')?;
+            Some((word.trim(), Level::parse(level)?))
+        })
+        .collect()
+});
+
+/// Level of `lemma` in the embedded word list, if it is listed.
+pub fn listed(lemma: &str) -> Option<Level> {
+    LISTED.get(lemma.trim().to_lowercase().as_str()).copied()
+}
+
+/// Every listed word at `level`, in alphabetical order.
+pub fn words_at(level: Level) -> Vec<String> {
+    let mut words: Vec<String> = LISTED
+        .iter()
+        .filter(|(_, listed)| **listed == level)
+        .map(|(word, _)| word.to_string())
+        .collect();
+    words.sort();
+    words
+}
+
+/// Level of a validated entry: its lemma's listed level, else the bottom of
+/// its `difficulty` band.
+pub fn level_of(entry: &Value) -> Option<Level> {
+    entry["baseForm"].as_str().and_then(listed).or_else(|| {
+        entry["difficulty"]
+            .as_str()
+            .and_then(Level::from_difficulty)
+    })
+}
+
+/// Add the CEFR level to an entry on its way out, as `cefr` or in place of `difficulty`.
+pub fn apply(mut entry: Value, mode: CefrMode) -> Value {
+    let field = match mode {
+        CefrMode::Off => return entry,
+        CefrMode::Augment => "cefr",
+        CefrMode::Replace => "difficulty",
+    };
+    if let (Some(level), Some(obj)) = (level_of(&entry), entry.as_object_mut()) {
+        obj.insert(field.to_string(), Value::String(level.as_str().to_string()));
+    }
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn listed_words_win_over_the_band() {
+        assert_eq!(listed("Decide"), Some(Level::A2));
+        assert_eq!(listed("zyzzyva"), None);
+
+        let entry = json!({ "baseForm": "decide", "difficulty": "beginner" });
+        assert_eq!(apply(entry.clone(), CefrMode::Augment)["cefr"], "A2");
+        assert_eq!(apply(entry.clone(), CefrMode::Replace)["difficulty"], "A2");
+        assert_eq!(apply(entry.clone(), CefrMode::Off), entry);
+
+        let unlisted = json!({ "baseForm": "zyzzyva", "difficulty": "advanced" });
+        assert_eq!(apply(unlisted, CefrMode::Augment)["cefr"], "C1");
+    }
+}
@@ -0,0 +1,597 @@
+use crate::config::{BackendKind, Config, GPU_BACKEND};
+use crate::contract::ContractProfiles;
+use crate::model::device::DeviceSpec;
+use crate::model::{gguf, prompt, PromptParts};
+use jsonschema::{Draft, JSONSchema};
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::Path;
+
+/// Tokens the llama backend keeps free beyond `max_tokens` for end-of-generation.
+const GENERATION_SLACK: usize = 8;
+
+/// Result of rendering the prompt for a sample word and measuring it.
+#[derive(Debug)]
+pub struct TemplateReport {
+    pub word: String,
+    /// Token count per section, in send order
+    pub sections: Vec<(&'static str, usize)>,
+    pub total: usize,
+    /// True when no model tokenizer was available and counts are approximate
+    pub estimated: bool,
+    pub n_ctx: usize,
+    pub max_tokens: usize,
+    /// Template placeholders left unsubstituted in the rendered prompt
+    pub placeholders: Vec<String>,
+    pub word_included: bool,
+    /// Optional sections the budgeter would drop to make room for the answer
+    pub dropped: Vec<&'static str>,
+}
+
+impl TemplateReport {
+    /// The whole prompt plus a full-length answer fits in the context.
+    pub fn fits(&self) -> bool {
+        self.total + self.max_tokens + GENERATION_SLACK <= self.n_ctx
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.fits() && self.placeholders.is_empty() && self.word_included
+    }
+}
+
+/// Render `parts` and measure it against `n_ctx`. `count_tokens` is the
+/// model's tokenizer when one is loaded; otherwise a chars/4 estimate is used.
+pub fn template(
+    parts: &PromptParts,
+    n_ctx: usize,
+    max_tokens: usize,
+    count_tokens: impl Fn(&str) -> Option<usize>,
+) -> TemplateReport {
+    let sections = prompt::sections(parts);
+    let estimated = count_tokens("").is_none();
+    let count = |text: &str| count_tokens(text).unwrap_or_else(|| text.chars().count().div_ceil(4));
+
+    let measured: Vec<_> = sections.iter().map(|s| (s.name, count(&s.text))).collect();
+    let total = measured.iter().map(|(_, n)| n).sum();
+    let rendered = prompt::render(parts);
+
+    // Same reserve as the llama backend, so "dropped" matches what serving would do
+    let reserve = max_tokens.min(n_ctx / 2) + GENERATION_SLACK;
+    let dropped = prompt::fit_to_budget(sections, n_ctx.saturating_sub(reserve), count)
+        .map(|b| b.dropped)
+        .unwrap_or_default();
+
+    TemplateReport {
+        word: parts.user_word.clone(),
+        sections: measured,
+        total,
+        estimated,
+        n_ctx,
+        max_tokens,
+        placeholders: placeholders(&rendered),
+        word_included: rendered.contains(&parts.user_word),
+        dropped,
+    }
+}
+
+/// `{name}`, `{{ name }}` and `${NAME}` markers: a template expecting
+/// substitution that never happened.
+fn placeholders(text: &str) -> Vec<String> {
+    let mut found: Vec<String> = Vec::new();
+    let mut from = 0;
+    while let Some(offset) = text[from..].find('{') {
+        let open = from + offset;
+        from = open + 1;
+        let (prefix, suffix) = if text[open..].starts_with("{{") {
+            ("{{", "}}")
+        } else {
+            ("{", "}")
+        };
+        let body = open + prefix.len();
+        let Some(len) = text[body..].find(suffix) else {
+            continue;
+        };
+        let name = text[body..body + len].trim();
+        // JSON in examples and the contract has quotes and colons, never a bare name
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            continue;
+        }
+        let start = if text[..open].ends_with('$') {
+            open - 1
+        } else {
+            open
+        };
+        let end = body + len + suffix.len();
+        if !found.iter().any(|m| m == &text[start..end]) {
+            found.push(text[start..end].to_string());
+        }
+        from = end;
+    }
+    found
+}
+
+impl fmt::Display for TemplateReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = if self.estimated {
+            "tokens (estimated, no tokenizer)"
+        } else {
+            "tokens"
+        };
+        writeln!(f, "Prompt for sample word {:?}:", self.word)?;
+        for (name, tokens) in &self.sections {
+            writeln!(f, "  {:<22} {:>6}", name, tokens)?;
+        }
+        writeln!(f, "  {:<22} {:>6} {}", "total", self.total, unit)?;
+        writeln!(
+            f,
+            "Context: {} prompt + {} max_tokens + {} slack vs n_ctx {}: {}",
+            self.total,
+            self.max_tokens,
+            GENERATION_SLACK,
+            self.n_ctx,
+            if self.fits() { "fits" } else { "DOES NOT FIT" }
+        )?;
+        if !self.dropped.is_empty() {
+            writeln!(f, "Would drop when serving: {}", self.dropped.join(", "))?;
+        }
+        if !self.word_included {
+            writeln!(f, "Sample word does not appear in the rendered prompt")?;
+        }
+        if !self.placeholders.is_empty() {
+            writeln!(
+                f,
+                "Unsubstituted placeholders: {}",
+                self.placeholders.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// How serious a configuration finding is; any `Error` fails the check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Ok,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub level: Level,
+    pub subject: &'static str,
+    pub message: String,
+}
+
+/// Result of validating the merged CLI/env/.env configuration.
+#[derive(Debug, Default)]
+pub struct ConfigReport {
+    pub findings: Vec<Finding>,
+}
+
+impl ConfigReport {
+    pub fn is_ok(&self) -> bool {
+        self.findings.iter().all(|f| f.level != Level::Error)
+    }
+
+    fn push(&mut self, level: Level, subject: &'static str, message: impl Into<String>) {
+        self.findings.push(Finding {
+            level,
+            subject,
+            message: message.into(),
+        });
+    }
+}
+
+const MIB: u64 = 1024 * 1024;
+
+/// Validate `cfg` without loading the model or binding the port. `vram_mb`
+/// is the GPU memory available for offload, when known. `schema_src` is the
+/// embedded schema, checked unless SCHEMA_FILE replaces it.
+pub fn config(cfg: &Config, schema_src: &str, vram_mb: Option<u64>) -> ConfigReport {
+    let mut report = ConfigReport::default();
+
+    match cfg.bind_addr.parse::<SocketAddr>() {
+        Ok(addr) => report.push(Level::Ok, "bind", format!("will listen on {}", addr)),
+        Err(e) => report.push(
+            Level::Error,
+            "bind",
+            format!(
+                "BIND_ADDR {:?} is not an address like 0.0.0.0:8080: {}",
+                cfg.bind_addr, e
+            ),
+        ),
+    }
+
+    let from_file = cfg
+        .schema_file
+        .as_ref()
+        .map(|path| (path, std::fs::read_to_string(path)));
+    let schema_src = match &from_file {
+        None => schema_src,
+        Some((_, Ok(src))) => src.as_str(),
+        Some((path, Err(e))) => {
+            report.push(
+                Level::Error,
+                "schema",
+                format!("SCHEMA_FILE {:?} is not readable: {}", path, e),
+            );
+            ""
+        }
+    };
+    match serde_json::from_str(schema_src) {
+        Err(_) if schema_src.is_empty() => {}
+        Err(e) => report.push(
+            Level::Error,
+            "schema",
+            format!("word contract schema is not valid JSON: {}", e),
+        ),
+        Ok(schema) => match JSONSchema::options()
+            .with_draft(Draft::Draft202012)
+            .compile(&schema)
+        {
+            Ok(_) => report.push(Level::Ok, "schema", "word contract schema compiles"),
+            Err(e) => report.push(
+                Level::Error,
+                "schema",
+                format!("word contract schema does not compile: {}", e),
+            ),
+        },
+    }
+
+    // The schema's enums are widened to whatever the profiles allow
+    if let Some(path) = &cfg.contract_profiles_file {
+        match ContractProfiles::load(path) {
+            Ok(profiles) => report.push(
+                Level::Ok,
+                "contract",
+                format!(
+                    "contract profiles for {}",
+                    profiles.languages().collect::<Vec<_>>().join(", ")
+                ),
+            ),
+            Err(e) => report.push(Level::Error, "contract", format!("{:#}", e)),
+        }
+    }
+
+    match cfg.backend {
+        BackendKind::Llama => check_llama(cfg, vram_mb, &mut report),
+        BackendKind::Openai | BackendKind::Ollama => match &cfg.backend_model {
+            Some(model) => report.push(
+                Level::Ok,
+                "backend",
+                format!("{:?} model {}", cfg.backend, model),
+            ),
+            None => report.push(
+                Level::Error,
+                "backend",
+                "BACKEND_MODEL is required for the openai and ollama backends",
+            ),
+        },
+        BackendKind::Upstream => match &cfg.backend_url {
+            Some(url) => report.push(Level::Ok, "backend", format!("upstream {}", url)),
+            None => report.push(
+                Level::Error,
+                "backend",
+                "BACKEND_URL is required for the upstream backend",
+            ),
+        },
+        BackendKind::Mock => report.push(Level::Ok, "backend", "mock backend; no model needed"),
+    }
+
+    if let Some(path) = &cfg.system_prompt_file {
+        match std::fs::read_to_string(path) {
+            Ok(text) if text.trim().is_empty() => report.push(
+                Level::Error,
+                "system_prompt",
+                format!("SYSTEM_PROMPT_FILE {:?} is empty", path),
+            ),
+            Ok(_) => report.push(Level::Ok, "system_prompt", format!("read from {:?}", path)),
+            Err(e) => report.push(
+                Level::Error,
+                "system_prompt",
+                format!("SYSTEM_PROMPT_FILE {:?} is not readable: {}", path, e),
+            ),
+        }
+    }
+    if let Some(dir) = &cfg.few_shot_dir {
+        if !Path::new(dir).is_dir() {
+            report.push(
+                Level::Error,
+                "few_shot",
+                format!("FEW_SHOT_DIR {:?} is not a directory", dir),
+            );
+        }
+    }
+    report
+}
+
+fn check_llama(cfg: &Config, vram_mb: Option<u64>, report: &mut ConfigReport) {
+    if !cfg!(feature = "llama") {
+        report.push(
+            Level::Error,
+            "backend",
+            "this build does not include the llama backend; rebuild with --features llama",
+        );
+    }
+    let Some(path) = &cfg.model_path else {
+        report.push(
+            Level::Error,
+            "model",
+            "MODEL_PATH is required for the llama backend",
+        );
+        return;
+    };
+    let info = match gguf::read_info(path) {
+        Ok(info) => info,
+        Err(e) => {
+            report.push(
+                Level::Error,
+                "model",
+                format!("{:#}; point MODEL_PATH at a readable .gguf file", e),
+            );
+            return;
+        }
+    };
+    report.push(
+        Level::Ok,
+        "model",
+        format!(
+            "GGUF v{}, {}, {} layers, {} MiB",
+            info.version,
+            info.architecture
+                .as_deref()
+                .unwrap_or("unknown architecture"),
+            info.block_count.map_or("?".to_string(), |n| n.to_string()),
+            info.file_size / MIB
+        ),
+    );
+
+    match (&cfg.device, GPU_BACKEND) {
+        (Some(spec @ DeviceSpec::Gpu { .. }), None) => report.push(
+            Level::Error,
+            "build",
+            format!(
+                "DEVICE={} needs a GPU backend this binary lacks; rebuild with --features cuda, vulkan or metal",
+                spec
+            ),
+        ),
+        (Some(DeviceSpec::Gpu { backend, .. }), Some(gpu)) if !backend.eq_ignore_ascii_case(gpu) => {
+            report.push(
+                Level::Warning,
+                "build",
+                format!(
+                    "DEVICE asks for {} but this binary was built for {}; see `lingua-fast devices`",
+                    backend, gpu
+                ),
+            )
+        }
+        _ => {}
+    }
+    if cfg.device == Some(DeviceSpec::Cpu) {
+        report.push(Level::Ok, "gpu", "DEVICE=cpu; nothing is offloaded");
+        return;
+    }
+    if GPU_BACKEND.is_none()
+        && cfg.device.is_none()
+        && (cfg.n_gpu_layers > 0 || cfg.auto_gpu_layers)
+    {
+        report.push(
+            Level::Warning,
+            "build",
+            "this binary has no GPU backend, so offloaded layers run on the CPU; rebuild with --features cuda, vulkan or metal",
+        );
+    }
+    if info.layer_bytes.is_empty() {
+        report.push(
+            Level::Warning,
+            "gpu",
+            "model does not report its layer count; cannot estimate VRAM use",
+        );
+        return;
+    }
+    let n_ctx = cfg.n_ctx.max(0) as u64;
+    if cfg.auto_gpu_layers {
+        match vram_mb {
+            Some(vram_mb) => report.push(
+                Level::Ok,
+                "gpu",
+                format!(
+                    "AUTO_GPU_LAYERS will offload {} layers into {} MiB of free VRAM",
+                    info.max_gpu_layers(vram_mb * MIB, n_ctx),
+                    vram_mb
+                ),
+            ),
+            None => report.push(
+                Level::Warning,
+                "gpu",
+                format!(
+                    "AUTO_GPU_LAYERS cannot detect VRAM and will fall back to N_GPU_LAYERS={}; set VRAM_MB",
+                    cfg.n_gpu_layers
+                ),
+            ),
+        }
+        return;
+    }
+
+    let requested = cfg.n_gpu_layers.max(0) as u32;
+    if requested == 0 {
+        report.push(Level::Ok, "gpu", "N_GPU_LAYERS=0; CPU-only inference");
+        return;
+    }
+    let needed_mb = info.offload_bytes(requested, n_ctx).div_ceil(MIB);
+    let Some(vram_mb) = vram_mb else {
+        report.push(
+            Level::Warning,
+            "gpu",
+            format!(
+                "could not detect VRAM; N_GPU_LAYERS={} needs about {} MiB (set VRAM_MB to check)",
+                requested, needed_mb
+            ),
+        );
+        return;
+    };
+    let fitting = info.max_gpu_layers(vram_mb * MIB, n_ctx);
+    if requested > fitting {
+        report.push(
+            Level::Error,
+            "gpu",
+            format!(
+                "N_GPU_LAYERS={} needs about {} MiB with a {}-token KV cache but {} MiB of VRAM is free; lower N_GPU_LAYERS to {} or set AUTO_GPU_LAYERS=true",
+                requested, needed_mb, n_ctx, vram_mb, fitting
+            ),
+        );
+    } else {
+        report.push(
+            Level::Ok,
+            "gpu",
+            format!(
+                "N_GPU_LAYERS={} needs about {} of {} MiB free",
+                requested, needed_mb, vram_mb
+            ),
+        );
+    }
+}
+
+/// Free memory of the first NVIDIA GPU via `nvidia-smi`; `None` when there
+/// is none or the tool is missing.
+pub fn detect_vram_mb() -> Option<u64> {
+    let out = std::process::Command::new("nvidia-smi")
+        .args(["--query-gpu=memory.free", "--format=csv,noheader,nounits"])
+        .output()
+        .ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .next()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+impl fmt::Display for ConfigReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for finding in &self.findings {
+            let level = match finding.level {
+                Level::Ok => "ok",
+                Level::Warning => "warn",
+                Level::Error => "ERROR",
+            };
+            writeln!(
+                f,
+                "{:<5} {:<14} {}",
+                level, finding.subject, finding.message
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::PromptTask;
+
+    fn parts(system: &str) -> PromptParts {
+        PromptParts {
+            system: system.to_string(),
+            user_word: "communicated".to_string(),
+            examples: Vec::new(),
+            context: None,
+            contract: Default::default(),
+            task: PromptTask::Entry,
+        }
+    }
+
+    #[test]
+    fn reports_fit_and_leftover_placeholders() {
+        let words = |s: &str| Some(s.split_whitespace().count());
+        let report = template(&parts("Be concise."), 4096, 1024, words);
+        assert!(report.is_ok(), "{}", report);
+        assert!(!report.estimated);
+
+        let report = template(
+            &parts("Annotate {word} for {{ audience }} in ${LANG}."),
+            4096,
+            1024,
+            words,
+        );
+        assert_eq!(
+            report.placeholders,
+            vec!["{word}", "{{ audience }}", "${LANG}"]
+        );
+        assert!(!report.is_ok());
+
+        let report = template(&parts("Be concise."), 1024, 1024, |_| None);
+        assert!(report.estimated);
+        assert!(!report.fits());
+    }
+
+    fn cfg(args: &[&str]) -> Config {
+        use clap::Parser;
+        Config::try_parse_from(std::iter::once("lingua-fast").chain(args.iter().copied())).unwrap()
+    }
+
+    fn level_of(report: &ConfigReport, subject: &str) -> Level {
+        report
+            .findings
+            .iter()
+            .find(|f| f.subject == subject)
+            .unwrap()
+            .level
+    }
+
+    #[test]
+    fn config_check_flags_actionable_problems() {
+        let schema = include_str!("../schema/word_contract.schema.json");
+        let report = config(
+            &cfg(&[
+                "--backend",
+                "llama",
+                "--MODEL_PATH",
+                "/nonexistent/model.gguf",
+                "--bind-addr",
+                "localhost",
+            ]),
+            schema,
+            None,
+        );
+        assert!(!report.is_ok());
+        assert_eq!(level_of(&report, "bind"), Level::Error);
+        assert_eq!(level_of(&report, "schema"), Level::Ok);
+        assert_eq!(level_of(&report, "model"), Level::Error);
+        assert_eq!(
+            level_of(&config(&cfg(&["--backend", "mock"]), "{", None), "schema"),
+            Level::Error
+        );
+
+        // 32 layers of 256 KiB: 90% of 4 MiB holds 14 of them
+        let dir = std::env::temp_dir().join(format!("lingua-check-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let model = dir.join("model.gguf");
+        std::fs::write(&model, gguf::fake_model("llama", 32, 256 * 1024)).unwrap();
+        let args = [
+            "--backend",
+            "llama",
+            "--MODEL_PATH",
+            model.to_str().unwrap(),
+            "--n-gpu-layers",
+            "32",
+        ];
+        let report = config(&cfg(&args), schema, Some(4));
+        assert_eq!(level_of(&report, "model"), Level::Ok);
+        assert_eq!(level_of(&report, "gpu"), Level::Error);
+        assert!(
+            report.to_string().contains("lower N_GPU_LAYERS to 14"),
+            "{}",
+            report
+        );
+        assert_eq!(
+            level_of(&config(&cfg(&args), schema, Some(64)), "gpu"),
+            Level::Ok
+        );
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,235 @@
+//! Typed HTTP client for a running lingua-fast server.
+//!
+//! Requests and responses use the same types the server serializes, so a
+//! consumer never hand-rolls JSON against the API:
+//!
+//! ```no_run
+//! # async fn demo() -> Result<(), lingua_fast::client::ClientError> {
+//! use lingua_fast::client::LinguaClient;
+//!
+//! let client = LinguaClient::new("http://127.0.0.1:8080");
+//! let entry = client.word("serendipity").await?;
+//! println!("{}", entry["baseForm"]);
+//!
+//! let batch = client.words(&["run", "swim"]).await?;
+//! println!("{} of {} succeeded", batch.summary.succeeded, batch.summary.total);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::api::{BatchResponse, ErrorResponse};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    /// The server answered with its error body
+    #[error("{status}: {} ({})", .body.error, .body.code)]
+    Api {
+        status: StatusCode,
+        body: ErrorResponse,
+    },
+    /// The request never completed, or the response was not what the API sends
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+}
+
+impl ClientError {
+    /// Whether the server suggested retrying; transport errors always may be retried.
+    pub fn retry_suggested(&self) -> bool {
+        match self {
+            Self::Api { body, .. } => body.retry_suggested,
+            Self::Http(_) => true,
+        }
+    }
+}
+
+/// Options for [`LinguaClient::words_with`].
+#[derive(Debug, Clone, Default)]
+pub struct BatchOptions {
+    /// Words analyzed at once; the server caps it at its own limit.
+    pub concurrency: Option<usize>,
+    /// Also return `by_word`, mapping each successful word to its entry.
+    pub by_word: bool,
+    /// Replaces the server's system prompt, when the server allows overrides.
+    pub system_prompt: Option<String>,
+}
+
+#[derive(Serialize)]
+struct WordBody<'a> {
+    word: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_prompt: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct WordsBody<'a> {
+    words: &'a [&'a str],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_prompt: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    concurrency: Option<usize>,
+}
+
+#[derive(Clone)]
+pub struct LinguaClient {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+impl LinguaClient {
+    /// `base_url` is the server root, e.g. `http://127.0.0.1:8080`.
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self::with_http_client(reqwest::Client::new(), base_url)
+    }
+
+    /// Reuse a configured `reqwest::Client` (timeouts, proxies, TLS).
+    pub fn with_http_client(http: reqwest::Client, base_url: impl Into<String>) -> Self {
+        let base_url = base_url.into().trim_end_matches('/').to_string();
+        Self { http, base_url }
+    }
+
+    /// Analyze one word via `POST /v1/word`.
+    pub async fn word(&self, word: &str) -> Result<Value, ClientError> {
+        let body = WordBody {
+            word,
+            system_prompt: None,
+        };
+        let res = self
+            .http
+            .post(self.url("/v1/word"))
+            .json(&body)
+            .send()
+            .await?;
+        decode(res, false).await
+    }
+
+    /// Analyze several words via `POST /v1/words`.
+    ///
+    /// Per-word failures are reported in the results, not as an error; this
+    /// includes the 207 and 502 answers, which still carry a full batch body.
+    pub async fn words(&self, words: &[&str]) -> Result<BatchResponse, ClientError> {
+        self.words_with(words, &BatchOptions::default()).await
+    }
+
+    pub async fn words_with(
+        &self,
+        words: &[&str],
+        opts: &BatchOptions,
+    ) -> Result<BatchResponse, ClientError> {
+        let body = WordsBody {
+            words,
+            system_prompt: opts.system_prompt.as_deref(),
+            concurrency: opts.concurrency,
+        };
+        let mut req = self.http.post(self.url("/v1/words")).json(&body);
+        if opts.by_word {
+            req = req.query(&[("result_format", "map")]);
+        }
+        decode(req.send().await?, true).await
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+}
+
+/// Successful bodies decode as `T`; anything else as the server's error body.
+/// `partial_ok` treats 207/502 as batch bodies rather than errors.
+async fn decode<T: DeserializeOwned>(
+    res: reqwest::Response,
+    partial_ok: bool,
+) -> Result<T, ClientError> {
+    let status = res.status();
+    let is_batch_status = status == StatusCode::MULTI_STATUS || status == StatusCode::BAD_GATEWAY;
+    if status.is_success() || (partial_ok && is_batch_status) {
+        return Ok(res.json().await?);
+    }
+    let body = res.json::<ErrorResponse>().await?;
+    Err(ClientError::Api { status, body })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{router, AppState};
+    use crate::cache::WordCache;
+    use crate::daily::DailyWords;
+    use crate::error::ErrorCode;
+    use crate::fewshot::FewShotLibrary;
+    use crate::health::Readiness;
+    use crate::lists::WordLists;
+    use crate::model::{mock::MockBackend, prompt, InferParams};
+    use crate::profile::Profiles;
+    use crate::quota::UsageTracker;
+    use crate::tenant::Tenants;
+    use crate::validate::Validator;
+    use std::sync::Arc;
+
+    async fn serve() -> LinguaClient {
+        let state = AppState {
+            backend: Arc::new(MockBackend::default()),
+            validator: Arc::new(Validator::new("").unwrap()),
+            params: InferParams::default(),
+            cache: Arc::new(WordCache::new(100, None, false)),
+            store: None,
+            admin_token: None,
+            readiness: Arc::new(Readiness::default()),
+            system_prompt: Arc::from(prompt::DEFAULT_SYSTEM),
+            allow_system_prompt_override: false,
+            few_shot: Arc::new(FewShotLibrary::default()),
+            few_shot_count: 0,
+            few_shot_from_cache: false,
+            back_translation_check: false,
+            word_time_budget: None,
+            schema_file: None,
+            batch_concurrency: 4,
+            batch_failure_threshold: 1.0,
+            batch_retry_budget: 0,
+            max_word_chars: 100,
+            profiles: Arc::new(Profiles::default()),
+            usage: Arc::new(UsageTracker::default()),
+            cefr: Default::default(),
+            daily_words: Arc::new(DailyWords::default()),
+            signer: None,
+            tenants: Arc::new(Tenants::default()),
+            trust_tenant_header: false,
+            tenant: None,
+            lists: Arc::new(WordLists::default()),
+            scheduler: None,
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router(state)).await });
+        LinguaClient::new(format!("http://{}/", addr))
+    }
+
+    #[tokio::test]
+    async fn typed_round_trip_against_the_router() {
+        let client = serve().await;
+
+        let entry = client.word("swim").await.unwrap();
+        assert_eq!(entry["baseForm"], "swim");
+
+        let opts = BatchOptions {
+            by_word: true,
+            ..BatchOptions::default()
+        };
+        let batch = client.words_with(&["run", ""], &opts).await.unwrap();
+        assert_eq!(batch.summary.total, 2);
+        assert_eq!(batch.summary.failed, 1);
+        assert!(batch.results[0].ok);
+        assert_eq!(batch.results[1].code, Some(ErrorCode::InvalidInput));
+        assert!(batch.by_word.unwrap().contains_key("run"));
+
+        match client.word("").await.unwrap_err() {
+            ClientError::Api { status, body } => {
+                assert_eq!(status, StatusCode::BAD_REQUEST);
+                assert_eq!(body.code, ErrorCode::InvalidInput);
+            }
+            other => panic!("expected an API error, got {other}"),
+        }
+    }
+}
@@ -1,33 +1,499 @@
-use clap::Parser;
+use crate::cefr::Level;
+use crate::model::device::DeviceSpec;
+use crate::model::preset::ModelFamily;
+use crate::model::prompt;
+use clap::{Parser, Subcommand, ValueEnum};
+
+/// Which inference backend serves requests.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendKind {
+    /// Local GGUF model through llama.cpp
+    Llama,
+    /// Any OpenAI-compatible chat completions server
+    Openai,
+    /// An Ollama server
+    Ollama,
+    /// Another lingua-fast server (BACKEND_URL), answering what is not cached here
+    Upstream,
+    /// Deterministic fake entries; no model or GPU needed
+    Mock,
+}
+
+/// The llama.cpp GPU backend compiled in, from the `cuda`, `vulkan` and
+/// `metal` features; llama.cpp always builds Metal on Apple Silicon.
+pub const GPU_BACKEND: Option<&str> = if cfg!(feature = "cuda") {
+    Some("cuda")
+} else if cfg!(feature = "vulkan") {
+    Some("vulkan")
+} else if cfg!(any(
+    feature = "metal",
+    all(target_os = "macos", target_arch = "aarch64")
+)) {
+    Some("metal")
+} else {
+    None
+};
+
+/// Default for `N_GPU_LAYERS`: offload on GPU builds, nothing on CPU-only ones.
+pub const DEFAULT_GPU_LAYERS: i32 = if GPU_BACKEND.is_some() { 28 } else { 0 };
+
+/// How letter case in the requested word affects analysis ("Polish" vs "polish").
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CasePolicy {
+    /// Each casing is its own word, generated, cached and stored separately
+    #[default]
+    Distinct,
+    /// Casings share one entry; its `word` echoes the casing of each request
+    Fold,
+    /// Casings share one entry; its `word` keeps the casing the model chose
+    Preserve,
+}
+
+impl CasePolicy {
+    /// Cache and store key for `word`. Lookups, refresh coalescing and the
+    /// negative cache all go through it, so they agree on what "same word" means.
+    /// Regional spelling variants ("colour") share the key of their canonical
+    /// spelling ("color").
+    pub fn key(self, word: &str) -> String {
+        let key = self.spelling_key(word);
+        match crate::variants::canonical(&key) {
+            Some(canonical) => canonical.to_string(),
+            None => key,
+        }
+    }
+
+    /// [`key`](Self::key) without merging spelling variants: what entries
+    /// were stored under before variants shared one.
+    pub fn spelling_key(self, word: &str) -> String {
+        let word = word.trim();
+        match self {
+            Self::Distinct => word.to_string(),
+            Self::Fold | Self::Preserve => word.to_lowercase(),
+        }
+    }
+
+    /// The `word` field of an entry requested as `requested` whose model
+    /// output or stored copy says `reported`.
+    pub fn surface<'a>(self, reported: Option<&'a str>, requested: &'a str) -> &'a str {
+        match reported {
+            Some(reported)
+                if self == Self::Preserve
+                    && reported.trim().to_lowercase() == requested.trim().to_lowercase() =>
+            {
+                reported.trim()
+            }
+            _ => requested,
+        }
+    }
+}
+
+/// Whether entries also carry a CEFR level (A1-C2) derived from `difficulty`.
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CefrMode {
+    /// Plain three-level `difficulty` only
+    #[default]
+    Off,
+    /// Add a `cefr` field next to `difficulty`
+    Augment,
+    /// Report the CEFR level as `difficulty` itself
+    Replace,
+}
+
+/// Offline checks run instead of serving.
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Render the prompt for a sample word, tokenize it with the configured
+    /// backend and report whether it fits N_CTX alongside MAX_TOKENS
+    CheckTemplate {
+        #[arg(long, default_value = "communicated")]
+        word: String,
+    },
+    /// Validate the merged configuration (model file, schema, bind address,
+    /// GPU offload vs VRAM) and exit non-zero on problems
+    CheckConfig,
+    /// List the compute devices llama.cpp sees in this build, with the
+    /// DEVICE value selecting each and its free and total memory
+    Devices,
+    /// Rerun the current validator over model outputs saved with RECORD_DIR
+    /// and report how many would pass
+    Revalidate {
+        #[arg(long)]
+        dir: String,
+    },
+    /// Generate and store entries for every word of a large list, skipping
+    /// words already in DATA_DIR and checkpointing progress so an interrupted
+    /// run picks up where it stopped
+    Corpus {
+        /// Word list, one word per line
+        #[arg(long)]
+        input: String,
+        /// Checkpoint file to resume from; created if missing
+        #[arg(long)]
+        resume: String,
+        /// Words analyzed at once; defaults to the inference slots
+        #[arg(long)]
+        concurrency: Option<usize>,
+        /// Most generations started per minute
+        #[arg(long)]
+        per_minute: Option<u32>,
+        /// Seconds between progress log lines
+        #[arg(long, default_value_t = 60)]
+        stats_secs: u64,
+    },
+    /// Add the words of a list to the shared Postgres work queue
+    #[cfg(feature = "queue")]
+    QueuePush {
+        /// Word list, one word per line
+        #[arg(long)]
+        input: String,
+        #[arg(long, env)]
+        queue_url: String,
+    },
+    /// Pull words from the shared Postgres work queue and generate them,
+    /// alongside any number of other instances
+    #[cfg(feature = "queue")]
+    QueueWork {
+        #[arg(long, env)]
+        queue_url: String,
+        /// Name recorded with claims; defaults to $HOSTNAME and the process id
+        #[arg(long)]
+        worker: Option<String>,
+        /// Words claimed and analyzed at once; defaults to the inference slots
+        #[arg(long)]
+        concurrency: Option<usize>,
+        /// Seconds before another instance may take over an unfinished claim
+        #[arg(long, default_value_t = 600)]
+        lease_secs: u64,
+        /// Exit once the queue is empty instead of waiting for more words
+        #[arg(long, default_value_t = false)]
+        exit_when_empty: bool,
+    },
+}
+
+/// How llama.cpp spreads work across NUMA nodes on multi-socket machines.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumaMode {
+    /// No NUMA handling (single-socket machines)
+    Disabled,
+    /// Spread threads and memory evenly across all nodes
+    Distribute,
+    /// Keep threads on the node the process started on
+    Isolate,
+    /// Follow the CPU map given by `numactl`
+    Numactl,
+    /// Mirror the model into every node's memory
+    Mirror,
+}
+
+/// Which tuned preset the llama backend applies to its model.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresetMode {
+    /// Detect the model family from GGUF metadata
+    Auto,
+    /// Send the plain prompt with the server's sampling defaults
+    Off,
+    Llama3,
+    Qwen,
+    Phi,
+    Gemma,
+}
+
+impl PresetMode {
+    /// The family this mode names outright, if any.
+    pub fn family(self) -> Option<ModelFamily> {
+        match self {
+            Self::Auto | Self::Off => None,
+            Self::Llama3 => Some(ModelFamily::Llama3),
+            Self::Qwen => Some(ModelFamily::Qwen),
+            Self::Phi => Some(ModelFamily::Phi),
+            Self::Gemma => Some(ModelFamily::Gemma),
+        }
+    }
+}
 
 #[derive(Parser, Debug, Clone)]
+#[command(name = "lingua-fast")]
 pub struct Config {
+    #[command(subcommand)]
+    pub command: Option<Command>,
     #[arg(long, env, default_value = "0.0.0.0:8080")]
     pub bind_addr: String,
+    // Inference backend selected at startup
+    #[arg(long, env, value_enum, default_value_t = BackendKind::Llama)]
+    pub backend: BackendKind,
+    // Load no model: serve cached and stored entries only, answering misses with
+    // NOT_CACHED (read replicas, edge deployments). Overrides BACKEND
+    #[arg(long, env, default_value_t = false)]
+    pub no_model: bool,
+    // Required by the llama backend
     #[arg(long = "MODEL_PATH", env = "MODEL_PATH")]
-    pub model_path: String,
+    pub model_path: Option<String>,
+    // Base URL for the openai/ollama backends, defaulting to the provider's usual
+    // endpoint; required for the upstream backend
+    #[arg(long, env)]
+    pub backend_url: Option<String>,
+    // Model name requested from the openai/ollama backends
+    #[arg(long, env)]
+    pub backend_model: Option<String>,
+    // Bearer token sent to the openai backend; X-API-Key sent to the upstream backend
+    #[arg(long, env)]
+    pub backend_api_key: Option<String>,
+    // Per-request timeout for the openai/ollama/upstream backends
+    #[arg(long, env, default_value_t = 120)]
+    pub backend_timeout_secs: u64,
+    // Synthetic latency added to every mock backend response
+    #[arg(long, env, default_value_t = 0)]
+    pub mock_latency_ms: u64,
     // Must be >= 1 to satisfy NonZeroU32 context requirement
     #[arg(long, env, default_value_t = 4096, value_parser = clap::value_parser!(i32).range(1..))]
     pub n_ctx: i32,
+    // Max prompt tokens per decode call; longer prompts are evaluated in chunks
     #[arg(long, env, default_value_t = 256)]
     pub n_batch: i32,
-    // Disallow negatives; 0 means CPU-only inference
-    #[arg(long, env, default_value_t = 28, value_parser = clap::value_parser!(i32).range(0..))]
+    // Physical batch size submitted to the GPU; 0 means auto (min(n_batch, 512))
+    #[arg(long, env, default_value_t = 0, value_parser = clap::value_parser!(i32).range(0..))]
+    pub n_ubatch: i32,
+    // Disallow negatives; 0 means CPU-only inference. Defaults to 0 on builds without a GPU backend
+    #[arg(long, env, default_value_t = DEFAULT_GPU_LAYERS, value_parser = clap::value_parser!(i32).range(0..))]
     pub n_gpu_layers: i32,
+    // Compute device to load the model on: cpu, or a backend with an optional
+    // index such as cuda:0, metal or vulkan:1 (see `lingua-fast devices`).
+    // Unset lets llama.cpp use every GPU the build supports
+    #[arg(long, env)]
+    pub device: Option<DeviceSpec>,
+    // Offload as many layers as fit in free VRAM instead of N_GPU_LAYERS
+    #[arg(long, env, default_value_t = false)]
+    pub auto_gpu_layers: bool,
+    // Free VRAM in MiB for offload planning; detected with nvidia-smi when unset
+    #[arg(long, env)]
+    pub vram_mb: Option<u64>,
     // 0 means auto-detect (use all available logical CPUs)
     #[arg(long, env = "THREADS", default_value_t = 0, value_parser = clap::value_parser!(i32).range(0..))]
     pub threads: i32,
+    // Threads for prompt evaluation; 0 means same as THREADS. Prompt processing is
+    // compute-bound and usually wants every physical core, generation fewer
+    #[arg(long, env, default_value_t = 0, value_parser = clap::value_parser!(i32).range(0..))]
+    pub threads_batch: i32,
+    // NUMA strategy for llama.cpp on multi-socket servers
+    #[arg(long, env, value_enum, default_value_t = NumaMode::Disabled)]
+    pub numa: NumaMode,
     // 0 means default (min(8, num_cpus)) per-process inference concurrency
     #[arg(long = "INFER_CONCURRENCY", env = "INFER_CONCURRENCY", default_value_t = 0, value_parser = clap::value_parser!(i32).range(0..))]
     pub infer_concurrency: i32,
+    // Longest a request waits for an inference slot before failing with 503; 0 waits indefinitely
+    #[arg(long, env, default_value_t = 0)]
+    pub max_queue_wait_ms: u64,
+    // Give free inference slots to waiting API keys in turn instead of in arrival order
+    #[arg(long, env, default_value_t = true, action = clap::ArgAction::Set)]
+    pub fair_scheduling: bool,
+    // Unload the model after this many minutes without inference and load it
+    // again on the next request (llama backend); 0 keeps it loaded
+    #[arg(long, env, default_value_t = 0)]
+    pub idle_unload_mins: u64,
     #[arg(long, env, default_value_t = 1024)]
     pub max_tokens: i32,
-    #[arg(long, env, default_value_t = 0.4)]
-    pub temp: f32,
-    #[arg(long, env, default_value_t = 0.9)]
-    pub top_p: f32,
-    #[arg(long, env, default_value_t = 0.05)]
-    pub min_p: f32,
-    #[arg(long, env, default_value_t = 1.1)]
-    pub repeat_penalty: f32,
+    // Sampling; each unset value comes from the model preset, else 0.4 / 0.9 / 0.05 / 1.1
+    #[arg(long, env)]
+    pub temp: Option<f32>,
+    #[arg(long, env)]
+    pub top_p: Option<f32>,
+    #[arg(long, env)]
+    pub min_p: Option<f32>,
+    #[arg(long, env)]
+    pub repeat_penalty: Option<f32>,
+    // Tokens the repetition penalties look back over; -1 for the whole context
+    #[arg(long, env, default_value_t = 64, allow_negative_numbers = true)]
+    pub repeat_last_n: i32,
+    // Penalize tokens by how often (frequency) or whether (presence) they
+    // already appeared; 0 disables
+    #[arg(long, env, default_value_t = 0.0)]
+    pub frequency_penalty: f32,
+    #[arg(long, env, default_value_t = 0.0)]
+    pub presence_penalty: f32,
+    // DRY sampler against repeated sequences (llama backend); 0 disables.
+    // Generations that loop anyway are cut short and retried with it on
+    #[arg(long, env, default_value_t = 0.0)]
+    pub dry_multiplier: f32,
+    #[arg(long, env, default_value_t = 1.75)]
+    pub dry_base: f32,
+    #[arg(long, env, default_value_t = 2)]
+    pub dry_allowed_length: i32,
+    // XTC sampler (llama backend): chance per token of dropping the top
+    // choices above the threshold; 0 disables
+    #[arg(long, env, default_value_t = 0.0)]
+    pub xtc_probability: f32,
+    #[arg(long, env, default_value_t = 0.1)]
+    pub xtc_threshold: f32,
+    // Tuned chat template, sampling and stop strings for the llama backend's model
+    #[arg(long, env, value_enum, default_value_t = PresetMode::Auto)]
+    pub model_preset: PresetMode,
+    // Maximum number of cached word entries; 0 disables the cache
+    #[arg(long, env, default_value_t = 10_000)]
+    pub cache_capacity: usize,
+    // Seconds before a cached entry is considered stale; 0 means entries never expire
+    #[arg(long, env, default_value_t = 0)]
+    pub cache_ttl_secs: u64,
+    // Serve expired entries immediately and refresh them in the background
+    #[arg(long, env, default_value_t = false)]
+    pub stale_while_revalidate: bool,
+    // With DATA_DIR, regenerate stored entries older than this many days in the background
+    #[arg(long, env)]
+    pub refresh_after_days: Option<u64>,
+    // With DATA_DIR, also regenerate stored entries another model generated
+    #[arg(long, env, default_value_t = false)]
+    pub refresh_other_models: bool,
+    // Seconds without interactive inference before a background refresh may start
+    #[arg(long, env, default_value_t = 30)]
+    pub refresh_idle_secs: u64,
+    // Least seconds between two background refreshes
+    #[arg(long, env, default_value_t = 60)]
+    pub refresh_interval_secs: u64,
+    // Seconds to remember inputs that repeatedly fail analysis
+    #[arg(long, env, default_value_t = 300)]
+    pub negative_cache_ttl_secs: u64,
+    // Consecutive failures before an input is rejected without inference; 0 disables
+    #[arg(long, env, default_value_t = 2)]
+    pub negative_cache_threshold: u32,
+    // Longest accepted word in characters (Unicode scalar values, not bytes)
+    #[arg(long, env, default_value_t = 100, value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_word_chars: u64,
+    // How casing affects analysis: distinct words, or one shared entry whose `word`
+    // echoes each request (fold) or keeps the model's casing (preserve)
+    #[arg(long, env, value_enum, default_value_t = CasePolicy::Distinct)]
+    pub case_policy: CasePolicy,
+    // Serve and store entries with sorted keys and sorted synonyms/antonyms, and
+    // write DATA_DIR entry files indented, for minimal diffs across regenerations
+    #[arg(long, env, default_value_t = false)]
+    pub canonical_json: bool,
+    // Add CEFR levels to responses (augment: `cefr` field; replace: in `difficulty`),
+    // and correct `difficulty` against the embedded CEFR word list
+    #[arg(long, env, value_enum, default_value_t = CefrMode::Off)]
+    pub cefr: CefrMode,
+    // Directory for persisted entries and their version history; unset disables persistence
+    #[arg(long, env)]
+    pub data_dir: Option<String>,
+    // Word contract schema file to validate against instead of the embedded
+    // one; reread on POST /admin/reload-schema and when it changes on disk
+    #[arg(long, env)]
+    pub schema_file: Option<String>,
+    // JSON file of per-language contract profiles (parts of speech, difficulty
+    // values, required meaning fields); unset uses the built-in English one
+    #[arg(long, env)]
+    pub contract_profiles_file: Option<String>,
+    // Seconds between checks of SCHEMA_FILE for changes; 0 reloads only on request
+    #[arg(long, env, default_value_t = 2)]
+    pub schema_watch_secs: u64,
+    // On Ctrl-C or SIGTERM, seconds to let in-flight requests finish before
+    // cached entries and usage counters are saved and the process exits
+    #[arg(long, env, default_value_t = 30)]
+    pub shutdown_grace_secs: u64,
+    // System prompt placed ahead of the word contract
+    #[arg(long, env, default_value = prompt::DEFAULT_SYSTEM)]
+    pub system_prompt: String,
+    // Read the system prompt from this file instead of SYSTEM_PROMPT
+    #[arg(long, env)]
+    pub system_prompt_file: Option<String>,
+    // Let requests replace the system prompt with their own `system_prompt`
+    #[arg(long, env, default_value_t = false)]
+    pub allow_system_prompt_override: bool,
+    // Directory of <word>.json exemplar entries shown to the model as few-shot examples
+    #[arg(long, env)]
+    pub few_shot_dir: Option<String>,
+    // Maximum few-shot examples per prompt; fewer are sent when the context is tight
+    #[arg(long, env, default_value_t = 2)]
+    pub few_shot_count: usize,
+    // Pick few-shot examples from cached entries that resemble the requested word
+    #[arg(long, env, default_value_t = false)]
+    pub few_shot_from_cache: bool,
+    // After generating an entry, have the model back-translate two of its translations and record diverging ones as quality warnings
+    #[arg(long, env, default_value_t = false)]
+    pub back_translation_check: bool,
+    // Word-of-the-day rotation, one word per line; defaults to a built-in curated list
+    #[arg(long, env, conflicts_with = "word_of_the_day_level")]
+    pub word_of_the_day_file: Option<String>,
+    // Draw the word of the day from the embedded CEFR list's words at this level instead
+    #[arg(long, env, value_enum, ignore_case = true)]
+    pub word_of_the_day_level: Option<Level>,
+    // JSON file of named parameter profiles and the X-API-Key values bound to them
+    #[arg(long, env)]
+    pub profiles_file: Option<String>,
+    // Let keyless requests and keys bound to no tenant pick one with X-Tenant,
+    // for gateways that set it themselves; otherwise only the admin token may
+    #[arg(long, env, default_value_t = false)]
+    pub trust_tenant_header: bool,
+    // Base64 Ed25519 seed (32 bytes) used to sign served entries; unset disables signing
+    #[arg(long, env)]
+    pub signing_key_file: Option<String>,
+    // Key ID sent with signatures; defaults to a fingerprint of the public key
+    #[arg(long, env)]
+    pub signing_key_id: Option<String>,
+    // Bearer token required by /admin endpoints; unset disables them
+    #[arg(long, env)]
+    pub admin_token: Option<String>,
+    // Share of failed words (0-1] at which /v1/words answers 502 instead of 207
+    #[arg(long, env, default_value_t = 1.0, value_parser = parse_fraction)]
+    pub batch_failure_threshold: f64,
+    // Retries all words of one /v1/words batch may make between them; once
+    // spent, words still needing generation fail at once. 0 disables the limit
+    #[arg(long, env, default_value_t = 8)]
+    pub batch_retry_budget: usize,
+    // Wall-clock seconds one word's generation may take, all retries and
+    // repairs included, before failing with 504 TIMEOUT; 0 disables the limit
+    #[arg(long, env, default_value_t = 0)]
+    pub word_time_budget_secs: u64,
+    // Save every raw model output with its prompt here, for replay with `revalidate`
+    #[arg(long, env)]
+    pub record_dir: Option<String>,
+    // Append token-by-token timing and sampler choices of sampled inferences here, as JSON Lines (llama backend)
+    #[arg(long, env)]
+    pub token_trace_file: Option<String>,
+    // Share of inferences (0-1] traced into TOKEN_TRACE_FILE, evenly spaced
+    #[arg(long, env, default_value_t = 0.01, value_parser = parse_fraction)]
+    pub token_trace_sample: f64,
+    // Time a warmup plus one measured inference at startup and log tokens/sec (llama backend)
+    #[arg(long, env, default_value_t = true, action = clap::ArgAction::Set)]
+    pub startup_benchmark: bool,
+    // Log each tracing span's busy/idle time when it closes (queue wait, prompt eval, generation)
+    #[arg(long, env, default_value_t = false)]
+    pub log_span_timings: bool,
+    // Seconds between canary inferences backing /readyz; 0 disables the canary
+    #[arg(long, env, default_value_t = 0)]
+    pub canary_interval_secs: u64,
+    // A canary slower than this marks the instance unready
+    #[arg(long, env, default_value_t = 30_000)]
+    pub canary_budget_ms: u64,
+}
+
+impl Config {
+    /// Inference slots per process, with 0 (auto) resolved to min(8, num_cpus).
+    /// Also the most words a batch runs at once.
+    pub fn infer_slots(&self) -> usize {
+        if self.infer_concurrency > 0 {
+            self.infer_concurrency as usize
+        } else {
+            usize::min(8, num_cpus::get().max(1))
+        }
+    }
+}
+
+fn parse_fraction(s: &str) -> Result<f64, String> {
+    let v: f64 = s.parse().map_err(|e| format!("{}", e))?;
+    if v > 0.0 && v <= 1.0 {
+        Ok(v)
+    } else {
+        Err(format!("{} is not in (0, 1]", v))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn infer_slots_resolve_auto() {
+        let cfg = Config::try_parse_from(["lingua-fast", "--INFER_CONCURRENCY", "3"]).unwrap();
+        assert_eq!(cfg.infer_slots(), 3);
+        let cfg = Config::try_parse_from(["lingua-fast", "--INFER_CONCURRENCY", "0"]).unwrap();
+        assert!((1..=8).contains(&cfg.infer_slots()));
+    }
 }
@@ -0,0 +1,304 @@
+use crate::validate::PARTS_OF_SPEECH;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+use tracing::info;
+
+/// Language of the built-in profile, and of every entry unless profiles for
+/// other languages are configured.
+pub const DEFAULT_LANGUAGE: &str = "english";
+
+/// The categories an entry in one language may use. The JSON schema still
+/// has the final say, widened to accept every profile's categories (see
+/// [`ContractProfiles::widen_schema`]); these lists keep one language's
+/// categories from being accepted for another.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ContractProfile {
+    /// Set from the key the profile is listed under
+    #[serde(skip)]
+    pub language: String,
+    /// Lowercase parts of speech a meaning may have
+    pub parts_of_speech: Vec<String>,
+    /// Accepted `difficulty` values, easiest first. One outside the list is
+    /// replaced by the middle value.
+    pub difficulties: Vec<String>,
+    /// Fields every meaning must have, besides `partOfSpeech`
+    pub required_meaning_fields: Vec<String>,
+}
+
+impl ContractProfile {
+    /// The contract the embedded schema describes.
+    pub fn english() -> Self {
+        let strings = |items: &[&str]| items.iter().map(|s| s.to_string()).collect();
+        Self {
+            language: DEFAULT_LANGUAGE.to_string(),
+            parts_of_speech: strings(&PARTS_OF_SPEECH),
+            difficulties: strings(&["beginner", "intermediate", "advanced"]),
+            required_meaning_fields: strings(&[
+                "definition",
+                "exampleSentence",
+                "grammarTip",
+                "translations",
+            ]),
+        }
+    }
+
+    pub fn allows_part_of_speech(&self, pos: &str) -> bool {
+        self.parts_of_speech.iter().any(|p| p == pos)
+    }
+
+    pub fn allows_difficulty(&self, difficulty: &str) -> bool {
+        self.difficulties.iter().any(|d| d == difficulty)
+    }
+
+    /// Stand-in for a difficulty outside the list.
+    pub fn fallback_difficulty(&self) -> &str {
+        &self.difficulties[self.difficulties.len() / 2]
+    }
+}
+
+/// Contract profiles by entry language, loaded from `CONTRACT_PROFILES_FILE`:
+///
+/// ```json
+/// {
+///   "german": {
+///     "parts_of_speech": ["noun", "verb", "adjective", "adverb", "article"],
+///     "difficulties": ["beginner", "intermediate", "advanced"],
+///     "required_meaning_fields": ["definition", "exampleSentence", "translations"]
+///   }
+/// }
+/// ```
+///
+/// The built-in English profile is always present unless the file replaces
+/// it. With English alone, entries claiming another language are corrected
+/// to English; once other languages are configured, an entry in a language
+/// without a profile is rejected instead.
+#[derive(Debug, Clone)]
+pub struct ContractProfiles {
+    by_language: BTreeMap<String, ContractProfile>,
+}
+
+impl Default for ContractProfiles {
+    fn default() -> Self {
+        Self {
+            by_language: BTreeMap::from([(
+                DEFAULT_LANGUAGE.to_string(),
+                ContractProfile::english(),
+            )]),
+        }
+    }
+}
+
+impl ContractProfiles {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let raw = crate::util::read_to_string(path)?;
+        let profiles =
+            Self::from_json(&raw).with_context(|| format!("load contract profiles {:?}", path))?;
+        info!(
+            languages = ?profiles.languages().collect::<Vec<_>>(),
+            ?path,
+            "loaded contract profiles"
+        );
+        Ok(profiles)
+    }
+
+    pub fn from_json(raw: &str) -> Result<Self> {
+        let file: BTreeMap<String, ContractProfile> = serde_json::from_str(raw)?;
+        let mut profiles = Self::default();
+        for (language, mut profile) in file {
+            let language = language.trim().to_lowercase();
+            if profile.parts_of_speech.is_empty() || profile.difficulties.is_empty() {
+                bail!(
+                    "contract profile '{}' needs at least one part of speech and one difficulty",
+                    language
+                );
+            }
+            if let Some(pos) = profile
+                .parts_of_speech
+                .iter()
+                .find(|p| p.to_lowercase() != **p)
+            {
+                bail!(
+                    "contract profile '{}' lists part of speech '{}'; parts of speech are lowercase",
+                    language,
+                    pos
+                );
+            }
+            profile.language = language.clone();
+            profiles.by_language.insert(language, profile);
+        }
+        Ok(profiles)
+    }
+
+    /// The profile for entries in `language` (matched case-insensitively).
+    pub fn get(&self, language: &str) -> Option<&ContractProfile> {
+        self.by_language.get(&language.trim().to_lowercase())
+    }
+
+    /// The profile entries fall back to when only one language is configured.
+    pub fn default_profile(&self) -> Option<&ContractProfile> {
+        match self.by_language.len() {
+            1 => self.by_language.values().next(),
+            _ => None,
+        }
+    }
+
+    /// Parts of speech any profile allows, for checks made before an entry's
+    /// language is known. With English alone these are its own.
+    pub fn parts_of_speech(&self) -> Vec<&str> {
+        let mut all: Vec<&str> = Vec::new();
+        for pos in self.by_language.values().flat_map(|p| &p.parts_of_speech) {
+            if !all.contains(&pos.as_str()) {
+                all.push(pos);
+            }
+        }
+        all
+    }
+
+    pub fn languages(&self) -> impl Iterator<Item = &str> {
+        self.by_language.keys().map(String::as_str)
+    }
+
+    pub fn profiles(&self) -> impl Iterator<Item = &ContractProfile> {
+        self.by_language.values()
+    }
+
+    /// Let the word contract `schema` accept every profile's languages,
+    /// difficulties and parts of speech, and require of each meaning only
+    /// the profile-governed fields all profiles require. The validator holds
+    /// each entry to its own language's profile. Enums the schema leaves out
+    /// stay out.
+    pub fn widen_schema(&self, schema: &mut Value) {
+        let difficulties = self.profiles().flat_map(|p| &p.difficulties);
+        widen_enum(schema.pointer_mut("/properties/language/enum"), self.languages());
+        widen_enum(
+            schema.pointer_mut("/properties/difficulty/enum"),
+            difficulties.map(String::as_str),
+        );
+        widen_enum(
+            schema.pointer_mut("/properties/meanings/items/properties/partOfSpeech/enum"),
+            self.parts_of_speech(),
+        );
+        let governed = ContractProfile::english().required_meaning_fields;
+        if let Some(Value::Array(required)) =
+            schema.pointer_mut("/properties/meanings/items/required")
+        {
+            required.retain(|field| {
+                let Some(field) = field.as_str() else {
+                    return true;
+                };
+                !governed.iter().any(|g| g == field)
+                    || self
+                        .profiles()
+                        .all(|p| p.required_meaning_fields.iter().any(|r| r == field))
+            });
+        }
+    }
+}
+
+/// Add `values` missing from the schema enum `allowed`, if there is one.
+fn widen_enum<'a>(allowed: Option<&mut Value>, values: impl IntoIterator<Item = &'a str>) {
+    let Some(Value::Array(allowed)) = allowed else {
+        return;
+    };
+    for value in values {
+        if !allowed.iter().any(|a| a == value) {
+            allowed.push(Value::String(value.to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_load_per_language_beside_english() {
+        let profiles = ContractProfiles::default();
+        assert_eq!(
+            profiles.default_profile(),
+            Some(&ContractProfile::english())
+        );
+        assert_eq!(
+            ContractProfile::english().fallback_difficulty(),
+            "intermediate"
+        );
+
+        let profiles = ContractProfiles::from_json(
+            r#"{ "German": {
+                "parts_of_speech": ["noun", "verb", "article"],
+                "difficulties": ["a1", "a2", "b1", "b2"],
+                "required_meaning_fields": ["definition"]
+            } }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            profiles.languages().collect::<Vec<_>>(),
+            ["english", "german"]
+        );
+        assert!(profiles.default_profile().is_none());
+        let german = profiles.get("GERMAN").unwrap();
+        assert_eq!(german.language, "german");
+        assert!(!german.allows_part_of_speech("gerund"));
+        // German adds no category English lacks
+        assert_eq!(profiles.parts_of_speech(), PARTS_OF_SPEECH);
+
+        let japanese = r#"{ "japanese": { "parts_of_speech": ["noun", "particle"], "difficulties": ["n5"], "required_meaning_fields": [] } }"#;
+        let all = ContractProfiles::from_json(japanese).unwrap();
+        assert_eq!(all.parts_of_speech().last(), Some(&"particle"));
+        assert_eq!(all.parts_of_speech().len(), PARTS_OF_SPEECH.len() + 1);
+        assert_eq!(german.fallback_difficulty(), "b1");
+
+        let empty = r#"{ "german": { "parts_of_speech": [], "difficulties": ["a1"], "required_meaning_fields": [] } }"#;
+        assert!(ContractProfiles::from_json(empty).is_err());
+        let cased = r#"{ "german": { "parts_of_speech": ["Noun"], "difficulties": ["a1"], "required_meaning_fields": [] } }"#;
+        assert!(ContractProfiles::from_json(cased).is_err());
+        let typo = r#"{ "german": { "pos": ["noun"] } }"#;
+        assert!(ContractProfiles::from_json(typo).is_err());
+    }
+
+    #[test]
+    fn the_schema_is_widened_to_every_profile() {
+        let schema = || {
+            serde_json::json!({ "properties": {
+                "language": { "enum": ["english"] },
+                "difficulty": { "enum": ["beginner", "intermediate", "advanced"] },
+                "meanings": { "items": {
+                    "properties": { "partOfSpeech": { "enum": ["noun"] } },
+                    "required": ["partOfSpeech", "definition", "grammarTip", "synonyms"]
+                } }
+            } })
+        };
+        let mut english = schema();
+        ContractProfiles::default().widen_schema(&mut english);
+        assert_eq!(english["properties"]["language"], schema()["properties"]["language"]);
+        assert_eq!(
+            english["properties"]["meanings"]["items"]["required"],
+            schema()["properties"]["meanings"]["items"]["required"]
+        );
+
+        let profiles = ContractProfiles::from_json(
+            r#"{ "japanese": { "parts_of_speech": ["noun", "particle"], "difficulties": ["n5"], "required_meaning_fields": ["definition"] } }"#,
+        )
+        .unwrap();
+        let mut widened = schema();
+        profiles.widen_schema(&mut widened);
+        let props = &widened["properties"];
+        assert_eq!(props["language"]["enum"], serde_json::json!(["english", "japanese"]));
+        assert_eq!(props["difficulty"]["enum"][3], "n5");
+        assert!(props["meanings"]["items"]["properties"]["partOfSpeech"]["enum"]
+            .as_array()
+            .unwrap()
+            .contains(&Value::from("particle")));
+        // grammarTip is only required of English meanings now; synonyms is
+        // not the profiles' to drop
+        assert_eq!(
+            props["meanings"]["items"]["required"],
+            serde_json::json!(["partOfSpeech", "definition", "synonyms"])
+        );
+    }
+}
@@ -0,0 +1,283 @@
+use crate::batch;
+use crate::service::{load_persisted, AnalyzeOptions, Persisted, WordService};
+use anyhow::{bail, Context, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+/// Words read per chunk for each concurrent slot. Progress is checkpointed
+/// between chunks, so this bounds the work redone after a crash.
+const CHUNK_PER_SLOT: usize = 8;
+
+/// Settings for [`run`].
+#[derive(Debug, Clone)]
+pub struct CorpusOptions {
+    /// Word list, one word per line; blank lines and `#` comments are skipped.
+    pub input: PathBuf,
+    /// Checkpoint file, created on the first run and resumed from afterwards.
+    pub checkpoint: PathBuf,
+    /// Words analyzed at once.
+    pub concurrency: usize,
+    /// Most generations started per minute; unlimited when unset.
+    pub per_minute: Option<u32>,
+    /// How often progress is logged.
+    pub stats_every: Duration,
+}
+
+/// Progress through one input file, saved after every chunk so a run can stop
+/// at any point and resume without redoing finished words.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub input: String,
+    /// Input lines fully processed; a resumed run starts after them.
+    pub lines_done: u64,
+    pub generated: u64,
+    /// Already persisted (or deleted by an operator), so not generated again
+    pub skipped: u64,
+    pub failed: u64,
+    /// Words whose generation failed, for a later run over just these
+    #[serde(default)]
+    pub failed_words: Vec<String>,
+}
+
+impl Checkpoint {
+    /// The checkpoint at `path`, or a fresh one for `input` if there is none.
+    /// Resuming with a different input file is refused.
+    fn load(path: &Path, input: &Path) -> Result<Self> {
+        let input = input.display().to_string();
+        if !path.exists() {
+            return Ok(Self {
+                input,
+                ..Self::default()
+            });
+        }
+        let raw = crate::util::read_to_string(path)?;
+        let checkpoint: Self =
+            serde_json::from_str(&raw).with_context(|| format!("parse checkpoint {:?}", path))?;
+        if checkpoint.input != input {
+            bail!(
+                "checkpoint {:?} belongs to input {}, not {}",
+                path,
+                checkpoint.input,
+                input
+            );
+        }
+        Ok(checkpoint)
+    }
+
+    /// Write via a temporary file and rename, so a crash never leaves a torn checkpoint.
+    fn save(&self, path: &Path) -> Result<()> {
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("write checkpoint {:?}", tmp))?;
+        fs::rename(&tmp, path).with_context(|| format!("replace checkpoint {:?}", path))
+    }
+}
+
+impl fmt::Display for Checkpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} lines of {}: {} generated, {} already stored, {} failed",
+            self.lines_done, self.input, self.generated, self.skipped, self.failed
+        )
+    }
+}
+
+enum Outcome {
+    Generated,
+    Skipped,
+    Failed,
+}
+
+/// Generate and persist entries for every word of a large list, offline.
+///
+/// Words already in the store are skipped, so overlapping lists and reruns
+/// cost nothing. Progress is checkpointed after every chunk; setting `stop`
+/// (e.g. on Ctrl-C) ends the run at the next checkpoint.
+pub async fn run(
+    service: WordService,
+    opts: &CorpusOptions,
+    stop: Arc<AtomicBool>,
+) -> Result<Checkpoint> {
+    if service.store.is_none() {
+        bail!("corpus runs need DATA_DIR: words are deduplicated against, and saved to, the store");
+    }
+    let mut progress = Checkpoint::load(&opts.checkpoint, &opts.input)?;
+    if progress.lines_done > 0 {
+        info!(lines_done = progress.lines_done, "resuming corpus run");
+    }
+    let file =
+        fs::File::open(&opts.input).with_context(|| format!("open word list {:?}", opts.input))?;
+    let mut lines = BufReader::new(file)
+        .lines()
+        .skip(progress.lines_done as usize);
+
+    let pacer = Arc::new(Pacer::new(opts.per_minute));
+    let chunk_size = opts.concurrency.max(1) * CHUNK_PER_SLOT;
+    let started = Instant::now();
+    let generated_before = progress.generated;
+    let mut last_stats = Instant::now();
+
+    while !stop.load(Ordering::Relaxed) {
+        let mut read = 0;
+        let mut chunk = Vec::with_capacity(chunk_size);
+        while chunk.len() < chunk_size {
+            let Some(line) = lines.next() else { break };
+            read += 1;
+            let word = line.with_context(|| format!("read word list {:?}", opts.input))?;
+            let word = word.trim();
+            if !word.is_empty() && !word.starts_with('#') {
+                chunk.push(word.to_string());
+            }
+        }
+        if read == 0 {
+            break;
+        }
+
+        let outcomes = batch::run_indexed(chunk.clone(), opts.concurrency, |word| {
+            let service = service.clone();
+            let pacer = pacer.clone();
+            async move { process(&service, &pacer, &word).await }
+        })
+        .await;
+        for (word, outcome) in chunk.into_iter().zip(outcomes) {
+            match outcome {
+                Ok(Outcome::Generated) => progress.generated += 1,
+                Ok(Outcome::Skipped) => progress.skipped += 1,
+                Ok(Outcome::Failed) | Err(_) => {
+                    progress.failed += 1;
+                    progress.failed_words.push(word);
+                }
+            }
+        }
+        progress.lines_done += read;
+        progress.save(&opts.checkpoint)?;
+
+        if last_stats.elapsed() >= opts.stats_every {
+            last_stats = Instant::now();
+            let minutes = started.elapsed().as_secs_f64() / 60.0;
+            info!(
+                lines_done = progress.lines_done,
+                generated = progress.generated,
+                skipped = progress.skipped,
+                failed = progress.failed,
+                per_minute = format!(
+                    "{:.1}",
+                    (progress.generated - generated_before) as f64 / minutes.max(1e-9)
+                ),
+                "corpus progress"
+            );
+        }
+    }
+    if stop.load(Ordering::Relaxed) {
+        info!(
+            lines_done = progress.lines_done,
+            "corpus run stopped; rerun to resume"
+        );
+    }
+    Ok(progress)
+}
+
+async fn process(service: &WordService, pacer: &Pacer, word: &str) -> Outcome {
+    match load_persisted(service.store.as_deref(), word) {
+        Persisted::Found { .. } | Persisted::Deleted => return Outcome::Skipped,
+        Persisted::Missing => {}
+    }
+    pacer.wait().await;
+    match service.analyze(word, &AnalyzeOptions::default()).await {
+        Ok(_) => Outcome::Generated,
+        Err(e) => {
+            warn!("Corpus word '{}' failed: {}", word, e.message());
+            Outcome::Failed
+        }
+    }
+}
+
+/// Spaces generation starts evenly to stay within a per-minute rate.
+struct Pacer {
+    gap: Option<Duration>,
+    next: Mutex<Instant>,
+}
+
+impl Pacer {
+    fn new(per_minute: Option<u32>) -> Self {
+        Self {
+            gap: per_minute
+                .filter(|&n| n > 0)
+                .map(|n| Duration::from_secs(60) / n),
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    async fn wait(&self) {
+        let Some(gap) = self.gap else { return };
+        let at = {
+            let mut next = self.next.lock();
+            let at = (*next).max(Instant::now());
+            *next = at + gap;
+            at
+        };
+        tokio::time::sleep_until(at).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::mock::MockBackend;
+    use crate::store::EntryStore;
+    use crate::validate::Validator;
+
+    #[tokio::test]
+    async fn resumes_from_the_checkpoint_and_skips_stored_words() {
+        let dir = std::env::temp_dir().join(format!("lingua-corpus-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("words.txt");
+        fs::write(&input, "alpha\n\n# comment\nbeta\nalpha\n").unwrap();
+        let store = Arc::new(EntryStore::open(dir.join("entries")).unwrap());
+        let service = WordService::new(
+            Arc::new(MockBackend::default()),
+            Arc::new(Validator::new("").unwrap()),
+        )
+        .with_store(store);
+        let opts = CorpusOptions {
+            input: input.clone(),
+            checkpoint: dir.join("state.json"),
+            concurrency: 1,
+            per_minute: None,
+            stats_every: Duration::from_secs(60),
+        };
+
+        let done = run(service.clone(), &opts, Arc::new(AtomicBool::new(false)))
+            .await
+            .unwrap();
+        assert_eq!(done.lines_done, 5);
+        assert_eq!((done.generated, done.skipped, done.failed), (2, 1, 0));
+        assert_eq!(Checkpoint::load(&opts.checkpoint, &input).unwrap(), done);
+
+        // More words appended later: only the new lines are read
+        fs::write(&input, "alpha\n\n# comment\nbeta\nalpha\ngamma\n").unwrap();
+        let done = run(service, &opts, Arc::new(AtomicBool::new(false)))
+            .await
+            .unwrap();
+        assert_eq!(done.lines_done, 6);
+        assert_eq!((done.generated, done.skipped), (3, 1));
+
+        let other = CorpusOptions {
+            input: dir.join("other.txt"),
+            ..opts.clone()
+        };
+        assert!(Checkpoint::load(&other.checkpoint, &other.input).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
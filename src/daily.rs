@@ -0,0 +1,167 @@
+use crate::cefr::{self, Level};
+use anyhow::{bail, Result};
+use std::fmt;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+/// A calendar date (proleptic Gregorian, UTC).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Date {
+    year: i64,
+    month: u32,
+    day: u32,
+}
+
+impl Date {
+    /// Parse `YYYY-MM-DD`, rejecting days the month does not have.
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut parts = s.trim().splitn(3, '-');
+        let (year, month, day) = (parts.next()?, parts.next()?, parts.next()?);
+        if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+            return None;
+        }
+        let date = Self {
+            year: year.parse().ok()?,
+            month: month.parse().ok()?,
+            day: day.parse().ok()?,
+        };
+        let valid = (1..=12).contains(&date.month)
+            && (1..=days_in_month(date.year, date.month)).contains(&date.day);
+        valid.then_some(date)
+    }
+
+    /// Today's date in UTC.
+    pub fn today() -> Self {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        Self::from_days((secs / 86_400) as i64)
+    }
+
+    /// Days since 1970-01-01 (negative before it).
+    pub fn days(self) -> i64 {
+        // Howard Hinnant's days_from_civil, with years starting in March
+        let year = if self.month <= 2 {
+            self.year - 1
+        } else {
+            self.year
+        };
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month = (self.month as i64 + 9) % 12;
+        let day_of_year = (153 * month + 2) / 5 + self.day as i64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146_097 + day_of_era - 719_468
+    }
+
+    fn from_days(days: i64) -> Self {
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * month + 2) / 5 + 1) as u32;
+        let month = if month < 10 { month + 3 } else { month - 9 } as u32;
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        Self { year, month, day }
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// The words `GET /v1/word-of-the-day` rotates through, one per date.
+///
+/// The same date always names the same word for a given list, so every
+/// client sees the same word of the day without any shared state.
+#[derive(Debug)]
+pub struct DailyWords {
+    words: Vec<String>,
+}
+
+impl Default for DailyWords {
+    /// The curated rotation in `data/word_of_the_day.txt`.
+    fn default() -> Self {
+        Self::from_lines(include_str!("../data/word_of_the_day.txt"))
+    }
+}
+
+impl DailyWords {
+    /// A rotation file: one word per line, `#` comments and blank lines ignored.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let words = Self::from_lines(&crate::util::read_to_string(path)?);
+        if words.words.is_empty() {
+            bail!("word-of-the-day list {:?} has no words", path);
+        }
+        info!(
+            count = words.words.len(),
+            ?path,
+            "loaded word-of-the-day list"
+        );
+        Ok(words)
+    }
+
+    /// Every word the embedded CEFR list places at `level`.
+    pub fn at_level(level: Level) -> Self {
+        Self {
+            words: cefr::words_at(level),
+        }
+    }
+
+    fn from_lines(text: &str) -> Self {
+        let words = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect();
+        Self { words }
+    }
+
+    /// The word for `date`, stepping one place through the list per day.
+    pub fn pick(&self, date: Date) -> &str {
+        let index = date.days().rem_euclid(self.words.len() as i64) as usize;
+        &self.words[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_map_to_a_daily_rotation() {
+        let epoch = Date::parse("1970-01-01").unwrap();
+        assert_eq!(epoch.days(), 0);
+        let leap = Date::parse("2024-02-29").unwrap();
+        assert_eq!(leap.days(), 19_782);
+        assert_eq!(Date::from_days(leap.days()), leap);
+        assert_eq!(leap.to_string(), "2024-02-29");
+        for bad in ["2023-02-29", "2024-13-01", "2024-1-01", "yesterday"] {
+            assert_eq!(Date::parse(bad), None, "{bad}");
+        }
+
+        let words = DailyWords::from_lines("# rotation\nalpha\n\nbeta\ngamma\n");
+        assert_eq!(words.pick(epoch), "alpha");
+        assert_eq!(words.pick(Date::parse("1970-01-05").unwrap()), "beta");
+        assert_eq!(words.pick(Date::parse("1969-12-31").unwrap()), "gamma");
+        assert!(!DailyWords::at_level(Level::C2).words.is_empty());
+    }
+}
@@ -0,0 +1,232 @@
+use crate::telemetry::{self, Served};
+use axum::extract::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// The operator page, self-contained: inline styles and script, no external assets.
+pub const PAGE: &str = include_str!("dashboard.html");
+
+/// Requests older than this drop out of rates and percentiles.
+const WINDOW: Duration = Duration::from_secs(300);
+/// Width of each bar in the request-rate history.
+const BUCKET: Duration = Duration::from_secs(10);
+/// Bound on remembered requests, so a burst cannot grow memory without limit.
+const MAX_SAMPLES: usize = 50_000;
+const RECENT_ERRORS: usize = 20;
+/// The dashboard's own polling is not counted as traffic.
+const OWN_PATH: &str = "/admin/dashboard";
+
+static STATS: Lazy<Mutex<Window>> = Lazy::new(|| Mutex::new(Window::new(Instant::now())));
+
+/// A request that ended with a 4xx or 5xx status.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecentError {
+    /// Unix timestamp in seconds
+    pub at: u64,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    pub latency_ms: u64,
+}
+
+/// Request latency percentiles over the window, in milliseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Latency {
+    pub p50: u64,
+    pub p95: u64,
+    pub p99: u64,
+    pub max: u64,
+}
+
+/// Everything the dashboard shows, from `GET /admin/dashboard/stats`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Snapshot {
+    pub uptime_secs: u64,
+    /// Requests per second over the last minute and the last five
+    pub requests_per_sec_1m: f64,
+    pub requests_per_sec_5m: f64,
+    /// Requests in each 10-second bucket of the window, oldest first
+    pub request_history: Vec<u64>,
+    pub errors_5m: u64,
+    pub latency_ms: Option<Latency>,
+    pub queue_depth: usize,
+    pub served: Served,
+    pub cache_hit_rate: Option<f64>,
+    pub decode_tokens_per_sec: Option<f64>,
+    /// Newest first
+    pub recent_errors: Vec<RecentError>,
+}
+
+struct Sample {
+    at: Instant,
+    latency: Duration,
+    error: bool,
+}
+
+struct Window {
+    started: Instant,
+    samples: VecDeque<Sample>,
+    errors: VecDeque<RecentError>,
+}
+
+impl Window {
+    fn new(started: Instant) -> Self {
+        Self {
+            started,
+            samples: VecDeque::new(),
+            errors: VecDeque::new(),
+        }
+    }
+
+    fn record(&mut self, now: Instant, latency: Duration, error: Option<RecentError>) {
+        self.expire(now);
+        if self.samples.len() == MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(Sample {
+            at: now,
+            latency,
+            error: error.is_some(),
+        });
+        if let Some(error) = error {
+            if self.errors.len() == RECENT_ERRORS {
+                self.errors.pop_back();
+            }
+            self.errors.push_front(error);
+        }
+    }
+
+    fn expire(&mut self, now: Instant) {
+        while self
+            .samples
+            .front()
+            .is_some_and(|s| now.duration_since(s.at) > WINDOW)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    fn snapshot(&mut self, now: Instant) -> Snapshot {
+        self.expire(now);
+        let uptime = now.duration_since(self.started);
+        let since = |window: Duration| {
+            self.samples
+                .iter()
+                .filter(|s| now.duration_since(s.at) <= window)
+                .count() as f64
+        };
+        // Before the service has run a full window, rate over the time it has
+        let rate = |window: Duration| since(window) / window.min(uptime).as_secs_f64().max(1.0);
+
+        let buckets = (WINDOW.as_secs() / BUCKET.as_secs()) as usize;
+        let mut request_history = vec![0; buckets];
+        for s in &self.samples {
+            let age = (now.duration_since(s.at).as_secs() / BUCKET.as_secs()) as usize;
+            if age < buckets {
+                request_history[buckets - 1 - age] += 1;
+            }
+        }
+
+        let mut latencies: Vec<u64> = self
+            .samples
+            .iter()
+            .map(|s| s.latency.as_millis() as u64)
+            .collect();
+        latencies.sort_unstable();
+        let latency_ms = latencies.last().map(|&max| {
+            let at = |q: f64| latencies[((latencies.len() - 1) as f64 * q).round() as usize];
+            Latency {
+                p50: at(0.50),
+                p95: at(0.95),
+                p99: at(0.99),
+                max,
+            }
+        });
+
+        let served = telemetry::served();
+        Snapshot {
+            uptime_secs: uptime.as_secs(),
+            requests_per_sec_1m: rate(Duration::from_secs(60)),
+            requests_per_sec_5m: rate(WINDOW),
+            request_history,
+            errors_5m: self.samples.iter().filter(|s| s.error).count() as u64,
+            latency_ms,
+            queue_depth: telemetry::queue_depth(),
+            served,
+            cache_hit_rate: served.cache_hit_rate(),
+            decode_tokens_per_sec: telemetry::rolling().decode_tps,
+            recent_errors: self.errors.iter().cloned().collect(),
+        }
+    }
+}
+
+/// Middleware feeding every response's status and latency into the dashboard.
+pub async fn track(req: Request, next: Next) -> Response {
+    let path = req.uri().path().to_string();
+    if path.starts_with(OWN_PATH) {
+        return next.run(req).await;
+    }
+    let method = req.method().to_string();
+    let started = Instant::now();
+    let res = next.run(req).await;
+    let latency = started.elapsed();
+    let status = res.status();
+    let error = (status.is_client_error() || status.is_server_error()).then(|| RecentError {
+        at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        method,
+        path,
+        status: status.as_u16(),
+        latency_ms: latency.as_millis() as u64,
+    });
+    STATS.lock().record(Instant::now(), latency, error);
+    res
+}
+
+/// Current request rates, latencies and service counters.
+pub fn snapshot() -> Snapshot {
+    STATS.lock().snapshot(Instant::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_rates_percentiles_and_errors() {
+        let start = Instant::now();
+        let mut window = Window::new(start);
+        let error = |status| RecentError {
+            at: 0,
+            method: "POST".into(),
+            path: "/v1/word".into(),
+            status,
+            latency_ms: 1,
+        };
+        // An old request that falls out of the window
+        window.record(start, Duration::from_millis(9_000), Some(error(500)));
+        let now = start + Duration::from_secs(400);
+        for ms in 1..=100 {
+            window.record(now, Duration::from_millis(ms), None);
+        }
+        window.record(now, Duration::from_millis(1), Some(error(404)));
+
+        let snap = window.snapshot(now);
+        assert_eq!(snap.uptime_secs, 400);
+        assert_eq!(snap.errors_5m, 1);
+        assert!((snap.requests_per_sec_1m - 101.0 / 60.0).abs() < 1e-9);
+        assert_eq!(snap.request_history.iter().sum::<u64>(), 101);
+        assert_eq!(*snap.request_history.last().unwrap(), 101);
+        let latency = snap.latency_ms.unwrap();
+        assert_eq!((latency.p50, latency.p99, latency.max), (50, 99, 100));
+        let statuses: Vec<u16> = snap.recent_errors.iter().map(|e| e.status).collect();
+        assert_eq!(statuses, [404, 500]);
+    }
+}
@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+
+/// Stable error codes returned by every endpoint.
+///
+/// Codes serialize as SCREAMING_SNAKE strings (`"NOT_A_WORD"`) and each has a
+/// fixed numeric value. Both are part of the public contract: existing codes
+/// are never renumbered or removed, so clients can match on them safely.
+///
+/// | Code                   | Numeric | Meaning                                         |
+/// |------------------------|---------|-------------------------------------------------|
+/// | `INVALID_INPUT`        | 1001    | Request input is empty, too long or malformed   |
+/// | `NOT_A_WORD`           | 1002    | Input is not something the service can analyze  |
+/// | `INVALID_CHARACTERS`   | 1003    | Input holds control or invisible characters     |
+/// | `VALIDATION_ERROR`     | 2001    | Model output violated the word contract         |
+/// | `JSON_PARSE_ERROR`     | 2002    | Model output was not valid JSON                 |
+/// | `INFERENCE_ERROR`      | 2003    | The model backend failed or is unavailable      |
+/// | `NOT_SUPPORTED`        | 2004    | The configured backend cannot do this           |
+/// | `TIMEOUT`              | 2005    | Generation ran past the word's time budget      |
+/// | `NOT_FOUND`            | 3001    | The requested entry or version does not exist   |
+/// | `ENTRY_LOCKED`         | 3002    | Entry is curated and cannot be regenerated      |
+/// | `PERSISTENCE_DISABLED` | 3003    | Endpoint needs persistence, which is off        |
+/// | `SENSE_NOT_FOUND`      | 3004    | The word has no sense with the requested PoS    |
+/// | `NOT_CACHED`           | 3005    | No stored entry, and no model to generate one   |
+/// | `UNAUTHORIZED`         | 4001    | Missing or invalid credentials                  |
+/// | `QUOTA_EXCEEDED`       | 4002    | The API key's daily token quota is used up      |
+/// | `FORBIDDEN`            | 4003    | Caller may not use the requested option         |
+/// | `RATE_LIMITED`         | 4029    | The API key sent too many requests this minute  |
+/// | `INTERNAL_ERROR`       | 5000    | Unexpected server-side failure                  |
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    InvalidInput,
+    NotAWord,
+    InvalidCharacters,
+    ValidationError,
+    JsonParseError,
+    InferenceError,
+    NotSupported,
+    Timeout,
+    NotFound,
+    EntryLocked,
+    PersistenceDisabled,
+    SenseNotFound,
+    NotCached,
+    Unauthorized,
+    Forbidden,
+    QuotaExceeded,
+    RateLimited,
+    InternalError,
+}
+
+impl ErrorCode {
+    pub fn numeric(self) -> u16 {
+        match self {
+            Self::InvalidInput => 1001,
+            Self::NotAWord => 1002,
+            Self::InvalidCharacters => 1003,
+            Self::ValidationError => 2001,
+            Self::JsonParseError => 2002,
+            Self::InferenceError => 2003,
+            Self::NotSupported => 2004,
+            Self::Timeout => 2005,
+            Self::NotFound => 3001,
+            Self::EntryLocked => 3002,
+            Self::PersistenceDisabled => 3003,
+            Self::SenseNotFound => 3004,
+            Self::NotCached => 3005,
+            Self::Unauthorized => 4001,
+            Self::Forbidden => 4003,
+            Self::QuotaExceeded => 4002,
+            Self::RateLimited => 4029,
+            Self::InternalError => 5000,
+        }
+    }
+
+    /// Legacy lowercase form, still emitted as `error_type` for older clients.
+    pub fn as_legacy_str(self) -> &'static str {
+        match self {
+            Self::InvalidInput => "invalid_input",
+            Self::NotAWord => "not_a_word",
+            Self::InvalidCharacters => "invalid_characters",
+            Self::ValidationError => "validation_error",
+            Self::JsonParseError => "json_parse_error",
+            Self::InferenceError => "inference_error",
+            Self::NotSupported => "not_supported",
+            Self::Timeout => "timeout",
+            Self::NotFound => "not_found",
+            Self::EntryLocked => "entry_locked",
+            Self::PersistenceDisabled => "persistence_disabled",
+            Self::SenseNotFound => "sense_not_found",
+            Self::NotCached => "not_cached",
+            Self::Unauthorized => "unauthorized",
+            Self::Forbidden => "forbidden",
+            Self::QuotaExceeded => "quota_exceeded",
+            Self::RateLimited => "rate_limited",
+            Self::InternalError => "internal_error",
+        }
+    }
+
+    /// Whether the same request may succeed if retried later.
+    pub fn is_retryable(self) -> bool {
+        matches!(
+            self,
+            Self::InferenceError | Self::Timeout | Self::InternalError | Self::RateLimited
+        )
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Reuse the serde representation so Display and JSON never disagree
+        let code = serde_json::to_value(self).map_err(|_| std::fmt::Error)?;
+        write!(f, "{}", code.as_str().unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_as_screaming_snake() {
+        assert_eq!(
+            serde_json::to_value(ErrorCode::NotAWord).unwrap(),
+            "NOT_A_WORD"
+        );
+        assert_eq!(ErrorCode::JsonParseError.to_string(), "JSON_PARSE_ERROR");
+        let parsed: ErrorCode = serde_json::from_str("\"ENTRY_LOCKED\"").unwrap();
+        assert_eq!(parsed, ErrorCode::EntryLocked);
+    }
+}
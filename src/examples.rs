@@ -0,0 +1,158 @@
+use crate::validate::{ValidationError, Violation};
+use jsonschema::JSONSchema;
+use once_cell::sync::Lazy;
+use serde_json::Value;
+use std::collections::HashSet;
+use tracing::warn;
+
+/// Sentences returned when a request does not say how many.
+pub const DEFAULT_EXAMPLES: usize = 3;
+/// Most sentences one request may ask for.
+pub const MAX_EXAMPLES: usize = 5;
+/// Longest accepted sentence, in words, as the entry contract asks of `exampleSentence`.
+const MAX_SENTENCE_WORDS: usize = 25;
+
+static SCHEMA: Lazy<JSONSchema> =
+    Lazy::new(|| crate::extract::compile(include_str!("../schema/examples.schema.json")));
+
+/// Check a model's extra example sentences against
+/// `schema/examples.schema.json` and keep the usable ones, up to `count`:
+/// sentences for one of the entry's senses (`parts_of_speech`), at most 25
+/// words long, using the word (`forms` are its surface and base form), and not
+/// repeating the entry's own examples (`existing`) or each other.
+///
+/// An answer with nothing usable is a retryable violation.
+pub fn validate(
+    mut v: Value,
+    surface_word: &str,
+    forms: &[&str],
+    parts_of_speech: &[&str],
+    existing: &[String],
+    count: usize,
+) -> Result<Value, ValidationError> {
+    if let Err(errors) = SCHEMA.validate(&v) {
+        return Err(ValidationError::SchemaValidation(
+            errors.map(Violation::from_schema_error).collect(),
+        ));
+    }
+    v["word"] = Value::String(surface_word.to_string());
+
+    let mut seen: HashSet<String> = existing.iter().map(|s| normalize(s)).collect();
+    let examples = v["examples"].as_array_mut().expect("checked by the schema");
+    examples.retain_mut(|example| {
+        let sentence = example["sentence"]
+            .as_str()
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        let pos = example["partOfSpeech"].as_str().unwrap_or_default();
+        let problem = if !parts_of_speech.contains(&pos) {
+            Some(format!("the entry has no {} sense", pos))
+        } else if sentence.split_whitespace().count() > MAX_SENTENCE_WORDS {
+            Some(format!("longer than {} words", MAX_SENTENCE_WORDS))
+        } else if !uses_word(&sentence, forms) {
+            Some("does not use the word".to_string())
+        } else if !seen.insert(normalize(&sentence)) {
+            Some("repeats an earlier example".to_string())
+        } else {
+            None
+        };
+        if let Some(problem) = problem {
+            warn!(
+                "Dropping example for '{}' ({}): {}",
+                surface_word, problem, sentence
+            );
+            return false;
+        }
+        example["sentence"] = Value::String(sentence);
+        true
+    });
+    examples.truncate(count);
+
+    if examples.is_empty() {
+        return Err(ValidationError::SchemaValidation(vec![Violation {
+            path: "/examples".to_string(),
+            keyword: "examples".to_string(),
+            message: "no usable example sentences".to_string(),
+        }]));
+    }
+    Ok(v)
+}
+
+/// Whether some word of `sentence` is built on one of `forms`. A final `e`
+/// or `y` of longer forms is optional, so "deciding" and "studied" count.
+fn uses_word(sentence: &str, forms: &[&str]) -> bool {
+    let stems: Vec<String> = forms
+        .iter()
+        .map(|form| {
+            let form = form.trim().to_lowercase();
+            match form.strip_suffix(['e', 'y']) {
+                Some(stem) if form.chars().count() >= 4 => stem.to_string(),
+                _ => form,
+            }
+        })
+        .filter(|stem| !stem.is_empty())
+        .collect();
+    sentence
+        .split(|c: char| !c.is_alphanumeric() && c != '-')
+        .map(str::to_lowercase)
+        .any(|token| stems.iter().any(|stem| token.starts_with(stem.as_str())))
+}
+
+/// Case and punctuation-insensitive form for spotting repeated sentences.
+fn normalize(sentence: &str) -> String {
+    sentence
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn keeps_new_sentences_that_use_the_word() {
+        let answer = json!({
+            "examples": [
+                { "partOfSpeech": "verb", "sentence": " We decided to stay home. " },
+                { "partOfSpeech": "verb", "sentence": "She is deciding today." },
+                { "partOfSpeech": "verb", "sentence": "They chose the red one." },
+                { "partOfSpeech": "noun", "sentence": "The decision was hard." },
+                { "partOfSpeech": "verb", "sentence": "I can't decide!" },
+                { "partOfSpeech": "verb", "sentence": "we decided to stay home" }
+            ]
+        });
+        let existing = vec!["I can't decide.".to_string()];
+        let out = validate(
+            answer.clone(),
+            "decide",
+            &["decide"],
+            &["verb"],
+            &existing,
+            5,
+        )
+        .unwrap();
+        assert_eq!(out["word"], "decide");
+        let sentences: Vec<&str> = out["examples"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["sentence"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            sentences,
+            ["We decided to stay home.", "She is deciding today."]
+        );
+
+        let out = validate(answer, "decide", &["decide"], &["verb"], &[], 1).unwrap();
+        assert_eq!(out["examples"].as_array().unwrap().len(), 1);
+
+        let unusable = json!({ "examples": [{ "partOfSpeech": "verb", "sentence": "No." }] });
+        let err = validate(unusable, "decide", &["decide"], &["verb"], &[], 3).unwrap_err();
+        assert!(err.is_retryable());
+    }
+}
@@ -0,0 +1,107 @@
+use crate::api::ErrorResponse;
+use crate::error::ErrorCode;
+use crate::validate::Violation;
+use axum::extract::{rejection::JsonRejection, FromRequest, Request};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use jsonschema::JSONSchema;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// A request body type with a JSON Schema for its shape.
+///
+/// Implement it with [`request_schema!`], which compiles the schema once.
+pub trait RequestSchema: DeserializeOwned {
+    fn schema() -> &'static JSONSchema;
+}
+
+/// Implement [`RequestSchema`] for `$ty` from a JSON Schema literal.
+macro_rules! request_schema {
+    ($ty:ty, $schema:expr) => {
+        impl $crate::extract::RequestSchema for $ty {
+            fn schema() -> &'static jsonschema::JSONSchema {
+                static SCHEMA: once_cell::sync::Lazy<jsonschema::JSONSchema> =
+                    once_cell::sync::Lazy::new(|| $crate::extract::compile($schema));
+                &SCHEMA
+            }
+        }
+    };
+}
+pub(crate) use request_schema;
+
+#[doc(hidden)]
+pub fn compile(src: &str) -> JSONSchema {
+    let schema: Value = serde_json::from_str(src).expect("request schema is valid JSON");
+    JSONSchema::options()
+        .with_draft(jsonschema::Draft::Draft202012)
+        .compile(&schema)
+        .expect("request schema compiles")
+}
+
+/// `Json<T>` that checks the body against `T`'s schema first, so malformed
+/// requests get the API's `ErrorResponse` shape with one `details` entry per
+/// offending field instead of axum's plain-text rejection. Status codes stay
+/// axum's: 400 for broken syntax, 415 without a JSON content type and 422 for
+/// well-formed JSON of the wrong shape.
+pub struct ValidJson<T>(pub T);
+
+#[axum::async_trait]
+impl<T, S> FromRequest<S> for ValidJson<T>
+where
+    T: RequestSchema,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(body) = Json::<Value>::from_request(req, state)
+            .await
+            .map_err(body_rejection)?;
+
+        if let Err(errors) = T::schema().validate(&body) {
+            let details: Vec<Violation> = errors.map(Violation::from_schema_error).collect();
+            let message = match details.as_slice() {
+                [only] => format!("Invalid request body: {}", describe(only)),
+                _ => format!("Invalid request body: {} problems", details.len()),
+            };
+            return Err(invalid(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                message,
+                Some(details),
+            ));
+        }
+        serde_json::from_value(body).map(ValidJson).map_err(|e| {
+            invalid(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("Invalid request body: {}", e),
+                None,
+            )
+        })
+    }
+}
+
+fn describe(violation: &Violation) -> String {
+    if violation.path.is_empty() {
+        violation.message.clone()
+    } else {
+        format!("{} at {}", violation.message, violation.path)
+    }
+}
+
+/// Missing content type, unreadable body or broken JSON syntax.
+fn body_rejection(rejection: JsonRejection) -> Response {
+    let message = match &rejection {
+        JsonRejection::MissingJsonContentType(_) => {
+            "Expected a JSON body with `Content-Type: application/json`".to_string()
+        }
+        _ => format!("Malformed JSON body: {}", rejection.body_text()),
+    };
+    invalid(rejection.status(), message, None)
+}
+
+fn invalid(status: StatusCode, message: String, details: Option<Vec<Violation>>) -> Response {
+    let mut error_response = ErrorResponse::new(ErrorCode::InvalidInput, message, None);
+    error_response.details = details;
+    (status, Json(error_response)).into_response()
+}
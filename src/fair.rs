@@ -0,0 +1,210 @@
+use crate::model::{BackendError, BackendStats, InferParams, LlmBackend, PromptParts, Token};
+use crate::telemetry;
+use anyhow::Result;
+use parking_lot::Mutex;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// Inference slots shared out in turn among the requesters waiting for one,
+/// rather than in arrival order, so a key submitting a large batch cannot
+/// hold every slot while others queue behind it for minutes.
+pub struct FairScheduler {
+    slots: usize,
+    max_wait: Option<Duration>,
+    queues: Mutex<Queues>,
+}
+
+#[derive(Default)]
+struct Queues {
+    running: usize,
+    /// Each requester's waiters, in arrival order
+    waiting: HashMap<Arc<str>, VecDeque<oneshot::Sender<Permit>>>,
+    /// Requesters with waiters, next turn first
+    turns: VecDeque<Arc<str>>,
+}
+
+impl Queues {
+    fn push(&mut self, requester: &str, waiter: oneshot::Sender<Permit>) {
+        match self.waiting.get_mut(requester) {
+            Some(queue) => queue.push_back(waiter),
+            None => {
+                let requester: Arc<str> = Arc::from(requester);
+                self.waiting
+                    .insert(requester.clone(), VecDeque::from([waiter]));
+                self.turns.push_back(requester);
+            }
+        }
+    }
+
+    /// The first waiter of the requester whose turn it is; that requester
+    /// goes to the back of the line if it has more.
+    fn next(&mut self) -> Option<oneshot::Sender<Permit>> {
+        while let Some(requester) = self.turns.pop_front() {
+            let Some(queue) = self.waiting.get_mut(&requester) else {
+                continue;
+            };
+            // Waiters that gave up (timed out, or their request was dropped)
+            while let Some(waiter) = queue.pop_front() {
+                if waiter.is_closed() {
+                    continue;
+                }
+                if queue.is_empty() {
+                    self.waiting.remove(&requester);
+                } else {
+                    self.turns.push_back(requester);
+                }
+                return Some(waiter);
+            }
+            self.waiting.remove(&requester);
+        }
+        None
+    }
+}
+
+impl FairScheduler {
+    /// `slots` inferences run at once; a request waiting longer than
+    /// `max_wait` for one fails with [`BackendError::QueueTimeout`].
+    pub fn new(slots: usize, max_wait: Option<Duration>) -> Self {
+        Self {
+            slots: slots.max(1),
+            max_wait,
+            queues: Mutex::new(Queues::default()),
+        }
+    }
+
+    /// Wait for `requester`'s turn at a slot, held until the permit drops.
+    pub async fn acquire(self: &Arc<Self>, requester: &str) -> Result<Permit, BackendError> {
+        let granted = {
+            let mut queues = self.queues.lock();
+            // A free slot means nobody is waiting; slots pass straight to waiters
+            if queues.running < self.slots {
+                queues.running += 1;
+                return Ok(Permit(Some(self.clone())));
+            }
+            let (waiter, granted) = oneshot::channel();
+            queues.push(requester, waiter);
+            granted
+        };
+        let _queued = telemetry::Queued::enter();
+        let permit = match self.max_wait {
+            Some(wait) => tokio::time::timeout(wait, granted)
+                .await
+                .map_err(|_| BackendError::QueueTimeout(wait))?,
+            None => granted.await,
+        };
+        Ok(permit.expect("scheduler outlives its waiters"))
+    }
+
+    fn release(self: &Arc<Self>) {
+        let mut queues = self.queues.lock();
+        while let Some(waiter) = queues.next() {
+            match waiter.send(Permit(Some(self.clone()))) {
+                Ok(()) => return,
+                // Gave up just now; disarm the permit so it frees nothing
+                Err(mut permit) => drop(permit.0.take()),
+            }
+        }
+        queues.running -= 1;
+    }
+}
+
+/// One inference slot, given to the next waiter when dropped.
+pub struct Permit(Option<Arc<FairScheduler>>);
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if let Some(scheduler) = self.0.take() {
+            scheduler.release();
+        }
+    }
+}
+
+/// The backend as one requester sees it: each inference first waits for that
+/// requester's turn at a slot.
+pub struct Scheduled {
+    inner: Arc<dyn LlmBackend>,
+    scheduler: Arc<FairScheduler>,
+    requester: Arc<str>,
+}
+
+impl Scheduled {
+    pub fn new(
+        inner: Arc<dyn LlmBackend>,
+        scheduler: Arc<FairScheduler>,
+        requester: impl Into<Arc<str>>,
+    ) -> Self {
+        Self {
+            inner,
+            scheduler,
+            requester: requester.into(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmBackend for Scheduled {
+    async fn infer_json(&self, prompt: PromptParts, params: &InferParams) -> Result<Vec<u8>> {
+        let _permit = self.scheduler.acquire(&self.requester).await?;
+        self.inner.infer_json(prompt, params).await
+    }
+
+    fn model_name(&self) -> String {
+        self.inner.model_name()
+    }
+
+    fn tokenize(&self, text: &str) -> Option<Result<Vec<Token>>> {
+        self.inner.tokenize(text)
+    }
+
+    fn count_tokens(&self, text: &str) -> Option<usize> {
+        self.inner.count_tokens(text)
+    }
+
+    fn stats(&self) -> Option<BackendStats> {
+        self.inner.stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn waiting_requesters_take_turns() {
+        let scheduler = Arc::new(FairScheduler::new(1, None));
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let held = scheduler.acquire("a").await.unwrap();
+
+        let mut tasks = Vec::new();
+        for (requester, name) in [("a", "a2"), ("a", "a3"), ("b", "b1")] {
+            let (scheduler, order) = (scheduler.clone(), order.clone());
+            tasks.push(tokio::spawn(async move {
+                let _permit = scheduler.acquire(requester).await.unwrap();
+                order.lock().push(name);
+            }));
+            tokio::task::yield_now().await;
+        }
+        drop(held);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        // In arrival order "a" would have run twice before "b"
+        assert_eq!(*order.lock(), ["a2", "b1", "a3"]);
+    }
+
+    #[tokio::test]
+    async fn waiters_that_give_up_do_not_hold_slots() {
+        let wait = Duration::from_millis(10);
+        let scheduler = Arc::new(FairScheduler::new(1, Some(wait)));
+        let held = scheduler.acquire("a").await.unwrap();
+        assert!(matches!(
+            scheduler.acquire("b").await,
+            Err(BackendError::QueueTimeout(_))
+        ));
+        drop(held);
+        let _again = scheduler.acquire("b").await.unwrap();
+        assert_eq!(scheduler.queues.lock().running, 1);
+    }
+}
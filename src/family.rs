@@ -0,0 +1,127 @@
+use crate::validate::{ValidationError, Violation};
+use jsonschema::JSONSchema;
+use once_cell::sync::Lazy;
+use serde_json::Value;
+use std::collections::HashSet;
+use tracing::warn;
+
+/// Most members a family may list; the schema rejects longer answers.
+pub const MAX_MEMBERS: usize = 12;
+
+static SCHEMA: Lazy<JSONSchema> =
+    Lazy::new(|| crate::extract::compile(include_str!("../schema/family.schema.json")));
+
+/// Check a model's word-family answer against `schema/family.schema.json`
+/// and `parts_of_speech`, and tidy it: `word` becomes the surface word,
+/// members are lowercased and deduplicated, and the headword itself and
+/// members that share no stem with it (look-alikes the model drifted into)
+/// are dropped.
+pub fn validate(
+    mut v: Value,
+    surface_word: &str,
+    parts_of_speech: &[&str],
+) -> Result<Value, ValidationError> {
+    if let Err(errors) = SCHEMA.validate(&v) {
+        return Err(ValidationError::SchemaValidation(
+            errors.map(Violation::from_schema_error).collect(),
+        ));
+    }
+    let members = v["members"].as_array().expect("checked by the schema");
+    if let Some(pos) = members
+        .iter()
+        .filter_map(|m| m["partOfSpeech"].as_str())
+        .find(|pos| !parts_of_speech.contains(pos))
+    {
+        return Err(ValidationError::InvalidFieldValue {
+            field: "partOfSpeech".to_string(),
+            reason: format!("'{}' is not a valid part of speech", pos),
+        });
+    }
+    v["word"] = Value::String(surface_word.to_string());
+
+    let headword = surface_word.trim().to_lowercase();
+    let mut seen = HashSet::from([headword.clone()]);
+    let members = v["members"].as_array_mut().expect("checked by the schema");
+    members.retain_mut(|member| {
+        let word = member["word"]
+            .as_str()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+        if !shares_stem(&headword, &word) {
+            warn!(
+                "Dropping '{}' from the family of '{}': no shared stem",
+                word, headword
+            );
+            return false;
+        }
+        member["word"] = Value::String(word.clone());
+        seen.insert(word)
+    });
+    Ok(v)
+}
+
+/// Whether `member` starts like `headword` for long enough to be built on the
+/// same stem. Short headwords change more under derivation ("deep" → "depth"),
+/// so two letters suffice for them.
+fn shares_stem(headword: &str, member: &str) -> bool {
+    let needed = if headword.chars().count() <= 4 { 2 } else { 3 };
+    let shared = headword
+        .chars()
+        .zip(member.chars())
+        .take_while(|(a, b)| a == b)
+        .count();
+    shared >= needed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate::PARTS_OF_SPEECH;
+    use serde_json::json;
+
+    #[test]
+    fn keeps_only_distinct_members_sharing_the_stem() {
+        let answer = json!({
+            "word": "ignored",
+            "members": [
+                { "word": "Decision", "partOfSpeech": "noun" },
+                { "word": "decisive", "partOfSpeech": "adjective" },
+                { "word": "decision", "partOfSpeech": "noun" },
+                { "word": "decide", "partOfSpeech": "verb" },
+                { "word": "resolve", "partOfSpeech": "verb" }
+            ]
+        });
+        let out = validate(answer, "decide", &PARTS_OF_SPEECH).unwrap();
+        assert_eq!(out["word"], "decide");
+        let words: Vec<&str> = out["members"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["word"].as_str().unwrap())
+            .collect();
+        assert_eq!(words, ["decision", "decisive"]);
+
+        let too_many = json!({
+            "word": "decide",
+            "members": vec![json!({ "word": "decider", "partOfSpeech": "noun" }); MAX_MEMBERS + 1]
+        });
+        assert!(matches!(
+            validate(too_many, "decide", &PARTS_OF_SPEECH),
+            Err(ValidationError::SchemaValidation(_))
+        ));
+        let bad_pos =
+            json!({ "word": "deep", "members": [{ "word": "depth", "partOfSpeech": "thing" }] });
+        assert!(validate(bad_pos, "deep", &PARTS_OF_SPEECH).is_err());
+    }
+
+    #[test]
+    fn member_tags_follow_the_contract_profiles() {
+        let answer =
+            json!({ "word": "ka", "members": [{ "word": "kana", "partOfSpeech": "particle" }] });
+        assert!(validate(answer.clone(), "ka", &PARTS_OF_SPEECH).is_err());
+        let mut japanese = PARTS_OF_SPEECH.to_vec();
+        japanese.push("particle");
+        assert!(validate(answer, "ka", &japanese).is_ok());
+    }
+}
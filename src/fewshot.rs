@@ -0,0 +1,195 @@
+use crate::cache::WordCache;
+use crate::model::FewShot;
+use crate::validate::Validator;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+use tracing::{info, warn};
+
+/// Exemplar word entries shown to the model ahead of the requested word.
+///
+/// Loaded from a directory of `<word>.json` files, each holding a complete
+/// entry. Examples that fail validation are skipped at load time so the model
+/// is only ever shown entries that honor the contract.
+#[derive(Debug, Default)]
+pub struct FewShotLibrary {
+    examples: Vec<FewShot>,
+}
+
+impl FewShotLibrary {
+    pub fn load<P: AsRef<Path>>(dir: P, validator: &Validator) -> Result<Self> {
+        let dir = dir.as_ref();
+        let mut paths: Vec<_> = fs::read_dir(dir)
+            .with_context(|| format!("read few-shot dir {:?}", dir))?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        // Directory order is platform dependent; keep prompts reproducible
+        paths.sort();
+
+        let mut examples = Vec::with_capacity(paths.len());
+        for path in paths {
+            let Some(word) = path.file_stem().map(|s| s.to_string_lossy().into_owned()) else {
+                continue;
+            };
+            let raw = fs::read(&path).with_context(|| format!("read {:?}", path))?;
+            let entry = match serde_json::from_slice(&raw) {
+                Ok(v) => v,
+                Err(e) => {
+                    warn!("Skipping few-shot example {:?}: invalid JSON: {}", path, e);
+                    continue;
+                }
+            };
+            match validator.validate_and_fix(entry, &word) {
+                Ok(entry) => examples.push(FewShot { word, entry }),
+                Err(e) => warn!("Skipping few-shot example {:?}: {}", path, e),
+            }
+        }
+        info!(count = examples.len(), ?dir, "loaded few-shot examples");
+        Ok(Self { examples })
+    }
+
+    /// Up to `count` examples for `word`, never including the word itself.
+    pub fn select(&self, word: &str, count: usize) -> Vec<FewShot> {
+        self.examples
+            .iter()
+            .filter(|ex| !ex.word.eq_ignore_ascii_case(word.trim()))
+            .take(count)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Endings that mark a morphological class, with the parts of speech words of
+/// that class usually take. Longer endings come first so they win.
+const MORPHOLOGY: &[(&str, &[&str])] = &[
+    ("tion", &["noun"]),
+    ("sion", &["noun"]),
+    ("ment", &["noun"]),
+    ("ness", &["noun"]),
+    ("ity", &["noun"]),
+    ("able", &["adjective"]),
+    ("ible", &["adjective"]),
+    ("less", &["adjective"]),
+    ("ous", &["adjective"]),
+    ("ful", &["adjective"]),
+    ("ive", &["adjective"]),
+    ("ing", &["gerund", "participle", "verb"]),
+    ("ed", &["participle", "verb", "adjective"]),
+    ("en", &["participle", "verb"]),
+    ("ly", &["adverb"]),
+    ("est", &["adjective"]),
+    ("er", &["noun", "adjective"]),
+];
+
+fn morphological_class(word: &str) -> Option<&'static (&'static str, &'static [&'static str])> {
+    MORPHOLOGY
+        .iter()
+        .find(|(ending, _)| word.len() > ending.len() + 2 && word.ends_with(ending))
+}
+
+/// How useful `candidate` is as an example for `word`: shared ending length,
+/// plus bonuses for the same morphological class and for the candidate
+/// actually carrying a part of speech that class predicts. 0 means unrelated.
+pub fn similarity(word: &str, candidate: &str, entry: &serde_json::Value) -> u32 {
+    let word = word.trim().to_lowercase();
+    let candidate = candidate.trim().to_lowercase();
+    if word == candidate {
+        return 0;
+    }
+    let shared = word
+        .chars()
+        .rev()
+        .zip(candidate.chars().rev())
+        .take_while(|(a, b)| a == b)
+        .count()
+        .min(5) as u32;
+
+    let class = morphological_class(&word);
+    let same_class = class.is_some() && class == morphological_class(&candidate);
+    if !same_class && shared < 3 {
+        return 0;
+    }
+
+    let mut score = shared;
+    if let (true, Some((_, expected_pos))) = (same_class, class) {
+        score += 4;
+        let has_expected_pos = entry
+            .get("meanings")
+            .and_then(|m| m.as_array())
+            .is_some_and(|meanings| {
+                meanings.iter().any(|m| {
+                    m.get("partOfSpeech")
+                        .and_then(|p| p.as_str())
+                        .is_some_and(|p| expected_pos.contains(&p))
+                })
+            });
+        if has_expected_pos {
+            score += 3;
+        }
+    }
+    score
+}
+
+/// Previously validated entries most similar to `word`, best first.
+pub fn similar_from_cache(cache: &WordCache, word: &str, count: usize) -> Vec<FewShot> {
+    cache
+        .best_matches(count, |key, entry| similarity(word, key, &entry.value))
+        .into_iter()
+        .map(|(word, entry)| FewShot { word, entry })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::mock::MockBackend;
+
+    #[test]
+    fn loads_valid_examples_in_name_order() {
+        let dir = std::env::temp_dir().join(format!("lingua-fewshot-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for word in ["swim", "bright"] {
+            let entry = MockBackend::entry_for(word);
+            fs::write(dir.join(format!("{}.json", word)), entry.to_string()).unwrap();
+        }
+        fs::write(dir.join("broken.json"), r#"{"word":"broken"}"#).unwrap();
+        fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        let library = FewShotLibrary::load(&dir, &Validator::new("").unwrap()).unwrap();
+        let words: Vec<_> = library
+            .select("run", 5)
+            .into_iter()
+            .map(|e| e.word)
+            .collect();
+        assert_eq!(words, vec!["bright", "swim"]);
+        let words: Vec<_> = library
+            .select("Bright", 5)
+            .into_iter()
+            .map(|e| e.word)
+            .collect();
+        assert_eq!(words, vec!["swim"]);
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn prefers_same_morphological_class_from_cache() {
+        let cache = WordCache::new(10, None, false);
+        let mut swimming = MockBackend::entry_for("swimming");
+        swimming["meanings"][0]["partOfSpeech"] = "gerund".into();
+        cache.insert("swimming", swimming, "m", "1");
+        cache.insert("singer", MockBackend::entry_for("singer"), "m", "1");
+        cache.insert("table", MockBackend::entry_for("table"), "m", "1");
+        cache.insert("running", MockBackend::entry_for("running"), "m", "1");
+
+        let picked: Vec<_> = similar_from_cache(&cache, "running", 3)
+            .into_iter()
+            .map(|e| e.word)
+            .collect();
+        // "running" itself is excluded and unrelated "table" never qualifies
+        assert_eq!(picked[0], "swimming");
+        assert!(!picked.contains(&"running".to_string()));
+        assert!(!picked.contains(&"table".to_string()));
+    }
+}
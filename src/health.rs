@@ -0,0 +1,331 @@
+use crate::model::{
+    prompt, BackendError, BackendStats, InferParams, LlmBackend, PromptParts, PromptTask, Token,
+};
+use anyhow::Result;
+use futures_util::FutureExt;
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::any::Any;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{debug, error, warn};
+
+/// Word sent by the canary; short and unambiguous so a healthy model answers fast.
+const CANARY_WORD: &str = "water";
+
+/// Panics in a row, without a successful inference between them, after which
+/// the backend is re-checked with a canary.
+const PANIC_RECHECK_THRESHOLD: u32 = 3;
+
+/// Outcome of the most recent canary inference.
+#[derive(Debug, Clone, Serialize)]
+pub struct CanaryReport {
+    pub ok: bool,
+    pub latency_ms: u64,
+    /// Unix timestamp in seconds
+    pub checked_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Readiness as seen by `/readyz`. Without a canary the instance is ready as
+/// soon as it serves; with one it is ready only while the last canary passed.
+#[derive(Debug, Default)]
+pub struct Readiness {
+    canary_enabled: bool,
+    last: RwLock<Option<CanaryReport>>,
+}
+
+impl Readiness {
+    pub fn with_canary() -> Self {
+        Self {
+            canary_enabled: true,
+            last: RwLock::new(None),
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        match &*self.last.read() {
+            Some(report) => report.ok,
+            None => !self.canary_enabled,
+        }
+    }
+
+    pub fn last_canary(&self) -> Option<CanaryReport> {
+        self.last.read().clone()
+    }
+
+    pub fn record(&self, report: CanaryReport) {
+        *self.last.write() = Some(report);
+    }
+}
+
+/// Run one canary inference. It fails when the backend errors, returns
+/// something that is not JSON, or takes longer than `budget`.
+pub async fn run_canary(
+    backend: &dyn LlmBackend,
+    params: &InferParams,
+    budget: Duration,
+) -> CanaryReport {
+    let prompt = PromptParts {
+        system: prompt::DEFAULT_SYSTEM.to_string(),
+        user_word: CANARY_WORD.to_string(),
+        examples: Vec::new(),
+        context: None,
+        contract: Default::default(),
+        task: PromptTask::Entry,
+    };
+    let started = Instant::now();
+    let outcome = tokio::time::timeout(budget, backend.infer_json(prompt, params)).await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    let error = match outcome {
+        Err(_) => Some(format!(
+            "exceeded latency budget of {} ms",
+            budget.as_millis()
+        )),
+        Ok(Err(e)) => Some(format!("{:#}", e)),
+        Ok(Ok(bytes)) => serde_json::from_slice::<serde_json::Value>(&bytes)
+            .err()
+            .map(|e| format!("canary output is not JSON: {}", e)),
+    };
+    CanaryReport {
+        ok: error.is_none(),
+        latency_ms,
+        checked_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        error,
+    }
+}
+
+/// Re-run the canary every `interval` for the life of the process.
+pub fn spawn_canary(
+    backend: Arc<dyn LlmBackend>,
+    params: InferParams,
+    readiness: Arc<Readiness>,
+    interval: Duration,
+    budget: Duration,
+) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            let report = run_canary(backend.as_ref(), &params, budget).await;
+            match &report.error {
+                None => debug!(latency_ms = report.latency_ms, "canary passed"),
+                Some(error) => {
+                    warn!(latency_ms = report.latency_ms, %error, "canary failed; marking unready")
+                }
+            }
+            readiness.record(report);
+        }
+    });
+}
+
+/// The message a panic was raised with, when it carried one.
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "panic without a message".to_string()
+    }
+}
+
+/// Wraps a backend so a panic inside it (typically in the llama.cpp
+/// bindings) fails that one inference with [`BackendError::Panicked`]
+/// instead of unwinding through the request. After
+/// [`PANIC_RECHECK_THRESHOLD`] panics in a row the canary runs at once and
+/// its result goes to `/readyz`, so a backend left broken is taken out of
+/// rotation without waiting for the next scheduled check.
+pub struct PanicGuard {
+    inner: Arc<dyn LlmBackend>,
+    readiness: Arc<Readiness>,
+    params: InferParams,
+    budget: Duration,
+    consecutive: Arc<AtomicU32>,
+    rechecking: Arc<AtomicBool>,
+}
+
+impl PanicGuard {
+    pub fn new(
+        inner: Arc<dyn LlmBackend>,
+        readiness: Arc<Readiness>,
+        params: InferParams,
+        budget: Duration,
+    ) -> Self {
+        Self {
+            inner,
+            readiness,
+            params,
+            budget,
+            consecutive: Arc::new(AtomicU32::new(0)),
+            rechecking: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    fn recheck(&self) {
+        if self.rechecking.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let inner = self.inner.clone();
+        let readiness = self.readiness.clone();
+        let (params, budget) = (self.params.clone(), self.budget);
+        let (consecutive, rechecking) = (self.consecutive.clone(), self.rechecking.clone());
+        tokio::spawn(async move {
+            let report = AssertUnwindSafe(run_canary(inner.as_ref(), &params, budget))
+                .catch_unwind()
+                .await
+                .unwrap_or_else(|payload| CanaryReport {
+                    ok: false,
+                    latency_ms: 0,
+                    checked_at: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map(|d| d.as_secs())
+                        .unwrap_or(0),
+                    error: Some(format!("canary panicked: {}", panic_message(&*payload))),
+                });
+            match &report.error {
+                None => warn!("backend passed its re-check after repeated panics"),
+                Some(e) => {
+                    error!(error = %e, "backend failed its re-check after repeated panics; marking unready")
+                }
+            }
+            readiness.record(report);
+            consecutive.store(0, Ordering::Relaxed);
+            rechecking.store(false, Ordering::Release);
+        });
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmBackend for PanicGuard {
+    async fn infer_json(&self, prompt: PromptParts, params: &InferParams) -> Result<Vec<u8>> {
+        match AssertUnwindSafe(self.inner.infer_json(prompt, params))
+            .catch_unwind()
+            .await
+        {
+            Ok(result) => {
+                if result.is_ok() {
+                    self.consecutive.store(0, Ordering::Relaxed);
+                }
+                result
+            }
+            Err(payload) => {
+                let message = panic_message(&*payload);
+                crate::telemetry::record_panic("inference");
+                let panics = self.consecutive.fetch_add(1, Ordering::Relaxed) + 1;
+                error!(panics, "inference panicked: {}", message);
+                if panics >= PANIC_RECHECK_THRESHOLD {
+                    self.recheck();
+                }
+                Err(BackendError::Panicked(message).into())
+            }
+        }
+    }
+
+    fn model_name(&self) -> String {
+        self.inner.model_name()
+    }
+
+    fn tokenize(&self, text: &str) -> Option<Result<Vec<Token>>> {
+        self.inner.tokenize(text)
+    }
+
+    fn count_tokens(&self, text: &str) -> Option<usize> {
+        self.inner.count_tokens(text)
+    }
+
+    fn stats(&self) -> Option<BackendStats> {
+        self.inner.stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::mock::MockBackend;
+
+    fn params() -> InferParams {
+        InferParams {
+            max_tokens: 64,
+            temp: 0.0,
+            top_p: 1.0,
+            min_p: 0.0,
+            repeat_penalty: 1.0,
+            ..InferParams::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn slow_backend_fails_the_budget() {
+        let readiness = Readiness::with_canary();
+        assert!(
+            !readiness.is_ready(),
+            "unready until the first canary passes"
+        );
+
+        let fast = MockBackend::new(Duration::ZERO);
+        readiness.record(run_canary(&fast, &params(), Duration::from_secs(1)).await);
+        assert!(readiness.is_ready());
+
+        let slow = MockBackend::new(Duration::from_millis(200));
+        let report = run_canary(&slow, &params(), Duration::from_millis(20)).await;
+        assert!(report.error.as_deref().unwrap().contains("latency budget"));
+        readiness.record(report);
+        assert!(!readiness.is_ready());
+    }
+
+    struct Panicking;
+
+    #[async_trait::async_trait]
+    impl LlmBackend for Panicking {
+        async fn infer_json(&self, _: PromptParts, _: &InferParams) -> Result<Vec<u8>> {
+            panic!("ggml_abort");
+        }
+    }
+
+    #[tokio::test]
+    async fn repeated_panics_trigger_a_recheck() {
+        let readiness = Arc::new(Readiness::default());
+        let guard = PanicGuard::new(
+            Arc::new(Panicking),
+            readiness.clone(),
+            params(),
+            Duration::from_secs(1),
+        );
+        let prompt = || PromptParts {
+            system: String::new(),
+            user_word: "run".to_string(),
+            examples: Vec::new(),
+            context: None,
+            contract: Default::default(),
+            task: PromptTask::Entry,
+        };
+        for _ in 0..PANIC_RECHECK_THRESHOLD {
+            let err = guard.infer_json(prompt(), &params()).await.unwrap_err();
+            assert!(matches!(
+                err.downcast_ref(),
+                Some(BackendError::Panicked(msg)) if msg == "ggml_abort"
+            ));
+        }
+        for _ in 0..100 {
+            if readiness.last_canary().is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let report = readiness.last_canary().expect("re-check ran");
+        assert!(report
+            .error
+            .unwrap()
+            .contains("canary panicked: ggml_abort"));
+        assert!(!readiness.is_ready());
+    }
+}
@@ -0,0 +1,265 @@
+use serde_json::Value;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Parse near-valid JSON from a model: [`repair`] the text, then parse it.
+pub fn parse(bytes: &[u8]) -> Option<Value> {
+    let text = String::from_utf8_lossy(bytes);
+    serde_json::from_str(&repair(&text)?).ok()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Expect {
+    Key,
+    Colon,
+    Value,
+    Comma,
+}
+
+struct Frame {
+    object: bool,
+    expect: Expect,
+    /// Where the pending key began in the output, to drop it if no value follows
+    key_start: usize,
+}
+
+/// Rewrite the first JSON object or array in `text` fixing the defects small
+/// models emit most: trailing commas, single-quoted strings, unquoted keys,
+/// missing commas, raw newlines inside strings, Python literals, and output
+/// cut off mid-string or mid-object (closed where it stops). Text before the
+/// value and after it is dropped. `None` when there is no object or array.
+///
+/// The result is not guaranteed to parse; callers still parse and validate it.
+pub fn repair(text: &str) -> Option<String> {
+    let start = text.find(['{', '['])?;
+    let mut out = String::with_capacity(text.len() - start + 16);
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut chars = text[start..].chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '[' => {
+                begin_value(&mut out, &mut stack);
+                // The parent has its value once this container opens
+                end_value(&mut stack);
+                out.push(c);
+                stack.push(Frame {
+                    object: c == '{',
+                    expect: if c == '{' { Expect::Key } else { Expect::Value },
+                    key_start: 0,
+                });
+            }
+            '}' | ']' => {
+                let Some(frame) = stack.pop() else { break };
+                close(&mut out, &frame);
+                if stack.is_empty() {
+                    return Some(out);
+                }
+            }
+            '"' | '\'' => {
+                let is_key = begin_value(&mut out, &mut stack);
+                read_string(&mut out, &mut chars, c);
+                if is_key {
+                    if let Some(frame) = stack.last_mut() {
+                        frame.expect = Expect::Colon;
+                    }
+                } else {
+                    end_value(&mut stack);
+                }
+            }
+            ':' => {
+                if let Some(frame) = stack.last_mut().filter(|f| f.expect == Expect::Colon) {
+                    out.push(':');
+                    frame.expect = Expect::Value;
+                }
+            }
+            ',' => {
+                if let Some(frame) = stack.last_mut().filter(|f| f.expect == Expect::Comma) {
+                    out.push(',');
+                    frame.expect = if frame.object {
+                        Expect::Key
+                    } else {
+                        Expect::Value
+                    };
+                }
+            }
+            c if c.is_whitespace() => out.push(c),
+            c if is_bare(c) => {
+                let mut word = String::from(c);
+                while let Some(&next) = chars.peek().filter(|&&n| is_bare(n)) {
+                    word.push(next);
+                    chars.next();
+                }
+                let is_key = begin_value(&mut out, &mut stack);
+                if is_key {
+                    push_quoted(&mut out, &word);
+                    if let Some(frame) = stack.last_mut() {
+                        frame.expect = Expect::Colon;
+                    }
+                } else {
+                    push_bare_value(&mut out, &word);
+                    end_value(&mut stack);
+                }
+            }
+            // Stray punctuation such as backticks from a code fence
+            _ => {}
+        }
+    }
+
+    // Cut off: close whatever is still open, innermost first
+    while let Some(frame) = stack.pop() {
+        close(&mut out, &frame);
+    }
+    Some(out)
+}
+
+/// Prepare to write a key or value in the innermost container, inserting a
+/// missing comma. Returns whether what follows is an object key.
+fn begin_value(out: &mut String, stack: &mut [Frame]) -> bool {
+    let Some(frame) = stack.last_mut() else {
+        return false;
+    };
+    if frame.expect == Expect::Comma {
+        out.push(',');
+        frame.expect = if frame.object {
+            Expect::Key
+        } else {
+            Expect::Value
+        };
+    }
+    if frame.expect == Expect::Key {
+        frame.key_start = out.len();
+        return true;
+    }
+    false
+}
+
+fn end_value(stack: &mut [Frame]) {
+    if let Some(frame) = stack.last_mut() {
+        frame.expect = Expect::Comma;
+    }
+}
+
+/// Write `frame`'s closing bracket, first dropping a trailing comma or a key
+/// left without a value, or filling in `null` after a dangling colon.
+fn close(out: &mut String, frame: &Frame) {
+    match frame.expect {
+        Expect::Colon => out.truncate(frame.key_start),
+        Expect::Value if frame.object => {
+            trim_end(out);
+            out.push_str("null");
+        }
+        _ => {}
+    }
+    trim_end(out);
+    if out.ends_with(',') {
+        out.pop();
+    }
+    out.push(if frame.object { '}' } else { ']' });
+}
+
+fn trim_end(out: &mut String) {
+    out.truncate(out.trim_end().len());
+}
+
+/// Copy a string delimited by `quote` as a double-quoted JSON string,
+/// escaping raw control characters; closes it if the input ends first.
+fn read_string(out: &mut String, chars: &mut Peekable<Chars<'_>>, quote: char) {
+    out.push('"');
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                // `\'` is only an escape in single-quoted strings, and not a JSON one
+                Some('\'') => out.push('\''),
+                Some(escaped) => {
+                    out.push('\\');
+                    out.push(escaped);
+                }
+                None => break,
+            },
+            c if c == quote => {
+                out.push('"');
+                return;
+            }
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn is_bare(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '$' | '-' | '+' | '.')
+}
+
+fn push_bare_value(out: &mut String, word: &str) {
+    match word {
+        "true" | "True" => out.push_str("true"),
+        "false" | "False" => out.push_str("false"),
+        "null" | "None" => out.push_str("null"),
+        _ if serde_json::from_str::<serde_json::Number>(word).is_ok() => out.push_str(word),
+        _ => push_quoted(out, word),
+    }
+}
+
+fn push_quoted(out: &mut String, word: &str) {
+    out.push_str(&Value::from(word).to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn fixed(text: &str) -> Value {
+        parse(text.as_bytes()).unwrap_or_else(|| panic!("not repaired: {text}"))
+    }
+
+    #[test]
+    fn repairs_common_model_defects() {
+        assert_eq!(
+            fixed(r#"{"word": "run", "synonyms": ["dash", "sprint",],}"#),
+            json!({"word": "run", "synonyms": ["dash", "sprint"]})
+        );
+        assert_eq!(
+            fixed(r#"{'word': 'it\'s', 'note': 'say "hi"'}"#),
+            json!({"word": "it's", "note": "say \"hi\""})
+        );
+        assert_eq!(
+            fixed(r#"{word: "run", partOfSpeech: verb, count: 2, plural: False, extra: None}"#),
+            json!({"word": "run", "partOfSpeech": "verb", "count": 2, "plural": false, "extra": null})
+        );
+        assert_eq!(
+            fixed("```json\n{\"a\": \"line one\nline two\" \"b\": 1}\n```"),
+            json!({"a": "line one\nline two", "b": 1})
+        );
+    }
+
+    #[test]
+    fn closes_truncated_output() {
+        assert_eq!(
+            fixed(r#"{"word": "run", "meanings": [{"definition": "To move fa"#),
+            json!({"word": "run", "meanings": [{"definition": "To move fa"}]})
+        );
+        assert_eq!(
+            fixed(r#"{"word": "run", "phonetic":"#),
+            json!({"word": "run", "phonetic": null})
+        );
+        assert_eq!(fixed(r#"{"word": "run", "phon"#), json!({"word": "run"}));
+        assert_eq!(fixed(r#"{"a": [1, 2,"#), json!({"a": [1, 2]}));
+    }
+
+    #[test]
+    fn leaves_valid_json_alone_and_gives_up_without_any() {
+        let valid = r#"{"a": {"b": [1, "x}"]}, "c": true} trailing"#;
+        assert_eq!(
+            repair(valid).unwrap(),
+            r#"{"a": {"b": [1, "x}"]}, "c": true}"#
+        );
+        assert_eq!(repair("no structure here"), None);
+    }
+}
@@ -1,5 +1,42 @@
+pub mod annotate;
 pub mod api;
+pub mod backtranslate;
+pub mod batch;
+pub mod cache;
+pub mod cefr;
+pub mod check;
+#[cfg(feature = "client")]
+pub mod client;
 pub mod config;
+pub mod contract;
+pub mod corpus;
+pub mod daily;
+pub mod dashboard;
+pub mod error;
+pub mod examples;
+pub mod extract;
+pub mod fair;
+pub mod family;
+pub mod fewshot;
+pub mod health;
+pub mod lenient;
+pub mod lists;
 pub mod model;
+pub mod patch;
+pub mod profile;
+pub mod pronunciation;
+pub mod queue;
+pub mod quota;
+pub mod record;
+pub mod refresh;
+pub mod schema;
+pub mod service;
+pub mod shutdown;
+pub mod signing;
+pub mod stats;
+pub mod store;
+pub mod telemetry;
+pub mod tenant;
 pub mod util;
 pub mod validate;
+pub mod variants;
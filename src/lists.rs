@@ -0,0 +1,192 @@
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Most words one list may hold.
+pub const MAX_LIST_WORDS: usize = 1000;
+
+/// Most lists one requester may keep at once; all keyless callers count as one.
+pub const MAX_LISTS_PER_OWNER: usize = 100;
+
+/// Lists untouched this long are dropped.
+pub const LIST_IDLE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A named list as the API returns it; entries are generated only when the
+/// list is exported.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListSummary {
+    pub id: String,
+    pub name: String,
+    /// In the order they were added, each once
+    pub words: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ListError {
+    /// No such list for this requester, or it expired
+    NotFound,
+    /// Adding the words would take the list past [`MAX_LIST_WORDS`]
+    TooManyWords,
+    /// The requester already keeps [`MAX_LISTS_PER_OWNER`] lists
+    TooManyLists,
+}
+
+struct WordList {
+    summary: ListSummary,
+    /// Tenant and API key that created the list; nobody else sees it
+    owner: String,
+    touched: Instant,
+}
+
+/// Word lists kept for the life of the process, each visible only to the
+/// requester that created it.
+pub struct WordLists {
+    lists: Mutex<HashMap<String, WordList>>,
+    ttl: Duration,
+    ids: RandomState,
+    next: AtomicU64,
+}
+
+impl Default for WordLists {
+    fn default() -> Self {
+        Self::new(LIST_IDLE_TTL)
+    }
+}
+
+impl WordLists {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            lists: Mutex::new(HashMap::new()),
+            ttl,
+            ids: RandomState::new(),
+            next: AtomicU64::new(0),
+        }
+    }
+
+    pub fn create(&self, owner: &str, name: &str) -> Result<ListSummary, ListError> {
+        let mut lists = self.lists.lock();
+        lists.retain(|_, list| list.touched.elapsed() < self.ttl);
+        if lists.values().filter(|list| list.owner == owner).count() >= MAX_LISTS_PER_OWNER {
+            return Err(ListError::TooManyLists);
+        }
+        let summary = ListSummary {
+            id: self.new_id(),
+            name: name.trim().to_string(),
+            words: Vec::new(),
+        };
+        lists.insert(
+            summary.id.clone(),
+            WordList {
+                summary: summary.clone(),
+                owner: owner.to_string(),
+                touched: Instant::now(),
+            },
+        );
+        Ok(summary)
+    }
+
+    /// Append `words` not already listed, trimmed, skipping blanks.
+    pub fn add_words(
+        &self,
+        owner: &str,
+        id: &str,
+        words: &[String],
+    ) -> Result<ListSummary, ListError> {
+        self.with_list(owner, id, |list| {
+            let mut added = list.words.clone();
+            for word in words.iter().map(|w| w.trim()) {
+                if !word.is_empty() && !added.iter().any(|w| w == word) {
+                    added.push(word.to_string());
+                }
+            }
+            if added.len() > MAX_LIST_WORDS {
+                return Err(ListError::TooManyWords);
+            }
+            list.words = added;
+            Ok(list.clone())
+        })
+    }
+
+    pub fn get(&self, owner: &str, id: &str) -> Result<ListSummary, ListError> {
+        self.with_list(owner, id, |list| Ok(list.clone()))
+    }
+
+    fn with_list<T>(
+        &self,
+        owner: &str,
+        id: &str,
+        f: impl FnOnce(&mut ListSummary) -> Result<T, ListError>,
+    ) -> Result<T, ListError> {
+        let mut lists = self.lists.lock();
+        match lists.get_mut(id) {
+            Some(list) if list.owner == owner && list.touched.elapsed() < self.ttl => {
+                list.touched = Instant::now();
+                f(&mut list.summary)
+            }
+            _ => Err(ListError::NotFound),
+        }
+    }
+
+    /// Unguessable without the process's random hash keys.
+    fn new_id(&self) -> String {
+        let n = self.next.fetch_add(1, Ordering::Relaxed);
+        let half = |salt: u64| {
+            let mut hasher = self.ids.build_hasher();
+            hasher.write_u64(n);
+            hasher.write_u64(salt);
+            hasher.finish()
+        };
+        format!("{:016x}{:016x}", half(0), half(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_collect_words_once_for_their_owner() {
+        let lists = WordLists::default();
+        let list = lists.create("key", " Week 1 ").unwrap();
+        assert_eq!(list.name, "Week 1");
+        assert_eq!(list.id.len(), 32);
+        assert_ne!(lists.create("key", "other").unwrap().id, list.id);
+
+        let words = ["run", " walk", "run", ""].map(String::from);
+        let added = lists.add_words("key", &list.id, &words).unwrap();
+        assert_eq!(added.words, ["run", "walk"]);
+        assert_eq!(lists.get("key", &list.id).unwrap(), added);
+        assert_eq!(lists.get("other-key", &list.id), Err(ListError::NotFound));
+
+        let many: Vec<String> = (0..MAX_LIST_WORDS).map(|i| format!("w{}", i)).collect();
+        assert_eq!(
+            lists.add_words("key", &list.id, &many),
+            Err(ListError::TooManyWords)
+        );
+        assert_eq!(lists.get("key", &list.id).unwrap().words.len(), 2);
+
+        let expired = WordLists::new(Duration::ZERO);
+        let list = expired.create("key", "gone").unwrap();
+        assert_eq!(expired.get("key", &list.id), Err(ListError::NotFound));
+    }
+
+    #[test]
+    fn each_owner_keeps_a_bounded_number_of_lists() {
+        let lists = WordLists::default();
+        for i in 0..MAX_LISTS_PER_OWNER {
+            lists.create("", &format!("list {}", i)).unwrap();
+        }
+        assert_eq!(lists.create("", "one more"), Err(ListError::TooManyLists));
+        assert!(lists.create("key", "another owner's").is_ok());
+
+        // Expired lists no longer count
+        let expiring = WordLists::new(Duration::ZERO);
+        for _ in 0..=MAX_LISTS_PER_OWNER {
+            expiring.create("", "brief").unwrap();
+        }
+    }
+}
@@ -1,16 +1,52 @@
-mod api;
-mod config;
-mod model;
-mod util;
-mod validate;
-use crate::model::llama::LlamaBackend;
-use crate::model::InferParams;
-use crate::validate::Validator;
-use config::Config;
+use anyhow::Context;
 use dotenvy::dotenv;
+use futures_util::FutureExt;
+use lingua_fast::api::{self, AppState};
+use lingua_fast::cache::WordCache;
+use lingua_fast::check;
+use lingua_fast::config::{BackendKind, CefrMode, Command, Config, PresetMode};
+use lingua_fast::contract::ContractProfiles;
+use lingua_fast::corpus::{self, CorpusOptions};
+use lingua_fast::daily::DailyWords;
+use lingua_fast::fair::FairScheduler;
+use lingua_fast::fewshot::FewShotLibrary;
+use lingua_fast::health::{self, PanicGuard, Readiness};
+use lingua_fast::lists::WordLists;
+use lingua_fast::model::gguf;
+use lingua_fast::model::mock::MockBackend;
+use lingua_fast::model::ollama::{self, OllamaBackend};
+use lingua_fast::model::openai::{self, OpenAiBackend};
+use lingua_fast::model::preset::{ModelFamily, Preset};
+use lingua_fast::model::readonly::ReadOnlyBackend;
+use lingua_fast::model::upstream::UpstreamBackend;
+#[cfg(feature = "llama")]
+use lingua_fast::model::{
+    device::{self, DeviceSpec},
+    llama::{self, LlamaBackend, LlamaSettings},
+    trace::TokenTracer,
+};
+use lingua_fast::model::{InferParams, LlmBackend, PromptParts, PromptTask};
+use lingua_fast::profile::Profiles;
+#[cfg(feature = "queue")]
+use lingua_fast::queue::{self, PostgresQueue, WorkQueue, WorkerOptions};
+use lingua_fast::quota::UsageTracker;
+use lingua_fast::record::{self, RecordingBackend};
+use lingua_fast::refresh::{self, Activity, RefreshOptions, Refresher, Tracked};
+use lingua_fast::schema;
+use lingua_fast::service::WordService;
+use lingua_fast::shutdown::{self, ShutdownHook};
+use lingua_fast::signing::EntrySigner;
+use lingua_fast::stats;
+use lingua_fast::store::EntryStore;
+use lingua_fast::telemetry;
+use lingua_fast::tenant::{self, Namespace, Tenants};
+use lingua_fast::util;
+use lingua_fast::validate::Validator;
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tracing_subscriber::{fmt, EnvFilter};
+use std::time::Duration;
+use tracing_subscriber::{fmt, fmt::format::FmtSpan, EnvFilter};
 
 #[tokio::main(flavor = "multi_thread")]
 async fn main() -> anyhow::Result<()> {
@@ -19,34 +55,612 @@ async fn main() -> anyhow::Result<()> {
 
     // logs
     let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
-    fmt().with_env_filter(filter).init();
+    let span_events = if cfg.log_span_timings {
+        FmtSpan::CLOSE
+    } else {
+        FmtSpan::NONE
+    };
+    fmt()
+        .with_env_filter(filter)
+        .with_span_events(span_events)
+        .init();
+
+    telemetry::install()?;
+    stats::mark_started();
 
-    // load schema & validator
     let schema_src: &str = include_str!("../schema/word_contract.schema.json");
-    let validator = Arc::new(Validator::new(schema_src)?);
-
-    // llama backend
-    let backend = LlamaBackend::new(
-        cfg.model_path.into(),
-        cfg.n_ctx,
-        cfg.n_batch,
-        cfg.n_gpu_layers,
-        cfg.threads,
-        cfg.infer_concurrency,
-    )?;
 
+    if let Some(Command::CheckConfig) = cfg.command {
+        let report = check::config(&cfg, schema_src, cfg.vram_mb.or_else(check::detect_vram_mb));
+        print!("{}", report);
+        anyhow::ensure!(report.is_ok(), "configuration check failed");
+        return Ok(());
+    }
+
+    if let Some(Command::Devices) = cfg.command {
+        #[cfg(feature = "llama")]
+        {
+            // Registers the compute backends before they are listed
+            let _backend = llama_cpp_2::llama_backend::LlamaBackend::init()?;
+            print!("{}", device::table(&llama::compute_devices()));
+            return Ok(());
+        }
+        #[cfg(not(feature = "llama"))]
+        anyhow::bail!(
+            "this build does not include the llama backend; rebuild with --features llama"
+        );
+    }
+
+    // load schema & validator
+    let contract_profiles = match &cfg.contract_profiles_file {
+        Some(path) => ContractProfiles::load(path)?,
+        None => ContractProfiles::default(),
+    };
+    // Tenants with their own schema file get a validator of their own
+    let new_validator = || -> anyhow::Result<Validator> {
+        Ok(Validator::new(schema_src)?
+            .with_case_policy(cfg.case_policy)
+            .with_cefr_check(cfg.cefr != CefrMode::Off)
+            .with_contract_profiles(contract_profiles.clone())
+            .with_canonical_output(cfg.canonical_json))
+    };
+    let validator = Arc::new(new_validator()?);
+
+    if let Some(path) = &cfg.schema_file {
+        schema::reload(&validator, path.as_ref())?;
+        tracing::info!(%path, hash = %validator.schema_hash(), "word contract schema loaded");
+    }
+
+    #[cfg(feature = "queue")]
+    if let Some(Command::QueuePush { input, queue_url }) = &cfg.command {
+        // No generation, so no model is needed; the lease only matters to workers
+        let queue = PostgresQueue::connect(queue_url, Duration::ZERO).await?;
+        let words: Vec<String> = util::read_to_string(input)?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect();
+        let added = queue.push(&words).await?;
+        println!("{} words read, {} newly queued", words.len(), added);
+        return Ok(());
+    }
+
+    if let Some(Command::Revalidate { dir }) = &cfg.command {
+        let report = record::revalidate(dir, &validator)?;
+        print!("{}", report);
+        anyhow::ensure!(report.is_ok(), "some recorded outputs fail validation");
+        return Ok(());
+    }
+
+    let system_prompt = match &cfg.system_prompt_file {
+        Some(path) => util::read_to_string(path)?.trim().to_string(),
+        None => cfg.system_prompt.clone(),
+    };
+
+    let few_shot = Arc::new(match &cfg.few_shot_dir {
+        Some(dir) => FewShotLibrary::load(dir, &validator)?,
+        None => FewShotLibrary::default(),
+    });
+
+    let preset = if cfg.no_model {
+        None
+    } else {
+        model_preset(&cfg)?
+    };
+    let mut backend: Arc<dyn LlmBackend> = if cfg.no_model {
+        tracing::info!("no model loaded; serving cached and stored entries only");
+        Arc::new(ReadOnlyBackend)
+    } else {
+        let backend = build_backend(&cfg, preset)?;
+        tracing::info!(backend = ?cfg.backend, model = %backend.model_name(), "backend ready");
+        backend
+    };
+
+    if let Some(Command::CheckTemplate { word }) = &cfg.command {
+        let parts = PromptParts {
+            system: system_prompt,
+            user_word: word.clone(),
+            examples: few_shot.select(word, cfg.few_shot_count),
+            context: None,
+            contract: validator.contract_profiles().clone(),
+            task: PromptTask::Entry,
+        };
+        let report = check::template(
+            &parts,
+            cfg.n_ctx.max(0) as usize,
+            cfg.max_tokens.max(0) as usize,
+            |text| backend.count_tokens(text),
+        );
+        print!("{}", report);
+        anyhow::ensure!(report.is_ok(), "prompt template check failed");
+        return Ok(());
+    }
+
+    if let Some(dir) = &cfg.record_dir {
+        tracing::info!(%dir, "recording model outputs");
+        backend = Arc::new(RecordingBackend::new(backend, dir)?);
+    }
+
+    let defaults = InferParams {
+        max_tokens: cfg.max_tokens,
+        ..InferParams::default()
+    };
+    let defaults = match preset {
+        Some(preset) => preset.params(&defaults),
+        None => defaults,
+    };
     let params = InferParams {
         max_tokens: cfg.max_tokens,
-        temp: cfg.temp,
-        top_p: cfg.top_p,
-        min_p: cfg.min_p,
-        repeat_penalty: cfg.repeat_penalty,
+        temp: cfg.temp.unwrap_or(defaults.temp),
+        top_p: cfg.top_p.unwrap_or(defaults.top_p),
+        min_p: cfg.min_p.unwrap_or(defaults.min_p),
+        repeat_penalty: cfg.repeat_penalty.unwrap_or(defaults.repeat_penalty),
+        repeat_last_n: cfg.repeat_last_n,
+        frequency_penalty: cfg.frequency_penalty,
+        presence_penalty: cfg.presence_penalty,
+        dry_multiplier: cfg.dry_multiplier,
+        dry_base: cfg.dry_base,
+        dry_allowed_length: cfg.dry_allowed_length,
+        xtc_probability: cfg.xtc_probability,
+        xtc_threshold: cfg.xtc_threshold,
+        ..defaults
+    };
+
+    // Without a model there is nothing for a canary to check
+    let canary_interval_secs = if cfg.no_model {
+        0
+    } else {
+        cfg.canary_interval_secs
+    };
+    let readiness = Arc::new(if canary_interval_secs > 0 {
+        Readiness::with_canary()
+    } else {
+        Readiness::default()
+    });
+    backend = Arc::new(PanicGuard::new(
+        backend,
+        readiness.clone(),
+        params.clone(),
+        Duration::from_millis(cfg.canary_budget_ms),
+    ));
+
+    if cfg.backend == BackendKind::Llama && cfg.startup_benchmark && !cfg.no_model {
+        match telemetry::benchmark(backend.as_ref(), &params).await {
+            Some(t) => tracing::info!(
+                prompt_tps = format!("{:.1}", t.prompt_tps().unwrap_or(0.0)),
+                decode_tps = format!("{:.1}", t.decode_tps().unwrap_or(0.0)),
+                n_gpu_layers = cfg.n_gpu_layers,
+                "startup benchmark; a decode rate far below this GPU's usual suggests layers are not offloaded"
+            ),
+            None => tracing::warn!("startup benchmark inference failed"),
+        }
+    }
+
+    let cache_ttl = (cfg.cache_ttl_secs > 0).then(|| Duration::from_secs(cfg.cache_ttl_secs));
+    let new_cache = || {
+        Arc::new(
+            WordCache::new(cfg.cache_capacity, cache_ttl, cfg.stale_while_revalidate)
+                .with_negative_caching(
+                    Duration::from_secs(cfg.negative_cache_ttl_secs),
+                    cfg.negative_cache_threshold,
+                )
+                .with_case_policy(cfg.case_policy),
+        )
     };
+    let cache = new_cache();
 
-    let app = api::routes(backend, validator, params);
+    let store = match &cfg.data_dir {
+        Some(dir) => {
+            tracing::info!(%dir, "persistence enabled");
+            Some(Arc::new(
+                EntryStore::open(dir)?
+                    .with_case_policy(cfg.case_policy)
+                    .with_pretty_files(cfg.canonical_json),
+            ))
+        }
+        None => None,
+    };
+
+    // Offline bulk modes generate through the same service the server uses
+    let service_for =
+        |validator: &Arc<Validator>, cache: &Arc<WordCache>, store: &Option<Arc<EntryStore>>| {
+            let service = WordService::new(backend.clone(), validator.clone())
+                .with_params(params.clone())
+                .with_cache(cache.clone())
+                .with_system_prompt(system_prompt.as_str())
+                .with_max_word_chars(cfg.max_word_chars as usize)
+                .with_few_shot(few_shot.clone(), cfg.few_shot_count);
+            match store {
+                Some(store) => service.with_store(store.clone()),
+                None => service,
+            }
+        };
+    let offline_service = || service_for(&validator, &cache, &store);
+
+    if let Some(Command::Corpus {
+        input,
+        resume,
+        concurrency,
+        per_minute,
+        stats_secs,
+    }) = &cfg.command
+    {
+        let opts = CorpusOptions {
+            input: input.into(),
+            checkpoint: resume.into(),
+            concurrency: concurrency.unwrap_or_else(|| cfg.infer_slots()),
+            per_minute: *per_minute,
+            stats_every: Duration::from_secs(*stats_secs),
+        };
+        let progress = corpus::run(offline_service(), &opts, stop_on_interrupt()).await?;
+        print!("{}", progress);
+        return Ok(());
+    }
+
+    #[cfg(feature = "queue")]
+    if let Some(Command::QueueWork {
+        queue_url,
+        worker,
+        concurrency,
+        lease_secs,
+        exit_when_empty,
+    }) = &cfg.command
+    {
+        let queue = PostgresQueue::connect(queue_url, Duration::from_secs(*lease_secs)).await?;
+        let opts = WorkerOptions {
+            worker: worker.clone().unwrap_or_else(|| {
+                let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "worker".to_string());
+                format!("{}-{}", host, std::process::id())
+            }),
+            concurrency: concurrency.unwrap_or_else(|| cfg.infer_slots()),
+            poll: Duration::from_secs(5),
+            exit_when_empty: *exit_when_empty,
+        };
+        let stats = queue::work(
+            offline_service(),
+            Arc::new(queue),
+            &opts,
+            stop_on_interrupt(),
+        )
+        .await?;
+        print!("{}", stats);
+        return Ok(());
+    }
+
+    if canary_interval_secs > 0 {
+        health::spawn_canary(
+            backend.clone(),
+            params.clone(),
+            readiness.clone(),
+            Duration::from_secs(canary_interval_secs),
+            Duration::from_millis(cfg.canary_budget_ms),
+        );
+    }
+
+    let profiles = Arc::new(match &cfg.profiles_file {
+        Some(path) => Profiles::load(path)?,
+        None => Profiles::default(),
+    });
+
+    let mut tenants = Tenants::default();
+    for (name, config) in profiles.tenants() {
+        let (tenant_validator, schema_file) = match &config.schema_file {
+            Some(path) => {
+                let own = Arc::new(new_validator()?);
+                schema::reload(&own, path.as_ref())?;
+                tracing::info!(tenant = %name, %path, hash = %own.schema_hash(), "tenant schema loaded");
+                if cfg.schema_watch_secs > 0 {
+                    schema::spawn_watch(
+                        own.clone(),
+                        path.into(),
+                        Duration::from_secs(cfg.schema_watch_secs),
+                    );
+                }
+                (own, Some(Arc::from(path.as_str())))
+            }
+            None => (validator.clone(), cfg.schema_file.as_deref().map(Arc::from)),
+        };
+        let store = match &cfg.data_dir {
+            Some(dir) => Some(Arc::new(
+                EntryStore::open(tenant::data_dir(dir, name))?
+                    .with_case_policy(cfg.case_policy)
+                    .with_pretty_files(cfg.canonical_json),
+            )),
+            None => None,
+        };
+        tenants.insert(Namespace {
+            name: name.clone(),
+            cache: new_cache(),
+            store,
+            validator: tenant_validator,
+            schema_file,
+        });
+    }
+    if !tenants.is_empty() {
+        tracing::info!(tenants = ?tenants.names().collect::<Vec<_>>(), "serving tenants");
+    }
+
+    if let (Some(path), true) = (&cfg.schema_file, cfg.schema_watch_secs > 0) {
+        schema::spawn_watch(
+            validator.clone(),
+            path.into(),
+            Duration::from_secs(cfg.schema_watch_secs),
+        );
+    }
+
+    let refresh = RefreshOptions {
+        max_age: cfg
+            .refresh_after_days
+            .map(|days| Duration::from_secs(days * 24 * 60 * 60)),
+        other_models: cfg.refresh_other_models,
+        idle: Duration::from_secs(cfg.refresh_idle_secs),
+        interval: Duration::from_secs(cfg.refresh_interval_secs),
+    };
+    if refresh.is_enabled() && cfg.no_model {
+        tracing::warn!("--no-model cannot regenerate entries; not refreshing");
+    } else if refresh.is_enabled() {
+        match &store {
+            Some(store) => {
+                // The refresher runs on the untracked backend, so its own
+                // inference never holds it off
+                let activity = Arc::new(Activity::default());
+                let refresher = Refresher::new(offline_service(), store.clone(), refresh.clone());
+                refresh::spawn(refresher, activity.clone());
+                for namespace in tenants.iter() {
+                    if let Some(store) = &namespace.store {
+                        let service =
+                            service_for(&namespace.validator, &namespace.cache, &namespace.store);
+                        let refresher = Refresher::new(service, store.clone(), refresh.clone());
+                        refresh::spawn(refresher, activity.clone());
+                    }
+                }
+                backend = Arc::new(Tracked::new(backend, activity));
+            }
+            None => tracing::warn!(
+                "REFRESH_AFTER_DAYS and REFRESH_OTHER_MODELS need DATA_DIR; not refreshing"
+            ),
+        }
+    }
+
+    let daily_words = Arc::new(
+        match (&cfg.word_of_the_day_file, cfg.word_of_the_day_level) {
+            (Some(path), _) => DailyWords::load(path)?,
+            (None, Some(level)) => DailyWords::at_level(level),
+            (None, None) => DailyWords::default(),
+        },
+    );
+
+    let signer = match &cfg.signing_key_file {
+        Some(path) => Some(Arc::new(EntrySigner::load(
+            path,
+            cfg.signing_key_id.clone(),
+        )?)),
+        None => None,
+    };
+
+    let usage = Arc::new(UsageTracker::default());
+    let mut on_shutdown = ShutdownHook::new(cache.clone(), usage.clone());
+    if let (Some(store), Some(dir)) = (&store, &cfg.data_dir) {
+        on_shutdown = on_shutdown
+            .with_store(store.clone())
+            .with_usage_file(std::path::Path::new(dir).join(shutdown::USAGE_FILE));
+        for namespace in tenants.iter() {
+            if let Some(store) = &namespace.store {
+                on_shutdown = on_shutdown.with_tenant(namespace.cache.clone(), store.clone());
+            }
+        }
+        let restored = on_shutdown.restore_usage()?;
+        if restored > 0 {
+            tracing::info!(keys = restored, "restored usage counters");
+        }
+    }
+
+    let batch_concurrency = cfg.infer_slots();
+    let app = api::router(AppState {
+        backend,
+        validator,
+        params,
+        cache,
+        store,
+        admin_token: cfg.admin_token.map(Arc::from),
+        readiness,
+        system_prompt: Arc::from(system_prompt),
+        allow_system_prompt_override: cfg.allow_system_prompt_override,
+        few_shot,
+        few_shot_count: cfg.few_shot_count,
+        few_shot_from_cache: cfg.few_shot_from_cache,
+        batch_concurrency,
+        batch_failure_threshold: cfg.batch_failure_threshold,
+        batch_retry_budget: cfg.batch_retry_budget,
+        max_word_chars: cfg.max_word_chars as usize,
+        back_translation_check: cfg.back_translation_check,
+        word_time_budget: (cfg.word_time_budget_secs > 0)
+            .then(|| Duration::from_secs(cfg.word_time_budget_secs)),
+        schema_file: cfg.schema_file.as_deref().map(Arc::from),
+        profiles,
+        usage,
+        cefr: cfg.cefr,
+        daily_words,
+        signer,
+        tenants: Arc::new(tenants),
+        trust_tenant_header: cfg.trust_tenant_header,
+        tenant: None,
+        lists: Arc::new(WordLists::default()),
+        scheduler: cfg.fair_scheduling.then(|| {
+            Arc::new(FairScheduler::new(
+                batch_concurrency,
+                (cfg.max_queue_wait_ms > 0).then(|| Duration::from_millis(cfg.max_queue_wait_ms)),
+            ))
+        }),
+    });
     let addr: SocketAddr = cfg.bind_addr.parse()?;
 
     tracing::info!(%addr, "listening");
-    axum::serve(tokio::net::TcpListener::bind(addr).await?, app).await?;
+    let stop = shutdown::signal().shared();
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    let server = axum::serve(listener, app).with_graceful_shutdown(stop.clone());
+    let grace = Duration::from_secs(cfg.shutdown_grace_secs);
+    tokio::select! {
+        served = server => served?,
+        _ = async {
+            stop.await;
+            tracing::info!(?grace, "shutting down; waiting for requests in flight");
+            tokio::time::sleep(grace).await;
+        } => tracing::warn!("requests still running after SHUTDOWN_GRACE_SECS; abandoning them"),
+    }
+    let report = on_shutdown.run();
+    tracing::info!("{}", report);
     Ok(())
 }
+
+/// The tuned preset for the llama backend's model: the family MODEL_PRESET
+/// names, or the one its GGUF metadata identifies.
+fn model_preset(cfg: &Config) -> anyhow::Result<Option<&'static Preset>> {
+    if cfg.backend != BackendKind::Llama {
+        return Ok(None);
+    }
+    let family = match (cfg.model_preset, &cfg.model_path) {
+        (PresetMode::Off, _) => None,
+        (PresetMode::Auto, Some(path)) => ModelFamily::of(&gguf::read_info(path)?),
+        (PresetMode::Auto, None) => None,
+        (forced, _) => forced.family(),
+    };
+    match family {
+        Some(family) => tracing::info!(?family, mode = ?cfg.model_preset, "applying model preset"),
+        None if cfg.model_preset == PresetMode::Auto => {
+            tracing::info!("no tuned preset for this model; using the plain prompt")
+        }
+        None => {}
+    }
+    Ok(family.map(ModelFamily::preset))
+}
+
+#[cfg_attr(not(feature = "llama"), allow(unused_variables))]
+fn build_backend(
+    cfg: &Config,
+    preset: Option<&'static Preset>,
+) -> anyhow::Result<Arc<dyn LlmBackend>> {
+    let timeout = Duration::from_secs(cfg.backend_timeout_secs);
+    let backend_model = || {
+        cfg.backend_model
+            .clone()
+            .context("BACKEND_MODEL is required for the openai and ollama backends")
+    };
+
+    Ok(match cfg.backend {
+        #[cfg(feature = "llama")]
+        BackendKind::Llama => {
+            let model_path = cfg
+                .model_path
+                .clone()
+                .context("MODEL_PATH is required for the llama backend")?;
+            let n_gpu_layers = if cfg.device == Some(DeviceSpec::Cpu) {
+                0
+            } else if cfg.auto_gpu_layers {
+                auto_gpu_layers(cfg, &model_path)?
+            } else {
+                cfg.n_gpu_layers
+            };
+            match lingua_fast::config::GPU_BACKEND {
+                Some(gpu) => tracing::info!(
+                    gpu,
+                    device = %cfg.device.as_ref().map_or("auto".to_string(), |d| d.to_string()),
+                    n_gpu_layers,
+                    "llama.cpp GPU backend"
+                ),
+                None if n_gpu_layers > 0 => tracing::warn!(
+                    n_gpu_layers,
+                    "this build has no GPU backend; all layers run on the CPU (rebuild with --features cuda, vulkan or metal)"
+                ),
+                None => tracing::info!("llama.cpp CPU-only build"),
+            }
+            Arc::new(LlamaBackend::new(LlamaSettings {
+                model_path: model_path.into(),
+                n_ctx: cfg.n_ctx,
+                n_batch: cfg.n_batch,
+                n_ubatch: cfg.n_ubatch,
+                n_gpu_layers,
+                device: cfg.device.clone(),
+                threads: cfg.threads,
+                threads_batch: cfg.threads_batch,
+                numa: cfg.numa,
+                infer_concurrency: cfg.infer_concurrency,
+                max_queue_wait: (cfg.max_queue_wait_ms > 0)
+                    .then(|| Duration::from_millis(cfg.max_queue_wait_ms)),
+                token_tracer: cfg
+                    .token_trace_file
+                    .as_deref()
+                    .map(|path| TokenTracer::open(path, cfg.token_trace_sample).map(Arc::new))
+                    .transpose()?,
+                preset,
+                idle_unload: (cfg.idle_unload_mins > 0)
+                    .then(|| Duration::from_secs(cfg.idle_unload_mins * 60)),
+            })?)
+        }
+        #[cfg(not(feature = "llama"))]
+        BackendKind::Llama => {
+            anyhow::bail!(
+                "this build does not include the llama backend; rebuild with --features llama"
+            )
+        }
+        BackendKind::Openai => Arc::new(OpenAiBackend::new(
+            cfg.backend_url
+                .as_deref()
+                .unwrap_or(openai::DEFAULT_BASE_URL),
+            backend_model()?,
+            cfg.backend_api_key.clone(),
+            timeout,
+        )?),
+        BackendKind::Ollama => Arc::new(OllamaBackend::new(
+            cfg.backend_url
+                .as_deref()
+                .unwrap_or(ollama::DEFAULT_BASE_URL),
+            backend_model()?,
+            timeout,
+        )?),
+        BackendKind::Upstream => Arc::new(UpstreamBackend::new(
+            cfg.backend_url
+                .as_deref()
+                .context("BACKEND_URL is required for the upstream backend")?,
+            cfg.backend_api_key.clone(),
+            timeout,
+        )?),
+        BackendKind::Mock => Arc::new(MockBackend::new(Duration::from_millis(cfg.mock_latency_ms))),
+    })
+}
+
+/// The most layers whose weights and KV cache fit in free VRAM, from the
+/// model's tensor table. Falls back to N_GPU_LAYERS when VRAM is unknown.
+#[cfg(feature = "llama")]
+fn auto_gpu_layers(cfg: &Config, model_path: &str) -> anyhow::Result<i32> {
+    let Some(vram_mb) = cfg.vram_mb.or_else(check::detect_vram_mb) else {
+        tracing::warn!(
+            n_gpu_layers = cfg.n_gpu_layers,
+            "AUTO_GPU_LAYERS could not detect VRAM; set VRAM_MB"
+        );
+        return Ok(cfg.n_gpu_layers);
+    };
+    let info = gguf::read_info(model_path)?;
+    let layers = info.max_gpu_layers(vram_mb * 1024 * 1024, cfg.n_ctx as u64);
+    tracing::info!(
+        layers,
+        of = info.layer_bytes.len(),
+        vram_mb,
+        "AUTO_GPU_LAYERS selected offload"
+    );
+    Ok(layers as i32)
+}
+
+/// Set once Ctrl-C or SIGTERM arrives, so bulk runs can stop at their next
+/// checkpoint instead of dying mid-batch.
+fn stop_on_interrupt() -> Arc<AtomicBool> {
+    let stop = Arc::new(AtomicBool::new(false));
+    let on_signal = stop.clone();
+    tokio::spawn(async move {
+        shutdown::signal().await;
+        tracing::info!("interrupt received; stopping after the words in flight");
+        on_signal.store(true, Ordering::Relaxed);
+    });
+    stop
+}
@@ -0,0 +1,195 @@
+use anyhow::{bail, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// A compute device llama.cpp can run layers on, as listed by `devices`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComputeDevice {
+    /// ggml's index across all backends, as passed to the model params
+    pub index: usize,
+    /// Backend as ggml names it: `CPU`, `CUDA`, `Metal`, `Vulkan`, ...
+    pub backend: String,
+    /// ggml's device name, e.g. `CUDA0`
+    pub name: String,
+    /// Usually the hardware, e.g. `NVIDIA GeForce RTX 4090`
+    pub description: String,
+    /// `cpu`, `gpu`, `igpu`, `accel` or `?`
+    pub kind: &'static str,
+    pub memory_total: u64,
+    pub memory_free: u64,
+}
+
+/// Which device to run on, from `--device`: `cpu`, or a backend with an
+/// optional position among that backend's devices (`cuda:1`, `vulkan`,
+/// `metal`). Without a position the backend's first device is used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceSpec {
+    Cpu,
+    Gpu { backend: String, ordinal: usize },
+}
+
+impl FromStr for DeviceSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+        let (backend, ordinal) = match s.split_once(':') {
+            Some((backend, ordinal)) => (
+                backend,
+                ordinal
+                    .parse()
+                    .map_err(|_| format!("device index {:?} is not a number", ordinal))?,
+            ),
+            None => (s.as_str(), 0),
+        };
+        if backend.is_empty() || !backend.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(format!(
+                "{:?} is not a device; expected cpu or a backend such as cuda:0, metal or vulkan:1",
+                s
+            ));
+        }
+        if backend == "cpu" {
+            return Ok(Self::Cpu);
+        }
+        Ok(Self::Gpu {
+            backend: backend.to_string(),
+            ordinal,
+        })
+    }
+}
+
+impl fmt::Display for DeviceSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cpu => f.write_str("cpu"),
+            Self::Gpu { backend, ordinal } => write!(f, "{}:{}", backend, ordinal),
+        }
+    }
+}
+
+impl DeviceSpec {
+    /// The device this spec names among `devices`; `None` for the CPU, which
+    /// needs no selection. Errors list what is available instead.
+    pub fn pick<'a>(&self, devices: &'a [ComputeDevice]) -> Result<Option<&'a ComputeDevice>> {
+        let Self::Gpu { backend, ordinal } = self else {
+            return Ok(None);
+        };
+        let matching: Vec<&ComputeDevice> = devices
+            .iter()
+            .filter(|d| d.backend.eq_ignore_ascii_case(backend))
+            .collect();
+        match matching.get(*ordinal) {
+            Some(device) => Ok(Some(device)),
+            None if matching.is_empty() => bail!(
+                "no {} device in this build; available: {} (see `lingua-fast devices`)",
+                backend,
+                available(devices)
+            ),
+            None => bail!(
+                "{} has only {} {} device(s); available: {}",
+                self,
+                matching.len(),
+                backend,
+                available(devices)
+            ),
+        }
+    }
+}
+
+/// The `--device` value selecting each of `devices`, in order.
+pub fn specs(devices: &[ComputeDevice]) -> Vec<String> {
+    devices
+        .iter()
+        .enumerate()
+        .map(|(i, device)| {
+            if device.kind == "cpu" {
+                return "cpu".to_string();
+            }
+            let ordinal = devices[..i]
+                .iter()
+                .filter(|d| d.backend.eq_ignore_ascii_case(&device.backend))
+                .count();
+            format!("{}:{}", device.backend.to_ascii_lowercase(), ordinal)
+        })
+        .collect()
+}
+
+fn available(devices: &[ComputeDevice]) -> String {
+    let specs = specs(devices);
+    if specs.is_empty() {
+        "none".to_string()
+    } else {
+        specs.join(", ")
+    }
+}
+
+/// `devices` as the table `lingua-fast devices` prints.
+pub fn table(devices: &[ComputeDevice]) -> String {
+    const MIB: u64 = 1024 * 1024;
+    let mut out = format!(
+        "{:<10} {:<8} {:<10} {:<6} {:>18}  {}\n",
+        "DEVICE", "BACKEND", "NAME", "TYPE", "FREE/TOTAL MiB", "DESCRIPTION"
+    );
+    for (device, spec) in devices.iter().zip(specs(devices)) {
+        out.push_str(&format!(
+            "{:<10} {:<8} {:<10} {:<6} {:>18}  {}\n",
+            spec,
+            device.backend,
+            device.name,
+            device.kind,
+            format!("{}/{}", device.memory_free / MIB, device.memory_total / MIB),
+            device.description
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(index: usize, backend: &str, kind: &'static str) -> ComputeDevice {
+        ComputeDevice {
+            index,
+            backend: backend.to_string(),
+            name: format!("{}{}", backend, index),
+            description: String::new(),
+            kind,
+            memory_total: 8 << 30,
+            memory_free: 6 << 30,
+        }
+    }
+
+    #[test]
+    fn specs_select_devices_by_backend_and_position() {
+        let devices = [
+            device(0, "CUDA", "gpu"),
+            device(1, "CUDA", "gpu"),
+            device(2, "Vulkan", "gpu"),
+            device(3, "CPU", "cpu"),
+        ];
+        assert_eq!(specs(&devices), ["cuda:0", "cuda:1", "vulkan:0", "cpu"]);
+
+        let pick = |s: &str| {
+            s.parse::<DeviceSpec>()
+                .unwrap()
+                .pick(&devices)
+                .map(|d| d.map(|d| d.index))
+        };
+        assert_eq!(pick("cpu").unwrap(), None);
+        assert_eq!(pick("CUDA:1").unwrap(), Some(1));
+        assert_eq!(pick("vulkan").unwrap(), Some(2));
+        let err = pick("cuda:2").unwrap_err().to_string();
+        assert!(err.contains("only 2 cuda device(s)"), "{}", err);
+        let err = pick("metal").unwrap_err().to_string();
+        assert!(
+            err.contains("available: cuda:0, cuda:1, vulkan:0, cpu"),
+            "{}",
+            err
+        );
+
+        assert!("cuda:x".parse::<DeviceSpec>().is_err());
+        assert!("".parse::<DeviceSpec>().is_err());
+        assert!(table(&devices).contains("6144/8192"));
+    }
+}
@@ -0,0 +1,286 @@
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"GGUF";
+const DEFAULT_ALIGNMENT: u64 = 32;
+/// K and V cache entries are f16 unless llama.cpp is told otherwise.
+const KV_BYTES_PER_VALUE: u64 = 2;
+
+/// What we need from a GGUF file to plan GPU offload, read without loading
+/// the model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GgufInfo {
+    pub version: u32,
+    pub architecture: Option<String>,
+    /// `general.name`, e.g. "Meta-Llama-3.1-8B-Instruct"
+    pub name: Option<String>,
+    /// Transformer layers (`<arch>.block_count`), the unit of GPU offload
+    pub block_count: Option<u32>,
+    pub file_size: u64,
+    /// Weight bytes of each transformer layer, by block index
+    pub layer_bytes: Vec<u64>,
+    /// Output head and final norm; offloaded only once every layer is
+    pub output_bytes: u64,
+    /// K and V cache bytes one layer needs per token of context
+    pub kv_bytes_per_token: u64,
+}
+
+impl GgufInfo {
+    /// VRAM needed to offload `n_gpu_layers` (llama.cpp semantics: more than
+    /// `block_count` also offloads the output head) with an `n_ctx` KV cache.
+    pub fn offload_bytes(&self, n_gpu_layers: u32, n_ctx: u64) -> u64 {
+        let layers = (n_gpu_layers as usize).min(self.layer_bytes.len());
+        let weights: u64 = self.layer_bytes[..layers].iter().sum();
+        let kv = self.kv_bytes_per_token * n_ctx * layers as u64;
+        let output = if n_gpu_layers as usize > self.layer_bytes.len() {
+            self.output_bytes
+        } else {
+            0
+        };
+        weights + kv + output
+    }
+
+    /// Largest `n_gpu_layers` whose offload fits in `vram_bytes`, keeping a
+    /// tenth back for compute buffers and the driver.
+    pub fn max_gpu_layers(&self, vram_bytes: u64, n_ctx: u64) -> u32 {
+        let budget = vram_bytes - vram_bytes / 10;
+        let all = self.layer_bytes.len() as u32 + 1;
+        (0..=all)
+            .rev()
+            .find(|&n| self.offload_bytes(n, n_ctx) <= budget)
+            .unwrap_or(0)
+    }
+}
+
+/// Read the header, metadata and tensor table of a GGUF file.
+pub fn read_info(path: impl AsRef<Path>) -> Result<GgufInfo> {
+    let path = path.as_ref();
+    let file = File::open(path).with_context(|| format!("open {:?}", path))?;
+    let file_size = file.metadata()?.len();
+    let mut r = BufReader::new(file);
+
+    let mut magic = [0u8; 4];
+    r.read_exact(&mut magic)
+        .with_context(|| format!("read header of {:?}", path))?;
+    if &magic != MAGIC {
+        bail!("{:?} is not a GGUF file (bad magic {:?})", path, magic);
+    }
+    let version = read_u32(&mut r)?;
+    if !(2..=3).contains(&version) {
+        bail!("{:?} has unsupported GGUF version {}", path, version);
+    }
+    let tensor_count = read_u64(&mut r)?;
+    let kv_count = read_u64(&mut r)?;
+
+    let mut architecture = None;
+    let mut name = None;
+    let mut numbers: HashMap<String, u32> = HashMap::new();
+    for _ in 0..kv_count {
+        let key = read_string(&mut r)?;
+        match read_u32(&mut r)? {
+            TYPE_STRING if key == "general.architecture" => {
+                architecture = Some(read_string(&mut r)?)
+            }
+            TYPE_STRING if key == "general.name" => name = Some(read_string(&mut r)?),
+            TYPE_U32 => {
+                numbers.insert(key, read_u32(&mut r)?);
+            }
+            ty => skip_value(&mut r, ty)?,
+        }
+    }
+    let arch_key = |name: &str| {
+        let arch = architecture.as_deref()?;
+        numbers.get(&format!("{}.{}", arch, name)).copied()
+    };
+    let block_count = arch_key("block_count");
+
+    let mut tensors = Vec::with_capacity(tensor_count.min(4096) as usize);
+    for _ in 0..tensor_count {
+        let name = read_string(&mut r)?;
+        let n_dims = read_u32(&mut r)?;
+        for _ in 0..n_dims {
+            read_u64(&mut r)?;
+        }
+        let _ty = read_u32(&mut r)?;
+        let offset = read_u64(&mut r)?;
+        tensors.push((offset, name));
+    }
+    let alignment = numbers
+        .get("general.alignment")
+        .map_or(DEFAULT_ALIGNMENT, |a| u64::from(*a).max(1));
+    let data_start = r.stream_position()?.div_ceil(alignment) * alignment;
+    let data_len = file_size.saturating_sub(data_start);
+
+    // Tensor data is contiguous, so each size is the gap to the next offset
+    tensors.sort();
+    let mut layer_bytes = vec![0u64; block_count.unwrap_or(0) as usize];
+    let mut output_bytes = 0;
+    for (idx, (offset, name)) in tensors.iter().enumerate() {
+        let end = tensors.get(idx + 1).map_or(data_len, |(next, _)| *next);
+        let size = end.saturating_sub(*offset);
+        let block = name
+            .strip_prefix("blk.")
+            .and_then(|rest| rest.split('.').next())
+            .and_then(|n| n.parse::<usize>().ok());
+        match block {
+            Some(b) if b < layer_bytes.len() => layer_bytes[b] += size,
+            Some(_) => {}
+            None if name.starts_with("output") => output_bytes += size,
+            None => {}
+        }
+    }
+
+    let kv_bytes_per_token = match (
+        arch_key("embedding_length"),
+        arch_key("attention.head_count"),
+    ) {
+        (Some(n_embd), Some(heads)) if heads > 0 => {
+            let kv_heads = arch_key("attention.head_count_kv").unwrap_or(heads);
+            2 * u64::from(n_embd) * u64::from(kv_heads) / u64::from(heads) * KV_BYTES_PER_VALUE
+        }
+        _ => 0,
+    };
+
+    Ok(GgufInfo {
+        version,
+        architecture,
+        name,
+        block_count,
+        file_size,
+        layer_bytes,
+        output_bytes,
+        kv_bytes_per_token,
+    })
+}
+
+const TYPE_U32: u32 = 4;
+const TYPE_STRING: u32 = 8;
+const TYPE_ARRAY: u32 = 9;
+
+fn skip_value(r: &mut impl Read, ty: u32) -> Result<()> {
+    let width = match ty {
+        0 | 1 | 7 => 1,
+        2 | 3 => 2,
+        4..=6 => 4,
+        10..=12 => 8,
+        TYPE_STRING => {
+            read_string(r)?;
+            return Ok(());
+        }
+        TYPE_ARRAY => {
+            let item_ty = read_u32(r)?;
+            let len = read_u64(r)?;
+            for _ in 0..len {
+                skip_value(r, item_ty)?;
+            }
+            return Ok(());
+        }
+        other => bail!("unknown GGUF metadata type {}", other),
+    };
+    std::io::copy(&mut r.take(width), &mut std::io::sink())?;
+    Ok(())
+}
+
+fn read_u32(r: &mut impl Read) -> Result<u32> {
+    let mut b = [0u8; 4];
+    r.read_exact(&mut b).context("truncated GGUF header")?;
+    Ok(u32::from_le_bytes(b))
+}
+
+fn read_u64(r: &mut impl Read) -> Result<u64> {
+    let mut b = [0u8; 8];
+    r.read_exact(&mut b).context("truncated GGUF header")?;
+    Ok(u64::from_le_bytes(b))
+}
+
+fn read_string(r: &mut impl Read) -> Result<String> {
+    let len = read_u64(r)?;
+    let mut buf = Vec::new();
+    r.take(len).read_to_end(&mut buf)?;
+    if buf.len() as u64 != len {
+        bail!("truncated GGUF header");
+    }
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+/// A GGUF file with `block_count` layers of `layer_size` bytes plus an output
+/// head of the same size and no KV metadata, for tests elsewhere in the crate.
+#[cfg(test)]
+pub(crate) fn fake_model(architecture: &str, block_count: u32, layer_size: u64) -> Vec<u8> {
+    fn string(out: &mut Vec<u8>, s: &str) {
+        out.extend((s.len() as u64).to_le_bytes());
+        out.extend(s.as_bytes());
+    }
+    let mut out = MAGIC.to_vec();
+    out.extend(3u32.to_le_bytes());
+    out.extend((u64::from(block_count) + 1).to_le_bytes());
+    out.extend(3u64.to_le_bytes());
+    // An array ahead of the keys we want exercises skipping
+    string(&mut out, "general.tags");
+    out.extend(TYPE_ARRAY.to_le_bytes());
+    out.extend(TYPE_STRING.to_le_bytes());
+    out.extend(2u64.to_le_bytes());
+    string(&mut out, "a");
+    string(&mut out, "bc");
+    string(&mut out, "general.architecture");
+    out.extend(TYPE_STRING.to_le_bytes());
+    string(&mut out, architecture);
+    string(&mut out, &format!("{}.block_count", architecture));
+    out.extend(TYPE_U32.to_le_bytes());
+    out.extend(block_count.to_le_bytes());
+
+    let names = (0..block_count)
+        .map(|b| format!("blk.{}.attn_q.weight", b))
+        .chain(std::iter::once("output.weight".to_string()));
+    for (idx, name) in names.enumerate() {
+        string(&mut out, &name);
+        out.extend(1u32.to_le_bytes());
+        out.extend(layer_size.to_le_bytes());
+        out.extend(0u32.to_le_bytes());
+        out.extend((idx as u64 * layer_size).to_le_bytes());
+    }
+    let data_start = (out.len() as u64).div_ceil(DEFAULT_ALIGNMENT) * DEFAULT_ALIGNMENT;
+    out.resize(
+        (data_start + (u64::from(block_count) + 1) * layer_size) as usize,
+        0,
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_layer_sizes_and_plans_offload() {
+        let dir = std::env::temp_dir().join(format!("lingua-gguf-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let good = dir.join("model.gguf");
+        std::fs::write(&good, fake_model("llama", 4, 1000)).unwrap();
+        let info = read_info(&good).unwrap();
+        assert_eq!(info.architecture.as_deref(), Some("llama"));
+        assert_eq!(info.block_count, Some(4));
+        assert_eq!(info.layer_bytes, vec![1000; 4]);
+        assert_eq!(info.output_bytes, 1000);
+
+        // 90% of 3400 bytes holds three layers; 5600 holds everything
+        assert_eq!(info.max_gpu_layers(3400, 0), 3);
+        assert_eq!(info.max_gpu_layers(5600, 0), 5);
+        let with_kv = GgufInfo {
+            kv_bytes_per_token: 1,
+            ..info.clone()
+        };
+        assert_eq!(with_kv.offload_bytes(2, 100), 2200);
+
+        let bad = dir.join("model.bin");
+        std::fs::write(&bad, b"not a model").unwrap();
+        assert!(read_info(&bad)
+            .unwrap_err()
+            .to_string()
+            .contains("not a GGUF"));
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -1,25 +1,267 @@
-use super::{InferParams, LlmBackend, PromptParts};
+use super::preset::Preset;
+use super::repetition::LoopDetector;
+use super::trace::{self, TokenStep, TokenTrace, TokenTracer};
+use super::watch::{ContractWatch, Verdict};
+use super::device::{ComputeDevice, DeviceSpec};
+use super::{gguf, prompt, BackendError, BackendStats, ContextStats, Degenerate, InferParams, LlmBackend, OffContract, PromptParts, Token};
+use crate::config::NumaMode;
+use crate::telemetry::{self, Throughput};
 
 use anyhow::{anyhow, Context, Result};
 use llama_cpp_2::context::params::LlamaContextParams;
-use llama_cpp_2::llama_backend::LlamaBackend as LLBackend;
+use llama_cpp_2::llama_backend::{LlamaBackend as LLBackend, NumaStrategy};
 use llama_cpp_2::llama_batch::LlamaBatch;
 use llama_cpp_2::model::params::LlamaModelParams;
 use llama_cpp_2::model::{AddBos, LlamaModel, Special};
 use llama_cpp_2::sampling::LlamaSampler;
-use llama_cpp_2::{ggml_time_us, send_logs_to_tracing, LogOptions};
+use llama_cpp_2::{ggml_time_us, send_logs_to_tracing, LlamaBackendDeviceType, LogOptions};
+use parking_lot::Mutex;
 use std::num::NonZeroU32;
+use std::ops::Deref;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::Semaphore;
+use tracing::{field, info_span, Instrument};
+
+/// Extensions granted to an answer that runs out of tokens mid-object.
+const MAX_CONTINUATIONS: u32 = 2;
+/// Fewest extra tokens one continuation allows.
+const MIN_CONTINUATION_TOKENS: i32 = 256;
 
 pub struct Inner {
     backend: LLBackend,
-    model: LlamaModel,
+    model: ModelSlot,
+    model_name: String,
     n_ctx: i32,
     n_batch: i32,
+    n_ubatch: i32,
     threads: i32,
+    threads_batch: i32,
     limiter: Arc<Semaphore>,
+    slots: usize,
+    max_queue_wait: Option<Duration>,
+    token_tracer: Option<Arc<TokenTracer>>,
+    preset: Option<&'static Preset>,
+    /// K and V bytes one position takes across all layers, from the GGUF header
+    kv_bytes_per_token: Option<u64>,
+    contexts: Mutex<Vec<Arc<LiveContext>>>,
+}
+
+/// A context one inference is using, for [`LlmBackend::stats`].
+struct LiveContext {
+    n_ctx: u32,
+    tokens: AtomicUsize,
+}
+
+/// Keeps a [`LiveContext`] listed while its inference runs, however it ends.
+struct ContextLease<'a> {
+    live: &'a Mutex<Vec<Arc<LiveContext>>>,
+    context: Arc<LiveContext>,
+}
+
+impl<'a> ContextLease<'a> {
+    fn new(live: &'a Mutex<Vec<Arc<LiveContext>>>, n_ctx: u32) -> Self {
+        let context = Arc::new(LiveContext { n_ctx, tokens: AtomicUsize::new(0) });
+        live.lock().push(context.clone());
+        Self { live, context }
+    }
+
+    fn set_tokens(&self, tokens: i32) {
+        self.context.tokens.store(tokens.max(0) as usize, Ordering::Relaxed);
+    }
+}
+
+impl Drop for ContextLease<'_> {
+    fn drop(&mut self) {
+        self.live.lock().retain(|c| !Arc::ptr_eq(c, &self.context));
+    }
+}
+
+/// Where the model is loaded from. `LlamaModelParams` cannot be shared
+/// between threads, so the params are rebuilt for each load.
+struct ModelSource {
+    path: PathBuf,
+    n_gpu_layers: u32,
+    /// ggml device index, when `DEVICE` picks one
+    device: Option<usize>,
+}
+
+impl ModelSource {
+    fn load(&self, backend: &LLBackend) -> Result<LlamaModel> {
+        let mut params = LlamaModelParams::default();
+        if let Some(index) = self.device {
+            params = params.with_devices(&[index]).context("select device")?;
+        }
+        if self.n_gpu_layers > 0 {
+            params = params.with_n_gpu_layers(self.n_gpu_layers);
+        }
+        tracing::info!("Loading model from file: {:?}", self.path);
+        let model = LlamaModel::load_from_file(backend, &self.path, &params).context("load GGUF model")?;
+        tracing::info!("Model loaded successfully");
+        telemetry::record_model_loaded(true);
+        Ok(model)
+    }
+}
+
+/// The model, unless it was unloaded for being idle; the next use loads it
+/// again.
+struct ModelSlot {
+    source: ModelSource,
+    /// Only ever held for a quick look or swap, never across a load, so
+    /// stats and token counts don't wait on one
+    loaded: Mutex<Option<Arc<LlamaModel>>>,
+    /// Held while reloading, so callers needing the model wait for one load
+    /// instead of each starting their own
+    loading: Mutex<()>,
+    last_used: Mutex<Instant>,
+}
+
+impl ModelSlot {
+    /// The model for one use, loading it first if it was unloaded. Callers
+    /// wait while another one loads it.
+    fn get(&self, backend: &LLBackend) -> Result<ModelLease<'_>> {
+        let model = self.load(backend)?;
+        Ok(self.lease(model))
+    }
+
+    /// The model, loaded now if it was unloaded.
+    fn load(&self, backend: &LLBackend) -> Result<Arc<LlamaModel>> {
+        if let Some(model) = self.loaded.lock().clone() {
+            return Ok(model);
+        }
+        let _loading = self.loading.lock();
+        // Another caller may have loaded it while this one waited
+        if let Some(model) = self.loaded.lock().clone() {
+            return Ok(model);
+        }
+        let started = Instant::now();
+        let model = Arc::new(self.source.load(backend)?);
+        tracing::info!(elapsed = ?started.elapsed(), "reloaded model after idle unload");
+        *self.loaded.lock() = Some(model.clone());
+        Ok(model)
+    }
+
+    fn lease(&self, model: Arc<LlamaModel>) -> ModelLease<'_> {
+        ModelLease { model, slot: self }
+    }
+
+    /// The model for one use if it is loaded, without loading it.
+    fn if_loaded(&self) -> Option<ModelLease<'_>> {
+        let model = self.loaded.lock().clone()?;
+        Some(self.lease(model))
+    }
+
+    fn size(&self) -> Option<u64> {
+        self.loaded.lock().as_ref().map(|model| model.size())
+    }
+
+    /// Drop the model if nothing used it for `idle` and no use is in
+    /// progress. Returns whether it was unloaded.
+    fn unload_if_idle(&self, idle: Duration) -> bool {
+        let mut loaded = self.loaded.lock();
+        // Every lease holds a clone, and new ones need the lock held here
+        let unused = loaded.as_ref().is_some_and(|model| Arc::strong_count(model) == 1);
+        if !unused || self.last_used.lock().elapsed() < idle {
+            return false;
+        }
+        *loaded = None;
+        telemetry::record_model_loaded(false);
+        true
+    }
+}
+
+/// The model held for one inference or tokenization; it is never unloaded
+/// while held, and releasing it restarts the idle clock.
+struct ModelLease<'a> {
+    model: Arc<LlamaModel>,
+    slot: &'a ModelSlot,
+}
+
+impl Deref for ModelLease<'_> {
+    type Target = LlamaModel;
+
+    fn deref(&self) -> &LlamaModel {
+        &self.model
+    }
+}
+
+impl Drop for ModelLease<'_> {
+    fn drop(&mut self) {
+        *self.slot.last_used.lock() = Instant::now();
+    }
+}
+
+/// Unload the model once it has gone `idle` unused, until the backend is dropped.
+fn spawn_idle_unload(inner: Weak<Inner>, idle: Duration) -> Result<()> {
+    let every = (idle / 10).clamp(Duration::from_secs(1), Duration::from_secs(60));
+    std::thread::Builder::new()
+        .name("llama-idle-unload".to_string())
+        .spawn(move || loop {
+            std::thread::sleep(every);
+            let Some(inner) = inner.upgrade() else {
+                return;
+            };
+            if inner.model.unload_if_idle(idle) {
+                tracing::info!(?idle, "unloaded idle model; the next request loads it again");
+            }
+        })
+        .context("start idle unload thread")?;
+    Ok(())
+}
+
+/// Compute devices registered with ggml in this build, CPU included, in the
+/// order `DEVICE` indexes them.
+pub fn compute_devices() -> Vec<ComputeDevice> {
+    llama_cpp_2::list_llama_ggml_backend_devices()
+        .into_iter()
+        .map(|dev| ComputeDevice {
+            index: dev.index,
+            backend: dev.backend,
+            name: dev.name,
+            description: dev.description,
+            kind: match dev.device_type {
+                LlamaBackendDeviceType::Cpu => "cpu",
+                LlamaBackendDeviceType::Gpu => "gpu",
+                LlamaBackendDeviceType::IntegratedGpu => "igpu",
+                LlamaBackendDeviceType::Accelerator => "accel",
+                LlamaBackendDeviceType::Unknown => "?",
+            },
+            memory_total: dev.memory_total as u64,
+            memory_free: dev.memory_free as u64,
+        })
+        .collect()
+}
+
+/// Load-time settings for [`LlamaBackend::new`].
+#[derive(Debug, Clone)]
+pub struct LlamaSettings {
+    pub model_path: PathBuf,
+    pub n_ctx: i32,
+    pub n_batch: i32,
+    /// 0 means auto (min(n_batch, 512))
+    pub n_ubatch: i32,
+    pub n_gpu_layers: i32,
+    /// Device to load the model on; `None` leaves the choice to llama.cpp
+    pub device: Option<DeviceSpec>,
+    /// Threads for token generation; 0 means all logical CPUs
+    pub threads: i32,
+    /// Threads for prompt evaluation; 0 means the same as `threads`
+    pub threads_batch: i32,
+    pub numa: NumaMode,
+    /// 0 means min(8, num_cpus)
+    pub infer_concurrency: i32,
+    /// Fail with [`BackendError::QueueTimeout`] instead of waiting longer than
+    /// this for an inference slot; `None` waits indefinitely
+    pub max_queue_wait: Option<Duration>,
+    /// Record token-level timing and sampler choices of some inferences
+    pub token_tracer: Option<Arc<TokenTracer>>,
+    /// Chat template and stop strings tuned for the model's family
+    pub preset: Option<&'static Preset>,
+    /// Unload the model after this long without inference, loading it again
+    /// on the next request; `None` keeps it loaded
+    pub idle_unload: Option<Duration>,
 }
 
 #[derive(Clone)]
@@ -28,33 +270,78 @@ pub struct LlamaBackend {
 }
 
 impl LlamaBackend {
-    pub fn new(
-        model_path: PathBuf,
-        n_ctx: i32,
-        n_batch: i32,
-        n_gpu_layers: i32,
-        threads: i32,
-        infer_concurrency: i32,
-    ) -> Result<Self> {
+    pub fn new(settings: LlamaSettings) -> Result<Self> {
+        let LlamaSettings {
+            model_path,
+            n_ctx,
+            n_batch,
+            n_ubatch,
+            n_gpu_layers,
+            device,
+            threads,
+            threads_batch,
+            numa,
+            infer_concurrency,
+            max_queue_wait,
+            token_tracer,
+            preset,
+            idle_unload,
+        } = settings;
         tracing::info!("Initializing LlamaBackend with model_path={:?}, n_ctx={}, n_batch={}, n_gpu_layers={}",
                       model_path, n_ctx, n_batch, n_gpu_layers);
 
         send_logs_to_tracing(LogOptions::default());
 
         tracing::debug!("Initializing llama backend...");
-        let backend = LLBackend::init().context("init llama backend")?;
+        let backend = match numa {
+            NumaMode::Disabled => LLBackend::init(),
+            mode => {
+                tracing::info!("Initializing llama backend with NUMA strategy {:?}", mode);
+                LLBackend::init_numa(match mode {
+                    NumaMode::Distribute => NumaStrategy::DISTRIBUTE,
+                    NumaMode::Isolate => NumaStrategy::ISOLATE,
+                    NumaMode::Numactl => NumaStrategy::NUMACTL,
+                    NumaMode::Mirror => NumaStrategy::MIRROR,
+                    NumaMode::Disabled => NumaStrategy::DISABLED,
+                })
+            }
+        }
+        .context("init llama backend")?;
         tracing::debug!("Llama backend initialized successfully");
 
-        let mut model_params = LlamaModelParams::default();
+        let mut device_index = None;
+        let n_gpu_layers = match &device {
+            Some(DeviceSpec::Cpu) => 0,
+            Some(spec) => {
+                if let Some(dev) = spec.pick(&compute_devices())? {
+                    tracing::info!("Using device {} ({}: {})", spec, dev.name, dev.description);
+                    device_index = Some(dev.index);
+                }
+                n_gpu_layers
+            }
+            None => n_gpu_layers,
+        };
         if n_gpu_layers > 0 {
             tracing::info!("Enabling {} GPU layers", n_gpu_layers);
-            model_params = model_params.with_n_gpu_layers(n_gpu_layers as u32);
         }
 
-        tracing::info!("Loading model from file: {:?}", model_path);
-        let model = LlamaModel::load_from_file(&backend, model_path, &model_params)
-            .context("load GGUF model")?;
-        tracing::info!("Model loaded successfully");
+        // Sizes the KV cache reported by stats(); f16 entries, as llama.cpp defaults to
+        let kv_bytes_per_token = gguf::read_info(&model_path)
+            .ok()
+            .map(|info| info.kv_bytes_per_token * info.layer_bytes.len() as u64)
+            .filter(|&bytes| bytes > 0);
+
+        let model_name = model_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "unknown".to_string());
+
+        let source = ModelSource {
+            path: model_path,
+            n_gpu_layers: n_gpu_layers as u32,
+            device: device_index,
+        };
+        let model = source.load(&backend)?;
 
         let permits = if infer_concurrency > 0 {
             usize::max(1, infer_concurrency as usize)
@@ -62,88 +349,139 @@ impl LlamaBackend {
             usize::min(8, usize::max(1, num_cpus::get()))
         };
 
-        Ok(Self {
-            inner: Arc::new(Inner {
-                backend,
-                model,
-                n_ctx,
-                n_batch,
-                threads,
-                limiter: Arc::new(Semaphore::new(permits)),
-            }),
-        })
-    }
-
-    fn build_prompt(prompt: PromptParts) -> String {
-        format!(
-            "{sys}\n\nYou are an expert linguist and lexicographer. Your only job is to produce a single valid JSON object describing an English word.\n\n## OUTPUT CONTRACT — ABSOLUTE RULES\n\n1) Output must be a single JSON object only. No explanations, no code fences, no comments, no trailing commas, no nulls, no placeholders like \"<...>\", no markdown.\n2) All required fields must be present and non-empty strings or arrays (arrays may be empty but must exist).\n3) Use straight quotes (\") only. Escape any internal quotes per JSON.\n4) Use UTF-8. IPA must be valid IPA characters.\n\n## CONTENT REQUIREMENTS\n\n- \"word\": the surface/inflected form exactly as given by the user (case-preserve).\n- \"baseForm\": the lemma/root form in lowercase.\n- \"phonetic\": the IPA transcription in slashes, e.g., \"/kəˈmjuːnɪkeɪt/\". Use a standard, contemporary pronunciation (General American or widely accepted international), not a regional outlier.\n- \"difficulty\": one of \"beginner\", \"intermediate\", \"advanced\" based on typical frequency and morphology; choose conservatively.\n- \"language\": always \"english\".\n- \"meanings\": an array of 1-4 sense objects. Each sense MUST have a unique \"partOfSpeech\" value across the array.\n  • \"definition\": 30-80 words, clear, neutral, and sense-specific; do not repeat the headword mechanically.\n  • \"partOfSpeech\": one of [\"noun\",\"verb\",\"adjective\",\"adverb\",\"pronoun\",\"preposition\",\"conjunction\",\"interjection\",\"article\",\"determiner\",\"numeral\",\"participle\",\"gerund\"].\n  • \"exampleSentence\": natural, contemporary usage; keep under 25 words; do not quote famous works.\n  • \"grammarTip\": short usage guidance (morphology, typical complements, common errors, or register).\n  • \"synonyms\": 2-8 near-synonyms as single tokens or short phrases; none may duplicate the headword; keep sense-appropriate.\n  • \"antonyms\": 0-6 reasonable opposites; empty array allowed if none fit.\n  • \"translations\": object with keys [\"es\",\"fr\",\"de\",\"zh\",\"ja\",\"it\",\"pt\",\"ru\",\"ar\"]; each value a common single-word or brief phrase capturing THIS sense.\n\n## QUALITY & CONSISTENCY CHECKS (perform before finalizing):\n\n- Valid JSON when parsed strictly.\n- \"meanings\" present with 1-4 items and all \"partOfSpeech\" values unique.\n- No hallucinated morphology (e.g., correct lemma and typical inflections).\n- No repetitive or circular definitions.\n- Translations match each individual sense, not copied across blindly.\n- Arrays contain unique, lower-case items unless proper-case is standard.\n- No extra keys beyond the schema.\n\nWord: {word}\nRespond with the JSON object only.",
-            sys = prompt.system,
-            word = prompt.user_word
-        )
+        let inner = Arc::new(Inner {
+            backend,
+            model: ModelSlot {
+                source,
+                loaded: Mutex::new(Some(Arc::new(model))),
+                loading: Mutex::new(()),
+                last_used: Mutex::new(Instant::now()),
+            },
+            model_name,
+            n_ctx,
+            n_batch,
+            n_ubatch,
+            threads,
+            threads_batch,
+            limiter: Arc::new(Semaphore::new(permits)),
+            slots: permits,
+            max_queue_wait,
+            token_tracer,
+            preset,
+            kv_bytes_per_token,
+            contexts: Mutex::new(Vec::new()),
+        });
+        if let Some(idle) = idle_unload {
+            tracing::info!(?idle, "unloading the model when idle");
+            spawn_idle_unload(Arc::downgrade(&inner), idle)?;
+        }
+        Ok(Self { inner })
     }
 
-    fn extract_json_bytes(s: &str) -> Option<Vec<u8>> {
-        let mut depth = 0i32;
-        let mut start = None;
-        for (i, ch) in s.char_indices() {
-            if ch == '{' {
-                if depth == 0 {
-                    start = Some(i);
-                }
-                depth += 1;
-            } else if ch == '}' {
-                depth -= 1;
-                if depth == 0 {
-                    if let Some(st) = start {
-                        return Some(s.as_bytes()[st..=i].to_vec());
-                    }
-                }
-            }
+    /// The model for one inference. Reloading a multi-gigabyte model after an
+    /// idle unload runs on a blocking thread rather than stalling the runtime.
+    async fn model(&self) -> Result<ModelLease<'_>> {
+        if let Some(model) = self.inner.model.if_loaded() {
+            return Ok(model);
         }
-        None
+        let inner = self.inner.clone();
+        let model = tokio::task::spawn_blocking(move || inner.model.load(&inner.backend))
+            .await
+            .context("model load panicked")??;
+        Ok(self.inner.model.lease(model))
     }
 }
 
 #[async_trait::async_trait]
 impl LlmBackend for LlamaBackend {
+    // One span per call with a child for each phase, so traces separate time
+    // spent waiting for a slot from time spent on the GPU
+    #[tracing::instrument(name = "infer", skip_all, fields(word = %prompt.user_word, task = prompt.task.name()))]
     async fn infer_json(&self, prompt: PromptParts, p: &InferParams) -> Result<Vec<u8>> {
         tracing::info!("Starting inference for word: {}", prompt.user_word);
-        let _permit = self
+        let acquire = self
             .inner
             .limiter
             .acquire()
-            .await
-            .expect("semaphore not closed");
+            .instrument(info_span!("queue_wait", available = self.inner.limiter.available_permits()));
+        let queued = telemetry::Queued::enter();
+        let _permit = match self.inner.max_queue_wait {
+            Some(wait) => tokio::time::timeout(wait, acquire)
+                .await
+                .map_err(|_| BackendError::QueueTimeout(wait))?,
+            None => acquire.await,
+        }
+        .expect("semaphore not closed");
+        drop(queued);
+        // Time to first token counts from here: queueing for a slot is excluded
+        let started = Instant::now();
 
         let threads = if self.inner.threads > 0 {
             self.inner.threads
         } else {
             num_cpus::get() as i32
         };
-        tracing::debug!("Creating context with n_ctx={}, n_threads={}",
-                       self.inner.n_ctx, threads);
-        let ctx_params = LlamaContextParams::default()
-            .with_n_ctx(Some(NonZeroU32::new(self.inner.n_ctx as u32).unwrap()))
-            .with_n_threads(threads)
-            .with_n_threads_batch(threads);
-        let mut ctx = self
-            .inner
-            .model
-            .new_context(&self.inner.backend, ctx_params)
-            .context("create llama context")?;
-        tracing::debug!("Context created successfully");
-
-        let prompt_text = Self::build_prompt(prompt);
+        // Leave room for the answer: up to max_tokens, but never more than half the context
+        let reserve = p.max_tokens.clamp(0, self.inner.n_ctx / 2) as usize + 8;
+        let model = self.model().await?;
+        let model: &LlamaModel = &model;
+        let template_tokens = self.inner.preset.map_or(0, |preset| {
+            model
+                .str_to_token(&preset.wrap(""), AddBos::Never)
+                .map(|t| t.len())
+                .unwrap_or(0)
+        });
+        let budget = (self.inner.n_ctx as usize).saturating_sub(reserve + template_tokens);
+        let fitted = prompt::fit_to_budget(prompt::sections(&prompt), budget, |text| {
+            model
+                .str_to_token(text, AddBos::Never)
+                .map(|t| t.len())
+                .unwrap_or(text.len())
+        })
+        .context("fit prompt to context")?;
+        if !fitted.dropped.is_empty() {
+            tracing::warn!("Prompt exceeded {} token budget; dropped sections {:?}", budget, fitted.dropped);
+        }
+        let prompt_text = match self.inner.preset {
+            Some(preset) => preset.wrap(&fitted.text),
+            None => fitted.text,
+        };
+        let grammar = prompt::grammar(&prompt).or_else(|| p.json_grammar.then(|| prompt::JSON_GRAMMAR.to_string()));
         tracing::debug!("Built prompt (length={}): {}", prompt_text.len(), &prompt_text[..prompt_text.len().min(200)]);
 
-        let tokens_list = self
-            .inner
-            .model
+        let tokens_list = model
             .str_to_token(&prompt_text, AddBos::Always)
             .with_context(|| format!("tokenize prompt: {}", prompt_text))?;
         tracing::debug!("Tokenized prompt into {} tokens", tokens_list.len());
 
+        // Size batches to the prompt so short prompts don't reserve compute
+        // buffers they never use and long ones are evaluated in chunks
+        let sizes = BatchSizes::for_prompt(
+            tokens_list.len(),
+            self.inner.n_ctx,
+            self.inner.n_batch,
+            self.inner.n_ubatch,
+        );
+        let threads_batch = if self.inner.threads_batch > 0 {
+            self.inner.threads_batch
+        } else {
+            threads
+        };
+        tracing::debug!("Creating context with n_ctx={}, n_threads={}, n_threads_batch={}, n_batch={}, n_ubatch={}",
+                       self.inner.n_ctx, threads, threads_batch, sizes.n_batch, sizes.n_ubatch);
+        let ctx_params = LlamaContextParams::default()
+            .with_n_ctx(Some(NonZeroU32::new(self.inner.n_ctx as u32).unwrap()))
+            .with_n_threads(threads)
+            .with_n_threads_batch(threads_batch)
+            .with_n_batch(sizes.n_batch)
+            .with_n_ubatch(sizes.n_ubatch);
+        let mut ctx = info_span!("context_create", n_ctx = self.inner.n_ctx, n_batch = sizes.n_batch)
+            .in_scope(|| model.new_context(&self.inner.backend, ctx_params))
+            .context("create llama context")?;
+        tracing::debug!("Context created successfully");
+
         let n_ctx = ctx.n_ctx() as i32;
+        let lease = ContextLease::new(&self.inner.contexts, n_ctx as u32);
         let max_new = p
             .max_tokens
             .min((n_ctx - 8).saturating_sub(tokens_list.len() as i32));
@@ -155,33 +493,69 @@ impl LlmBackend for LlamaBackend {
         }
 
         tracing::debug!("Creating batch and decoding prompt...");
-        let mut batch = LlamaBatch::new(self.inner.n_batch as usize, 1);
-        let last_index: i32 = (tokens_list.len() - 1) as i32;
-        for (i, token) in (0_i32..).zip(tokens_list.into_iter()) {
-            let is_last = i == last_index;
-            batch.add(token, i, [0_i32].as_slice(), is_last)
-                .with_context(|| format!("failed to add token {} to batch at position {}", token, i))?;
-        }
-        ctx.decode(&mut batch)
-            .context("decode prompt - this may indicate model compatibility issues")?;
+        let mut batch = LlamaBatch::new(sizes.n_batch as usize, 1);
+        let n_prompt = tokens_list.len() as i32;
+        let prompt_eval = info_span!("prompt_eval", tokens = n_prompt).entered();
+        let prompt_started = Instant::now();
+        // Only the final prompt token needs logits; earlier chunks just fill the KV cache
+        for (chunk_idx, chunk) in tokens_list.chunks(sizes.n_batch as usize).enumerate() {
+            batch.clear();
+            let offset = (chunk_idx * sizes.n_batch as usize) as i32;
+            for (i, token) in (offset..).zip(chunk.iter().copied()) {
+                let is_last = i == n_prompt - 1;
+                batch.add(token, i, [0_i32].as_slice(), is_last)
+                    .with_context(|| format!("failed to add token {} to batch at position {}", token, i))?;
+            }
+            ctx.decode(&mut batch)
+                .with_context(|| format!("decode prompt chunk {} - this may indicate model compatibility issues", chunk_idx))?;
+        }
+        let prompt_time = prompt_started.elapsed();
+        prompt_eval.exit();
+        lease.set_tokens(n_prompt);
         tracing::debug!("Prompt decoded successfully");
 
         let mut samplers: Vec<LlamaSampler> = vec![
             LlamaSampler::temp(p.temp),
             LlamaSampler::top_p(p.top_p, 1),
             LlamaSampler::min_p(p.min_p, 1),
-            LlamaSampler::penalties(64, p.repeat_penalty, 0.0, 0.0),
+            LlamaSampler::penalties(
+                p.repeat_last_n,
+                p.repeat_penalty,
+                p.frequency_penalty,
+                p.presence_penalty,
+            ),
         ];
+        if p.dry_multiplier > 0.0 {
+            // llama.cpp's default breakers; `"` and `:` keep repeated JSON keys unpenalized
+            samplers.push(LlamaSampler::dry(model, p.dry_multiplier, p.dry_base,
+                                            p.dry_allowed_length, -1, ["\n", ":", "\"", "*"]));
+        }
+        if p.xtc_probability > 0.0 {
+            // XTC rolls the dice per token; seed it differently each run
+            samplers.push(LlamaSampler::xtc(p.xtc_probability, p.xtc_threshold, 1, run_seed()));
+        }
 
-        // Skip GBNF grammar due to inference crashes - use JSON extraction instead
-        tracing::info!("Using unconstrained generation with JSON extraction (GBNF disabled due to stability issues)");
-        // Note: GBNF grammar constraints cause SIGABRT during inference with this model/setup
-        // The extract_json_bytes function will extract valid JSON from the free-form output
+        match grammar.as_deref() {
+            // Small fixed-shape grammars (focused repairs) and plain JSON syntax constrain cleanly
+            Some(gbnf) => match LlamaSampler::grammar(model, gbnf, "root") {
+                Some(g) => samplers.insert(0, g),
+                None => tracing::warn!("Failed to compile task grammar; generating unconstrained"),
+            },
+            None => {
+                // Skip GBNF grammar due to inference crashes - use JSON extraction instead
+                tracing::info!("Using unconstrained generation with JSON extraction (GBNF disabled due to stability issues)");
+                // Note: the full entry grammar causes SIGABRT during inference with this model/setup
+                // The extract_json_bytes function will extract valid JSON from the free-form output
+            }
+        }
 
-        samplers.push(LlamaSampler::greedy());
+        samplers.push(match Pick::for_params(p) {
+            Pick::Random => LlamaSampler::dist(run_seed()),
+            Pick::Greedy => LlamaSampler::greedy(),
+        });
         let mut sampler = LlamaSampler::chain_simple(samplers);
 
-        let mut n_cur = batch.n_tokens();
+        let mut n_cur = n_prompt;
         let mut n_decode = 0;
         let _t_main_start = ggml_time_us();
 
@@ -189,24 +563,91 @@ impl LlmBackend for LlamaBackend {
         let mut decoder = encoding_rs::UTF_8.new_decoder();
 
         tracing::info!("Starting generation loop, max_new={}", max_new);
-        while n_decode < max_new {
-            tracing::trace!("Sampling token {} of {}", n_decode + 1, max_new);
+        let generation = info_span!("generate", max_new, tokens = field::Empty).entered();
+        let decode_started = Instant::now();
+        let mut first_token = None;
+        let tracer = self.inner.token_tracer.as_ref().filter(|t| t.should_trace());
+        let mut steps: Vec<TokenStep> = Vec::new();
+        let mut last_step = decode_started;
+        let mut stopped = "max_tokens";
+        let room = n_ctx - 8 - n_prompt;
+        let mut limit = max_new;
+        let mut continuations = 0;
+        let mut watch = ContractWatch::new(&prompt.task, &prompt.contract.parts_of_speech());
+        let mut off_contract = None;
+        let mut loops = (!prompt.task.wants_raw_output()).then(LoopDetector::default);
+        let mut degenerate = None;
+        loop {
+            if n_decode >= limit {
+                // Out of budget mid-object: the KV cache still holds everything,
+                // so keep decoding rather than hand back JSON that cannot parse
+                if continuations < MAX_CONTINUATIONS && limit < room
+                    && !prompt.task.wants_raw_output() && prompt::is_truncated_json(&out) {
+                    continuations += 1;
+                    limit = (limit + (p.max_tokens / 2).max(MIN_CONTINUATION_TOKENS)).min(room);
+                    tracing::warn!("Output truncated at {} tokens with an open object; continuing up to {}", n_decode, limit);
+                    metrics::counter!("lingua_output_continuations_total").increment(1);
+                } else {
+                    break;
+                }
+            }
+            tracing::trace!("Sampling token {} of {}", n_decode + 1, limit);
 
             // Sample next token with error handling
             let token = sampler.sample(&ctx, batch.n_tokens() - 1);
             sampler.accept(token);
+            first_token.get_or_insert_with(|| started.elapsed());
+            if tracer.is_some() {
+                let (p, rank) = trace::choice(ctx.get_logits_ith(batch.n_tokens() - 1), token.0);
+                let now = Instant::now();
+                steps.push(TokenStep { id: token.0, text: String::new(), ms: millis(now - last_step), p, rank });
+                last_step = now;
+            }
 
-            if self.inner.model.is_eog_token(token) {
+            if model.is_eog_token(token) {
                 tracing::debug!("Encountered end-of-generation token at position {}", n_decode);
+                stopped = "eog";
                 break;
             }
 
             // Convert token to string with error handling
-            let output_bytes = self.inner.model.token_to_bytes(token, Special::Tokenize)
+            let output_bytes = model.token_to_bytes(token, Special::Tokenize)
                 .with_context(|| format!("failed to convert token {} to bytes", token))?;
             let mut output_string = String::with_capacity(16);
             let _ = decoder.decode_to_string(&output_bytes, &mut output_string, false);
             out.push_str(&output_string);
+            // Only filled while tracing, and then the last step is this token's
+            if let Some(step) = steps.last_mut() {
+                step.text = output_string.clone();
+            }
+            if let Some(end) = self.inner.preset.and_then(|preset| preset.stop_at(&out)) {
+                tracing::debug!("Stop string reached at position {}", n_decode);
+                out.truncate(end);
+                stopped = "stop";
+                break;
+            }
+            // Stop as soon as the answer is whole, or can no longer be used
+            match watch.as_mut().and_then(|w| w.feed(&output_string)) {
+                Some(Verdict::Complete) => {
+                    stopped = "complete";
+                    break;
+                }
+                Some(Verdict::OffContract(reason)) => {
+                    tracing::warn!("Stopping generation at token {}: {}", n_decode + 1, reason);
+                    metrics::counter!("lingua_off_contract_aborts_total").increment(1);
+                    stopped = "off_contract";
+                    off_contract = Some(reason);
+                    break;
+                }
+                None => {}
+            }
+            if let Some(reason) = loops.as_mut().and_then(|d| d.push(token.0)) {
+                tracing::warn!("Stopping generation at token {}: {}", n_decode + 1, reason);
+                metrics::counter!("lingua_degenerate_aborts_total").increment(1);
+                stopped = "loop";
+                degenerate = Some(reason);
+                break;
+            }
 
             // Prepare for next iteration
             batch.clear();
@@ -216,16 +657,197 @@ impl LlmBackend for LlamaBackend {
             ctx.decode(&mut batch)
                 .with_context(|| format!("decode step failed at token {}", n_decode + 1))?;
             n_decode += 1;
+            lease.set_tokens(n_cur);
         }
 
-        tracing::info!("Generation completed after {} tokens, output length: {}",
-                      n_decode, out.len());
+        generation.record("tokens", n_decode);
+        generation.exit();
+        let throughput = Throughput {
+            prompt_tokens: tokens_list.len(),
+            prompt_time,
+            output_tokens: n_decode as usize,
+            decode_time: decode_started.elapsed(),
+            first_token,
+            total_time: started.elapsed(),
+        };
+        telemetry::record_throughput(throughput);
+        if let Some(tracer) = tracer {
+            tracer.write(&TokenTrace {
+                at: trace::unix_millis(),
+                word: prompt.user_word.clone(),
+                task: prompt.task.name().to_string(),
+                model: self.inner.model_name.clone(),
+                params: p.clone(),
+                prompt_tokens: tokens_list.len(),
+                prompt_ms: millis(prompt_time),
+                total_ms: millis(throughput.total_time),
+                stopped,
+                tokens: steps,
+            });
+        }
+        tracing::info!("Generation completed after {} tokens, output length: {}, prompt {:.1} tok/s, decode {:.1} tok/s",
+                      n_decode, out.len(),
+                      throughput.prompt_tps().unwrap_or(0.0), throughput.decode_tps().unwrap_or(0.0));
         tracing::debug!("Raw output: {}", &out[..out.len().min(500)]);
+        if let Some(reason) = off_contract {
+            return Err(OffContract(reason).into());
+        }
+        if let Some(reason) = degenerate {
+            return Err(Degenerate(reason).into());
+        }
 
-        if let Some(bytes) = Self::extract_json_bytes(&out) {
+        if prompt.task.wants_raw_output() {
+            return Ok(out.into_bytes());
+        }
+        if let Some(bytes) = prompt::extract_json_bytes(&out) {
             return Ok(bytes);
         }
 
         Ok(out.into_bytes())
     }
+
+    fn model_name(&self) -> String {
+        self.inner.model_name.clone()
+    }
+
+    fn stats(&self) -> Option<BackendStats> {
+        let inner = &self.inner;
+        let kv_bytes = |positions: usize| inner.kv_bytes_per_token.map(|b| b * positions as u64);
+        let contexts = inner
+            .contexts
+            .lock()
+            .iter()
+            .map(|c| {
+                let tokens = c.tokens.load(Ordering::Relaxed);
+                ContextStats {
+                    n_ctx: c.n_ctx,
+                    tokens,
+                    kv_bytes_used: kv_bytes(tokens),
+                    kv_bytes_reserved: kv_bytes(c.n_ctx as usize),
+                }
+            })
+            .collect();
+        let model_bytes = inner.model.size();
+        Some(BackendStats {
+            model_bytes: model_bytes.unwrap_or(0),
+            model_loaded: model_bytes.is_some(),
+            slots: inner.slots,
+            slots_in_use: inner.slots - inner.limiter.available_permits(),
+            contexts,
+        })
+    }
+
+    fn tokenize(&self, text: &str) -> Option<Result<Vec<Token>>> {
+        let tokenize = || -> Result<Vec<Token>> {
+            let model = self.inner.model.get(&self.inner.backend)?;
+            model
+                .str_to_token(text, AddBos::Never)
+                .context("tokenize text")?
+                .into_iter()
+                .map(|token| {
+                    let bytes = model
+                        .token_to_bytes(token, Special::Tokenize)
+                        .with_context(|| format!("detokenize token {}", token))?;
+                    Ok(Token {
+                        id: token.0,
+                        text: String::from_utf8_lossy(&bytes).into_owned(),
+                    })
+                })
+                .collect()
+        };
+        Some(tokenize())
+    }
+
+    // Skips detokenizing each token, which `tokenize` has to do. Counting
+    // never reloads an idle-unloaded model; callers estimate instead
+    fn count_tokens(&self, text: &str) -> Option<usize> {
+        let model = self.inner.model.if_loaded()?;
+        model.str_to_token(text, AddBos::Never).ok().map(|t| t.len())
+    }
+}
+
+fn millis(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+/// How the last sampler in the chain picks the next token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pick {
+    /// Draw from what the other samplers left, so temperature, top-p and
+    /// min-p shape the output
+    Random,
+    /// Always the top token, which those samplers cannot change
+    Greedy,
+}
+
+impl Pick {
+    /// Greedy only at temperature 0, as on the greedy rungs of the sampling ladder.
+    fn for_params(p: &InferParams) -> Self {
+        if p.temp > 0.0 { Self::Random } else { Self::Greedy }
+    }
+}
+
+/// A different seed for each run's random samplers.
+fn run_seed() -> u32 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.subsec_nanos())
+}
+
+/// Logical (`n_batch`) and physical (`n_ubatch`) batch sizes for one request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BatchSizes {
+    n_batch: u32,
+    n_ubatch: u32,
+}
+
+impl BatchSizes {
+    /// Default physical batch when `n_ubatch` is left at 0 (auto), matching llama.cpp.
+    const AUTO_UBATCH: u32 = 512;
+
+    /// Never larger than the prompt or the context; `n_ubatch` never exceeds `n_batch`.
+    fn for_prompt(prompt_len: usize, n_ctx: i32, n_batch: i32, n_ubatch: i32) -> Self {
+        let prompt_len = u32::try_from(prompt_len).unwrap_or(u32::MAX).max(1);
+        let n_batch = (n_batch.max(1) as u32).min(n_ctx.max(1) as u32).min(prompt_len);
+        let n_ubatch = if n_ubatch > 0 { n_ubatch as u32 } else { Self::AUTO_UBATCH };
+        Self {
+            n_batch,
+            n_ubatch: n_ubatch.min(n_batch),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batch_sizes_follow_prompt_and_context() {
+        // Long prompt: chunks of the configured n_batch, auto ubatch capped by it
+        assert_eq!(
+            BatchSizes::for_prompt(1500, 4096, 256, 0),
+            BatchSizes { n_batch: 256, n_ubatch: 256 }
+        );
+        // Short prompt shrinks both
+        assert_eq!(
+            BatchSizes::for_prompt(40, 4096, 2048, 512),
+            BatchSizes { n_batch: 40, n_ubatch: 40 }
+        );
+        // Context smaller than n_batch
+        assert_eq!(
+            BatchSizes::for_prompt(3000, 1024, 2048, 0),
+            BatchSizes { n_batch: 1024, n_ubatch: 512 }
+        );
+    }
+
+    #[test]
+    fn each_sampling_rung_samples_differently() {
+        use crate::model::Sampling;
+        let configured = InferParams::default();
+        let rungs = [Sampling::Configured, Sampling::Cooler, Sampling::Greedy, Sampling::Constrained]
+            .map(|rung| rung.apply(&configured));
+        let picks = rungs.each_ref().map(Pick::for_params);
+        assert_eq!(picks, [Pick::Random, Pick::Random, Pick::Greedy, Pick::Greedy]);
+        // Drawn at different temperatures, or held to JSON by a grammar
+        assert!(rungs[1].temp < rungs[0].temp);
+        assert!(!rungs[2].json_grammar && rungs[3].json_grammar);
+    }
 }
@@ -0,0 +1,188 @@
+use super::{InferParams, LlmBackend, PromptParts, PromptTask};
+
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// Model-free backend returning deterministic, schema-valid entries, for
+/// frontend development and CI against the real HTTP surface.
+///
+/// The same word always yields the same entry; the shape (difficulty, number
+/// and kind of senses) varies between words so clients see realistic spread.
+#[derive(Clone, Default)]
+pub struct MockBackend {
+    latency: Duration,
+}
+
+impl MockBackend {
+    pub fn new(latency: Duration) -> Self {
+        Self { latency }
+    }
+
+    pub fn entry_for(word: &str) -> Value {
+        const POS: [&str; 4] = ["noun", "verb", "adjective", "adverb"];
+        let hash = fnv1a(word);
+        let base = word.trim().to_lowercase();
+        let difficulty = match base.chars().count() {
+            0..=5 => "beginner",
+            6..=9 => "intermediate",
+            _ => "advanced",
+        };
+
+        let senses = 1 + (hash % 2) as usize;
+        let meanings: Vec<Value> = (0..senses)
+            .map(|i| {
+                let pos = POS[(hash as usize + i) % POS.len()];
+                json!({
+                    "senseRank": i + 1,
+                    "partOfSpeech": pos,
+                    "definition": format!(
+                        "Mock {} sense {} of \"{}\", generated without a model for development and testing.",
+                        pos, i + 1, base
+                    ),
+                    "exampleSentence": format!("This sentence uses \"{}\" as a {}.", base, pos),
+                    "grammarTip": format!("Mock entry: treat \"{}\" as a regular {}.", base, pos),
+                    "synonyms": [format!("{}-like", base), format!("quasi-{}", base)],
+                    "antonyms": [format!("non-{}", base)],
+                    "translations": {
+                        "es": format!("{} (es)", base), "fr": format!("{} (fr)", base),
+                        "de": format!("{} (de)", base), "zh": format!("{} (zh)", base),
+                        "ja": format!("{} (ja)", base), "it": format!("{} (it)", base),
+                        "pt": format!("{} (pt)", base), "ru": format!("{} (ru)", base),
+                        "ar": format!("{} (ar)", base)
+                    }
+                })
+            })
+            .collect();
+
+        json!({
+            "word": word,
+            "baseForm": base,
+            "phonetic": format!("/{}/", base),
+            "difficulty": difficulty,
+            "language": "english",
+            "meanings": meanings,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmBackend for MockBackend {
+    async fn infer_json(&self, prompt: PromptParts, _p: &InferParams) -> Result<Vec<u8>> {
+        if !self.latency.is_zero() {
+            tokio::time::sleep(self.latency).await;
+        }
+        let out = match &prompt.task {
+            PromptTask::Entry => Self::entry_for(&prompt.user_word),
+            // Mock words only have their mock senses
+            PromptTask::Sense { part_of_speech } => {
+                let mut entry = Self::entry_for(&prompt.user_word);
+                let meanings = entry["meanings"].as_array_mut().expect("mock meanings");
+                meanings.retain(|m| m["partOfSpeech"] == part_of_speech.as_str());
+                if meanings.is_empty() {
+                    json!({ "senseNotFound": true })
+                } else {
+                    meanings[0]["senseRank"] = json!(1);
+                    entry
+                }
+            }
+            PromptTask::Senses { max_meanings, .. } => {
+                let mut entry = Self::entry_for(&prompt.user_word);
+                let meanings = entry["meanings"].as_array_mut().expect("mock meanings");
+                meanings.truncate(*max_meanings);
+                entry
+            }
+            PromptTask::Translations { languages, .. } => {
+                let base = prompt.user_word.trim().to_lowercase();
+                languages
+                    .iter()
+                    .map(|lang| (lang.clone(), json!(format!("{} ({})", base, lang))))
+                    .collect()
+            }
+            // Undoes the mock translations above
+            PromptTask::BackTranslation { items } => items
+                .iter()
+                .map(|(lang, text)| {
+                    let english = text.trim_end_matches(&format!(" ({})", lang));
+                    (lang.clone(), json!([english]))
+                })
+                .collect(),
+            PromptTask::Field { path, entry } => {
+                let Some(ptr) = crate::patch::field_pointer(path) else {
+                    anyhow::bail!("unknown field path {}", path);
+                };
+                // Meanings beyond the mock's own senses keep their current value
+                let fresh = Self::entry_for(&prompt.user_word).pointer(&ptr).cloned();
+                json!({ "value": fresh.or_else(|| entry.pointer(&ptr).cloned()) })
+            }
+            PromptTask::Family => {
+                let base = prompt.user_word.trim().to_lowercase();
+                json!({
+                    "word": base,
+                    "members": [
+                        { "word": format!("{}ness", base), "partOfSpeech": "noun" },
+                        { "word": format!("{}ly", base), "partOfSpeech": "adverb" },
+                    ],
+                })
+            }
+            // One syllable keeps the mock's IPA and spelling trivially consistent
+            PromptTask::Pronunciation => {
+                let base = prompt.user_word.trim().to_lowercase();
+                json!({
+                    "word": base,
+                    "ipa": format!("/{}/", base),
+                    "syllables": [base],
+                    "stress": 1,
+                    "minimalPairs": [
+                        { "word": format!("{}s", base), "ipa": format!("/{}s/", base) },
+                        { "word": format!("{}t", base), "ipa": format!("/{}t/", base) },
+                    ],
+                })
+            }
+            // Every new sentence goes to the first sense
+            PromptTask::Examples { count, senses, .. } => {
+                let base = prompt.user_word.trim().to_lowercase();
+                let pos = senses.first().map_or("noun", |(pos, _)| pos.as_str());
+                let examples: Vec<Value> = (1..=*count)
+                    .map(|i| {
+                        json!({
+                            "partOfSpeech": pos,
+                            "sentence": format!("Mock sentence {} with \"{}\" as a {}.", i, base, pos),
+                        })
+                    })
+                    .collect();
+                json!({ "word": base, "examples": examples })
+            }
+            // No model to run the prompt through; echo it so callers can see what was sent
+            PromptTask::Raw { prompt } => json!({ "prompt": prompt }),
+        };
+        Ok(serde_json::to_vec(&out)?)
+    }
+
+    fn model_name(&self) -> String {
+        "mock".to_string()
+    }
+}
+
+/// Stable across runs and platforms, unlike `DefaultHasher`.
+fn fnv1a(s: &str) -> u64 {
+    s.bytes().fold(0xcbf29ce484222325, |h, b| {
+        (h ^ u64::from(b)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate::Validator;
+
+    #[test]
+    fn entries_are_deterministic_and_valid() {
+        let validator = Validator::new("").unwrap();
+        for word in ["run", "Serendipity", "ox", "quickly"] {
+            let entry = MockBackend::entry_for(word);
+            assert_eq!(entry, MockBackend::entry_for(word));
+            validator.validate_and_fix(entry, word).unwrap();
+        }
+    }
+}
@@ -1,23 +1,349 @@
+use crate::contract::ContractProfiles;
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct InferParams {
     pub max_tokens: i32,
     pub temp: f32,
     pub top_p: f32,
     pub min_p: f32,
     pub repeat_penalty: f32,
+    /// Recent tokens the penalties look back over; 0 disables them, -1 means
+    /// the whole context
+    pub repeat_last_n: i32,
+    /// Penalty growing with how often a token already appeared
+    pub frequency_penalty: f32,
+    /// Flat penalty on any token that already appeared
+    pub presence_penalty: f32,
+    /// DRY ("don't repeat yourself") strength against extending a sequence
+    /// that already occurred; 0 disables it. Llama backend only
+    pub dry_multiplier: f32,
+    pub dry_base: f32,
+    /// Repeated sequences up to this many tokens go unpenalized by DRY
+    pub dry_allowed_length: i32,
+    /// Chance per token that XTC drops the most likely choices above
+    /// `xtc_threshold`; 0 disables it. Llama backend only
+    pub xtc_probability: f32,
+    pub xtc_threshold: f32,
+    /// Constrain the output to JSON syntax where the task has no grammar of
+    /// its own. Llama backend only
+    pub json_grammar: bool,
+}
+
+impl InferParams {
+    /// Sampling for a retry after generation fell into a loop: stronger
+    /// repetition penalties over a longer window, and DRY switched on.
+    pub fn against_repetition(&self) -> Self {
+        Self {
+            repeat_penalty: self.repeat_penalty.max(1.0) + 0.1,
+            repeat_last_n: match self.repeat_last_n {
+                -1 => -1,
+                n => n.max(256),
+            },
+            presence_penalty: self.presence_penalty + 0.3,
+            dry_multiplier: self.dry_multiplier.max(0.8),
+            ..self.clone()
+        }
+    }
+}
+
+/// The server's default sampling.
+impl Default for InferParams {
+    fn default() -> Self {
+        Self {
+            max_tokens: 1024,
+            temp: 0.4,
+            top_p: 0.9,
+            min_p: 0.05,
+            repeat_penalty: 1.1,
+            repeat_last_n: 64,
+            frequency_penalty: 0.0,
+            presence_penalty: 0.0,
+            dry_multiplier: 0.0,
+            dry_base: 1.75,
+            dry_allowed_length: 2,
+            xtc_probability: 0.0,
+            xtc_threshold: 0.1,
+            json_grammar: false,
+        }
+    }
+}
+
+/// Rungs of progressively more conservative sampling, climbed one per retry
+/// of output that failed to parse or validate: sampling the same way again
+/// tends to fail the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sampling {
+    /// The server's (or profile's) own parameters
+    Configured,
+    /// Half the temperature, at most 0.2
+    Cooler,
+    /// Always the most likely token
+    Greedy,
+    /// Greedy, with the output held to JSON syntax by a grammar
+    Constrained,
+}
+
+impl Sampling {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Configured => "configured",
+            Self::Cooler => "cooler",
+            Self::Greedy => "greedy",
+            Self::Constrained => "constrained",
+        }
+    }
+
+    /// The rung above this one, `None` at the top.
+    pub fn next(self) -> Option<Self> {
+        match self {
+            Self::Configured => Some(Self::Cooler),
+            Self::Cooler => Some(Self::Greedy),
+            Self::Greedy => Some(Self::Constrained),
+            Self::Constrained => None,
+        }
+    }
+
+    /// `params` sampled the way this rung does; token budget and penalties
+    /// are kept.
+    pub fn apply(self, params: &InferParams) -> InferParams {
+        let greedy = InferParams {
+            temp: 0.0,
+            top_p: 1.0,
+            min_p: 0.0,
+            xtc_probability: 0.0,
+            ..params.clone()
+        };
+        match self {
+            Self::Configured => params.clone(),
+            Self::Cooler => InferParams {
+                temp: (params.temp * 0.5).min(0.2),
+                ..params.clone()
+            },
+            Self::Greedy => greedy,
+            Self::Constrained => InferParams {
+                json_grammar: true,
+                ..greedy
+            },
+        }
+    }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct PromptParts {
     pub system: String,
     pub user_word: String,
+    /// Worked examples shown before the word, most relevant first.
+    pub examples: Vec<FewShot>,
+    /// A sentence the word was met in, so the sense used there is listed first.
+    pub context: Option<String>,
+    /// Languages and categories an entry may use; the built-in English
+    /// profile by default.
+    pub contract: ContractProfiles,
+    pub task: PromptTask,
+}
+
+/// How finely a word's uses are split into senses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    /// Closely related uses merged into a few broad senses, as on a flashcard
+    Coarse,
+    /// Distinct uses kept apart even when closely related, as in a reference dictionary
+    Fine,
+}
+
+/// What the model is asked to produce for the word.
+#[derive(Clone, Debug, Default)]
+pub enum PromptTask {
+    /// A complete word entry.
+    #[default]
+    Entry,
+    /// An entry holding only the sense with this part of speech, or
+    /// `{"senseNotFound": true}` when the word has none.
+    Sense { part_of_speech: String },
+    /// A complete entry with at most `max_meanings` meanings, its uses split
+    /// into senses at `granularity`.
+    Senses {
+        max_meanings: usize,
+        granularity: Granularity,
+    },
+    /// The word's derivational family as `{"word", "members": [{"word", "partOfSpeech"}]}`.
+    Family,
+    /// Syllabified IPA, stress position and minimal pairs for practice, as in
+    /// `schema/pronunciation.schema.json`.
+    Pronunciation,
+    /// `count` more example sentences for an entry's senses (part of speech
+    /// and definition) that differ from its `existing` ones, as
+    /// `{"examples": [{"partOfSpeech", "sentence"}]}`.
+    Examples {
+        count: usize,
+        base_form: String,
+        senses: Vec<(String, String)>,
+        existing: Vec<String>,
+    },
+    /// Only the listed translations of one sense, as a flat JSON object keyed
+    /// by language code. Used to repair entries that are otherwise valid.
+    Translations {
+        part_of_speech: String,
+        definition: String,
+        languages: Vec<String>,
+    },
+    /// English renderings of translations of the word, given as `(language
+    /// code, translation)` without the word itself, as `{"<code>": [...]}`.
+    /// Used to spot translations that drifted from the sense.
+    BackTranslation { items: Vec<(String, String)> },
+    /// A new value for one field of an existing entry, returned as
+    /// `{"value": ...}`. `path` is the caller-facing field path.
+    Field {
+        path: String,
+        entry: serde_json::Value,
+    },
+    /// An operator-supplied prompt sent verbatim, with the output returned as
+    /// generated. For debugging prompts against the loaded model.
+    Raw { prompt: String },
+}
+
+impl PromptTask {
+    /// Short name used in logs and recordings.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Entry => "entry",
+            Self::Sense { .. } => "sense",
+            Self::Senses { .. } => "senses",
+            Self::Family => "family",
+            Self::Pronunciation => "pronunciation",
+            Self::Examples { .. } => "examples",
+            Self::Translations { .. } => "translations",
+            Self::BackTranslation { .. } => "back_translation",
+            Self::Field { .. } => "field",
+            Self::Raw { .. } => "raw",
+        }
+    }
+
+    /// Whether backends should hand back the output untouched instead of
+    /// extracting the JSON object from it.
+    pub fn wants_raw_output(&self) -> bool {
+        matches!(self, Self::Raw { .. })
+    }
 }
 
+/// A known-good entry used as a few-shot example.
+#[derive(Clone, Debug)]
+pub struct FewShot {
+    pub word: String,
+    pub entry: serde_json::Value,
+}
+
+/// One token of the model's vocabulary as it occurs in some text.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Token {
+    pub id: i32,
+    /// The token's bytes as text; partial UTF-8 sequences are shown lossily
+    pub text: String,
+}
+
+/// Backend failures that callers handle specifically; anything else is a
+/// plain `anyhow::Error`.
+#[derive(Debug, thiserror::Error)]
+pub enum BackendError {
+    /// Every inference slot stayed busy for the configured maximum queue wait.
+    #[error("no inference slot became free within {} ms", .0.as_millis())]
+    QueueTimeout(Duration),
+    /// The backend panicked mid-inference; its state may be inconsistent.
+    #[error("inference panicked: {0}")]
+    Panicked(String),
+    /// The instance runs without a model and only serves stored entries.
+    #[error("this instance has no model")]
+    NoModel,
+    /// The upstream server of an edge instance answered with an error.
+    #[error("upstream answered {code}: {message}")]
+    Upstream {
+        code: crate::error::ErrorCode,
+        message: String,
+    },
+}
+
+/// Memory and slot accounting of a backend running models in-process.
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendStats {
+    /// Model weights loaded, on CPU and GPU together
+    pub model_bytes: u64,
+    /// False while the model is unloaded for being idle
+    pub model_loaded: bool,
+    /// Inferences that may run at once
+    pub slots: usize,
+    pub slots_in_use: usize,
+    /// One per inference in progress
+    pub contexts: Vec<ContextStats>,
+}
+
+/// One live inference context and how much of its KV cache is filled.
+#[derive(Debug, Clone, Serialize)]
+pub struct ContextStats {
+    pub n_ctx: u32,
+    /// Positions filled so far: prompt plus generated tokens
+    pub tokens: usize,
+    /// KV cache bytes the filled positions hold; `None` when the model's
+    /// metadata doesn't say how large an entry is
+    pub kv_bytes_used: Option<u64>,
+    /// KV cache bytes allocated for all `n_ctx` positions
+    pub kv_bytes_reserved: Option<u64>,
+}
+
+/// Generation stopped early because the output kept repeating itself; worth
+/// retrying at once with [`InferParams::against_repetition`].
+#[derive(Debug, thiserror::Error)]
+#[error("generation fell into a loop: {0}")]
+pub struct Degenerate(pub String);
+
+/// Generation stopped early because the output had already left the JSON
+/// contract; unlike [`BackendError`] this is worth retrying at once.
+#[derive(Debug, thiserror::Error)]
+#[error("model went off contract: {0}")]
+pub struct OffContract(pub String);
+
 #[async_trait::async_trait]
 pub trait LlmBackend: Send + Sync + 'static {
     async fn infer_json(&self, prompt: PromptParts, params: &InferParams) -> Result<Vec<u8>>;
+
+    /// Identifier of the loaded model, recorded alongside cached entries.
+    fn model_name(&self) -> String {
+        "unknown".to_string()
+    }
+
+    /// Split `text` into the model's own tokens, when the backend has a local
+    /// tokenizer. No BOS token is added.
+    fn tokenize(&self, _text: &str) -> Option<Result<Vec<Token>>> {
+        None
+    }
+
+    /// Tokens `text` occupies in the model's own vocabulary, when the backend
+    /// has a local tokenizer.
+    fn count_tokens(&self, text: &str) -> Option<usize> {
+        self.tokenize(text)?.ok().map(|tokens| tokens.len())
+    }
+
+    /// Memory and slots in use, for backends that hold the model themselves.
+    fn stats(&self) -> Option<BackendStats> {
+        None
+    }
 }
 
+pub mod device;
+pub mod gguf;
+#[cfg(feature = "llama")]
 pub mod llama;
+pub mod mock;
+pub mod ollama;
+pub mod openai;
+pub mod preset;
+pub mod prompt;
+pub mod readonly;
+pub mod repetition;
+pub mod trace;
+pub mod upstream;
+pub mod watch;
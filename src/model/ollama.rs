@@ -0,0 +1,83 @@
+use super::{prompt, InferParams, LlmBackend, PromptParts};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+pub const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+
+/// Backend for a local or remote Ollama server, using its native chat API
+/// with JSON mode so the sampler only emits well-formed JSON.
+#[derive(Clone)]
+pub struct OllamaBackend {
+    client: reqwest::Client,
+    base_url: String,
+    model: String,
+}
+
+impl OllamaBackend {
+    pub fn new(base_url: &str, model: String, timeout: Duration) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .context("build HTTP client")?;
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmBackend for OllamaBackend {
+    async fn infer_json(&self, prompt: PromptParts, p: &InferParams) -> Result<Vec<u8>> {
+        let body = json!({
+            "model": self.model,
+            "messages": [{ "role": "user", "content": prompt::render(&prompt) }],
+            "stream": false,
+            "format": "json",
+            "options": {
+                "temperature": p.temp,
+                "top_p": p.top_p,
+                "min_p": p.min_p,
+                "repeat_penalty": p.repeat_penalty,
+                "repeat_last_n": p.repeat_last_n,
+                "frequency_penalty": p.frequency_penalty,
+                "presence_penalty": p.presence_penalty,
+                "num_predict": p.max_tokens,
+            },
+        });
+
+        let res = self
+            .client
+            .post(format!("{}/api/chat", self.base_url))
+            .json(&body)
+            .send()
+            .await
+            .context("send ollama chat request")?;
+        let status = res.status();
+        if !status.is_success() {
+            let text = res.text().await.unwrap_or_default();
+            bail!(
+                "ollama returned {}: {}",
+                status,
+                text.chars().take(500).collect::<String>()
+            );
+        }
+
+        let reply: Value = res.json().await.context("decode ollama response")?;
+        let content = reply
+            .pointer("/message/content")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("ollama response has no message content"))?;
+        if prompt.task.wants_raw_output() {
+            return Ok(content.as_bytes().to_vec());
+        }
+        Ok(prompt::extract_json_bytes(content).unwrap_or_else(|| content.as_bytes().to_vec()))
+    }
+
+    fn model_name(&self) -> String {
+        self.model.clone()
+    }
+}
@@ -0,0 +1,91 @@
+use super::{prompt, InferParams, LlmBackend, PromptParts};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+pub const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+
+/// Backend for any server speaking the OpenAI chat completions API
+/// (OpenAI itself, vLLM, llama-server, LM Studio, ...).
+///
+/// `min_p`, `repeat_penalty` and `repeat_last_n` have no equivalent in that API
+/// and are not sent.
+#[derive(Clone)]
+pub struct OpenAiBackend {
+    client: reqwest::Client,
+    base_url: String,
+    model: String,
+    api_key: Option<String>,
+}
+
+impl OpenAiBackend {
+    pub fn new(
+        base_url: &str,
+        model: String,
+        api_key: Option<String>,
+        timeout: Duration,
+    ) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .context("build HTTP client")?;
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model,
+            api_key,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmBackend for OpenAiBackend {
+    async fn infer_json(&self, prompt: PromptParts, p: &InferParams) -> Result<Vec<u8>> {
+        let body = json!({
+            "model": self.model,
+            "messages": [{ "role": "user", "content": prompt::render(&prompt) }],
+            "temperature": p.temp,
+            "top_p": p.top_p,
+            "frequency_penalty": p.frequency_penalty,
+            "presence_penalty": p.presence_penalty,
+            "max_tokens": p.max_tokens,
+            "response_format": { "type": "json_object" },
+        });
+
+        let mut req = self
+            .client
+            .post(format!("{}/chat/completions", self.base_url))
+            .json(&body);
+        if let Some(key) = &self.api_key {
+            req = req.bearer_auth(key);
+        }
+        let res = req.send().await.context("send chat completion request")?;
+        let status = res.status();
+        if !status.is_success() {
+            let text = res.text().await.unwrap_or_default();
+            bail!(
+                "chat completion returned {}: {}",
+                status,
+                text.chars().take(500).collect::<String>()
+            );
+        }
+
+        let reply: Value = res
+            .json()
+            .await
+            .context("decode chat completion response")?;
+        let content = reply
+            .pointer("/choices/0/message/content")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("chat completion response has no message content"))?;
+        if prompt.task.wants_raw_output() {
+            return Ok(content.as_bytes().to_vec());
+        }
+        Ok(prompt::extract_json_bytes(content).unwrap_or_else(|| content.as_bytes().to_vec()))
+    }
+
+    fn model_name(&self) -> String {
+        self.model.clone()
+    }
+}
@@ -0,0 +1,183 @@
+use super::gguf::GgufInfo;
+use super::InferParams;
+use clap::ValueEnum;
+
+/// Model families with a tuned preset, told apart by GGUF metadata.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelFamily {
+    /// Meta Llama 3 and 3.x
+    Llama3,
+    /// Qwen 2 and later, ChatML
+    Qwen,
+    /// Microsoft Phi-3 and later
+    Phi,
+    /// Google Gemma
+    Gemma,
+}
+
+impl ModelFamily {
+    /// The family of a model from its `general.architecture` and
+    /// `general.name`. Llama 2, Mistral and other fine-tunes share the
+    /// `llama` architecture, so Llama 3 is also recognized by name.
+    pub fn detect(architecture: &str, name: Option<&str>) -> Option<Self> {
+        let arch = architecture.to_ascii_lowercase();
+        if arch.starts_with("qwen") {
+            return Some(Self::Qwen);
+        }
+        if arch.starts_with("phi") {
+            return Some(Self::Phi);
+        }
+        if arch.starts_with("gemma") {
+            return Some(Self::Gemma);
+        }
+        let name: String = name?
+            .chars()
+            .filter(|c| c.is_alphanumeric() || *c == '.')
+            .flat_map(char::to_lowercase)
+            .collect();
+        // "llama30b" is the original LLaMA, not Llama 3
+        let llama3 = name
+            .match_indices("llama3")
+            .any(|(at, m)| !name[at + m.len()..].starts_with(|c: char| c.is_ascii_digit()));
+        (arch == "llama" && llama3).then_some(Self::Llama3)
+    }
+
+    /// The family of a model file, when its metadata names a known one.
+    pub fn of(info: &GgufInfo) -> Option<Self> {
+        Self::detect(info.architecture.as_deref()?, info.name.as_deref())
+    }
+
+    pub fn preset(self) -> &'static Preset {
+        match self {
+            Self::Llama3 => &LLAMA3,
+            Self::Qwen => &QWEN,
+            Self::Phi => &PHI,
+            Self::Gemma => &GEMMA,
+        }
+    }
+}
+
+/// Settings that work well for one model family: the chat turn the prompt is
+/// wrapped in, sampling, and strings that end the answer when the model does
+/// not stop on its own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preset {
+    pub family: ModelFamily,
+    /// Opens the user turn; the whole prompt is sent as one user message
+    pub turn_prefix: &'static str,
+    /// Closes the user turn and opens the model's
+    pub turn_suffix: &'static str,
+    pub temp: f32,
+    pub top_p: f32,
+    pub min_p: f32,
+    pub repeat_penalty: f32,
+    pub stop: &'static [&'static str],
+}
+
+impl Preset {
+    /// `prompt` as the user turn of the family's chat template.
+    pub fn wrap(&self, prompt: &str) -> String {
+        format!("{}{}{}", self.turn_prefix, prompt, self.turn_suffix)
+    }
+
+    /// `base` with the preset's sampling in place of the server defaults.
+    pub fn params(&self, base: &InferParams) -> InferParams {
+        InferParams {
+            temp: self.temp,
+            top_p: self.top_p,
+            min_p: self.min_p,
+            repeat_penalty: self.repeat_penalty,
+            ..base.clone()
+        }
+    }
+
+    /// Where the first stop string starts in `output`, if one has appeared.
+    pub fn stop_at(&self, output: &str) -> Option<usize> {
+        self.stop.iter().filter_map(|s| output.find(s)).min()
+    }
+}
+
+static LLAMA3: Preset = Preset {
+    family: ModelFamily::Llama3,
+    turn_prefix: "<|start_header_id|>user<|end_header_id|>\n\n",
+    turn_suffix: "<|eot_id|><|start_header_id|>assistant<|end_header_id|>\n\n",
+    temp: 0.3,
+    top_p: 0.9,
+    min_p: 0.05,
+    repeat_penalty: 1.05,
+    stop: &["<|eot_id|>", "<|start_header_id|>"],
+};
+
+static QWEN: Preset = Preset {
+    family: ModelFamily::Qwen,
+    turn_prefix: "<|im_start|>user\n",
+    turn_suffix: "<|im_end|>\n<|im_start|>assistant\n",
+    temp: 0.3,
+    top_p: 0.8,
+    min_p: 0.05,
+    repeat_penalty: 1.05,
+    stop: &["<|im_end|>", "<|im_start|>", "<|endoftext|>"],
+};
+
+static PHI: Preset = Preset {
+    family: ModelFamily::Phi,
+    turn_prefix: "<|user|>\n",
+    turn_suffix: "<|end|>\n<|assistant|>\n",
+    temp: 0.2,
+    top_p: 0.9,
+    min_p: 0.05,
+    repeat_penalty: 1.0,
+    stop: &["<|end|>", "<|user|>", "<|endoftext|>"],
+};
+
+// Gemma has no system role; the instructions ride in the user turn anyway
+static GEMMA: Preset = Preset {
+    family: ModelFamily::Gemma,
+    turn_prefix: "<start_of_turn>user\n",
+    turn_suffix: "<end_of_turn>\n<start_of_turn>model\n",
+    temp: 0.3,
+    top_p: 0.95,
+    min_p: 0.05,
+    repeat_penalty: 1.0,
+    stop: &["<end_of_turn>", "<start_of_turn>"],
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_families_and_applies_presets() {
+        let detect = ModelFamily::detect;
+        assert_eq!(
+            detect("qwen2", Some("Qwen2.5 7B Instruct")),
+            Some(ModelFamily::Qwen)
+        );
+        assert_eq!(detect("phi3", None), Some(ModelFamily::Phi));
+        assert_eq!(
+            detect("gemma2", Some("gemma-2-9b-it")),
+            Some(ModelFamily::Gemma)
+        );
+        assert_eq!(
+            detect("llama", Some("Meta-Llama-3.1-8B-Instruct")),
+            Some(ModelFamily::Llama3)
+        );
+        assert_eq!(
+            detect("llama", Some("Llama 3.2 3B")),
+            Some(ModelFamily::Llama3)
+        );
+        assert_eq!(detect("llama", Some("Mistral-7B-Instruct-v0.3")), None);
+        assert_eq!(detect("llama", None), None);
+        assert_eq!(detect("llama", Some("LLaMA-30B")), None);
+
+        let qwen = ModelFamily::Qwen.preset();
+        assert_eq!(
+            qwen.wrap("Word: run"),
+            "<|im_start|>user\nWord: run<|im_end|>\n<|im_start|>assistant\n"
+        );
+        assert_eq!(qwen.stop_at("{\"a\": 1}<|im_end|>\n<|im_start|>"), Some(8));
+        assert_eq!(qwen.stop_at("{\"a\": 1}"), None);
+        let params = qwen.params(&InferParams::default());
+        assert_eq!((params.top_p, params.max_tokens), (0.8, 1024));
+    }
+}
@@ -0,0 +1,690 @@
+use super::{Granularity, PromptParts, PromptTask};
+use crate::contract::{ContractProfile, ContractProfiles, DEFAULT_LANGUAGE};
+use anyhow::{bail, Result};
+
+/// System prompt used unless the operator configures another.
+pub const DEFAULT_SYSTEM: &str =
+    "You are an expert linguist and lexicographer. Produce a single valid JSON object only.";
+
+const OUTPUT_CONTRACT: &str = "## OUTPUT CONTRACT — ABSOLUTE RULES\n\n1) Output must be a single JSON object only. No explanations, no code fences, no comments, no trailing commas, no nulls, no placeholders like \"<...>\", no markdown.\n2) All required fields must be present and non-empty strings or arrays (arrays may be empty but must exist).\n3) Use straight quotes (\") only. Escape any internal quotes per JSON.\n4) Use UTF-8. IPA must be valid IPA characters.\n\n";
+
+const QUALITY_CHECKS: &str = "## QUALITY & CONSISTENCY CHECKS (perform before finalizing):\n\n- Valid JSON when parsed strictly.\n- \"meanings\" present with 1-4 items, all \"partOfSpeech\" values unique, ordered by \"senseRank\" starting at 1.\n- No hallucinated morphology (e.g., correct lemma and typical inflections).\n- No repetitive or circular definitions.\n- Translations match each individual sense, not copied across blindly.\n- Arrays contain unique, lower-case items unless proper-case is standard.\n- No extra keys beyond the schema.\n\n";
+
+/// The opening line, naming what kind of word is described.
+fn role(contract: &ContractProfiles) -> String {
+    let subject = match contract.default_profile() {
+        Some(profile) if profile.language == DEFAULT_LANGUAGE => "an English word".to_string(),
+        Some(profile) => format!("a {} word", profile.language),
+        None => format!(
+            "a word in one of these languages: {}",
+            contract.languages().collect::<Vec<_>>().join(", ")
+        ),
+    };
+    format!("You are an expert linguist and lexicographer. Your only job is to produce a single valid JSON object describing {}.\n\n", subject)
+}
+
+/// What each field must hold, with the languages, difficulties and parts of
+/// speech of the configured contract profiles. With several languages the
+/// categories are listed per language, as the validator checks them.
+fn content_requirements(contract: &ContractProfiles) -> String {
+    let quoted = |items: &[String], sep: &str| {
+        items
+            .iter()
+            .map(|item| format!("\"{}\"", item))
+            .collect::<Vec<_>>()
+            .join(sep)
+    };
+    let (difficulty, language, parts_of_speech) = match contract.default_profile() {
+        Some(profile) => (
+            format!("one of {}", quoted(&profile.difficulties, ", ")),
+            format!("always \"{}\"", profile.language),
+            format!("one of [{}]", quoted(&profile.parts_of_speech, ",")),
+        ),
+        None => {
+            let per_language = |list: fn(&ContractProfile) -> &[String]| {
+                contract
+                    .profiles()
+                    .map(|p| format!("{} [{}]", p.language, quoted(list(p), ",")))
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            };
+            (
+                format!(
+                    "for the entry's language, one of {}",
+                    per_language(|p| &p.difficulties)
+                ),
+                format!(
+                    "the language the word belongs to, one of {}",
+                    contract
+                        .languages()
+                        .map(|l| format!("\"{}\"", l))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                format!(
+                    "for the entry's language, one of {}",
+                    per_language(|p| &p.parts_of_speech)
+                ),
+            )
+        }
+    };
+    format!("## CONTENT REQUIREMENTS\n\n- \"word\": the surface/inflected form exactly as given by the user (case-preserve).\n- \"baseForm\": the lemma/root form in lowercase.\n- \"phonetic\": the IPA transcription in slashes, e.g., \"/kəˈmjuːnɪkeɪt/\". Use a standard, contemporary pronunciation (General American or widely accepted international), not a regional outlier.\n- \"difficulty\": {difficulty} based on typical frequency and morphology; choose conservatively.\n- \"language\": {language}.\n- \"meanings\": an array of 1-4 sense objects ordered from the most to the least common sense. Each sense MUST have a unique \"partOfSpeech\" value across the array.\n  • \"senseRank\": integer frequency rank of this sense, 1 for the most common, matching its position in the array.\n  • \"definition\": 30-80 words, clear, neutral, and sense-specific; do not repeat the headword mechanically.\n  • \"partOfSpeech\": {parts_of_speech}.\n  • \"exampleSentence\": natural, contemporary usage; keep under 25 words; do not quote famous works.\n  • \"grammarTip\": short usage guidance (morphology, typical complements, common errors, or register).\n  • \"synonyms\": 2-8 near-synonyms as single tokens or short phrases; none may duplicate the headword; keep sense-appropriate.\n  • \"antonyms\": 0-6 reasonable opposites; empty array allowed if none fit.\n  • \"translations\": object with keys [\"es\",\"fr\",\"de\",\"zh\",\"ja\",\"it\",\"pt\",\"ru\",\"ar\"]; each value a common single-word or brief phrase capturing THIS sense.\n\n")
+}
+
+/// One named piece of the prompt. Required sections are always sent; optional
+/// ones are dropped lowest `keep_priority` first when the prompt would not fit.
+#[derive(Debug, Clone)]
+pub struct Section {
+    pub name: &'static str,
+    pub text: String,
+    pub keep_priority: Option<u8>,
+}
+
+impl Section {
+    fn required(name: &'static str, text: String) -> Self {
+        Self {
+            name,
+            text,
+            keep_priority: None,
+        }
+    }
+
+    fn optional(name: &'static str, text: String, keep_priority: u8) -> Self {
+        Self {
+            name,
+            text,
+            keep_priority: Some(keep_priority),
+        }
+    }
+}
+
+/// The word-contract prompt split into sections, in the order they are sent.
+/// Every backend sends the same instructions so entries look alike whichever
+/// model produced them.
+pub fn sections(prompt: &PromptParts) -> Vec<Section> {
+    if let PromptTask::Translations {
+        part_of_speech,
+        definition,
+        languages,
+    } = &prompt.task
+    {
+        return translation_sections(prompt, part_of_speech, definition, languages);
+    }
+    if let PromptTask::BackTranslation { items } = &prompt.task {
+        return back_translation_sections(prompt, items);
+    }
+    if let PromptTask::Field { path, entry } = &prompt.task {
+        return field_sections(prompt, path, entry);
+    }
+    if let PromptTask::Family = &prompt.task {
+        return family_sections(prompt);
+    }
+    if let PromptTask::Pronunciation = &prompt.task {
+        return pronunciation_sections(prompt);
+    }
+    if let PromptTask::Examples {
+        count,
+        senses,
+        existing,
+        ..
+    } = &prompt.task
+    {
+        return examples_sections(prompt, *count, senses, existing);
+    }
+    if let PromptTask::Raw { prompt } = &prompt.task {
+        return vec![Section::required("raw", prompt.clone())];
+    }
+    let mut sections = vec![
+        Section::required("system", format!("{}\n\n", prompt.system)),
+        Section::required("role", role(&prompt.contract)),
+        Section::required("output_contract", OUTPUT_CONTRACT.to_string()),
+        Section::required(
+            "content_requirements",
+            content_requirements(&prompt.contract),
+        ),
+        Section::optional("quality_checks", QUALITY_CHECKS.to_string(), 1),
+    ];
+    // Examples are the first thing to go when space is short
+    sections.extend(prompt.examples.iter().map(|ex| {
+        Section::optional(
+            "example",
+            format!("## EXAMPLE\n\nWord: {}\n{}\n\n", ex.word, ex.entry),
+            0,
+        )
+    }));
+    if let PromptTask::Sense { part_of_speech } = &prompt.task {
+        sections.push(Section::required(
+            "sense",
+            format!(
+                "## REQUESTED SENSE\n\nDescribe only the word's {pos} sense: \"meanings\" must hold exactly one object, with \"partOfSpeech\": \"{pos}\" and \"senseRank\": 1. If the word has no {pos} sense, output {{\"senseNotFound\": true}} and nothing else.\n\n",
+                pos = part_of_speech
+            ),
+        ));
+    }
+    if let PromptTask::Senses {
+        max_meanings,
+        granularity,
+    } = &prompt.task
+    {
+        let split = match granularity {
+            Granularity::Coarse => "Merge closely related uses into one broad sense each, so a learner sees only the clearly different meanings.",
+            Granularity::Fine => "Give each distinct use its own sense, even closely related ones, as a reference dictionary would.",
+        };
+        sections.push(Section::required(
+            "senses",
+            format!(
+                "## REQUESTED SENSES\n\n{} \"meanings\" must hold at most {} object(s), the most common sense first, with senseRank counting up from 1.\n\n",
+                split, max_meanings
+            ),
+        ));
+    }
+    if let Some(context) = &prompt.context {
+        sections.push(Section::required(
+            "context",
+            format!(
+                "## CONTEXT\n\nThe user met the word in this sentence: \"{}\"\nList the sense used there first in \"meanings\" (senseRank 1), then the other common senses.\n\n",
+                context
+            ),
+        ));
+    }
+    sections.push(Section::required(
+        "word",
+        format!(
+            "Word: {}\nRespond with the JSON object only.",
+            prompt.user_word
+        ),
+    ));
+    sections
+}
+
+fn translation_sections(
+    prompt: &PromptParts,
+    part_of_speech: &str,
+    definition: &str,
+    languages: &[String],
+) -> Vec<Section> {
+    let keys = languages
+        .iter()
+        .map(|l| format!("\"{}\"", l))
+        .collect::<Vec<_>>()
+        .join(",");
+    vec![
+        Section::required("system", format!("{}\n\n", prompt.system)),
+        Section::required(
+            "translations_contract",
+            format!(
+                "Translate one sense of an English word. Output a single JSON object with exactly the keys [{}] (ISO 639-1 codes); each value is a common single word or brief phrase capturing THIS sense only. No other keys, no explanations.\n\n",
+                keys
+            ),
+        ),
+        Section::required(
+            "word",
+            format!(
+                "Word: {}\nPart of speech: {}\nDefinition: {}\nRespond with the JSON object only.",
+                prompt.user_word, part_of_speech, definition
+            ),
+        ),
+    ]
+}
+
+fn family_sections(prompt: &PromptParts) -> Vec<Section> {
+    vec![
+        Section::required("system", format!("{}\n\n", prompt.system)),
+        Section::required(
+            "family_contract",
+            format!(
+                "List the derivational family of an English word: other words built on the same stem by derivation, e.g. \"decide\" → \"decision\", \"decisive\", \"decidedly\". Leave out inflections of the word itself (\"decides\", \"decided\") and words that only look alike. Output a single JSON object {{\"word\": <the word>, \"members\": [{{\"word\": <member>, \"partOfSpeech\": <tag>}}]}} with at most {} members, most common first; each tag is one of [{}]. No other keys, no explanations.\n\n",
+                crate::family::MAX_MEMBERS,
+                prompt
+                    .contract
+                    .parts_of_speech()
+                    .iter()
+                    .map(|p| format!("\"{}\"", p))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+        ),
+        Section::required(
+            "word",
+            format!(
+                "Word: {}\nRespond with the JSON object only.",
+                prompt.user_word
+            ),
+        ),
+    ]
+}
+
+fn pronunciation_sections(prompt: &PromptParts) -> Vec<Section> {
+    vec![
+        Section::required("system", format!("{}\n\n", prompt.system)),
+        Section::required(
+            "pronunciation_contract",
+            "Describe how an English word is pronounced, for pronunciation practice. Output a single JSON object with exactly these keys:\n- \"word\": the word as given.\n- \"ipa\": General American IPA in slashes with every syllable boundary marked: \"ˈ\" before the primary-stressed syllable, \"ˌ\" before a secondary-stressed one and \".\" between the others, e.g. \"/ˌɪn.fɚˈmeɪ.ʃən/\".\n- \"syllables\": the written syllables in order, which together spell the word, one per IPA syllable, e.g. [\"in\",\"for\",\"ma\",\"tion\"].\n- \"stress\": the 1-based position of the primary-stressed syllable.\n- \"minimalPairs\": 2-3 common words differing from this one in a single sound, each as {\"word\": <word>, \"ipa\": <IPA in slashes>}.\nNo other keys, no explanations.\n\n".to_string(),
+        ),
+        Section::required(
+            "word",
+            format!(
+                "Word: {}\nRespond with the JSON object only.",
+                prompt.user_word
+            ),
+        ),
+    ]
+}
+
+fn examples_sections(
+    prompt: &PromptParts,
+    count: usize,
+    senses: &[(String, String)],
+    existing: &[String],
+) -> Vec<Section> {
+    let senses = senses
+        .iter()
+        .map(|(pos, definition)| format!("- {}: {}", pos, definition))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let existing = existing
+        .iter()
+        .map(|sentence| format!("- {}", sentence))
+        .collect::<Vec<_>>()
+        .join("\n");
+    vec![
+        Section::required("system", format!("{}\n\n", prompt.system)),
+        Section::required(
+            "examples_contract",
+            format!(
+                "Write {} new example sentences for an English word, for a learner who already has the examples listed below. Each sentence uses the word naturally in one of its senses below, in contemporary everyday language, under 25 words, and differs from the existing examples and from the other new ones. Output a single JSON object {{\"examples\": [{{\"partOfSpeech\": <the sense's part of speech>, \"sentence\": <sentence>}}]}}. No other keys, no explanations.\n\nSenses:\n{}\n\nExisting examples:\n{}\n\n",
+                count, senses, existing
+            ),
+        ),
+        Section::required(
+            "word",
+            format!(
+                "Word: {}\nRespond with the JSON object only.",
+                prompt.user_word
+            ),
+        ),
+    ]
+}
+
+/// Leaves the word out so the answer reflects the translations alone.
+fn back_translation_sections(prompt: &PromptParts, items: &[(String, String)]) -> Vec<Section> {
+    let items = items
+        .iter()
+        .map(|(lang, text)| format!("- {}: {}", lang, text))
+        .collect::<Vec<_>>()
+        .join("\n");
+    vec![
+        Section::required("system", format!("{}\n\n", prompt.system)),
+        Section::required(
+            "back_translation_contract",
+            "Translate each word or phrase below into English, giving its 1-3 most likely English equivalents, most likely first. Output a single JSON object keyed by the language code shown before each item, e.g. {\"es\": [\"house\", \"home\"]}. No other keys, no explanations.\n\n".to_string(),
+        ),
+        Section::required(
+            "items",
+            format!("{}\nRespond with the JSON object only.", items),
+        ),
+    ]
+}
+
+fn field_sections(prompt: &PromptParts, path: &str, entry: &serde_json::Value) -> Vec<Section> {
+    vec![
+        Section::required("system", format!("{}\n\n", prompt.system)),
+        Section::required("role", role(&prompt.contract)),
+        Section::required("content_requirements", content_requirements(&prompt.contract)),
+        Section::required(
+            "field_contract",
+            format!(
+                "## TASK\n\nThe entry below is final except for one field. Write a better value for `{}` only, following the requirements above and consistent with the rest of the entry. Output a single JSON object {{\"value\": <new value>}} where the value has the same JSON type as the current one.\n\nCurrent entry:\n{}\n\n",
+                path, entry
+            ),
+        ),
+        Section::required(
+            "word",
+            format!(
+                "Word: {}\nField: {}\nRespond with the JSON object only.",
+                prompt.user_word, path
+            ),
+        ),
+    ]
+}
+
+/// GBNF grammar pinning the output to the shape the task expects, for
+/// backends that support constrained sampling. Only small, fixed-shape tasks
+/// get one; full entries rely on JSON extraction and validation instead.
+pub fn grammar(prompt: &PromptParts) -> Option<String> {
+    let PromptTask::Translations { languages, .. } = &prompt.task else {
+        return None;
+    };
+    let pairs = languages
+        .iter()
+        .map(|l| format!(r#""\"{}\"" ws ":" ws string ws"#, l))
+        .collect::<Vec<_>>()
+        .join(r#" "," ws "#);
+    Some(format!(
+        concat!(
+            r#"root ::= "{{" ws {} "}}""#,
+            "\n",
+            r#"string ::= "\"" ([^"\\\x00-\x1F] | "\\" ["\\/bfnrt])+ "\"""#,
+            "\n",
+            r#"ws ::= [ \t\n]*"#,
+            "\n",
+        ),
+        pairs
+    ))
+}
+
+/// GBNF grammar for any JSON object, for the last rung of the sampling ladder
+/// ([`crate::model::Sampling::Constrained`]). It holds the output to JSON
+/// syntax only; the entry's shape is still left to validation.
+pub const JSON_GRAMMAR: &str = r#"root ::= object
+value ::= object | array | string | number | ("true" | "false" | "null") ws
+object ::= "{" ws ( string ":" ws value ("," ws string ":" ws value)* )? "}" ws
+array ::= "[" ws ( value ("," ws value)* )? "]" ws
+string ::= "\"" ( [^"\\\x7F\x00-\x1F] | "\\" (["\\/bfnrt] | "u" [0-9a-fA-F]{4}) )* "\"" ws
+number ::= ("-"? ([0-9] | [1-9] [0-9]{0,15})) ("." [0-9]+)? ([eE] [-+]? [0-9]{1,15})? ws
+ws ::= | " " | "\n" [ \t]{0,20}
+"#;
+
+/// Render the full prompt with every section included.
+pub fn render(prompt: &PromptParts) -> String {
+    sections(prompt).iter().map(|s| s.text.as_str()).collect()
+}
+
+/// A prompt trimmed to fit a token budget.
+#[derive(Debug)]
+pub struct Budgeted {
+    pub text: String,
+    /// Sum of per-section counts; tokenizing the joined text may differ slightly
+    pub tokens: usize,
+    pub dropped: Vec<&'static str>,
+}
+
+/// Drop optional sections, least important first, until the prompt fits in
+/// `budget` tokens as measured by `count_tokens`. Fails only when the required
+/// sections alone exceed the budget.
+pub fn fit_to_budget(
+    sections: Vec<Section>,
+    budget: usize,
+    count_tokens: impl Fn(&str) -> usize,
+) -> Result<Budgeted> {
+    let counts: Vec<usize> = sections.iter().map(|s| count_tokens(&s.text)).collect();
+    let mut total: usize = counts.iter().sum();
+    let mut keep = vec![true; sections.len()];
+
+    let mut droppable: Vec<usize> = (0..sections.len())
+        .filter(|&i| sections[i].keep_priority.is_some())
+        .collect();
+    // Ties go from the back, so later (less relevant) examples are dropped first
+    droppable.sort_by_key(|&i| (sections[i].keep_priority, std::cmp::Reverse(i)));
+
+    let mut dropped = Vec::new();
+    for i in droppable {
+        if total <= budget {
+            break;
+        }
+        keep[i] = false;
+        total -= counts[i];
+        dropped.push(sections[i].name);
+    }
+    if total > budget {
+        bail!(
+            "prompt needs {} tokens even without optional sections, budget is {}",
+            total,
+            budget
+        );
+    }
+
+    let text = sections
+        .iter()
+        .zip(&keep)
+        .filter(|(_, keep)| **keep)
+        .map(|(s, _)| s.text.as_str())
+        .collect();
+    Ok(Budgeted {
+        text,
+        tokens: total,
+        dropped,
+    })
+}
+
+/// Whether `s` opens a JSON object that never closes: the signature of an
+/// answer cut off by the token budget. Braces inside strings don't count.
+pub fn is_truncated_json(s: &str) -> bool {
+    s.find('{')
+        .is_some_and(|start| object_end(s, start).is_none())
+}
+
+/// Pull the first JSON object out of free-form model output: prose, code
+/// fences and trailing chatter around it are dropped. Braces inside strings
+/// (a definition mentioning `{`) don't count, and a brace-delimited aside
+/// that isn't valid JSON is skipped in favor of the next object that is.
+pub fn extract_json_bytes(s: &str) -> Option<Vec<u8>> {
+    let mut from = 0;
+    while let Some(offset) = s[from..].find('{') {
+        let start = from + offset;
+        let candidate = object_end(s, start).map(|end| &s[start..=end]);
+        if let Some(candidate) =
+            candidate.filter(|c| serde_json::from_str::<serde::de::IgnoredAny>(c).is_ok())
+        {
+            return Some(candidate.as_bytes().to_vec());
+        }
+        from = start + 1;
+    }
+    None
+}
+
+/// Byte index of the `}` closing the object that opens at `start`, skipping
+/// over string contents and their escapes; `None` if it never closes.
+fn object_end(s: &str, start: usize) -> Option<usize> {
+    let mut depth = 0u32;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, b) in s.bytes().enumerate().skip(start) {
+        if in_string {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match b {
+            b'"' => in_string = true,
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts() -> PromptParts {
+        PromptParts {
+            system: "sys".to_string(),
+            user_word: "run".to_string(),
+            examples: Vec::new(),
+            context: None,
+            contract: Default::default(),
+            task: PromptTask::Entry,
+        }
+    }
+
+    // Roughly one token per word is enough to exercise the budgeter
+    fn words(s: &str) -> usize {
+        s.split_whitespace().count()
+    }
+
+    #[test]
+    fn keeps_everything_when_it_fits() {
+        let fitted = fit_to_budget(sections(&parts()), usize::MAX, words).unwrap();
+        assert_eq!(fitted.text, render(&parts()));
+        assert!(fitted.dropped.is_empty());
+    }
+
+    #[test]
+    fn drops_quality_checks_before_failing() {
+        let full = words(&render(&parts()));
+        let quality = words(QUALITY_CHECKS);
+        let fitted = fit_to_budget(sections(&parts()), full - quality, words).unwrap();
+        assert_eq!(fitted.dropped, vec!["quality_checks"]);
+        assert!(!fitted.text.contains("QUALITY"));
+        assert!(fitted.text.ends_with("Respond with the JSON object only."));
+
+        assert!(fit_to_budget(sections(&parts()), full - quality - 1, words).is_err());
+    }
+
+    #[test]
+    fn context_hint_precedes_the_word() {
+        let mut hinted = parts();
+        hinted.context = Some("He caught a bass off the pier.".to_string());
+        let text = render(&hinted);
+        let context = text.find("caught a bass").unwrap();
+        assert!(context < text.find("Word: run").unwrap());
+        assert!(!render(&parts()).contains("CONTEXT"));
+    }
+
+    #[test]
+    fn sense_task_names_the_part_of_speech() {
+        let mut sense = parts();
+        sense.task = PromptTask::Sense {
+            part_of_speech: "verb".to_string(),
+        };
+        let text = render(&sense);
+        assert!(text.contains("CONTENT REQUIREMENTS"));
+        assert!(text.contains(r#""partOfSpeech": "verb""#));
+        assert!(text.contains(r#"{"senseNotFound": true}"#));
+    }
+
+    #[test]
+    fn senses_task_caps_the_meanings() {
+        let mut senses = parts();
+        senses.task = PromptTask::Senses {
+            max_meanings: 2,
+            granularity: Granularity::Coarse,
+        };
+        let text = render(&senses);
+        assert!(text.contains("CONTENT REQUIREMENTS"));
+        assert!(text.contains("at most 2 object(s)"));
+        assert!(text.contains("Merge closely related uses"));
+        assert!(!render(&parts()).contains("REQUESTED SENSES"));
+    }
+
+    #[test]
+    fn categories_come_from_the_contract_profiles() {
+        let english = render(&parts());
+        assert!(english.contains("describing an English word"));
+        assert!(english.contains(r#""language": always "english"."#));
+
+        let mut japanese = parts();
+        japanese.contract = ContractProfiles::from_json(
+            r#"{ "japanese": { "parts_of_speech": ["noun", "particle"], "difficulties": ["n5", "n4"], "required_meaning_fields": [] } }"#,
+        )
+        .unwrap();
+        let text = render(&japanese);
+        assert!(text.contains("in one of these languages: english, japanese"));
+        assert!(text.contains(r#"one of "english", "japanese""#));
+        assert!(text.contains(r#"japanese ["n5","n4"]"#));
+        assert!(text.contains(r#"japanese ["noun","particle"]"#));
+        assert!(text.contains(r#"english ["beginner","intermediate","advanced"]"#));
+    }
+
+    #[test]
+    fn drops_later_examples_first() {
+        let mut with_examples = parts();
+        with_examples.examples = ["alpha", "beta"]
+            .iter()
+            .map(|w| crate::model::FewShot {
+                word: w.to_string(),
+                entry: serde_json::json!({ "word": w }),
+            })
+            .collect();
+        let full = words(&render(&with_examples));
+        let fitted = fit_to_budget(sections(&with_examples), full - 1, words).unwrap();
+        assert_eq!(fitted.dropped, vec!["example"]);
+        assert!(fitted.text.contains("Word: alpha"));
+        assert!(!fitted.text.contains("Word: beta"));
+        assert!(fitted.text.contains("QUALITY"));
+    }
+
+    #[test]
+    fn translations_task_is_focused_and_constrained() {
+        let mut focused = parts();
+        focused.task = PromptTask::Translations {
+            part_of_speech: "verb".to_string(),
+            definition: "To move quickly on foot.".to_string(),
+            languages: vec!["ja".to_string(), "ru".to_string()],
+        };
+        let text = render(&focused);
+        assert!(text.contains(r#"["ja","ru"]"#));
+        assert!(text.contains("Definition: To move quickly on foot."));
+        assert!(!text.contains("CONTENT REQUIREMENTS"));
+
+        let gbnf = grammar(&focused).unwrap();
+        assert!(gbnf.starts_with(r#"root ::= "{" ws "\"ja\"" ws ":" ws string ws "," ws "\"ru\"""#));
+        assert!(grammar(&parts()).is_none());
+    }
+
+    #[test]
+    fn detects_answers_cut_off_mid_object() {
+        assert!(is_truncated_json(
+            r#"{"word": "run", "meanings": [{"definition": "To mo"#
+        ));
+        assert!(is_truncated_json(r#"Sure: {"word": "a \"}\" b"#));
+        assert!(!is_truncated_json(
+            r#"{"word": "run", "note": "{ unbalanced"}"#
+        ));
+        assert!(!is_truncated_json(r#"{"word": "run"} and then {"#));
+        assert!(!is_truncated_json("I cannot answer that."));
+    }
+
+    #[test]
+    fn extracts_the_first_valid_object_from_adversarial_output() {
+        let extract = |s: &str| extract_json_bytes(s).map(|b| String::from_utf8(b).unwrap());
+
+        let braces_in_strings = r#"{"definition": "A brace } or { in text", "word": "x"}"#;
+        assert_eq!(
+            extract(braces_in_strings).as_deref(),
+            Some(braces_in_strings)
+        );
+        let escaped_quotes = r#"{"example": "She said \"}\" loudly\\", "n": 1}"#;
+        assert_eq!(extract(escaped_quotes).as_deref(), Some(escaped_quotes));
+
+        let fenced =
+            "Here you go:\n```json\n{\"word\": \"run\", \"tags\": [\"}\"]}\n```\nAnything else?";
+        assert_eq!(
+            extract(fenced).as_deref(),
+            Some(r#"{"word": "run", "tags": ["}"]}"#)
+        );
+        // The first object wins when the model repeats itself
+        assert_eq!(
+            extract(r#"{"word": "a"} {"word": "b"}"#).as_deref(),
+            Some(r#"{"word": "a"}"#)
+        );
+        // Prose braces, closed or not, are skipped
+        assert_eq!(
+            extract(r#"Fill in {word} then: {"word": "c"}"#).as_deref(),
+            Some(r#"{"word": "c"}"#)
+        );
+        assert_eq!(
+            extract(r#"An open { brace, then {"word": "d"}"#).as_deref(),
+            Some(r#"{"word": "d"}"#)
+        );
+        assert_eq!(
+            extract("héllo {\"mot\": \"été\"}").as_deref(),
+            Some("{\"mot\": \"été\"}")
+        );
+
+        assert_eq!(extract(r#"{"word": "cut off", "meanings": ["#), None);
+        assert_eq!(extract("no json here"), None);
+    }
+}
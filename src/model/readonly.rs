@@ -0,0 +1,20 @@
+use super::{BackendError, InferParams, LlmBackend, PromptParts};
+use anyhow::Result;
+
+/// Stands in for a model on instances started with `--no-model`, which serve
+/// cached and stored entries only. Every inference fails with
+/// [`BackendError::NoModel`], so a miss is answered `NOT_CACHED` instead of
+/// being generated.
+#[derive(Clone, Copy, Default)]
+pub struct ReadOnlyBackend;
+
+#[async_trait::async_trait]
+impl LlmBackend for ReadOnlyBackend {
+    async fn infer_json(&self, _prompt: PromptParts, _params: &InferParams) -> Result<Vec<u8>> {
+        Err(BackendError::NoModel.into())
+    }
+
+    fn model_name(&self) -> String {
+        "none".to_string()
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::VecDeque;
+
+/// Longest repeating unit looked for, in tokens.
+const MAX_PERIOD: usize = 32;
+/// Back-to-back copies of a unit that make a loop...
+const MIN_REPEATS: usize = 4;
+/// ...provided they cover at least this many tokens, so a short run such as
+/// a row of spaces or digits does not count.
+const MIN_SPAN: usize = 48;
+
+/// Spots a generation stuck in a loop: the same token sequence, of up to
+/// [`MAX_PERIOD`] tokens, emitted again and again. Left alone such output
+/// runs to the token budget and is garbage either way.
+pub struct LoopDetector {
+    /// The last `MAX_PERIOD` tokens
+    recent: VecDeque<i32>,
+    /// `runs[p]`: tokens in a row equal to the token `p` positions before
+    runs: [usize; MAX_PERIOD + 1],
+}
+
+impl Default for LoopDetector {
+    fn default() -> Self {
+        Self {
+            recent: VecDeque::with_capacity(MAX_PERIOD),
+            runs: [0; MAX_PERIOD + 1],
+        }
+    }
+}
+
+impl LoopDetector {
+    /// Take the next generated token; `Some` with a description once the
+    /// output has become a loop.
+    pub fn push(&mut self, token: i32) -> Option<String> {
+        for period in 1..=MAX_PERIOD {
+            let repeats =
+                self.recent.len() >= period && self.recent[self.recent.len() - period] == token;
+            self.runs[period] = if repeats { self.runs[period] + 1 } else { 0 };
+        }
+        if self.recent.len() == MAX_PERIOD {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(token);
+
+        // The shortest unit is the one that repeats; its multiples match too
+        (1..=MAX_PERIOD).find_map(|period| {
+            let span = self.runs[period] + period;
+            (span >= MIN_SPAN.max(MIN_REPEATS * period)).then(|| {
+                format!(
+                    "the same {}-token sequence repeated {} times",
+                    period,
+                    span / period
+                )
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn first_loop(tokens: impl IntoIterator<Item = i32>) -> Option<(usize, String)> {
+        let mut detector = LoopDetector::default();
+        tokens
+            .into_iter()
+            .enumerate()
+            .find_map(|(i, t)| detector.push(t).map(|reason| (i, reason)))
+    }
+
+    #[test]
+    fn flags_repeated_sequences_only() {
+        // A 5-token phrase over and over: caught once it covers 48 tokens
+        let phrase = [10, 11, 12, 13, 14];
+        let (at, reason) = first_loop(phrase.iter().copied().cycle().take(200)).unwrap();
+        assert_eq!(at, 47);
+        assert_eq!(reason, "the same 5-token sequence repeated 9 times");
+
+        // A single token needs the full span too
+        assert_eq!(first_loop([7; 47]), None);
+        assert_eq!(
+            first_loop([7; 48]).unwrap().1,
+            "the same 1-token sequence repeated 48 times"
+        );
+
+        // Long units need four copies
+        let unit: Vec<i32> = (100..130).collect();
+        let (at, _) = first_loop(unit.iter().copied().cycle().take(200)).unwrap();
+        assert_eq!(at, 4 * 30 - 1);
+
+        // Varied output with recurring structure is left alone
+        let varied = (0..500).map(|i| if i % 7 == 0 { 1 } else { i });
+        assert_eq!(first_loop(varied), None);
+    }
+}
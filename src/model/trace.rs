@@ -0,0 +1,142 @@
+use super::InferParams;
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+/// One generated token and how the sampler came to pick it.
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenStep {
+    pub id: i32,
+    /// Decoded text; empty for the end-of-generation token
+    pub text: String,
+    /// Milliseconds since the previous token (or since prompt evaluation
+    /// ended, for the first): one decode step plus sampling
+    pub ms: f64,
+    /// Probability the model's raw distribution gave the chosen token, before
+    /// temperature, top-p, min-p or the grammar reshaped it
+    pub p: f32,
+    /// 1 when the chosen token was the model's most likely one
+    pub rank: u32,
+}
+
+/// Token-by-token record of one inference, one JSON line in the trace file.
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenTrace {
+    /// Unix timestamp in milliseconds
+    pub at: u64,
+    pub word: String,
+    pub task: String,
+    pub model: String,
+    pub params: InferParams,
+    pub prompt_tokens: usize,
+    pub prompt_ms: f64,
+    pub total_ms: f64,
+    /// `eog` when the model ended the answer, `complete` when its JSON object
+    /// closed, `stop` on one of the preset's stop strings, `off_contract` when
+    /// it was stopped for leaving the contract, `max_tokens` when it was cut off
+    pub stopped: &'static str,
+    pub tokens: Vec<TokenStep>,
+}
+
+/// Appends token-level traces of a sampled fraction of inferences to a
+/// JSON Lines file, for finding where generation slows down or wanders.
+#[derive(Debug)]
+pub struct TokenTracer {
+    file: Mutex<File>,
+    sample: f64,
+    credit: Mutex<f64>,
+}
+
+impl TokenTracer {
+    /// Trace `sample` (0-1] of inferences into `path`, appending if it exists.
+    pub fn open(path: impl AsRef<Path>, sample: f64) -> Result<Self> {
+        let path = path.as_ref();
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("open token trace file {:?}", path))?;
+        info!(?path, sample, "tracing tokens of sampled inferences");
+        Ok(Self {
+            file: Mutex::new(file),
+            sample,
+            // Full credit up front, so the first inference is always traced
+            credit: Mutex::new(1.0 - sample),
+        })
+    }
+
+    /// Whether to trace the next inference. Sampling is evenly spaced rather
+    /// than random: 0.1 traces exactly every tenth.
+    pub fn should_trace(&self) -> bool {
+        let mut credit = self.credit.lock();
+        *credit += self.sample;
+        if *credit >= 1.0 {
+            *credit -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Append one trace; failures are logged, never passed to the request.
+    pub fn write(&self, trace: &TokenTrace) {
+        let mut line = match serde_json::to_vec(trace) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("Failed to serialize token trace: {}", e);
+                return;
+            }
+        };
+        line.push(b'\n');
+        if let Err(e) = self.file.lock().write_all(&line) {
+            warn!("Failed to write token trace: {}", e);
+        }
+    }
+}
+
+/// The softmax probability and rank of `token` among `logits`.
+pub fn choice(logits: &[f32], token: i32) -> (f32, u32) {
+    let Some(&chosen) = usize::try_from(token).ok().and_then(|i| logits.get(i)) else {
+        return (0.0, 0);
+    };
+    let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let total: f32 = logits.iter().map(|&l| (l - max).exp()).sum();
+    let rank = 1 + logits.iter().filter(|&&l| l > chosen).count() as u32;
+    ((chosen - max).exp() / total, rank)
+}
+
+pub fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_evenly_and_scores_choices() {
+        let path = std::env::temp_dir().join(format!("lingua-trace-{}.jsonl", std::process::id()));
+        let tracer = TokenTracer::open(&path, 0.25).unwrap();
+        let picked: Vec<bool> = (0..8).map(|_| tracer.should_trace()).collect();
+        assert_eq!(
+            picked,
+            [true, false, false, false, true, false, false, false]
+        );
+
+        let (p, rank) = choice(&[0.0, 0.0, 2.0_f32.ln()], 2);
+        assert!((p - 0.5).abs() < 1e-6);
+        assert_eq!(rank, 1);
+        assert_eq!(choice(&[1.0, 3.0, 2.0], 0).1, 3);
+        assert_eq!(choice(&[1.0], 5), (0.0, 0));
+
+        fs::remove_file(path).ok();
+    }
+}
@@ -0,0 +1,110 @@
+use super::{BackendError, InferParams, LlmBackend, PromptParts, PromptTask};
+use crate::api::ErrorResponse;
+use crate::error::ErrorCode;
+
+use anyhow::{bail, Context, Result};
+use serde_json::json;
+use std::time::Duration;
+
+/// Backend of an edge instance: what is not cached or stored locally is
+/// fetched from a central lingua-fast server, then validated, cached and
+/// stored here like any generated entry.
+///
+/// Only whole entries, single senses, word families and pronunciations are
+/// forwarded; sampling parameters are the upstream's own.
+#[derive(Clone)]
+pub struct UpstreamBackend {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+}
+
+impl UpstreamBackend {
+    pub fn new(base_url: &str, api_key: Option<String>, timeout: Duration) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .context("build HTTP client")?;
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmBackend for UpstreamBackend {
+    async fn infer_json(&self, prompt: PromptParts, _: &InferParams) -> Result<Vec<u8>> {
+        let word = &prompt.user_word;
+        let (path, body) = match &prompt.task {
+            PromptTask::Entry => (
+                "/v1/word",
+                json!({ "word": word, "context": prompt.context }),
+            ),
+            PromptTask::Sense { part_of_speech } => (
+                "/v1/word",
+                json!({ "word": word, "context": prompt.context, "pos": part_of_speech }),
+            ),
+            PromptTask::Senses {
+                max_meanings,
+                granularity,
+            } => (
+                "/v1/word",
+                json!({
+                    "word": word,
+                    "context": prompt.context,
+                    "max_meanings": max_meanings,
+                    "granularity": granularity,
+                }),
+            ),
+            PromptTask::Family => ("/v1/family", json!({ "word": word })),
+            PromptTask::Pronunciation => ("/v1/pronunciation", json!({ "word": word })),
+            task => {
+                return Err(BackendError::Upstream {
+                    code: ErrorCode::NotSupported,
+                    message: format!("{} requests are not forwarded upstream", task.name()),
+                }
+                .into())
+            }
+        };
+
+        let mut req = self
+            .client
+            .post(format!("{}{}", self.base_url, path))
+            .json(&body);
+        if let Some(key) = &self.api_key {
+            req = req.header("x-api-key", key);
+        }
+        let res = req.send().await.context("send upstream request")?;
+        let status = res.status();
+        let bytes = res.bytes().await.context("read upstream response")?;
+        if status.is_success() {
+            return Ok(bytes.to_vec());
+        }
+
+        // Verdicts about the word are final; anything else is the upstream failing
+        match serde_json::from_slice::<ErrorResponse>(&bytes) {
+            Ok(error) if error.code == ErrorCode::SenseNotFound => {
+                Ok(br#"{"senseNotFound": true}"#.to_vec())
+            }
+            Ok(error) => Err(BackendError::Upstream {
+                code: error.code,
+                message: error.error,
+            }
+            .into()),
+            Err(_) => bail!(
+                "upstream returned {}: {}",
+                status,
+                String::from_utf8_lossy(&bytes)
+                    .chars()
+                    .take(500)
+                    .collect::<String>()
+            ),
+        }
+    }
+
+    fn model_name(&self) -> String {
+        format!("upstream:{}", self.base_url)
+    }
+}
@@ -0,0 +1,287 @@
+use super::PromptTask;
+use crate::validate::meaning_fields;
+use std::collections::HashSet;
+
+/// Non-fence text tolerated before the opening brace, e.g. "Here is the entry:".
+const MAX_PRELUDE: usize = 48;
+
+/// What [`ContractWatch::feed`] concluded from the output so far.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    /// The JSON object closed; anything generated after it would be dropped.
+    Complete,
+    /// The output can no longer become an acceptable answer.
+    OffContract(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Container {
+    Object,
+    Array,
+}
+
+/// Follows model output as it is generated, character by character, to stop
+/// generation as soon as the answer is complete or has left the contract:
+/// prose or markdown instead of JSON, a misspelled meaning field (such as
+/// `part_of_speech`, which validation would strip and then miss), or a part
+/// of speech the validator would reject. Fields the schema lacks altogether
+/// are left alone; validation strips them.
+pub struct ContractWatch {
+    /// Field names and values are only checked for word entries
+    entry: bool,
+    started: bool,
+    prelude: String,
+    stack: Vec<Container>,
+    in_string: bool,
+    escaped: bool,
+    string: String,
+    /// Whether the string being read is an object key
+    reading_key: bool,
+    /// Key of the value being read, per open object
+    keys: Vec<Option<String>>,
+    /// Parts of speech the configured contract profiles allow
+    allowed: Vec<String>,
+    parts_of_speech: HashSet<String>,
+}
+
+impl ContractWatch {
+    /// A watch for `task`'s output, whose meanings may use `parts_of_speech`;
+    /// `None` for tasks with free-form output.
+    pub fn new(task: &PromptTask, parts_of_speech: &[&str]) -> Option<Self> {
+        if task.wants_raw_output() {
+            return None;
+        }
+        Some(Self {
+            entry: matches!(
+                task,
+                PromptTask::Entry | PromptTask::Sense { .. } | PromptTask::Senses { .. }
+            ),
+            started: false,
+            prelude: String::new(),
+            stack: Vec::new(),
+            in_string: false,
+            escaped: false,
+            string: String::new(),
+            reading_key: false,
+            keys: Vec::new(),
+            allowed: parts_of_speech.iter().map(|p| p.to_string()).collect(),
+            parts_of_speech: HashSet::new(),
+        })
+    }
+
+    /// Take the next piece of output; `Some` once generation should stop.
+    pub fn feed(&mut self, text: &str) -> Option<Verdict> {
+        text.chars().find_map(|c| self.push(c))
+    }
+
+    fn push(&mut self, c: char) -> Option<Verdict> {
+        if !self.started {
+            return self.push_prelude(c);
+        }
+        if self.in_string {
+            match c {
+                _ if self.escaped => {
+                    self.escaped = false;
+                    self.string.push(c);
+                }
+                '\\' => self.escaped = true,
+                '"' => {
+                    self.in_string = false;
+                    return self.end_string();
+                }
+                _ => self.string.push(c),
+            }
+            return None;
+        }
+        match c {
+            '"' => {
+                self.in_string = true;
+                self.string.clear();
+                self.reading_key = self.stack.last() == Some(&Container::Object)
+                    && self.keys.last().is_some_and(Option::is_none);
+            }
+            '{' => {
+                self.stack.push(Container::Object);
+                self.keys.push(None);
+            }
+            '[' => self.stack.push(Container::Array),
+            '}' | ']' => {
+                if self.stack.pop() == Some(Container::Object) {
+                    self.keys.pop();
+                }
+                if self.stack.is_empty() {
+                    return Some(Verdict::Complete);
+                }
+                self.end_value();
+            }
+            ',' => self.end_value(),
+            _ => {}
+        }
+        None
+    }
+
+    fn push_prelude(&mut self, c: char) -> Option<Verdict> {
+        if c == '{' {
+            self.started = true;
+            self.stack.push(Container::Object);
+            self.keys.push(None);
+            return None;
+        }
+        self.prelude.push(c);
+        let text = self.prelude.trim_start();
+        // Code fences are harmless; extraction strips them
+        let text = text
+            .strip_prefix("```json")
+            .or(text.strip_prefix("```"))
+            .unwrap_or(text);
+        let text = text.trim();
+        if text.starts_with(['#', '*', '|', '>']) || text.contains("\n\n") {
+            return Some(Verdict::OffContract("markdown instead of JSON".to_string()));
+        }
+        if text.chars().count() > MAX_PRELUDE {
+            return Some(Verdict::OffContract("prose instead of JSON".to_string()));
+        }
+        None
+    }
+
+    /// A value finished in the innermost object: its next string is a key.
+    fn end_value(&mut self) {
+        if self.stack.last() == Some(&Container::Object) {
+            if let Some(key) = self.keys.last_mut() {
+                *key = None;
+            }
+        }
+    }
+
+    fn end_string(&mut self) -> Option<Verdict> {
+        let in_meaning = self.entry && self.in_meaning();
+        if self.reading_key {
+            if in_meaning {
+                if let Some(field) = misspelled_field(&self.string) {
+                    return Some(Verdict::OffContract(format!(
+                        "meaning field '{}' instead of '{}'",
+                        self.string, field
+                    )));
+                }
+            }
+            if let Some(key) = self.keys.last_mut() {
+                *key = Some(std::mem::take(&mut self.string));
+            }
+            return None;
+        }
+        let key = self.keys.last().cloned().flatten();
+        if in_meaning && key.as_deref() == Some("partOfSpeech") {
+            let pos = self.string.to_lowercase();
+            if !self.allowed.contains(&pos) {
+                return Some(Verdict::OffContract(format!(
+                    "'{}' is not a part of speech",
+                    self.string
+                )));
+            }
+            if !self.parts_of_speech.insert(pos) {
+                return Some(Verdict::OffContract(format!(
+                    "part of speech '{}' repeated",
+                    self.string
+                )));
+            }
+        }
+        None
+    }
+
+    /// Whether the innermost container is a meaning: an object directly in
+    /// the top-level `meanings` array.
+    fn in_meaning(&self) -> bool {
+        self.stack == [Container::Object, Container::Array, Container::Object]
+            && self.keys.first().cloned().flatten().as_deref() == Some("meanings")
+    }
+}
+
+/// The schema field `key` is a variant spelling of, if it is not one itself.
+fn misspelled_field(key: &str) -> Option<&'static str> {
+    let fold = |s: &str| {
+        s.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect::<String>()
+    };
+    if meaning_fields().any(|f| f == key) {
+        return None;
+    }
+    let folded = fold(key);
+    meaning_fields().find(|f| fold(f) == folded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate::PARTS_OF_SPEECH;
+
+    fn watch(chunks: &[&str]) -> Option<Verdict> {
+        watch_with(&PARTS_OF_SPEECH, chunks)
+    }
+
+    fn watch_with(parts_of_speech: &[&str], chunks: &[&str]) -> Option<Verdict> {
+        let mut watch = ContractWatch::new(&PromptTask::Entry, parts_of_speech).unwrap();
+        chunks.iter().find_map(|chunk| watch.feed(chunk))
+    }
+
+    #[test]
+    fn stops_when_the_object_closes() {
+        let entry = r#"{"word": "run", "note": "a } in text", "meanings": [{"partOfSpeech": "Verb", "synonyms": ["dash"]}, {"partOfSpeech": "noun"}]}"#;
+        assert_eq!(
+            watch(&["```json\n", entry, "\n```"]),
+            Some(Verdict::Complete)
+        );
+        // Split mid-token, as tokens arrive
+        let pieces: Vec<String> = entry.chars().map(String::from).collect();
+        let pieces: Vec<&str> = pieces.iter().map(String::as_str).collect();
+        assert_eq!(watch(&pieces), Some(Verdict::Complete));
+        assert_eq!(watch(&[&entry[..entry.len() - 1]]), None);
+    }
+
+    #[test]
+    fn flags_output_leaving_the_contract() {
+        assert!(matches!(
+            watch(&["## Analysis of \"run\"\n"]),
+            Some(Verdict::OffContract(_))
+        ));
+        assert!(matches!(
+            watch(&["Sure! The word run is a common English verb used when"]),
+            Some(Verdict::OffContract(_))
+        ));
+        assert_eq!(watch(&["Here is the entry:\n{"]), None);
+
+        let snake_case = r#"{"meanings": [{"part_of_speech": "#;
+        assert_eq!(
+            watch(&[snake_case]),
+            Some(Verdict::OffContract(
+                "meaning field 'part_of_speech' instead of 'partOfSpeech'".to_string()
+            ))
+        );
+        // Unknown fields are stripped by validation, not worth a retry
+        assert_eq!(
+            watch(&[r#"{"meanings": [{"etymology": "Old English", "partOfSpeech": "verb", "#]),
+            None
+        );
+        let repeated = r#"{"meanings": [{"partOfSpeech": "verb"}, {"partOfSpeech": "Verb"}"#;
+        assert!(matches!(watch(&[repeated]), Some(Verdict::OffContract(_))));
+        assert!(matches!(
+            watch(&[r#"{"meanings": [{"partOfSpeech": "phrasal verb""#]),
+            Some(Verdict::OffContract(_))
+        ));
+        // Top-level fields and values elsewhere are left to the validator
+        assert_eq!(
+            watch(&[r#"{"extra": {"partOfSpeech": "x"}, "difficulty": "hard", "#]),
+            None
+        );
+    }
+
+    #[test]
+    fn parts_of_speech_come_from_the_contract_profiles() {
+        let particle = r#"{"meanings": [{"partOfSpeech": "particle", "#;
+        assert!(matches!(watch(&[particle]), Some(Verdict::OffContract(_))));
+        let mut japanese = PARTS_OF_SPEECH.to_vec();
+        japanese.push("particle");
+        assert_eq!(watch_with(&japanese, &[particle]), None);
+    }
+}
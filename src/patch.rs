@@ -0,0 +1,153 @@
+use serde_json::{json, Value};
+
+/// Compute an RFC 6902 JSON Patch that transforms `from` into `to`.
+///
+/// Objects are diffed key by key and equal-length arrays element by element;
+/// arrays whose length changed are replaced wholesale, which keeps the patch
+/// valid and readable for the small arrays in word entries.
+pub fn diff(from: &Value, to: &Value) -> Vec<Value> {
+    let mut ops = Vec::new();
+    diff_at(String::new(), from, to, &mut ops);
+    ops
+}
+
+fn diff_at(path: String, from: &Value, to: &Value, ops: &mut Vec<Value>) {
+    if from == to {
+        return;
+    }
+    match (from, to) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, old) in a {
+                let child = format!("{}/{}", path, escape(key));
+                match b.get(key) {
+                    Some(new) => diff_at(child, old, new, ops),
+                    None => ops.push(json!({ "op": "remove", "path": child })),
+                }
+            }
+            for (key, new) in b {
+                if !a.contains_key(key) {
+                    let child = format!("{}/{}", path, escape(key));
+                    ops.push(json!({ "op": "add", "path": child, "value": new }));
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) if a.len() == b.len() => {
+            for (idx, (old, new)) in a.iter().zip(b).enumerate() {
+                diff_at(format!("{}/{}", path, idx), old, new, ops);
+            }
+        }
+        _ => ops.push(json!({ "op": "replace", "path": path, "value": to })),
+    }
+}
+
+/// Apply an RFC 7396 JSON Merge Patch to `target` in place: objects merge
+/// recursively, `null` removes a key, and anything else replaces the value.
+pub fn merge(target: &mut Value, patch: &Value) {
+    let Value::Object(patch_obj) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    if let Value::Object(target_obj) = target {
+        for (key, value) in patch_obj {
+            if value.is_null() {
+                target_obj.shift_remove(key);
+            } else {
+                merge(target_obj.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+/// Top-level entry fields that can be regenerated on their own.
+const ENTRY_FIELDS: [&str; 3] = ["baseForm", "phonetic", "difficulty"];
+/// Per-meaning fields that can be regenerated on their own. `partOfSpeech`
+/// and `senseRank` shape the entry and are left to full regeneration.
+const MEANING_FIELDS: [&str; 6] = [
+    "definition",
+    "exampleSentence",
+    "grammarTip",
+    "synonyms",
+    "antonyms",
+    "translations",
+];
+
+/// Turn a field path such as `phonetic` or `meanings[0].exampleSentence`
+/// into a JSON Pointer, or `None` if it does not name a regenerable field.
+pub fn field_pointer(path: &str) -> Option<String> {
+    if ENTRY_FIELDS.contains(&path) {
+        return Some(format!("/{}", path));
+    }
+    let rest = path.strip_prefix("meanings[")?;
+    let (idx, field) = rest.split_once("].")?;
+    let idx: usize = idx.parse().ok()?;
+    MEANING_FIELDS
+        .contains(&field)
+        .then(|| format!("/meanings/{}/{}", idx, field))
+}
+
+/// Escape a key as a JSON Pointer reference token (RFC 6901).
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diffs_nested_objects_and_arrays() {
+        let from = json!({
+            "phonetic": "/a/",
+            "stale": true,
+            "meanings": [{ "definition": "old", "synonyms": ["x"] }]
+        });
+        let to = json!({
+            "phonetic": "/b/",
+            "meanings": [{ "definition": "new", "synonyms": ["x", "y"] }],
+            "a/b": 1
+        });
+        assert_eq!(
+            diff(&from, &to),
+            vec![
+                json!({ "op": "replace", "path": "/phonetic", "value": "/b/" }),
+                json!({ "op": "remove", "path": "/stale" }),
+                json!({ "op": "replace", "path": "/meanings/0/definition", "value": "new" }),
+                json!({ "op": "replace", "path": "/meanings/0/synonyms", "value": ["x", "y"] }),
+                json!({ "op": "add", "path": "/a~1b", "value": 1 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_patch_follows_rfc7396() {
+        let mut target = json!({ "a": "b", "c": { "d": "e", "f": "g" } });
+        merge(&mut target, &json!({ "a": "z", "c": { "f": null } }));
+        assert_eq!(target, json!({ "a": "z", "c": { "d": "e" } }));
+    }
+
+    #[test]
+    fn field_paths_map_to_pointers() {
+        assert_eq!(field_pointer("phonetic").as_deref(), Some("/phonetic"));
+        assert_eq!(
+            field_pointer("meanings[2].exampleSentence").as_deref(),
+            Some("/meanings/2/exampleSentence")
+        );
+        for bad in [
+            "word",
+            "meanings[0].partOfSpeech",
+            "meanings[x].definition",
+            "meanings[0]",
+        ] {
+            assert_eq!(field_pointer(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn identical_documents_produce_empty_patch() {
+        let v = json!({ "word": "same" });
+        assert!(diff(&v, &v).is_empty());
+    }
+}
@@ -0,0 +1,193 @@
+use crate::model::InferParams;
+use crate::tenant::{self, TenantConfig};
+use crate::validate::SCHEMA_VERSION;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::Arc;
+use tracing::info;
+
+/// Defaults for one consuming product, applied to every request made with
+/// one of its API keys. Unset fields fall back to the server's configuration.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub max_tokens: Option<i32>,
+    pub temp: Option<f32>,
+    pub top_p: Option<f32>,
+    pub min_p: Option<f32>,
+    pub repeat_penalty: Option<f32>,
+    pub repeat_last_n: Option<i32>,
+    pub frequency_penalty: Option<f32>,
+    pub presence_penalty: Option<f32>,
+    /// Translation languages returned, in preference order; others are dropped.
+    /// An `Accept-Language` header on the request still takes precedence.
+    pub languages: Option<Vec<String>>,
+    /// Word contract version the product was built against
+    pub schema_version: Option<String>,
+    /// Requests admitted per minute; more are answered with 429
+    pub requests_per_minute: Option<u32>,
+    /// Generated-output tokens per day; once used up requests get 402
+    pub tokens_per_day: Option<u64>,
+    /// Tenant whose entries, overrides and quotas the profile's keys use
+    pub tenant: Option<String>,
+}
+
+impl Profile {
+    /// `base` with this profile's sampling overrides applied.
+    pub fn params(&self, base: &InferParams) -> InferParams {
+        InferParams {
+            max_tokens: self.max_tokens.unwrap_or(base.max_tokens),
+            temp: self.temp.unwrap_or(base.temp),
+            top_p: self.top_p.unwrap_or(base.top_p),
+            min_p: self.min_p.unwrap_or(base.min_p),
+            repeat_penalty: self.repeat_penalty.unwrap_or(base.repeat_penalty),
+            repeat_last_n: self.repeat_last_n.unwrap_or(base.repeat_last_n),
+            frequency_penalty: self.frequency_penalty.unwrap_or(base.frequency_penalty),
+            presence_penalty: self.presence_penalty.unwrap_or(base.presence_penalty),
+            ..base.clone()
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProfilesFile {
+    #[serde(default)]
+    profiles: HashMap<String, Profile>,
+    /// API key to profile name
+    #[serde(default)]
+    keys: HashMap<String, String>,
+    #[serde(default)]
+    tenants: BTreeMap<String, TenantConfig>,
+}
+
+/// Named profiles and the API keys bound to them, loaded from `PROFILES_FILE`:
+///
+/// ```json
+/// {
+///   "profiles": { "flashcards": { "temp": 0.2, "languages": ["es", "fr"] } },
+///   "keys": { "fc-live-1234": "flashcards" }
+/// }
+/// ```
+///
+/// A `tenants` section declares the tenants profiles may name, e.g.
+/// `"tenants": { "kids": { "schema_file": "kids.schema.json" } }`.
+#[derive(Debug, Default)]
+pub struct Profiles {
+    by_key: HashMap<String, (String, Arc<Profile>)>,
+    tenants: BTreeMap<String, TenantConfig>,
+}
+
+impl Profiles {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let raw = crate::util::read_to_string(path)?;
+        let profiles =
+            Self::from_json(&raw).with_context(|| format!("load profiles {:?}", path))?;
+        info!(
+            keys = profiles.by_key.len(),
+            ?path,
+            "loaded API key profiles"
+        );
+        Ok(profiles)
+    }
+
+    pub fn from_json(raw: &str) -> Result<Self> {
+        let file: ProfilesFile = serde_json::from_str(raw)?;
+        for name in file.tenants.keys() {
+            tenant::check_name(name)?;
+        }
+        for (name, profile) in &file.profiles {
+            if let Some(tenant) = &profile.tenant {
+                if !file.tenants.contains_key(tenant) {
+                    bail!("profile '{}' names undeclared tenant '{}'", name, tenant);
+                }
+            }
+            // Only one contract is served; a product expecting another must not get it silently
+            if let Some(version) = &profile.schema_version {
+                if version != SCHEMA_VERSION {
+                    bail!(
+                        "profile '{}' expects schema version {}, but this server serves {}",
+                        name,
+                        version,
+                        SCHEMA_VERSION
+                    );
+                }
+            }
+        }
+        let profiles: HashMap<String, Arc<Profile>> = file
+            .profiles
+            .into_iter()
+            .map(|(name, profile)| (name, Arc::new(profile)))
+            .collect();
+
+        let mut by_key = HashMap::with_capacity(file.keys.len());
+        for (key, name) in file.keys {
+            let Some(profile) = profiles.get(&name) else {
+                bail!("an API key is bound to unknown profile '{}'", name);
+            };
+            by_key.insert(key, (name, profile.clone()));
+        }
+        Ok(Self {
+            by_key,
+            tenants: file.tenants,
+        })
+    }
+
+    /// Whether any API keys are configured.
+    pub fn is_empty(&self) -> bool {
+        self.by_key.is_empty()
+    }
+
+    /// Profile name and settings bound to `key`.
+    pub fn for_key(&self, key: &str) -> Option<(&str, &Arc<Profile>)> {
+        self.by_key
+            .get(key)
+            .map(|(name, profile)| (name.as_str(), profile))
+    }
+
+    /// Declared tenants and their settings.
+    pub fn tenants(&self) -> &BTreeMap<String, TenantConfig> {
+        &self.tenants
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_resolve_to_profiles_and_bad_files_are_rejected() {
+        let profiles = Profiles::from_json(
+            r#"{
+                "profiles": { "cards": { "temp": 0.1, "languages": ["es"], "schema_version": "2" } },
+                "keys": { "k1": "cards" }
+            }"#,
+        )
+        .unwrap();
+        let (name, profile) = profiles.for_key("k1").unwrap();
+        assert_eq!(name, "cards");
+        let params = profile.params(&InferParams::default());
+        assert_eq!(params.temp, 0.1);
+        assert_eq!(params.max_tokens, InferParams::default().max_tokens);
+        assert!(profiles.for_key("k2").is_none());
+
+        let dangling = r#"{ "keys": { "k1": "missing" } }"#;
+        assert!(Profiles::from_json(dangling).is_err());
+        let old_schema = r#"{ "profiles": { "p": { "schema_version": "1" } } }"#;
+        assert!(Profiles::from_json(old_schema).is_err());
+        let typo = r#"{ "profiles": { "p": { "temperature": 0.1 } } }"#;
+        assert!(Profiles::from_json(typo).is_err());
+        let undeclared = r#"{ "profiles": { "p": { "tenant": "kids" } } }"#;
+        assert!(Profiles::from_json(undeclared).is_err());
+        let tenanted = Profiles::from_json(
+            r#"{ "profiles": { "p": { "tenant": "kids" } }, "tenants": { "kids": {} } }"#,
+        )
+        .unwrap();
+        assert_eq!(tenanted.tenants().keys().collect::<Vec<_>>(), ["kids"]);
+        let bad_name = r#"{ "tenants": { "Kids": {} } }"#;
+        assert!(Profiles::from_json(bad_name).is_err());
+    }
+}
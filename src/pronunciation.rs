@@ -0,0 +1,183 @@
+use crate::validate::{ValidationError, Violation};
+use jsonschema::JSONSchema;
+use once_cell::sync::Lazy;
+use serde_json::Value;
+
+const PRIMARY_STRESS: char = 'ˈ';
+const SECONDARY_STRESS: char = 'ˌ';
+const SYLLABLE_BREAK: char = '.';
+
+static SCHEMA: Lazy<JSONSchema> =
+    Lazy::new(|| crate::extract::compile(include_str!("../schema/pronunciation.schema.json")));
+
+/// Check a model's pronunciation answer against
+/// `schema/pronunciation.schema.json`, then that its parts agree: the IPA
+/// marks one syllable per listed syllable (with `.` or stress marks between
+/// them), the spelled syllables make up the word, `stress` points at the
+/// syllable carrying `ˈ`, and the minimal pairs are other words.
+///
+/// Disagreements are reported as retryable violations, since another
+/// sample usually gets them right.
+pub fn validate(mut v: Value, surface_word: &str) -> Result<Value, ValidationError> {
+    if let Err(errors) = SCHEMA.validate(&v) {
+        return Err(ValidationError::SchemaValidation(
+            errors.map(Violation::from_schema_error).collect(),
+        ));
+    }
+    v["word"] = Value::String(surface_word.to_string());
+
+    let mut violations = Vec::new();
+    let mut violation = |path: &str, keyword: &str, message: String| {
+        violations.push(Violation {
+            path: path.to_string(),
+            keyword: keyword.to_string(),
+            message,
+        })
+    };
+
+    let headword = surface_word.trim().to_lowercase();
+    let syllables: Vec<&str> = v["syllables"]
+        .as_array()
+        .expect("checked by the schema")
+        .iter()
+        .filter_map(Value::as_str)
+        .collect();
+    let spelled: String = syllables.concat().to_lowercase().replace('-', "");
+    if spelled != headword.replace('-', "") {
+        violation(
+            "/syllables",
+            "spelling",
+            format!("syllables spell '{}', not '{}'", spelled, headword),
+        );
+    }
+
+    let ipa = ipa_syllables(v["ipa"].as_str().unwrap_or_default());
+    if ipa.len() != syllables.len() {
+        violation(
+            "/ipa",
+            "syllableBoundaries",
+            format!(
+                "IPA marks {} syllable(s) but {} are listed; separate them with '.' or stress marks",
+                ipa.len(),
+                syllables.len()
+            ),
+        );
+    }
+
+    let stress = v["stress"].as_u64().unwrap_or_default() as usize;
+    let primary: Vec<usize> = ipa
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| s.contains(PRIMARY_STRESS))
+        .map(|(i, _)| i + 1)
+        .collect();
+    match primary.as_slice() {
+        [] if ipa.len() == 1 && stress == 1 => {}
+        [marked] if *marked == stress => {}
+        [] => violation(
+            "/ipa",
+            "stress",
+            format!("no primary stress mark '{}' in the IPA", PRIMARY_STRESS),
+        ),
+        [marked] => violation(
+            "/stress",
+            "stress",
+            format!(
+                "stress is syllable {}, but the IPA stresses syllable {}",
+                stress, marked
+            ),
+        ),
+        _ => violation(
+            "/ipa",
+            "stress",
+            "more than one primary stress mark".to_string(),
+        ),
+    }
+
+    for (i, pair) in v["minimalPairs"]
+        .as_array()
+        .expect("checked by the schema")
+        .iter()
+        .enumerate()
+    {
+        if pair["word"].as_str().map(|w| w.trim().to_lowercase()) == Some(headword.clone()) {
+            violation(
+                &format!("/minimalPairs/{}/word", i),
+                "minimalPair",
+                "a minimal pair must be a different word".to_string(),
+            );
+        }
+    }
+
+    if violations.is_empty() {
+        Ok(v)
+    } else {
+        Err(ValidationError::SchemaValidation(violations))
+    }
+}
+
+/// Syllables of an IPA transcription, split at `.` and before stress marks.
+fn ipa_syllables(ipa: &str) -> Vec<String> {
+    let mut syllables = vec![String::new()];
+    for c in ipa.trim().trim_matches('/').chars() {
+        let current = syllables.last_mut().expect("never empty");
+        match c {
+            SYLLABLE_BREAK => syllables.push(String::new()),
+            PRIMARY_STRESS | SECONDARY_STRESS if !current.is_empty() => {
+                syllables.push(c.to_string())
+            }
+            _ => current.push(c),
+        }
+    }
+    syllables.retain(|s| !s.is_empty());
+    syllables
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn answer() -> Value {
+        json!({
+            "word": "x",
+            "ipa": "/dɪˈsaɪd/",
+            "syllables": ["de", "cide"],
+            "stress": 2,
+            "minimalPairs": [
+                { "word": "deride", "ipa": "/dɪˈraɪd/" },
+                { "word": "beside", "ipa": "/bɪˈsaɪd/" }
+            ]
+        })
+    }
+
+    #[test]
+    fn parts_must_agree() {
+        assert_eq!(ipa_syllables("/ˌɪn.fə.ˈmeɪ.ʃən/").len(), 4);
+        assert_eq!(ipa_syllables("/dɪˈsaɪd/"), ["dɪ", "ˈsaɪd"]);
+
+        let out = validate(answer(), "decide").unwrap();
+        assert_eq!(out["word"], "decide");
+        let mono = json!({
+            "word": "cat", "ipa": "/kæt/", "syllables": ["cat"], "stress": 1,
+            "minimalPairs": [{ "word": "bat", "ipa": "/bæt/" }, { "word": "cap", "ipa": "/kæp/" }]
+        });
+        validate(mono, "cat").unwrap();
+
+        let mut unmarked = answer();
+        unmarked["ipa"] = json!("/dɪsaɪd/");
+        let mut wrong_stress = answer();
+        wrong_stress["stress"] = json!(1);
+        let mut misspelled = answer();
+        misspelled["syllables"] = json!(["de", "side"]);
+        for (bad, path) in [
+            (unmarked, "/ipa"),
+            (wrong_stress, "/stress"),
+            (misspelled, "/syllables"),
+        ] {
+            let err = validate(bad, "decide").unwrap_err();
+            assert!(err.is_retryable());
+            assert_eq!(err.violations()[0].path, path);
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use crate::profile::Profile;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+const QUOTA_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// What a key has left after a request was admitted; `None` where its
+/// profile sets no limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Remaining {
+    pub requests: Option<u32>,
+    pub tokens: Option<u64>,
+}
+
+/// Why a request was turned away before any work was done.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    /// Over `requests_per_minute`; answered with 429
+    RateLimited { retry_after: Duration },
+    /// `tokens_per_day` used up; answered with 402
+    QuotaExceeded { retry_after: Duration },
+}
+
+#[derive(Debug)]
+struct KeyUsage {
+    rate_window: Instant,
+    requests: u32,
+    quota_window: Instant,
+    tokens: u64,
+}
+
+/// One key's counters as written on shutdown, with window starts as Unix
+/// seconds so they survive a restart.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedUsage {
+    pub key: String,
+    pub requests: u32,
+    pub rate_window_start: u64,
+    pub tokens: u64,
+    pub quota_window_start: u64,
+}
+
+/// Per-API-key request rate (fixed one-minute windows) and generated-token
+/// usage (fixed one-day windows), checked against each key's profile.
+/// Counters live in memory; [`save`](Self::save) and
+/// [`restore`](Self::restore) carry them across restarts.
+#[derive(Debug, Default)]
+pub struct UsageTracker {
+    keys: Mutex<HashMap<String, KeyUsage>>,
+}
+
+impl UsageTracker {
+    /// Count one request for `key`, or reject it when a limit is reached.
+    pub fn admit(&self, key: &str, profile: &Profile) -> Result<Remaining, Rejection> {
+        self.admit_at(key, profile, Instant::now())
+    }
+
+    fn admit_at(&self, key: &str, profile: &Profile, now: Instant) -> Result<Remaining, Rejection> {
+        let mut keys = self.keys.lock();
+        let usage = keys.entry(key.to_string()).or_insert_with(|| KeyUsage {
+            rate_window: now,
+            requests: 0,
+            quota_window: now,
+            tokens: 0,
+        });
+        roll_windows(usage, now);
+
+        if let Some(quota) = profile.tokens_per_day {
+            if usage.tokens >= quota {
+                return Err(Rejection::QuotaExceeded {
+                    retry_after: QUOTA_WINDOW.saturating_sub(now - usage.quota_window),
+                });
+            }
+        }
+        if let Some(limit) = profile.requests_per_minute {
+            if usage.requests >= limit {
+                return Err(Rejection::RateLimited {
+                    retry_after: RATE_WINDOW.saturating_sub(now - usage.rate_window),
+                });
+            }
+        }
+        usage.requests += 1;
+        Ok(remaining(usage, profile))
+    }
+
+    /// Charge `tokens` of generated output to `key`.
+    pub fn record_tokens(&self, key: &str, tokens: u64) {
+        if let Some(usage) = self.keys.lock().get_mut(key) {
+            usage.tokens = usage.tokens.saturating_add(tokens);
+        }
+    }
+
+    /// What `key` has left, without counting a request.
+    pub fn remaining(&self, key: &str, profile: &Profile) -> Remaining {
+        let mut keys = self.keys.lock();
+        match keys.get_mut(key) {
+            Some(usage) => {
+                roll_windows(usage, Instant::now());
+                remaining(usage, profile)
+            }
+            None => Remaining {
+                requests: profile.requests_per_minute,
+                tokens: profile.tokens_per_day,
+            },
+        }
+    }
+
+    /// Every key's counters, for writing out on shutdown.
+    pub fn save(&self) -> Vec<SavedUsage> {
+        let (now, unix) = (Instant::now(), unix_now());
+        let started = |window: Instant| unix.saturating_sub((now - window).as_secs());
+        let mut saved: Vec<SavedUsage> = self
+            .keys
+            .lock()
+            .iter()
+            .map(|(key, usage)| SavedUsage {
+                key: key.clone(),
+                requests: usage.requests,
+                rate_window_start: started(usage.rate_window),
+                tokens: usage.tokens,
+                quota_window_start: started(usage.quota_window),
+            })
+            .collect();
+        saved.sort_by(|a, b| a.key.cmp(&b.key));
+        saved
+    }
+
+    /// Take over counters written by [`save`](Self::save), returning how many
+    /// keys were restored. Windows that ended while the process was down are
+    /// dropped instead of restored.
+    pub fn restore(&self, saved: Vec<SavedUsage>) -> usize {
+        let (now, unix) = (Instant::now(), unix_now());
+        // Window starts before this process's clock began count from now,
+        // which errs towards the limits
+        let window = |start: u64, length: Duration| {
+            let age = Duration::from_secs(unix.saturating_sub(start));
+            (age < length).then(|| now.checked_sub(age).unwrap_or(now))
+        };
+        let mut keys = self.keys.lock();
+        let mut restored = 0;
+        for usage in saved {
+            let rate = window(usage.rate_window_start, RATE_WINDOW);
+            let quota = window(usage.quota_window_start, QUOTA_WINDOW);
+            if rate.is_none() && quota.is_none() {
+                continue;
+            }
+            keys.insert(
+                usage.key,
+                KeyUsage {
+                    rate_window: rate.unwrap_or(now),
+                    requests: rate.map_or(0, |_| usage.requests),
+                    quota_window: quota.unwrap_or(now),
+                    tokens: quota.map_or(0, |_| usage.tokens),
+                },
+            );
+            restored += 1;
+        }
+        restored
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn roll_windows(usage: &mut KeyUsage, now: Instant) {
+    if now - usage.rate_window >= RATE_WINDOW {
+        usage.rate_window = now;
+        usage.requests = 0;
+    }
+    if now - usage.quota_window >= QUOTA_WINDOW {
+        usage.quota_window = now;
+        usage.tokens = 0;
+    }
+}
+
+fn remaining(usage: &KeyUsage, profile: &Profile) -> Remaining {
+    Remaining {
+        requests: profile
+            .requests_per_minute
+            .map(|limit| limit.saturating_sub(usage.requests)),
+        tokens: profile
+            .tokens_per_day
+            .map(|quota| quota.saturating_sub(usage.tokens)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_and_token_limits_reject_then_reset() {
+        let tracker = UsageTracker::default();
+        let profile = Profile {
+            requests_per_minute: Some(2),
+            tokens_per_day: Some(100),
+            ..Profile::default()
+        };
+        let start = Instant::now();
+
+        let first = tracker.admit_at("k", &profile, start).unwrap();
+        assert_eq!(first.requests, Some(1));
+        assert_eq!(first.tokens, Some(100));
+        tracker.admit_at("k", &profile, start).unwrap();
+        assert!(matches!(
+            tracker.admit_at("k", &profile, start),
+            Err(Rejection::RateLimited { .. })
+        ));
+
+        // A new minute admits again until the day's tokens run out
+        let later = start + RATE_WINDOW;
+        tracker.admit_at("k", &profile, later).unwrap();
+        tracker.record_tokens("k", 150);
+        assert!(matches!(
+            tracker.admit_at("k", &profile, later),
+            Err(Rejection::QuotaExceeded { .. })
+        ));
+        assert!(tracker
+            .admit_at("k", &profile, start + QUOTA_WINDOW)
+            .is_ok());
+    }
+
+    #[test]
+    fn saved_usage_survives_a_restart_until_its_window_ends() {
+        let profile = Profile {
+            tokens_per_day: Some(100),
+            ..Profile::default()
+        };
+        let tracker = UsageTracker::default();
+        tracker.admit("k", &profile).unwrap();
+        tracker.record_tokens("k", 60);
+        let saved = tracker.save();
+        assert_eq!(saved[0].requests, 1);
+        assert_eq!(saved[0].tokens, 60);
+
+        let restarted = UsageTracker::default();
+        assert_eq!(restarted.restore(saved.clone()), 1);
+        assert_eq!(restarted.remaining("k", &profile).tokens, Some(40));
+
+        // A day later nothing is left to restore
+        let stale = saved
+            .into_iter()
+            .map(|u| SavedUsage {
+                rate_window_start: u.rate_window_start - QUOTA_WINDOW.as_secs(),
+                quota_window_start: u.quota_window_start - QUOTA_WINDOW.as_secs(),
+                ..u
+            })
+            .collect();
+        let restarted = UsageTracker::default();
+        assert_eq!(restarted.restore(stale), 0);
+        assert_eq!(restarted.remaining("k", &profile).tokens, Some(100));
+    }
+}
@@ -0,0 +1,301 @@
+use crate::model::{prompt, BackendStats, InferParams, LlmBackend, PromptParts, PromptTask, Token};
+use crate::validate::Validator;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// One model call as it happened: what was asked and exactly what came back.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recording {
+    pub word: String,
+    /// `entry`, `translations`, `field` or `raw`
+    pub task: String,
+    pub model: String,
+    /// Unix timestamp in milliseconds
+    pub recorded_at: u64,
+    pub params: InferParams,
+    /// The prompt rendered with every section; the backend may have trimmed it
+    pub prompt: String,
+    /// Backend output before parsing or validation
+    pub output: String,
+}
+
+/// Wraps a backend and saves every successful output to `dir`, one JSON file
+/// per call, so validator changes can be replayed against real outputs.
+pub struct RecordingBackend {
+    inner: Arc<dyn LlmBackend>,
+    dir: PathBuf,
+    seq: AtomicU64,
+}
+
+impl RecordingBackend {
+    pub fn new(inner: Arc<dyn LlmBackend>, dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).with_context(|| format!("create record dir {:?}", dir))?;
+        Ok(Self {
+            inner,
+            dir,
+            seq: AtomicU64::new(0),
+        })
+    }
+
+    fn save(&self, recording: &Recording) -> Result<()> {
+        let seq = self.seq.fetch_add(1, Ordering::Relaxed);
+        let name = format!(
+            "{}-{:06}-{}.json",
+            recording.recorded_at,
+            seq,
+            file_safe(&recording.word)
+        );
+        let path = self.dir.join(name);
+        fs::write(&path, serde_json::to_vec_pretty(recording)?)
+            .with_context(|| format!("write recording {:?}", path))
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmBackend for RecordingBackend {
+    async fn infer_json(&self, prompt: PromptParts, params: &InferParams) -> Result<Vec<u8>> {
+        let rendered = prompt::render(&prompt);
+        let word = prompt.user_word.clone();
+        let task = prompt.task.name();
+        let output = self.inner.infer_json(prompt, params).await?;
+
+        let recording = Recording {
+            word,
+            task: task.to_string(),
+            model: self.inner.model_name(),
+            recorded_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            params: params.clone(),
+            prompt: rendered,
+            output: String::from_utf8_lossy(&output).into_owned(),
+        };
+        // Recording is a debugging aid; never fail the request over it
+        if let Err(e) = self.save(&recording) {
+            warn!("Failed to record model output: {:#}", e);
+        }
+        Ok(output)
+    }
+
+    fn model_name(&self) -> String {
+        self.inner.model_name()
+    }
+
+    fn tokenize(&self, text: &str) -> Option<Result<Vec<Token>>> {
+        self.inner.tokenize(text)
+    }
+
+    fn count_tokens(&self, text: &str) -> Option<usize> {
+        self.inner.count_tokens(text)
+    }
+
+    fn stats(&self) -> Option<BackendStats> {
+        self.inner.stats()
+    }
+}
+
+/// Words can hold anything a client sent; keep file names portable.
+fn file_safe(word: &str) -> String {
+    let safe: String = word
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(40)
+        .collect();
+    if safe.is_empty() {
+        "_".to_string()
+    } else {
+        safe
+    }
+}
+
+/// Outcome of rerunning the validator over a directory of recordings.
+#[derive(Debug, Default)]
+pub struct RevalidateReport {
+    pub passed: usize,
+    /// Passed only after the validator's fixes changed the entry
+    pub fixed: usize,
+    /// File name and validation error for each rejected output
+    pub failed: Vec<(String, String)>,
+    /// Outputs that were not JSON, or files that were not recordings
+    pub unparseable: Vec<(String, String)>,
+    /// Recordings of focused tasks, which are not whole entries
+    pub skipped: usize,
+}
+
+impl RevalidateReport {
+    pub fn total(&self) -> usize {
+        self.passed + self.failed.len() + self.unparseable.len()
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.failed.is_empty() && self.unparseable.is_empty()
+    }
+}
+
+impl fmt::Display for RevalidateReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const LISTED: usize = 20;
+        writeln!(
+            f,
+            "{} entries: {} passed ({} after fixes), {} failed, {} unparseable; {} other recordings skipped",
+            self.total(),
+            self.passed,
+            self.fixed,
+            self.failed.len(),
+            self.unparseable.len(),
+            self.skipped
+        )?;
+
+        let mut reasons: BTreeMap<&str, usize> = BTreeMap::new();
+        for (_, error) in &self.failed {
+            *reasons.entry(error.as_str()).or_default() += 1;
+        }
+        let mut reasons: Vec<_> = reasons.into_iter().collect();
+        reasons.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+        if !reasons.is_empty() {
+            writeln!(f, "\nfailures by reason:")?;
+            for (reason, count) in reasons {
+                writeln!(f, "  {:>6}  {}", count, reason)?;
+            }
+        }
+
+        for (title, list) in [("failed", &self.failed), ("unparseable", &self.unparseable)] {
+            if list.is_empty() {
+                continue;
+            }
+            writeln!(f, "\n{}:", title)?;
+            for (file, error) in list.iter().take(LISTED) {
+                writeln!(f, "  {}: {}", file, error)?;
+            }
+            if list.len() > LISTED {
+                writeln!(f, "  ... and {} more", list.len() - LISTED)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run `validator` over every whole-entry recording in `dir`, in name order.
+pub fn revalidate(dir: impl AsRef<Path>, validator: &Validator) -> Result<RevalidateReport> {
+    let dir = dir.as_ref();
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .with_context(|| format!("read record dir {:?}", dir))?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+
+    let mut report = RevalidateReport::default();
+    for path in paths {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let raw = fs::read(&path).with_context(|| format!("read {:?}", path))?;
+        let recording: Recording = match serde_json::from_slice(&raw) {
+            Ok(r) => r,
+            Err(e) => {
+                report
+                    .unparseable
+                    .push((name, format!("not a recording: {}", e)));
+                continue;
+            }
+        };
+        if recording.task != PromptTask::Entry.name() {
+            report.skipped += 1;
+            continue;
+        }
+        let entry: Value = match serde_json::from_str(&recording.output) {
+            Ok(v) => v,
+            Err(e) => {
+                report
+                    .unparseable
+                    .push((name, format!("output is not JSON: {}", e)));
+                continue;
+            }
+        };
+        match validator.validate_and_fix(entry.clone(), &recording.word) {
+            Ok(fixed) => {
+                report.passed += 1;
+                if fixed != entry {
+                    report.fixed += 1;
+                }
+            }
+            Err(e) => report.failed.push((name, e.to_string())),
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::mock::MockBackend;
+
+    #[tokio::test]
+    async fn recorded_outputs_replay_through_the_validator() {
+        let dir = std::env::temp_dir().join(format!("lingua-record-{}", std::process::id()));
+        let backend = RecordingBackend::new(Arc::new(MockBackend::default()), &dir).unwrap();
+        let params = InferParams {
+            max_tokens: 64,
+            temp: 0.0,
+            top_p: 1.0,
+            min_p: 0.0,
+            repeat_penalty: 1.0,
+            ..InferParams::default()
+        };
+        for word in ["swim", "a/b"] {
+            let parts = PromptParts {
+                user_word: word.to_string(),
+                ..PromptParts::default()
+            };
+            backend.infer_json(parts, &params).await.unwrap();
+        }
+        let raw = PromptParts {
+            task: PromptTask::Raw {
+                prompt: "hello".to_string(),
+            },
+            ..PromptParts::default()
+        };
+        backend.infer_json(raw, &params).await.unwrap();
+
+        // A recording whose output the current validator rejects
+        let mut broken: Recording = serde_json::from_slice(
+            &fs::read(fs::read_dir(&dir).unwrap().next().unwrap().unwrap().path()).unwrap(),
+        )
+        .unwrap();
+        broken.task = "entry".to_string();
+        broken.output = r#"{"word":"swim"}"#.to_string();
+        fs::write(
+            dir.join("zz-broken.json"),
+            serde_json::to_vec(&broken).unwrap(),
+        )
+        .unwrap();
+
+        let report = revalidate(&dir, &Validator::new("").unwrap()).unwrap();
+        assert_eq!(report.passed, 2);
+        assert_eq!(report.skipped, 1);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, "zz-broken.json");
+        assert!(!report.is_ok());
+
+        fs::remove_dir_all(dir).ok();
+    }
+}
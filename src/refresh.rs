@@ -0,0 +1,248 @@
+use crate::model::{BackendStats, InferParams, LlmBackend, PromptParts, Token};
+use crate::service::WordService;
+use crate::store::EntryStore;
+use crate::telemetry;
+use anyhow::Result;
+use parking_lot::Mutex;
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{debug, info, warn};
+
+/// How often a busy server is checked for having gone idle.
+const IDLE_POLL: Duration = Duration::from_secs(1);
+
+/// When interactive inference last ran, so background work can stay out of
+/// its way.
+pub struct Activity {
+    in_flight: AtomicUsize,
+    last_done: Mutex<Instant>,
+}
+
+impl Default for Activity {
+    fn default() -> Self {
+        Self {
+            in_flight: AtomicUsize::new(0),
+            last_done: Mutex::new(Instant::now()),
+        }
+    }
+}
+
+impl Activity {
+    /// No inference running, and none finished within `quiet`.
+    pub fn is_idle(&self, quiet: Duration) -> bool {
+        self.in_flight.load(Ordering::Acquire) == 0 && self.last_done.lock().elapsed() >= quiet
+    }
+
+    fn enter(&self) -> Busy<'_> {
+        self.in_flight.fetch_add(1, Ordering::AcqRel);
+        Busy(self)
+    }
+}
+
+/// One inference in progress; finishing it restarts the quiet period.
+struct Busy<'a>(&'a Activity);
+
+impl Drop for Busy<'_> {
+    fn drop(&mut self) {
+        *self.0.last_done.lock() = Instant::now();
+        self.0.in_flight.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// The backend as interactive requests see it: every inference is reported
+/// to an [`Activity`]. The refresher uses the unwrapped backend, so its own
+/// work never counts as traffic.
+pub struct Tracked {
+    inner: Arc<dyn LlmBackend>,
+    activity: Arc<Activity>,
+}
+
+impl Tracked {
+    pub fn new(inner: Arc<dyn LlmBackend>, activity: Arc<Activity>) -> Self {
+        Self { inner, activity }
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmBackend for Tracked {
+    async fn infer_json(&self, prompt: PromptParts, params: &InferParams) -> Result<Vec<u8>> {
+        let _busy = self.activity.enter();
+        self.inner.infer_json(prompt, params).await
+    }
+
+    fn model_name(&self) -> String {
+        self.inner.model_name()
+    }
+
+    fn tokenize(&self, text: &str) -> Option<Result<Vec<Token>>> {
+        self.inner.tokenize(text)
+    }
+
+    fn count_tokens(&self, text: &str) -> Option<usize> {
+        self.inner.count_tokens(text)
+    }
+
+    fn stats(&self) -> Option<BackendStats> {
+        self.inner.stats()
+    }
+}
+
+/// Which stored entries count as aging, and how gently to regenerate them.
+#[derive(Debug, Clone)]
+pub struct RefreshOptions {
+    /// Entries older than this are regenerated
+    pub max_age: Option<Duration>,
+    /// Entries generated by another model than the loaded one are regenerated
+    pub other_models: bool,
+    /// How long interactive inference must have been quiet first
+    pub idle: Duration,
+    /// Least time between two refreshes
+    pub interval: Duration,
+}
+
+impl RefreshOptions {
+    pub fn is_enabled(&self) -> bool {
+        self.max_age.is_some() || self.other_models
+    }
+}
+
+/// Regenerates aging stored entries one at a time while the server is idle.
+pub struct Refresher {
+    service: WordService,
+    store: Arc<EntryStore>,
+    opts: RefreshOptions,
+    due: VecDeque<String>,
+    /// Words that failed to regenerate, not retried until restart
+    failed: HashSet<String>,
+}
+
+impl Refresher {
+    /// `service` must run on a backend that is not [`Tracked`].
+    pub fn new(service: WordService, store: Arc<EntryStore>, opts: RefreshOptions) -> Self {
+        Self {
+            service,
+            store,
+            opts,
+            due: VecDeque::new(),
+            failed: HashSet::new(),
+        }
+    }
+
+    /// Refresh the next aging word, scanning the store again once the last
+    /// scan's words are done. Returns the word tried, `None` when nothing is due.
+    pub async fn step(&mut self) -> Result<Option<String>> {
+        if self.due.is_empty() {
+            self.scan().await?;
+        }
+        let Some(word) = self.due.pop_front() else {
+            return Ok(None);
+        };
+        let outcome = match self.service.refresh_stored(&word).await {
+            Ok(Some(version)) => {
+                info!(%word, version, "refreshed aging entry");
+                "refreshed"
+            }
+            Ok(None) => {
+                debug!(%word, "skipped refreshing entry");
+                "skipped"
+            }
+            Err(e) => {
+                warn!("Failed to refresh aging entry '{}': {}", word, e.message());
+                self.failed.insert(word.clone());
+                "failed"
+            }
+        };
+        telemetry::record_background_refresh(outcome);
+        Ok(Some(word))
+    }
+
+    async fn scan(&mut self) -> Result<()> {
+        let cutoff = self.opts.max_age.map(|age| {
+            SystemTime::now()
+                .checked_sub(age)
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_secs())
+        });
+        let model = self
+            .opts
+            .other_models
+            .then(|| self.service.backend.model_name());
+        let store = self.store.clone();
+        // Reads every entry file; keep it off the async workers
+        let due =
+            tokio::task::spawn_blocking(move || store.aging(cutoff, model.as_deref())).await??;
+        self.due = due
+            .into_iter()
+            .filter(|word| !self.failed.contains(word))
+            .collect();
+        if !self.due.is_empty() {
+            info!(words = self.due.len(), "found aging entries to refresh");
+        }
+        Ok(())
+    }
+}
+
+/// Run `refresher` for the life of the process: at most one refresh per
+/// `interval`, each only once interactive inference tracked by `activity`
+/// has been quiet for `idle`.
+pub fn spawn(mut refresher: Refresher, activity: Arc<Activity>) {
+    tokio::spawn(async move {
+        let (idle, interval) = (refresher.opts.idle, refresher.opts.interval);
+        loop {
+            tokio::time::sleep(interval).await;
+            while !activity.is_idle(idle) {
+                tokio::time::sleep(IDLE_POLL).await;
+            }
+            if let Err(e) = refresher.step().await {
+                warn!("Background refresh scan failed: {:#}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::mock::MockBackend;
+    use crate::validate::Validator;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn entries_from_other_models_are_refreshed_once_idle() {
+        let dir = std::env::temp_dir().join(format!("lingua-refresh-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let store = Arc::new(EntryStore::open(&dir).unwrap());
+        store
+            .append("run", &json!({ "word": "run" }), "retired-model", "1")
+            .unwrap();
+        store
+            .append("walk", &json!({ "word": "walk" }), "mock", "1")
+            .unwrap();
+
+        let backend: Arc<dyn LlmBackend> = Arc::new(MockBackend::default());
+        let service = WordService::new(backend.clone(), Arc::new(Validator::new("").unwrap()))
+            .with_store(store.clone());
+        let opts = RefreshOptions {
+            max_age: None,
+            other_models: true,
+            idle: Duration::ZERO,
+            interval: Duration::ZERO,
+        };
+        let mut refresher = Refresher::new(service, store.clone(), opts);
+        assert_eq!(refresher.step().await.unwrap().as_deref(), Some("run"));
+        assert_eq!(store.latest("run").unwrap().unwrap().model, "mock");
+        assert_eq!(refresher.step().await.unwrap(), None);
+
+        let activity = Arc::new(Activity::default());
+        let tracked = Tracked::new(backend, activity.clone());
+        assert!(!activity.is_idle(Duration::from_secs(60)));
+        assert!(activity.is_idle(Duration::ZERO));
+        let busy = activity.enter();
+        assert!(!activity.is_idle(Duration::ZERO));
+        drop(busy);
+        assert_eq!(tracked.model_name(), "mock");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
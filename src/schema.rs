@@ -0,0 +1,55 @@
+use crate::telemetry;
+use crate::validate::Validator;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+/// Reread the schema at `path` into `validator`, returning whether it
+/// changed. A file that cannot be read or compiled leaves the current
+/// contract in force.
+pub fn reload(validator: &Validator, path: &Path) -> Result<bool> {
+    let outcome = std::fs::read_to_string(path)
+        .with_context(|| format!("read schema {:?}", path))
+        .and_then(|src| {
+            validator
+                .reload(&src)
+                .with_context(|| format!("load schema {:?}", path))
+        });
+    telemetry::record_schema_reload(match &outcome {
+        Ok(true) => "changed",
+        Ok(false) => "unchanged",
+        Err(_) => "failed",
+    });
+    outcome
+}
+
+/// Poll `path` every `every` and reload it into `validator` whenever its
+/// modification time moves. Editors that save by rename are handled the same
+/// way; a save that leaves the file broken is logged and retried on the next
+/// change.
+pub fn spawn_watch(validator: Arc<Validator>, path: PathBuf, every: Duration) {
+    tokio::spawn(async move {
+        let mut seen = modified(&path);
+        loop {
+            tokio::time::sleep(every).await;
+            let now = modified(&path);
+            if now == seen {
+                continue;
+            }
+            seen = now;
+            match reload(&validator, &path) {
+                Ok(true) => {
+                    info!(?path, hash = %validator.schema_hash(), "word contract schema reloaded")
+                }
+                Ok(false) => {}
+                Err(e) => warn!("Keeping the current schema: {:#}", e),
+            }
+        }
+    });
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
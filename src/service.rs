@@ -0,0 +1,1734 @@
+use crate::{
+    backtranslate,
+    cache::{CacheEntry, Lookup, WordCache},
+    error::ErrorCode,
+    examples, family,
+    fewshot::{self, FewShotLibrary},
+    lenient,
+    model::{
+        BackendError, Degenerate, FewShot, Granularity, InferParams, LlmBackend, OffContract,
+        PromptParts, PromptTask, Sampling,
+    },
+    patch, pronunciation,
+    store::{CurrentEntry, EntryStore, StoredVersion},
+    telemetry,
+    validate::{
+        count_fixes, limit_meanings, reports_missing_sense, restrict_to_sense, ValidationError,
+        Validator, Violation, MAX_MEANINGS, SCHEMA_VERSION,
+    },
+};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::{debug, error, info, warn};
+
+/// The word pipeline shared by every entry point: cache and persistence
+/// lookups, input screening, inference with retries and repairs, and storing
+/// the result. Handlers only translate requests and outcomes.
+///
+/// Embedders build one around a backend and call [`WordService::analyze`]:
+///
+/// ```no_run
+/// # async fn demo() -> Result<(), lingua_fast::service::AnalyzeError> {
+/// use lingua_fast::model::mock::MockBackend;
+/// use lingua_fast::service::{AnalyzeOptions, WordService};
+/// use lingua_fast::validate::Validator;
+/// use std::sync::Arc;
+///
+/// let service = WordService::new(
+///     Arc::new(MockBackend::default()),
+///     Arc::new(Validator::new("").unwrap()),
+/// );
+/// let found = service.analyze("serendipity", &AnalyzeOptions::default()).await?;
+/// println!("{} from {:?}", found.entry, found.source);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct WordService {
+    pub backend: Arc<dyn LlmBackend>,
+    pub validator: Arc<Validator>,
+    pub params: InferParams,
+    pub cache: Arc<WordCache>,
+    pub store: Option<Arc<EntryStore>>,
+    pub system_prompt: Arc<str>,
+    pub few_shot: Arc<FewShotLibrary>,
+    /// Examples offered per prompt; the budgeter may send fewer.
+    pub few_shot_count: usize,
+    /// Prefer cached entries resembling the requested word over the fixed library.
+    pub few_shot_from_cache: bool,
+    /// Longest accepted input, in characters (not bytes) after trimming.
+    pub max_word_chars: usize,
+    /// Back-translate a sample of each generated entry's translations and
+    /// record diverging ones as quality warnings.
+    pub back_translation_check: bool,
+    /// Wall-clock time one generation may take, all retries and repairs
+    /// included, before failing with [`AnalyzeError::Timeout`].
+    pub word_time_budget: Option<Duration>,
+}
+
+/// Default for [`WordService::max_word_chars`].
+pub const DEFAULT_MAX_WORD_CHARS: usize = 100;
+
+/// Per-call options for [`WordService::analyze`].
+#[derive(Debug, Clone, Default)]
+pub struct AnalyzeOptions {
+    /// Replaces the service's system prompt. Such entries belong to the caller
+    /// alone: they are neither read from nor written to the cache or store.
+    pub system_prompt: Option<String>,
+    /// A sentence the word appeared in. The entry is generated afresh with
+    /// the sense used there listed first, then cached and stored as usual with
+    /// the hint recorded alongside the stored version. Locked entries are
+    /// served unchanged.
+    pub context: Option<String>,
+    /// Restrict the entry to its one meaning with this part of speech. Served
+    /// from the full entry when it has that sense, otherwise generated on its
+    /// own (and then neither cached nor stored).
+    pub part_of_speech: Option<String>,
+    /// Retries shared with the other words of a batch. Once spent, words
+    /// still needing generation fail at once instead of each running their
+    /// own doomed attempts.
+    pub retry_budget: Option<Arc<RetryBudget>>,
+    /// Keep only this many meanings, highest ranked first. Served from the
+    /// full entry unless `granularity` is also set.
+    pub max_meanings: Option<usize>,
+    /// Split the word's uses into senses coarsely or finely rather than the
+    /// contract's usual way. Such entries are generated on their own and
+    /// neither cached nor stored.
+    pub granularity: Option<Granularity>,
+}
+
+/// Retries several generations may still make between them.
+#[derive(Debug)]
+pub struct RetryBudget {
+    remaining: AtomicUsize,
+}
+
+impl RetryBudget {
+    pub fn new(retries: usize) -> Self {
+        Self {
+            remaining: AtomicUsize::new(retries),
+        }
+    }
+
+    /// Claim one retry; `false` once none are left.
+    pub fn take(&self) -> bool {
+        self.remaining
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| n.checked_sub(1))
+            .is_ok()
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining.load(Ordering::Acquire) == 0
+    }
+}
+
+/// Where an analyzed entry came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntrySource {
+    Cache,
+    Store,
+    Generated,
+}
+
+/// A validated entry for one word.
+#[derive(Debug, Clone, Serialize)]
+pub struct WordEntry {
+    pub word: String,
+    pub entry: Value,
+    pub source: EntrySource,
+    pub provenance: Provenance,
+    /// How generation went; `None` for cached and stored entries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attempts: Option<Attempts>,
+}
+
+/// Which model generated an entry against which schema, and when.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Provenance {
+    pub model: String,
+    pub schema_version: String,
+    /// Unix seconds
+    pub generated_at: u64,
+}
+
+impl Provenance {
+    /// An entry `model` generated just now.
+    pub fn now(model: String) -> Self {
+        Self {
+            model,
+            schema_version: SCHEMA_VERSION.to_string(),
+            generated_at: unix_secs(SystemTime::now()),
+        }
+    }
+
+    fn cached(entry: &CacheEntry) -> Self {
+        Self {
+            model: entry.model.clone(),
+            schema_version: entry.schema_version.clone(),
+            generated_at: unix_secs(entry.created_at),
+        }
+    }
+
+    fn stored(version: &StoredVersion) -> Self {
+        Self {
+            model: version.model.clone(),
+            schema_version: version.schema_version.clone(),
+            generated_at: version.created_at,
+        }
+    }
+}
+
+fn unix_secs(at: SystemTime) -> u64 {
+    at.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// Why a generated answer was thrown away and generated again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryReason {
+    /// The backend failed the inference
+    BackendError,
+    /// The output left the JSON contract while being generated
+    OffContract,
+    /// The output fell into a loop
+    Degenerate,
+    /// The output ran out of tokens inside the JSON object
+    Truncated,
+    /// The output was not JSON, even after repair
+    InvalidJson,
+    /// The JSON broke a contract rule another attempt may get right
+    Validation,
+}
+
+impl RetryReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::BackendError => "backend_error",
+            Self::OffContract => "off_contract",
+            Self::Degenerate => "degenerate",
+            Self::Truncated => "truncated",
+            Self::InvalidJson => "invalid_json",
+            Self::Validation => "validation",
+        }
+    }
+}
+
+/// The inferences behind one generated answer or generation failure.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Attempts {
+    pub attempts: usize,
+    /// Why each attempt but the last was retried, in order
+    pub retry_reasons: Vec<RetryReason>,
+    /// Values the validator corrected in the accepted output
+    pub fixes: usize,
+    /// Store hashes of each attempt's raw output, in order; the last is the
+    /// accepted one on success. Empty without persistence.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub raw_outputs: Vec<String>,
+}
+
+impl Attempts {
+    fn retry(&mut self, reason: RetryReason) {
+        telemetry::record_retry(reason.as_str());
+        self.retry_reasons.push(reason);
+    }
+}
+
+/// Why a word could not be analyzed.
+#[derive(Debug, Clone)]
+pub enum AnalyzeError {
+    InvalidInput(String),
+    NotAWord(String),
+    /// Control, zero-width or bidi formatting characters in the input.
+    InvalidCharacters(String),
+    /// Soft-deleted by an operator.
+    Deleted,
+    /// The model reports the word has no sense with the requested part of speech.
+    SenseNotFound(String),
+    /// Nothing cached or stored, and this instance has no model to generate it.
+    NotCached(String),
+    Validation {
+        error: ValidationError,
+        attempts: usize,
+    },
+    Inference(String),
+    /// Generation ran past the word's time budget.
+    Timeout(String),
+    JsonParse(String),
+    Internal(String),
+    /// `error` ended generation, after the inferences in `attempts`.
+    Generation {
+        error: Box<AnalyzeError>,
+        attempts: Attempts,
+    },
+}
+
+impl AnalyzeError {
+    fn after(self, attempts: Attempts) -> Self {
+        Self::Generation {
+            error: Box::new(self),
+            attempts,
+        }
+    }
+
+    /// The error an upstream server answered with, as this instance reports
+    /// it. Verdicts about the word pass through; anything else means the
+    /// upstream could not produce the entry.
+    pub fn from_upstream(code: ErrorCode, message: &str) -> Self {
+        let message = message.to_string();
+        match code {
+            ErrorCode::InvalidInput => Self::InvalidInput(message),
+            ErrorCode::NotAWord => Self::NotAWord(message),
+            ErrorCode::InvalidCharacters => Self::InvalidCharacters(message),
+            ErrorCode::SenseNotFound => Self::SenseNotFound(message),
+            ErrorCode::NotCached => Self::NotCached(message),
+            ErrorCode::NotFound => Self::Deleted,
+            ErrorCode::Timeout => Self::Timeout(message),
+            _ => Self::Inference(format!("Upstream failed: {} ({})", message, code)),
+        }
+    }
+
+    /// The inferences made before giving up, for failures of generation.
+    pub fn attempts(&self) -> Option<&Attempts> {
+        match self {
+            Self::Generation { attempts, .. } => Some(attempts),
+            _ => None,
+        }
+    }
+
+    /// Failures caused by what the model produced for this input, as opposed to
+    /// the backend being unavailable.
+    pub fn is_content_failure(&self) -> bool {
+        match self {
+            Self::Generation { error, .. } => error.is_content_failure(),
+            _ => matches!(self, Self::Validation { .. } | Self::JsonParse(_)),
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::Generation { error, .. } => error.code(),
+            Self::InvalidInput(_) => ErrorCode::InvalidInput,
+            Self::NotAWord(_) => ErrorCode::NotAWord,
+            Self::InvalidCharacters(_) => ErrorCode::InvalidCharacters,
+            Self::Deleted => ErrorCode::NotFound,
+            Self::SenseNotFound(_) => ErrorCode::SenseNotFound,
+            Self::NotCached(_) => ErrorCode::NotCached,
+            Self::Validation { .. } => ErrorCode::ValidationError,
+            Self::JsonParse(_) => ErrorCode::JsonParseError,
+            Self::Inference(_) => ErrorCode::InferenceError,
+            Self::Timeout(_) => ErrorCode::Timeout,
+            Self::Internal(_) => ErrorCode::InternalError,
+        }
+    }
+
+    /// Validation failures carry their own verdict; everything else follows the code.
+    pub fn retry_suggested(&self) -> bool {
+        match self {
+            Self::Validation { error, .. } => error.is_retryable(),
+            Self::Generation { error, .. } => error.retry_suggested(),
+            _ => self.code().is_retryable(),
+        }
+    }
+
+    /// Each contract violation, for validation failures.
+    pub fn details(&self) -> Option<Vec<Violation>> {
+        match self {
+            Self::Validation { error, .. } => Some(error.violations()),
+            Self::Generation { error, .. } => error.details(),
+            _ => None,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            Self::Validation { error, attempts } if *attempts > 1 => {
+                format!("Validation failed after {} attempts: {}", attempts, error)
+            }
+            Self::Validation { error, .. } => error.to_string(),
+            Self::Deleted => "Entry has been removed".to_string(),
+            Self::Generation { error, .. } => error.message(),
+            Self::InvalidInput(msg)
+            | Self::NotAWord(msg)
+            | Self::InvalidCharacters(msg)
+            | Self::SenseNotFound(msg)
+            | Self::NotCached(msg)
+            | Self::JsonParse(msg)
+            | Self::Inference(msg)
+            | Self::Timeout(msg)
+            | Self::Internal(msg) => msg.clone(),
+        }
+    }
+}
+
+impl fmt::Display for AnalyzeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message())
+    }
+}
+
+impl std::error::Error for AnalyzeError {}
+
+impl WordService {
+    /// A service with an in-memory cache, no persistence, the default system
+    /// prompt and sampling, and no few-shot examples.
+    pub fn new(backend: Arc<dyn LlmBackend>, validator: Arc<Validator>) -> Self {
+        Self {
+            backend,
+            validator,
+            params: InferParams::default(),
+            cache: Arc::new(WordCache::new(10_000, None, false)),
+            store: None,
+            system_prompt: Arc::from(crate::model::prompt::DEFAULT_SYSTEM),
+            few_shot: Arc::new(FewShotLibrary::default()),
+            few_shot_count: 0,
+            few_shot_from_cache: false,
+            max_word_chars: DEFAULT_MAX_WORD_CHARS,
+            back_translation_check: false,
+            word_time_budget: None,
+        }
+    }
+
+    pub fn with_params(mut self, params: InferParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    pub fn with_cache(mut self, cache: Arc<WordCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    pub fn with_store(mut self, store: Arc<EntryStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    pub fn with_system_prompt(mut self, system_prompt: impl Into<Arc<str>>) -> Self {
+        self.system_prompt = system_prompt.into();
+        self
+    }
+
+    pub fn with_max_word_chars(mut self, max_word_chars: usize) -> Self {
+        self.max_word_chars = max_word_chars;
+        self
+    }
+
+    pub fn with_few_shot(mut self, library: Arc<FewShotLibrary>, count: usize) -> Self {
+        self.few_shot = library;
+        self.few_shot_count = count;
+        self
+    }
+
+    /// The entry for `word`: cached or persisted when available, generated
+    /// and stored otherwise.
+    pub async fn analyze(
+        &self,
+        word: &str,
+        opts: &AnalyzeOptions,
+    ) -> Result<WordEntry, AnalyzeError> {
+        if let Some(max) = opts.max_meanings {
+            if !(1..=MAX_MEANINGS).contains(&max) {
+                return Err(AnalyzeError::InvalidInput(format!(
+                    "max_meanings must be between 1 and {}",
+                    MAX_MEANINGS
+                )));
+            }
+        }
+        // A single sense needs no limit on senses
+        let Some(pos) = opts.part_of_speech.as_deref() else {
+            if let Some(granularity) = opts.granularity {
+                return self.analyze_senses(word, opts, granularity).await;
+            }
+            let mut found = self.analyze_entry(word, opts).await?;
+            if let Some(max) = opts.max_meanings {
+                limit_meanings(&mut found.entry, max);
+            }
+            return Ok(found);
+        };
+        let pos = pos.trim().to_lowercase();
+        let allowed = self.validator.contract_profiles().parts_of_speech();
+        if !allowed.contains(&pos.as_str()) {
+            return Err(AnalyzeError::InvalidInput(format!(
+                "Unknown part of speech '{}'; expected one of: {}",
+                pos,
+                allowed.join(", ")
+            )));
+        }
+
+        let found = self.analyze_entry(word, opts).await?;
+        if let Some(entry) = restrict_to_sense(&found.entry, &pos) {
+            return Ok(WordEntry { entry, ..found });
+        }
+        // Full entries stop at four senses; ask for this one on its own
+        let system = opts.system_prompt.as_deref().unwrap_or(&self.system_prompt);
+        let task = PromptTask::Sense {
+            part_of_speech: pos,
+        };
+        let budget = opts.retry_budget.as_deref();
+        let (entry, attempts) = self
+            .run_generation(word, system, None, task, budget)
+            .await?;
+        Ok(WordEntry {
+            word: word.to_string(),
+            entry,
+            source: EntrySource::Generated,
+            provenance: Provenance::now(self.backend.model_name()),
+            attempts: Some(attempts),
+        })
+    }
+
+    /// An entry split into senses at `granularity`, generated on its own.
+    async fn analyze_senses(
+        &self,
+        word: &str,
+        opts: &AnalyzeOptions,
+        granularity: Granularity,
+    ) -> Result<WordEntry, AnalyzeError> {
+        self.check_input(word)?;
+        if let Persisted::Deleted = load_persisted(self.store.as_deref(), word) {
+            return Err(AnalyzeError::Deleted);
+        }
+        reject_non_word(word)?;
+
+        let system = opts.system_prompt.as_deref().unwrap_or(&self.system_prompt);
+        let context = opts
+            .context
+            .as_deref()
+            .map(str::trim)
+            .filter(|c| !c.is_empty());
+        let task = PromptTask::Senses {
+            max_meanings: opts.max_meanings.unwrap_or(MAX_MEANINGS),
+            granularity,
+        };
+        let budget = opts.retry_budget.as_deref();
+        let (entry, attempts) = self
+            .run_generation(word, system, context, task, budget)
+            .await?;
+        telemetry::record_served(EntrySource::Generated);
+        Ok(WordEntry {
+            word: word.to_string(),
+            entry,
+            source: EntrySource::Generated,
+            provenance: Provenance::now(self.backend.model_name()),
+            attempts: Some(attempts),
+        })
+    }
+
+    async fn analyze_entry(
+        &self,
+        word: &str,
+        opts: &AnalyzeOptions,
+    ) -> Result<WordEntry, AnalyzeError> {
+        let custom_system = opts.system_prompt.as_deref();
+        let context = opts
+            .context
+            .as_deref()
+            .map(str::trim)
+            .filter(|c| !c.is_empty());
+        // Hits may come from an entry generated for another casing of the word
+        let found = |mut entry, source, provenance| {
+            telemetry::record_served(source);
+            self.validator.fix_word(&mut entry, word);
+            WordEntry {
+                word: word.to_string(),
+                entry,
+                source,
+                provenance,
+                attempts: None,
+            }
+        };
+
+        self.check_input(word)?;
+
+        let cache = &self.cache;
+        let cached = if custom_system.is_some() || context.is_some() {
+            Lookup::Miss
+        } else {
+            cache.lookup(word)
+        };
+        match cached {
+            Lookup::Fresh(entry) => {
+                debug!("Cache hit for word: {}", word);
+                let provenance = Provenance::cached(&entry);
+                return Ok(found(entry.value, EntrySource::Cache, provenance));
+            }
+            Lookup::Stale(entry) => {
+                debug!("Serving stale cache entry for word: {}", word);
+                if cache.begin_refresh(word) {
+                    self.spawn_refresh(word.to_string());
+                }
+                let provenance = Provenance::cached(&entry);
+                return Ok(found(entry.value, EntrySource::Cache, provenance));
+            }
+            Lookup::Miss if custom_system.is_some() => {}
+            Lookup::Miss => match load_persisted(self.store.as_deref(), word) {
+                Persisted::Found { stored, locked } if locked || context.is_none() => {
+                    debug!("Serving persisted entry for word: {}", word);
+                    cache.insert(
+                        word,
+                        stored.entry.clone(),
+                        &stored.model,
+                        &stored.schema_version,
+                    );
+                    let provenance = Provenance::stored(&stored);
+                    return Ok(found(stored.entry, EntrySource::Store, provenance));
+                }
+                Persisted::Deleted => return Err(AnalyzeError::Deleted),
+                Persisted::Found { .. } | Persisted::Missing => {}
+            },
+        }
+
+        reject_non_word(word)?;
+
+        if custom_system.is_none() && cache.is_known_bad(word) {
+            debug!("Negative cache hit for word: {}", word);
+            return Err(AnalyzeError::NotAWord(
+                "Input repeatedly failed analysis and does not appear to be a word".to_string(),
+            ));
+        }
+
+        let model_name = self.backend.model_name();
+        let system = custom_system.unwrap_or(&self.system_prompt);
+        let generated = self
+            .run_generation(
+                word,
+                system,
+                context,
+                PromptTask::Entry,
+                opts.retry_budget.as_deref(),
+            )
+            .await;
+        match generated {
+            Ok((entry, attempts)) => {
+                info!("Successfully processed word: {}", word);
+                if custom_system.is_none() {
+                    cache.insert(word, entry.clone(), &model_name, SCHEMA_VERSION);
+                    let version = persist(
+                        self.store.as_deref(),
+                        word,
+                        &entry,
+                        &model_name,
+                        context,
+                        Some(&attempts),
+                    );
+                    self.spawn_back_translation(word, &entry, version);
+                }
+                Ok(WordEntry {
+                    attempts: Some(attempts),
+                    ..found(entry, EntrySource::Generated, Provenance::now(model_name))
+                })
+            }
+            Err(api_error) => {
+                error!("Failed to process word '{}': {}", word, api_error.message());
+                if custom_system.is_none() && api_error.is_content_failure() {
+                    cache.record_failure(word);
+                }
+                Err(api_error)
+            }
+        }
+    }
+
+    /// The derivational family of `word`, generated fresh on every call.
+    pub async fn family(&self, word: &str) -> Result<WordEntry, AnalyzeError> {
+        self.focused(word, PromptTask::Family).await
+    }
+
+    /// Syllabified IPA, stress and minimal pairs for `word`, generated fresh on every call.
+    pub async fn pronunciation(&self, word: &str) -> Result<WordEntry, AnalyzeError> {
+        self.focused(word, PromptTask::Pronunciation).await
+    }
+
+    /// Up to `count` new example sentences for `word`, written for the senses
+    /// of its current `entry` and different from the examples it has. Generated
+    /// on every call; the entry is not changed.
+    pub async fn examples(
+        &self,
+        word: &str,
+        entry: &Value,
+        count: usize,
+    ) -> Result<WordEntry, AnalyzeError> {
+        let meanings = entry["meanings"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let text = |v: &Value| v.as_str().unwrap_or_default().to_string();
+        let task = PromptTask::Examples {
+            count,
+            base_form: entry["baseForm"].as_str().unwrap_or(word).to_string(),
+            senses: meanings
+                .iter()
+                .map(|m| (text(&m["partOfSpeech"]), text(&m["definition"])))
+                .collect(),
+            existing: meanings
+                .iter()
+                .filter_map(|m| m["exampleSentence"].as_str().map(str::to_string))
+                .collect(),
+        };
+        self.focused(word, task).await
+    }
+
+    /// Answer a focused task about `word` that is not an entry, so it never
+    /// touches the cache or store.
+    async fn focused(&self, word: &str, task: PromptTask) -> Result<WordEntry, AnalyzeError> {
+        self.check_input(word)?;
+        if let Some(reason) = classify_input(word) {
+            return Err(AnalyzeError::NotAWord(format!(
+                "Input does not look like a word: {}",
+                reason
+            )));
+        }
+        let (entry, attempts) = self
+            .run_generation(word, &self.system_prompt, None, task, None)
+            .await?;
+        Ok(WordEntry {
+            word: word.to_string(),
+            entry,
+            source: EntrySource::Generated,
+            provenance: Provenance::now(self.backend.model_name()),
+            attempts: Some(attempts),
+        })
+    }
+
+    /// Reject empty, overlong and invisible-character input before any lookup.
+    fn check_input(&self, word: &str) -> Result<(), AnalyzeError> {
+        if word.trim().is_empty() {
+            return Err(AnalyzeError::InvalidInput(
+                "Word cannot be empty".to_string(),
+            ));
+        }
+        if let Some(reason) = hidden_characters(word) {
+            return Err(AnalyzeError::InvalidCharacters(format!(
+                "Word contains {}",
+                reason
+            )));
+        }
+        if word.trim().chars().count() > self.max_word_chars {
+            return Err(AnalyzeError::InvalidInput(format!(
+                "Word too long (max {} characters)",
+                self.max_word_chars
+            )));
+        }
+        Ok(())
+    }
+
+    /// Re-run inference for a stale cache entry without blocking the caller.
+    /// Failures keep the stale copy in place so it can be retried on the next hit.
+    pub(crate) fn spawn_refresh(&self, word: String) {
+        let service = self.clone();
+        tokio::spawn(async move {
+            let cache = &service.cache;
+            match load_persisted(service.store.as_deref(), &word) {
+                Persisted::Found {
+                    stored,
+                    locked: true,
+                } => {
+                    debug!("Skipping refresh of locked entry: {}", word);
+                    cache.insert(&word, stored.entry, &stored.model, &stored.schema_version);
+                    cache.end_refresh(&word);
+                    return;
+                }
+                Persisted::Deleted => {
+                    cache.remove(&word);
+                    cache.end_refresh(&word);
+                    return;
+                }
+                _ => {}
+            }
+
+            let model_name = service.backend.model_name();
+            match service.generate(&word, &service.system_prompt, None).await {
+                Ok((value, attempts)) => {
+                    info!("Refreshed stale cache entry for word: {}", word);
+                    let version = persist(
+                        service.store.as_deref(),
+                        &word,
+                        &value,
+                        &model_name,
+                        None,
+                        Some(&attempts),
+                    );
+                    service.spawn_back_translation(&word, &value, version);
+                    cache.insert(&word, value, &model_name, SCHEMA_VERSION);
+                }
+                Err(api_error) => {
+                    warn!(
+                        "Background refresh failed for '{}': {}",
+                        word,
+                        api_error.message()
+                    );
+                }
+            }
+            cache.end_refresh(&word);
+        });
+    }
+
+    /// Regenerate `word`'s persisted entry as a stale cache hit would, storing
+    /// a new version. Returns that version; `None` when the word is locked,
+    /// deleted, already being refreshed, or the new version failed to store.
+    pub async fn refresh_stored(&self, word: &str) -> Result<Option<u32>, AnalyzeError> {
+        if let Persisted::Found { locked: true, .. } | Persisted::Deleted =
+            load_persisted(self.store.as_deref(), word)
+        {
+            return Ok(None);
+        }
+        if !self.cache.begin_refresh(word) {
+            return Ok(None);
+        }
+        let model_name = self.backend.model_name();
+        let outcome =
+            self.generate(word, &self.system_prompt, None)
+                .await
+                .map(|(value, attempts)| {
+                    let version = persist(
+                        self.store.as_deref(),
+                        word,
+                        &value,
+                        &model_name,
+                        None,
+                        Some(&attempts),
+                    );
+                    self.spawn_back_translation(word, &value, version);
+                    self.cache.insert(word, value, &model_name, SCHEMA_VERSION);
+                    version
+                });
+        self.cache.end_refresh(word);
+        outcome
+    }
+
+    /// Examples for the prompt: the most similar cached entries first when enabled,
+    /// topped up from the configured library.
+    fn few_shot_examples(&self, word: &str) -> Vec<FewShot> {
+        let count = self.few_shot_count;
+        let mut examples = if self.few_shot_from_cache {
+            fewshot::similar_from_cache(&self.cache, word, count)
+        } else {
+            Vec::new()
+        };
+        if examples.len() < count {
+            let fill = self
+                .few_shot
+                .select(word, count)
+                .into_iter()
+                .filter(|ex| !examples.iter().any(|e| e.word == ex.word))
+                .take(count - examples.len())
+                .collect::<Vec<_>>();
+            examples.extend(fill);
+        }
+        examples
+    }
+
+    /// When [`back_translation_check`](Self::back_translation_check) is on,
+    /// ask the model in the background what a couple of the new entry's
+    /// translations mean in English. Translations whose answer has nothing to
+    /// do with the sense are logged, counted and attached to the stored
+    /// `version` as quality warnings; the entry itself is served unchanged.
+    /// A failed check is no verdict and only logged.
+    pub(crate) fn spawn_back_translation(&self, word: &str, entry: &Value, version: Option<u32>) {
+        if !self.back_translation_check {
+            return;
+        }
+        let Some(sample) = backtranslate::sample(entry, word) else {
+            return;
+        };
+        let service = self.clone();
+        let word = word.to_string();
+        let entry = entry.clone();
+        tokio::spawn(async move {
+            let prompt = PromptParts {
+                system: service.system_prompt.to_string(),
+                user_word: word.clone(),
+                examples: Vec::new(),
+                context: None,
+                contract: Default::default(),
+                task: PromptTask::BackTranslation {
+                    items: sample.items.clone(),
+                },
+            };
+            let answer = match service.backend.infer_json(prompt, &service.params).await {
+                Ok(bytes) => serde_json::from_slice(&bytes)
+                    .ok()
+                    .or_else(|| lenient::parse(&bytes)),
+                Err(e) => {
+                    debug!("Back-translation check failed for '{}': {:#}", word, e);
+                    return;
+                }
+            };
+            let Some(answer) = answer else {
+                debug!("Back-translation check for '{}' returned no JSON", word);
+                return;
+            };
+            let warnings = backtranslate::warnings(&answer, &entry, &word, &sample);
+            for warning in &warnings {
+                warn!("Quality warning for '{}': {}", word, warning);
+                telemetry::record_quality_warning("back_translation");
+            }
+            let (Some(store), Some(version)) = (service.store.as_deref(), version) else {
+                return;
+            };
+            if warnings.is_empty() {
+                return;
+            }
+            if let Err(e) = store.add_warnings(&word, version, &warnings) {
+                error!("Failed to record quality warnings for '{}': {:#}", word, e);
+            }
+        });
+    }
+
+    /// Fill in missing translations with small focused inferences, one per
+    /// affected meaning, instead of regenerating an otherwise valid entry.
+    /// `None` when something else is wrong too or the repair falls short.
+    async fn repair_translations(&self, word: &str, system: &str, entry: &Value) -> Option<Value> {
+        let gaps = self.validator.translation_gaps(entry, word)?;
+        let mut patched = entry.clone();
+        for gap in gaps {
+            let prompt = PromptParts {
+                system: system.to_string(),
+                user_word: word.to_string(),
+                examples: Vec::new(),
+                context: None,
+                contract: Default::default(),
+                task: PromptTask::Translations {
+                    part_of_speech: gap.part_of_speech,
+                    definition: gap.definition,
+                    languages: gap.languages.iter().map(|l| l.to_string()).collect(),
+                },
+            };
+            let filled = match self.backend.infer_json(prompt, &self.params).await {
+                Ok(bytes) => serde_json::from_slice::<Value>(&bytes).ok()?,
+                Err(e) => {
+                    warn!("Translation repair failed for '{}': {:#}", word, e);
+                    return None;
+                }
+            };
+            let translations = patched["meanings"][gap.meaning]
+                .as_object_mut()?
+                .entry("translations")
+                .or_insert_with(|| json!({}));
+            for lang in gap.languages {
+                let text = filled
+                    .get(lang)
+                    .and_then(Value::as_str)
+                    .map(str::trim)
+                    .filter(|t| !t.is_empty())?;
+                translations[lang] = Value::String(text.to_string());
+            }
+        }
+        match self.validator.validate_and_fix(patched, word) {
+            Ok(repaired) => {
+                info!("Repaired missing translations for '{}'", word);
+                Some(repaired)
+            }
+            Err(e) => {
+                warn!("Translation repair for '{}' still invalid: {}", word, e);
+                None
+            }
+        }
+    }
+
+    /// Generate a fresh entry with retries and repairs, bypassing the cache
+    /// and entry versions; only raw outputs are kept.
+    pub async fn generate(
+        &self,
+        word: &str,
+        system: &str,
+        context: Option<&str>,
+    ) -> Result<(Value, Attempts), AnalyzeError> {
+        self.check_input(word)?;
+        reject_non_word(word)?;
+        self.run_generation(word, system, context, PromptTask::Entry, None)
+            .await
+    }
+
+    /// A new value for one `field` of `word`'s `entry`, a path
+    /// [`patch::field_pointer`] accepts. Retried, timed and kept like a whole
+    /// entry; the value has the field's current JSON type.
+    pub async fn generate_field(
+        &self,
+        word: &str,
+        field: &str,
+        entry: &Value,
+    ) -> Result<(Value, Attempts), AnalyzeError> {
+        self.check_input(word)?;
+        let task = PromptTask::Field {
+            path: field.to_string(),
+            entry: entry.clone(),
+        };
+        let (mut reply, attempts) = self
+            .run_generation(word, &self.system_prompt, None, task, None)
+            .await?;
+        Ok((reply["value"].take(), attempts))
+    }
+
+    /// Generate an entry holding only the `part_of_speech` sense of `word`.
+    pub async fn generate_sense(
+        &self,
+        word: &str,
+        system: &str,
+        part_of_speech: &str,
+    ) -> Result<Value, AnalyzeError> {
+        let task = PromptTask::Sense {
+            part_of_speech: part_of_speech.to_string(),
+        };
+        self.run_generation(word, system, None, task, None)
+            .await
+            .map(|(entry, _)| entry)
+    }
+
+    /// Prompt for `task`, retrying transient failures and validating the
+    /// answer with the task's rules. Retries also draw on `budget` when given.
+    async fn run_generation(
+        &self,
+        word: &str,
+        system: &str,
+        context: Option<&str>,
+        task: PromptTask,
+        budget: Option<&RetryBudget>,
+    ) -> Result<(Value, Attempts), AnalyzeError> {
+        const MAX_RETRIES: usize = 2;
+        /// Output that fails to parse or validate may also climb to the top
+        /// of the sampling ladder
+        const MAX_CLIMBS: usize = 3;
+        const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+        if budget.is_some_and(RetryBudget::is_exhausted) {
+            return Err(AnalyzeError::Inference(
+                "Skipped: the batch used up its retry budget, so the model is failing repeatedly"
+                    .to_string(),
+            ));
+        }
+        let retry = |attempt: usize| attempt < MAX_RETRIES && budget.is_none_or(RetryBudget::take);
+        let climb = |attempt: usize, sampling: Sampling| {
+            sampling
+                .next()
+                .filter(|_| attempt < MAX_CLIMBS && budget.is_none_or(RetryBudget::take))
+        };
+
+        let prompt = PromptParts {
+            system: system.to_string(),
+            user_word: word.to_string(),
+            // Examples are whole entries, which only help when asking for one
+            examples: match task {
+                PromptTask::Entry | PromptTask::Sense { .. } | PromptTask::Senses { .. } => {
+                    self.few_shot_examples(word)
+                }
+                _ => Vec::new(),
+            },
+            context: context.map(str::to_string),
+            contract: self.validator.contract_profiles().clone(),
+            task,
+        };
+        let mut params = self.params.clone();
+        let mut sampling = Sampling::Configured;
+        let mut tried = Attempts::default();
+
+        // Retries stop once the budget is spent, and a running inference is
+        // abandoned at the deadline wherever the backend awaits
+        let deadline = self
+            .word_time_budget
+            .map(|budget| tokio::time::Instant::now() + budget);
+        let out_of_time = |tried: Attempts| {
+            let budget = self.word_time_budget.unwrap_or_default();
+            warn!("Gave up on '{}' after its {:?} time budget", word, budget);
+            let error = AnalyzeError::Timeout(format!(
+                "Generating '{}' took longer than its {:?} time budget",
+                word, budget
+            ));
+            error.after(tried)
+        };
+        // The pause between attempts, cut short at the deadline
+        let backoff = || {
+            let until = tokio::time::Instant::now() + RETRY_DELAY;
+            tokio::time::sleep_until(deadline.map_or(until, |deadline| deadline.min(until)))
+        };
+
+        for attempt in 0..=MAX_CLIMBS {
+            if deadline.is_some_and(|deadline| tokio::time::Instant::now() >= deadline) {
+                return Err(out_of_time(tried));
+            }
+            debug!("Inference attempt {} for word: {}", attempt + 1, word);
+            tried.attempts = attempt + 1;
+
+            let inference = self.backend.infer_json(prompt.clone(), &params);
+            let inference_result = match deadline {
+                Some(deadline) => match tokio::time::timeout_at(deadline, inference).await {
+                    Ok(result) => result,
+                    Err(_) => return Err(out_of_time(tried)),
+                },
+                None => inference.await,
+            }
+            .context("LLM inference failed");
+
+            // The upstream already retried; its verdict stands
+            if let Some(BackendError::Upstream { code, message }) = inference_result
+                .as_ref()
+                .err()
+                .and_then(|e| e.downcast_ref())
+            {
+                debug!("Upstream answered {} for '{}': {}", code, word, message);
+                return Err(AnalyzeError::from_upstream(*code, message));
+            }
+            let bytes = match inference_result {
+                Ok(bytes) => bytes,
+                // A panic may have left the backend inconsistent; don't feed it retries
+                Err(e) if matches!(e.downcast_ref(), Some(BackendError::Panicked(_))) => {
+                    error!("Inference for '{}' panicked: {:#}", word, e);
+                    return Err(AnalyzeError::Internal(format!("{:#}", e)));
+                }
+                Err(e) if matches!(e.downcast_ref(), Some(BackendError::NoModel)) => {
+                    debug!(
+                        "No stored entry for '{}' and no model to generate one",
+                        word
+                    );
+                    return Err(AnalyzeError::NotCached(format!(
+                        "'{}' is not cached, and this instance has no model to generate it",
+                        word
+                    )));
+                }
+                // Retrying a full queue only adds to it; shed load and let the client back off
+                Err(e) if e.downcast_ref::<BackendError>().is_some() => {
+                    warn!("Rejecting '{}': {:#}", word, e);
+                    return Err(AnalyzeError::Inference(format!("Server is busy: {:#}", e)));
+                }
+                Err(e) => {
+                    warn!(
+                        "Inference attempt {} failed for '{}': {}",
+                        attempt + 1,
+                        word,
+                        e
+                    );
+                    if retry(attempt) {
+                        if e.downcast_ref::<Degenerate>().is_some() {
+                            // The same sampling would likely loop again
+                            params = params.against_repetition();
+                            debug!("Retrying '{}' with stronger repetition penalties", word);
+                            tried.retry(RetryReason::Degenerate);
+                        } else if e.downcast_ref::<OffContract>().is_some() {
+                            // Stopped early for going off contract: nothing to wait out
+                            tried.retry(RetryReason::OffContract);
+                        } else {
+                            tried.retry(RetryReason::BackendError);
+                            backoff().await;
+                        }
+                        continue;
+                    }
+                    let error = AnalyzeError::Inference(format!(
+                        "LLM inference failed after {} attempts: {}",
+                        attempt + 1,
+                        e
+                    ));
+                    return Err(error.after(tried));
+                }
+            };
+
+            // Kept for forensics whatever becomes of it
+            if let Some(store) = &self.store {
+                match store.put_raw(&bytes) {
+                    Ok(hash) => {
+                        debug!(
+                            "Raw output {} for '{}' on attempt {}",
+                            hash,
+                            word,
+                            attempt + 1
+                        );
+                        tried.raw_outputs.push(hash);
+                    }
+                    Err(e) => warn!("Failed to keep raw output for '{}': {:#}", word, e),
+                }
+            }
+
+            // Parse JSON. An answer cut off by the token budget is retried with a
+            // bigger one while attempts remain, since the same budget would cut it
+            // off again; other near-JSON, common from small models, is repaired
+            let can_retry = attempt < MAX_RETRIES && !budget.is_some_and(RetryBudget::is_exhausted);
+            let truncated = can_retry
+                && crate::model::prompt::is_truncated_json(&String::from_utf8_lossy(&bytes));
+            let parsed: serde_json::Result<Value> = serde_json::from_slice(&bytes).or_else(|e| {
+                let repaired = lenient::parse(&bytes).filter(|_| !truncated).ok_or(e)?;
+                debug!("Repaired malformed JSON for '{}'", word);
+                telemetry::record_json_repair();
+                Ok(repaired)
+            });
+            let json_value = match parsed {
+                Ok(v) => v,
+                Err(e) => {
+                    warn!(
+                        "JSON parsing failed for '{}' on attempt {}: {}",
+                        word,
+                        attempt + 1,
+                        e
+                    );
+                    if truncated {
+                        params.max_tokens = params.max_tokens.saturating_mul(2);
+                        warn!(
+                            "Output for '{}' was truncated; retrying with max_tokens {}",
+                            word, params.max_tokens
+                        );
+                        if retry(attempt) {
+                            tried.retry(RetryReason::Truncated);
+                            backoff().await;
+                            continue;
+                        }
+                    } else if let Some(next) = climb(attempt, sampling) {
+                        (sampling, params) = (next, next.apply(&params));
+                        debug!("Retrying '{}' with {} sampling", word, next.as_str());
+                        tried.retry(RetryReason::InvalidJson);
+                        backoff().await;
+                        continue;
+                    }
+                    let error =
+                        AnalyzeError::JsonParse(format!("Failed to parse JSON response: {}", e));
+                    return Err(error.after(tried));
+                }
+            };
+
+            // Validate and fix
+            let validated = match &prompt.task {
+                PromptTask::Sense { part_of_speech } => {
+                    if reports_missing_sense(&json_value) {
+                        debug!("Model reports no {} sense for '{}'", part_of_speech, word);
+                        return Err(AnalyzeError::SenseNotFound(format!(
+                            "'{}' has no {} sense",
+                            word, part_of_speech
+                        )));
+                    }
+                    self.validator
+                        .validate_sense(json_value.clone(), word, part_of_speech)
+                }
+                PromptTask::Senses { max_meanings, .. } => {
+                    self.validator
+                        .validate_senses(json_value.clone(), word, *max_meanings)
+                }
+                PromptTask::Family => family::validate(
+                    json_value.clone(),
+                    word,
+                    &self.validator.contract_profiles().parts_of_speech(),
+                ),
+                PromptTask::Pronunciation => pronunciation::validate(json_value.clone(), word),
+                PromptTask::Field { path, entry } => {
+                    let value = json_value.get("value").unwrap_or(&Value::Null);
+                    let current = patch::field_pointer(path)
+                        .and_then(|pointer| entry.pointer(&pointer))
+                        .unwrap_or(&Value::Null);
+                    if std::mem::discriminant(value) == std::mem::discriminant(current) {
+                        Ok(json!({ "value": value }))
+                    } else {
+                        Err(ValidationError::Malformed(format!(
+                            "new value for '{}' is missing or has the wrong JSON type",
+                            path
+                        )))
+                    }
+                }
+                PromptTask::Examples {
+                    count,
+                    base_form,
+                    senses,
+                    existing,
+                } => {
+                    let parts: Vec<&str> = senses.iter().map(|(pos, _)| pos.as_str()).collect();
+                    examples::validate(
+                        json_value.clone(),
+                        word,
+                        &[word, base_form],
+                        &parts,
+                        existing,
+                        *count,
+                    )
+                }
+                _ => self.validator.validate_and_fix(json_value.clone(), word),
+            };
+            match validated {
+                Ok(validated) => {
+                    debug!(
+                        "Successfully processed '{}' on attempt {}",
+                        word,
+                        attempt + 1
+                    );
+                    tried.fixes = count_fixes(&json_value, &validated);
+                    telemetry::record_sampling_success(sampling.as_str());
+                    return Ok((validated, tried));
+                }
+                Err(e @ ValidationError::SchemaUnavailable(_)) => {
+                    return Err(AnalyzeError::Internal(e.to_string()));
+                }
+                Err(e) if !e.is_retryable() => {
+                    if e.is_repairable() && matches!(prompt.task, PromptTask::Entry) {
+                        let repair = self.repair_translations(word, system, &json_value);
+                        let repaired = match deadline {
+                            Some(deadline) => match tokio::time::timeout_at(deadline, repair).await
+                            {
+                                Ok(repaired) => repaired,
+                                Err(_) => return Err(out_of_time(tried)),
+                            },
+                            None => repair.await,
+                        };
+                        if let Some(repaired) = repaired {
+                            tried.fixes = count_fixes(&json_value, &repaired);
+                            telemetry::record_sampling_success(sampling.as_str());
+                            return Ok((repaired, tried));
+                        }
+                    }
+                    warn!("Validation failed for '{}': {}", word, e);
+                    let error = AnalyzeError::Validation {
+                        error: e,
+                        attempts: attempt + 1,
+                    };
+                    return Err(error.after(tried));
+                }
+                Err(e) => {
+                    warn!(
+                        "Validation attempt {} failed for '{}': {}",
+                        attempt + 1,
+                        word,
+                        e
+                    );
+                    if let Some(next) = climb(attempt, sampling) {
+                        (sampling, params) = (next, next.apply(&params));
+                        debug!("Retrying '{}' with {} sampling", word, next.as_str());
+                        tried.retry(RetryReason::Validation);
+                        backoff().await;
+                        continue;
+                    }
+                    let error = AnalyzeError::Validation {
+                        error: e,
+                        attempts: attempt + 1,
+                    };
+                    return Err(error.after(tried));
+                }
+            }
+        }
+
+        Err(AnalyzeError::Internal(
+            "Unexpected end of retry loop".to_string(),
+        ))
+    }
+}
+
+/// What persistence knows about a word before any inference happens.
+pub(crate) enum Persisted {
+    /// A usable stored version; `locked` entries must never be regenerated.
+    Found {
+        stored: Box<StoredVersion>,
+        locked: bool,
+    },
+    /// Soft-deleted by an operator.
+    Deleted,
+    Missing,
+}
+
+/// Latest persisted version of a word. Model-generated versions from an older
+/// schema are ignored, but curated (locked) entries are always served.
+pub(crate) fn load_persisted(store: Option<&EntryStore>, word: &str) -> Persisted {
+    let Some(store) = store else {
+        return Persisted::Missing;
+    };
+    match store.current(word) {
+        Ok(CurrentEntry { flags, .. }) if flags.deleted => Persisted::Deleted,
+        Ok(CurrentEntry {
+            latest: Some(stored),
+            flags,
+        }) if flags.locked || stored.schema_version == SCHEMA_VERSION => Persisted::Found {
+            stored: Box::new(stored),
+            locked: flags.locked,
+        },
+        Ok(_) => Persisted::Missing,
+        Err(e) => {
+            warn!("Failed to load persisted entry for '{}': {:#}", word, e);
+            Persisted::Missing
+        }
+    }
+}
+
+/// Append a new version when persistence is enabled, linked to the raw output
+/// `attempts` accepted. Storage failures are logged, not surfaced, since the
+/// entry itself was produced successfully.
+pub(crate) fn persist(
+    store: Option<&EntryStore>,
+    word: &str,
+    entry: &Value,
+    model: &str,
+    context: Option<&str>,
+    attempts: Option<&Attempts>,
+) -> Option<u32> {
+    let raw_output = attempts
+        .and_then(|a| a.raw_outputs.last())
+        .map(String::as_str);
+    match store?.append_generated(word, entry, model, SCHEMA_VERSION, context, raw_output) {
+        Ok(version) => Some(version),
+        Err(e) => {
+            error!("Failed to persist entry for '{}': {:#}", word, e);
+            None
+        }
+    }
+}
+
+/// Characters a client almost never means to send: control characters,
+/// invisible zero-width and bidi formatting marks. Returns what was found.
+/// ZWJ/ZWNJ are allowed between letters, where scripts such as Persian and
+/// Devanagari need them.
+fn hidden_characters(input: &str) -> Option<&'static str> {
+    let chars: Vec<char> = input.trim().chars().collect();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_control() {
+            return Some("control characters");
+        }
+        match c {
+            '\u{200C}' | '\u{200D}' => {
+                let between_letters = i > 0
+                    && chars[i - 1].is_alphabetic()
+                    && chars.get(i + 1).is_some_and(|n| n.is_alphabetic());
+                if !between_letters {
+                    return Some("zero-width characters");
+                }
+            }
+            '\u{200B}' | '\u{2060}' | '\u{FEFF}' | '\u{00AD}' | '\u{180E}' => {
+                return Some("zero-width characters");
+            }
+            '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}' | '\u{200E}' | '\u{200F}' => {
+                return Some("bidirectional formatting characters");
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// [`classify_input`] as the error analysis answers with.
+fn reject_non_word(word: &str) -> Result<(), AnalyzeError> {
+    match classify_input(word) {
+        Some(reason) => {
+            debug!("Rejected non-word input '{}': {}", word, reason);
+            Err(AnalyzeError::NotAWord(format!(
+                "Input does not look like a word: {}",
+                reason
+            )))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Cheap pre-inference guard: returns why the input is clearly not a word
+/// (numbers, URLs, code, sentences) so it never reaches the model.
+fn classify_input(input: &str) -> Option<&'static str> {
+    const CODE_CHARS: &[char] = &[
+        '{', '}', '[', ']', '<', '>', ';', '=', '(', ')', '|', '\\', '`', '$',
+    ];
+    const MAX_WORDS: usize = 4;
+    /// Abbreviations whose period does not end a sentence
+    const ABBREVIATIONS: &[&str] = &[
+        "dr.", "e.g.", "etc.", "i.e.", "jr.", "mr.", "mrs.", "ms.", "mt.", "no.", "prof.", "sr.",
+        "st.", "vs.",
+    ];
+
+    let s = input.trim();
+    let lower = s.to_lowercase();
+
+    if !s.chars().any(char::is_alphabetic) {
+        return Some("contains no letters");
+    }
+    if lower.contains("://") || lower.starts_with("www.") || s.contains('@') {
+        return Some("looks like a URL or email address");
+    }
+    if s.contains(CODE_CHARS) || s.contains("::") || s.contains("->") {
+        return Some("looks like a code fragment");
+    }
+    // Numbers with a stray letter ("1234x"), but not "3D" or "4K": a run of
+    // two letters counts as a word, and so does a letter per digit
+    let letters = s.chars().filter(|c| c.is_alphabetic()).count();
+    let digits = s.chars().filter(char::is_ascii_digit).count();
+    let has_letter_run = s
+        .chars()
+        .zip(s.chars().skip(1))
+        .any(|(a, b)| a.is_alphabetic() && b.is_alphabetic());
+    if !has_letter_run && digits > letters {
+        return Some("is mostly numeric");
+    }
+    // A terminator followed by more text means at least two sentences, unless
+    // it ends an abbreviation ("St. Louis") or an initial ("J. Smith")
+    let mut tokens = s.split_whitespace().peekable();
+    while let Some(token) = tokens.next() {
+        if tokens.peek().is_none() || !token.ends_with(['.', '!', '?']) {
+            continue;
+        }
+        let token = token.to_lowercase();
+        let initial = token.len() == 2 && token.starts_with(char::is_alphabetic);
+        if !(token.ends_with('.') && (initial || ABBREVIATIONS.contains(&token.as_str()))) {
+            return Some("contains multiple sentences");
+        }
+    }
+    if s.split_whitespace().count() > MAX_WORDS {
+        return Some("is a phrase rather than a word");
+    }
+    // Keyboard mashing like "aaaaaa" or "!!!!!"
+    let mut run = 1;
+    let mut prev = None;
+    for c in s.chars() {
+        run = if Some(c) == prev { run + 1 } else { 1 };
+        if run >= 4 {
+            return Some("repeats the same character");
+        }
+        prev = Some(c);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CasePolicy;
+    use crate::model::mock::MockBackend;
+
+    fn service() -> WordService {
+        WordService::new(
+            Arc::new(MockBackend::default()),
+            Arc::new(Validator::new("").unwrap()),
+        )
+    }
+
+    #[tokio::test]
+    async fn analyze_generates_once_then_serves_from_cache() {
+        let service = service();
+        let opts = AnalyzeOptions::default();
+        let first = service.analyze("harbor", &opts).await.unwrap();
+        assert_eq!(first.source, EntrySource::Generated);
+        assert_eq!(first.entry["word"], "harbor");
+        let second = service.analyze("harbor", &opts).await.unwrap();
+        assert_eq!(second.source, EntrySource::Cache);
+
+        // A caller's own prompt never touches the shared cache
+        let custom = AnalyzeOptions {
+            system_prompt: Some("Be brief.".to_string()),
+            ..AnalyzeOptions::default()
+        };
+        let own = service.analyze("harbor", &custom).await.unwrap();
+        assert_eq!(own.source, EntrySource::Generated);
+    }
+
+    #[tokio::test]
+    async fn sense_requests_accept_parts_of_speech_from_any_profile() {
+        let particle = AnalyzeOptions {
+            part_of_speech: Some("particle".to_string()),
+            ..AnalyzeOptions::default()
+        };
+        assert!(matches!(
+            service().analyze("ne", &particle).await,
+            Err(AnalyzeError::InvalidInput(_))
+        ));
+
+        let profiles = crate::contract::ContractProfiles::from_json(
+            r#"{ "japanese": { "parts_of_speech": ["noun", "particle"], "difficulties": ["n5"], "required_meaning_fields": [] } }"#,
+        )
+        .unwrap();
+        let validator = Validator::new("").unwrap().with_contract_profiles(profiles);
+        let service = WordService::new(Arc::new(MockBackend::default()), Arc::new(validator));
+        // Past the check; the mock's entries have no particle sense
+        assert!(matches!(
+            service.analyze("ne", &particle).await,
+            Err(AnalyzeError::SenseNotFound(_))
+        ));
+    }
+
+    /// Cuts its answer off unless given more than the default token budget.
+    #[derive(Default)]
+    struct TightBudget {
+        budgets: parking_lot::Mutex<Vec<i32>>,
+    }
+
+    #[async_trait::async_trait]
+    impl LlmBackend for TightBudget {
+        async fn infer_json(
+            &self,
+            prompt: PromptParts,
+            params: &InferParams,
+        ) -> anyhow::Result<Vec<u8>> {
+            self.budgets.lock().push(params.max_tokens);
+            let full = MockBackend::default().infer_json(prompt, params).await?;
+            if params.max_tokens > InferParams::default().max_tokens {
+                Ok(full)
+            } else {
+                Ok(full[..full.len() / 2].to_vec())
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn truncated_output_is_retried_with_a_bigger_budget() {
+        let backend = Arc::new(TightBudget::default());
+        let service = WordService::new(backend.clone(), Arc::new(Validator::new("").unwrap()));
+        let found = service
+            .analyze("harbor", &AnalyzeOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(found.entry["word"], "harbor");
+        assert_eq!(*backend.budgets.lock(), [1024, 2048]);
+    }
+
+    /// Rambles in prose unless held to JSON by a grammar.
+    #[derive(Default)]
+    struct NeedsGrammar {
+        sampled: parking_lot::Mutex<Vec<(f32, bool)>>,
+    }
+
+    #[async_trait::async_trait]
+    impl LlmBackend for NeedsGrammar {
+        async fn infer_json(
+            &self,
+            prompt: PromptParts,
+            params: &InferParams,
+        ) -> anyhow::Result<Vec<u8>> {
+            self.sampled.lock().push((params.temp, params.json_grammar));
+            if params.json_grammar {
+                MockBackend::default().infer_json(prompt, params).await
+            } else {
+                Ok(b"The word harbor means a sheltered port.".to_vec())
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn failing_output_is_retried_with_ever_more_conservative_sampling() {
+        let backend = Arc::new(NeedsGrammar::default());
+        let service = WordService::new(backend.clone(), Arc::new(Validator::new("").unwrap()));
+        let found = service
+            .analyze("harbor", &AnalyzeOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(found.entry["word"], "harbor");
+        let attempts = found.attempts.unwrap();
+        assert_eq!(attempts.attempts, 4);
+        assert_eq!(attempts.retry_reasons, [RetryReason::InvalidJson; 3]);
+        assert_eq!(
+            *backend.sampled.lock(),
+            [(0.4, false), (0.2, false), (0.0, false), (0.0, true)]
+        );
+    }
+
+    /// Takes longer than any time budget in these tests.
+    struct Stalled;
+
+    #[async_trait::async_trait]
+    impl LlmBackend for Stalled {
+        async fn infer_json(&self, _: PromptParts, _: &InferParams) -> anyhow::Result<Vec<u8>> {
+            tokio::time::sleep(Duration::from_secs(30)).await;
+            anyhow::bail!("never answers in time")
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn generation_stops_at_the_word_time_budget() {
+        let mut service =
+            WordService::new(Arc::new(Stalled), Arc::new(Validator::new("").unwrap()));
+        service.word_time_budget = Some(Duration::from_millis(20));
+        let err = service
+            .analyze("harbor", &AnalyzeOptions::default())
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::Timeout);
+        assert!(err.retry_suggested());
+        assert_eq!(err.attempts().unwrap().attempts, 1);
+
+        // Retries are not started once the budget is spent, and the pause
+        // before one ends at the deadline rather than overrunning it
+        let backend = Arc::new(NeedsGrammar::default());
+        let mut service = WordService::new(backend.clone(), Arc::new(Validator::new("").unwrap()));
+        service.word_time_budget = Some(Duration::from_millis(700));
+        let started = tokio::time::Instant::now();
+        let err = service
+            .analyze("harbor", &AnalyzeOptions::default())
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::Timeout);
+        assert_eq!(backend.sampled.lock().len(), 2);
+        assert_eq!(started.elapsed(), Duration::from_millis(700));
+    }
+
+    /// Answers with a trailing comma, as small models often do.
+    struct TrailingComma;
+
+    #[async_trait::async_trait]
+    impl LlmBackend for TrailingComma {
+        async fn infer_json(
+            &self,
+            prompt: PromptParts,
+            params: &InferParams,
+        ) -> anyhow::Result<Vec<u8>> {
+            let mut out = MockBackend::default().infer_json(prompt, params).await?;
+            out.pop();
+            out.extend_from_slice(b",}");
+            Ok(out)
+        }
+    }
+
+    #[tokio::test]
+    async fn near_json_is_repaired_instead_of_failing() {
+        let service = WordService::new(
+            Arc::new(TrailingComma),
+            Arc::new(Validator::new("").unwrap()),
+        );
+        let found = service
+            .analyze("harbor", &AnalyzeOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(found.entry["word"], "harbor");
+    }
+
+    #[tokio::test]
+    async fn analyze_screens_input_before_inference() {
+        let service = service();
+        let opts = AnalyzeOptions::default();
+        let err = service.analyze("  ", &opts).await.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InvalidInput);
+        let err = service.analyze("12345", &opts).await.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::NotAWord);
+        assert!(!err.retry_suggested());
+    }
+
+    #[tokio::test]
+    async fn length_is_counted_in_characters_and_hidden_ones_rejected() {
+        let service = service().with_max_word_chars(5);
+        let opts = AnalyzeOptions::default();
+        // Three characters, six bytes
+        service.analyze("мир", &opts).await.unwrap();
+        let err = service.analyze("словарь", &opts).await.unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InvalidInput);
+
+        for junk in ["swim\u{200B}", "a\nb", "\u{202E}abc", "\u{FEFF}tab"] {
+            let err = service.analyze(junk, &opts).await.unwrap_err();
+            assert_eq!(err.code(), ErrorCode::InvalidCharacters, "{:?}", junk);
+        }
+        // ZWNJ inside a Persian word is spelling, not junk
+        assert_eq!(hidden_characters("می\u{200C}خواهم"), None);
+        assert!(hidden_characters("\u{200C}swim").is_some());
+    }
+
+    #[tokio::test]
+    async fn folded_casings_share_an_entry_but_echo_the_request() {
+        let service = WordService::new(
+            Arc::new(MockBackend::default()),
+            Arc::new(
+                Validator::new("")
+                    .unwrap()
+                    .with_case_policy(CasePolicy::Fold),
+            ),
+        )
+        .with_cache(Arc::new(
+            WordCache::new(10, None, false).with_case_policy(CasePolicy::Fold),
+        ));
+        let opts = AnalyzeOptions::default();
+        let first = service.analyze("Polish", &opts).await.unwrap();
+        assert_eq!(first.entry["word"], "Polish");
+        let second = service.analyze("polish", &opts).await.unwrap();
+        assert_eq!(second.source, EntrySource::Cache);
+        assert_eq!(second.entry["word"], "polish");
+    }
+}
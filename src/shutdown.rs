@@ -0,0 +1,271 @@
+use crate::cache::WordCache;
+use crate::quota::{SavedUsage, UsageTracker};
+use crate::store::{content_hash, EntryStore};
+use anyhow::{Context, Result};
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+use tracing::warn;
+
+/// Usage counters written on shutdown, next to `entries/` in DATA_DIR.
+pub const USAGE_FILE: &str = "usage.json";
+
+/// Resolves on Ctrl-C or, on Unix, SIGTERM (what `docker stop`, systemd and
+/// Kubernetes send before killing the process).
+pub async fn signal() {
+    let interrupt = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("cannot listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => {
+                term.recv().await;
+            }
+            Err(e) => {
+                warn!("cannot listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = interrupt => {}
+        _ = terminate => {}
+    }
+}
+
+/// What [`ShutdownHook::run`] saved.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// Cached entries the store lacked, now appended as new versions
+    pub flushed: usize,
+    /// Cached entries already stored, or superseded by a newer stored version
+    pub already_stored: usize,
+    /// Cached entries of locked or deleted words, left as the operator set them
+    pub skipped: usize,
+    /// Cached entries the store failed to write
+    pub failed: usize,
+    /// Keys whose usage counters were written; `None` without a usage file
+    pub usage_keys: Option<usize>,
+}
+
+impl fmt::Display for ShutdownReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "flushed {} cached entries to the store ({} already stored, {} locked or deleted, {} failed)",
+            self.flushed, self.already_stored, self.skipped, self.failed
+        )?;
+        if let Some(keys) = self.usage_keys {
+            write!(f, "; saved usage for {} keys", keys)?;
+        }
+        Ok(())
+    }
+}
+
+/// State kept in memory that a restart would otherwise lose, written out
+/// once the server has stopped taking requests: cached entries persistence
+/// never saw (a failed write, or a cache filled before DATA_DIR was set) and
+/// per-key usage counters, so quotas are not reset by a redeploy.
+pub struct ShutdownHook {
+    cache: Arc<WordCache>,
+    store: Option<Arc<EntryStore>>,
+    /// Each tenant's cache and the store it flushes into
+    tenants: Vec<(Arc<WordCache>, Arc<EntryStore>)>,
+    usage: Arc<UsageTracker>,
+    usage_file: Option<PathBuf>,
+}
+
+impl ShutdownHook {
+    pub fn new(cache: Arc<WordCache>, usage: Arc<UsageTracker>) -> Self {
+        Self {
+            cache,
+            store: None,
+            tenants: Vec::new(),
+            usage,
+            usage_file: None,
+        }
+    }
+
+    /// Flush cached entries into `store`.
+    pub fn with_store(mut self, store: Arc<EntryStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Also flush a tenant's cached entries into its own store.
+    pub fn with_tenant(mut self, cache: Arc<WordCache>, store: Arc<EntryStore>) -> Self {
+        self.tenants.push((cache, store));
+        self
+    }
+
+    /// Write usage counters to `path`, and read them back with
+    /// [`restore_usage`](Self::restore_usage).
+    pub fn with_usage_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.usage_file = Some(path.into());
+        self
+    }
+
+    /// Take over the counters an earlier shutdown wrote, returning how many
+    /// keys were restored. A missing file restores nothing.
+    pub fn restore_usage(&self) -> Result<usize> {
+        let Some(path) = &self.usage_file else {
+            return Ok(0);
+        };
+        let raw = match fs::read(path) {
+            Ok(raw) => raw,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e).with_context(|| format!("read {:?}", path)),
+        };
+        let saved: Vec<SavedUsage> =
+            serde_json::from_slice(&raw).with_context(|| format!("parse {:?}", path))?;
+        Ok(self.usage.restore(saved))
+    }
+
+    /// Save everything; failures are logged and counted, never fatal, so one
+    /// bad write does not cost the rest.
+    pub fn run(&self) -> ShutdownReport {
+        let mut report = ShutdownReport::default();
+        if let Some(store) = &self.store {
+            flush_cache(&self.cache, store, &mut report);
+        }
+        for (cache, store) in &self.tenants {
+            flush_cache(cache, store, &mut report);
+        }
+        if let Some(path) = &self.usage_file {
+            let saved = self.usage.save();
+            match write_json(path, &saved) {
+                Ok(()) => report.usage_keys = Some(saved.len()),
+                Err(e) => warn!("Failed to save usage counters: {:#}", e),
+            }
+        }
+        report
+    }
+}
+
+fn flush_cache(cache: &WordCache, store: &EntryStore, report: &mut ShutdownReport) {
+    for (word, cached) in cache.snapshot() {
+        let current = match store.current(&word) {
+            Ok(current) => current,
+            Err(e) => {
+                warn!("Failed to read stored entry for '{}': {:#}", word, e);
+                report.failed += 1;
+                continue;
+            }
+        };
+        if current.flags.locked || current.flags.deleted {
+            report.skipped += 1;
+            continue;
+        }
+        let cached_at = cached
+            .created_at
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let stored = current.latest.is_some_and(|latest| {
+            latest.hash() == content_hash(&cached.value) || latest.created_at > cached_at
+        });
+        if stored {
+            report.already_stored += 1;
+            continue;
+        }
+        match store.append(&word, &cached.value, &cached.model, &cached.schema_version) {
+            Ok(_) => report.flushed += 1,
+            Err(e) => {
+                warn!("Failed to flush cached entry for '{}': {:#}", word, e);
+                report.failed += 1;
+            }
+        }
+    }
+}
+
+/// Write via a temporary file and rename, so a crash never leaves a torn file.
+fn write_json<T: serde::Serialize>(path: &PathBuf, value: &T) -> Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_vec_pretty(value)?)
+        .with_context(|| format!("write {:?}", tmp))?;
+    fs::rename(&tmp, path).with_context(|| format!("replace {:?}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::Profile;
+    use serde_json::json;
+
+    #[test]
+    fn run_flushes_unstored_entries_and_saves_usage() {
+        let dir = std::env::temp_dir().join(format!("lingua-shutdown-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let store = Arc::new(EntryStore::open(&dir).unwrap());
+        let cache = Arc::new(WordCache::new(10, None, false));
+        let usage = Arc::new(UsageTracker::default());
+        let hook = ShutdownHook::new(cache.clone(), usage.clone())
+            .with_store(store.clone())
+            .with_usage_file(dir.join(USAGE_FILE));
+
+        let run = json!({ "word": "run" });
+        store.append("run", &run, "m", "1").unwrap();
+        store
+            .append("tree", &json!({ "word": "tree" }), "m", "1")
+            .unwrap();
+        store.update_flags("tree", |f| f.deleted = true).unwrap();
+        cache.insert("run", run, "m", "1");
+        cache.insert("tree", json!({ "word": "tree", "new": true }), "m", "1");
+        cache.insert("walk", json!({ "word": "walk" }), "m", "1");
+        usage.admit("key", &Profile::default()).unwrap();
+        usage.record_tokens("key", 42);
+
+        let report = hook.run();
+        assert_eq!(
+            report,
+            ShutdownReport {
+                flushed: 1,
+                already_stored: 1,
+                skipped: 1,
+                failed: 0,
+                usage_keys: Some(1),
+            }
+        );
+        assert_eq!(store.latest("walk").unwrap().unwrap().entry["word"], "walk");
+        assert_eq!(store.history("run").unwrap().len(), 1);
+        // A second shutdown has nothing new to write
+        assert_eq!(hook.run().flushed, 0);
+
+        let restarted = ShutdownHook::new(cache, Arc::new(UsageTracker::default()))
+            .with_usage_file(dir.join(USAGE_FILE));
+        assert_eq!(restarted.restore_usage().unwrap(), 1);
+        assert_eq!(restarted.usage.save()[0].tokens, 42);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn run_flushes_each_tenant_into_its_own_store() {
+        let dir =
+            std::env::temp_dir().join(format!("lingua-shutdown-tenant-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let store = Arc::new(EntryStore::open(dir.join("own")).unwrap());
+        let kids = Arc::new(EntryStore::open(dir.join("kids")).unwrap());
+        let cache = Arc::new(WordCache::new(10, None, false));
+        let kids_cache = Arc::new(WordCache::new(10, None, false));
+        let hook = ShutdownHook::new(cache.clone(), Arc::new(UsageTracker::default()))
+            .with_store(store.clone())
+            .with_tenant(kids_cache.clone(), kids.clone());
+
+        cache.insert("run", json!({ "word": "run" }), "m", "1");
+        kids_cache.insert("walk", json!({ "word": "walk", "level": "A1" }), "m", "1");
+
+        assert_eq!(hook.run().flushed, 2);
+        assert!(store.latest("walk").unwrap().is_none());
+        assert_eq!(kids.latest("walk").unwrap().unwrap().entry["level"], "A1");
+        assert!(kids.latest("run").unwrap().is_none());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
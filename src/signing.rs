@@ -0,0 +1,139 @@
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde_json::Value;
+use std::path::Path;
+use tracing::info;
+
+/// Response header carrying the base64 Ed25519 signature of the entry.
+pub const SIGNATURE_HEADER: &str = "x-signature";
+/// Response header naming the key that made the signature.
+pub const KEY_ID_HEADER: &str = "x-signature-key-id";
+
+/// Signs validated entries so consumers of exported dictionaries can check
+/// they came from this service unaltered.
+///
+/// Signatures cover the entry's canonical JSON ([`canonical_json`]), so they
+/// survive re-serialization with a different key order or whitespace.
+pub struct EntrySigner {
+    key: SigningKey,
+    key_id: String,
+}
+
+impl EntrySigner {
+    /// Load a key file holding the base64 32-byte Ed25519 secret seed, e.g.
+    /// from `head -c 32 /dev/urandom | base64`. `key_id` defaults to a short
+    /// fingerprint of the public key.
+    pub fn load(path: impl AsRef<Path>, key_id: Option<String>) -> Result<Self> {
+        let path = path.as_ref();
+        let raw = crate::util::read_to_string(path)?;
+        let seed = STANDARD
+            .decode(raw.trim())
+            .with_context(|| format!("signing key {:?} is not base64", path))?;
+        let Ok(seed) = <[u8; 32]>::try_from(seed.as_slice()) else {
+            bail!(
+                "signing key {:?} holds {} bytes; an Ed25519 seed is 32",
+                path,
+                seed.len()
+            );
+        };
+        if let Some(id) = &key_id {
+            if id.is_empty() || !id.chars().all(|c| c.is_ascii_graphic()) {
+                bail!("signing key ID {:?} must be non-empty printable ASCII", id);
+            }
+        }
+        let signer = Self::from_seed(&seed, key_id);
+        info!(key_id = %signer.key_id, "signing entries");
+        Ok(signer)
+    }
+
+    pub fn from_seed(seed: &[u8; 32], key_id: Option<String>) -> Self {
+        let key = SigningKey::from_bytes(seed);
+        let key_id =
+            key_id.unwrap_or_else(|| URL_SAFE_NO_PAD.encode(&key.verifying_key().as_bytes()[..8]));
+        Self { key, key_id }
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// The base64 public key consumers verify with.
+    pub fn public_key(&self) -> String {
+        STANDARD.encode(self.key.verifying_key().as_bytes())
+    }
+
+    /// Base64 signature of `entry`'s canonical JSON.
+    pub fn sign(&self, entry: &Value) -> String {
+        STANDARD.encode(self.key.sign(canonical_json(entry).as_bytes()).to_bytes())
+    }
+}
+
+/// Whether `signature` (base64) is `public_key`'s (base64) signature of `entry`.
+pub fn verify(public_key: &str, entry: &Value, signature: &str) -> bool {
+    let key = STANDARD
+        .decode(public_key)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes.as_slice()).ok())
+        .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok());
+    let signature = STANDARD
+        .decode(signature)
+        .ok()
+        .and_then(|bytes| Signature::from_slice(&bytes).ok());
+    match (key, signature) {
+        (Some(key), Some(signature)) => key
+            .verify(canonical_json(entry).as_bytes(), &signature)
+            .is_ok(),
+        _ => false,
+    }
+}
+
+/// `v` as compact JSON with object keys sorted at every level, so the same
+/// entry always yields the same bytes.
+pub fn canonical_json(v: &Value) -> String {
+    fn sorted(v: &Value) -> Value {
+        match v {
+            Value::Object(map) => {
+                let mut keys: Vec<&String> = map.keys().collect();
+                keys.sort();
+                Value::Object(
+                    keys.into_iter()
+                        .map(|k| (k.clone(), sorted(&map[k])))
+                        .collect(),
+                )
+            }
+            Value::Array(items) => Value::Array(items.iter().map(sorted).collect()),
+            other => other.clone(),
+        }
+    }
+    sorted(v).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn signatures_survive_reordering_but_not_edits() {
+        let signer = EntrySigner::from_seed(&[7; 32], None);
+        let entry = json!({ "word": "run", "meanings": [{ "b": 1, "a": 2 }] });
+        let signature = signer.sign(&entry);
+
+        let reordered: Value =
+            serde_json::from_str(r#"{ "meanings": [{ "a": 2, "b": 1 }], "word": "run" }"#).unwrap();
+        assert_eq!(
+            canonical_json(&reordered),
+            r#"{"meanings":[{"a":2,"b":1}],"word":"run"}"#
+        );
+        assert!(verify(&signer.public_key(), &reordered, &signature));
+
+        let edited = json!({ "word": "ran", "meanings": [{ "b": 1, "a": 2 }] });
+        assert!(!verify(&signer.public_key(), &edited, &signature));
+        let other = EntrySigner::from_seed(&[8; 32], Some("other".into()));
+        assert!(!verify(&other.public_key(), &entry, &signature));
+        assert_eq!(other.key_id(), "other");
+        assert_eq!(signer.key_id().len(), 11);
+    }
+}
@@ -0,0 +1,103 @@
+use crate::model::BackendStats;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::process::Command;
+use std::time::Instant;
+
+static STARTED: Lazy<Instant> = Lazy::new(Instant::now);
+
+/// Everything `GET /admin/stats` reports.
+#[derive(Debug, Clone, Serialize)]
+pub struct Stats {
+    #[serde(flatten)]
+    pub process: ProcessStats,
+    /// Entries in the in-memory word cache
+    pub cache_entries: usize,
+    /// Model memory, KV cache per live context and inference slots; `None`
+    /// for backends that run the model elsewhere
+    pub backend: Option<BackendStats>,
+}
+
+/// Memory and uptime of this process. Sampled over a long run, steady
+/// growth points at a leak.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessStats {
+    pub uptime_secs: u64,
+    /// Resident set size; `None` where `/proc` is unavailable
+    pub rss_bytes: Option<u64>,
+    /// GPU memory this process holds across devices, from `nvidia-smi`;
+    /// `None` without an NVIDIA GPU
+    pub gpu_memory_mb: Option<u64>,
+}
+
+/// Start the uptime clock; called once at startup.
+pub fn mark_started() {
+    Lazy::force(&STARTED);
+}
+
+pub fn process() -> ProcessStats {
+    ProcessStats {
+        uptime_secs: STARTED.elapsed().as_secs(),
+        rss_bytes: rss_bytes(),
+        gpu_memory_mb: gpu_memory_mb(std::process::id()),
+    }
+}
+
+/// Resident pages from `/proc/self/statm`, in bytes.
+fn rss_bytes() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    Some(pages * page_size())
+}
+
+fn page_size() -> u64 {
+    // 4 KiB nearly everywhere, but some arm64 kernels use 16 or 64 KiB
+    Command::new("getconf")
+        .arg("PAGESIZE")
+        .output()
+        .ok()
+        .and_then(|out| String::from_utf8_lossy(&out.stdout).trim().parse().ok())
+        .unwrap_or(4096)
+}
+
+fn gpu_memory_mb(pid: u32) -> Option<u64> {
+    let out = Command::new("nvidia-smi")
+        .args([
+            "--query-compute-apps=pid,used_memory",
+            "--format=csv,noheader,nounits",
+        ])
+        .output()
+        .ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    Some(compute_app_memory(
+        &String::from_utf8_lossy(&out.stdout),
+        pid,
+    ))
+}
+
+/// Sum the `pid, used_memory` rows of `nvidia-smi --query-compute-apps`
+/// belonging to `pid`, one per GPU it uses.
+fn compute_app_memory(csv: &str, pid: u32) -> u64 {
+    csv.lines()
+        .filter_map(|line| line.split_once(','))
+        .filter(|(row_pid, _)| row_pid.trim().parse() == Ok(pid))
+        .filter_map(|(_, mb)| mb.trim().parse::<u64>().ok())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_own_memory() {
+        if std::path::Path::new("/proc/self/statm").exists() {
+            assert!(rss_bytes().unwrap() > 0);
+        }
+        let csv = "4242, 5120\n17, 900\n4242, 2048\n";
+        assert_eq!(compute_app_memory(csv, 4242), 7168);
+        assert_eq!(compute_app_memory(csv, 1), 0);
+    }
+}
@@ -0,0 +1,694 @@
+use crate::config::CasePolicy;
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// One generated (or restored) version of a word's entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredVersion {
+    pub version: u32,
+    pub model: String,
+    pub schema_version: String,
+    /// Unix timestamp in seconds
+    pub created_at: u64,
+    /// Sentence the client supplied to pick the sense order, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    /// [`content_hash`] of `entry`; absent on versions stored before hashing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    /// Quality concerns found after the version was stored
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// [`raw_hash`] of the model output this version was validated from,
+    /// retrievable with [`EntryStore::raw`]; absent on edited and restored versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_output: Option<String>,
+    pub entry: Value,
+}
+
+impl StoredVersion {
+    /// The recorded content hash, or one computed now for older versions.
+    pub fn hash(&self) -> String {
+        self.content_hash
+            .clone()
+            .unwrap_or_else(|| content_hash(&self.entry))
+    }
+}
+
+/// Version metadata without the entry body, for history listings.
+#[derive(Debug, Clone, Serialize)]
+pub struct VersionSummary {
+    pub version: u32,
+    pub model: String,
+    pub schema_version: String,
+    pub created_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    pub content_hash: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_output: Option<String>,
+}
+
+/// Words whose latest entry changed between two models, from [`EntryStore::diff_models`].
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct ModelDiff {
+    pub model_a: String,
+    pub model_b: String,
+    /// Words with versions from both models
+    pub compared: u64,
+    pub changed: u64,
+    pub unchanged: u64,
+    /// Words only `model_a` (or only `model_b`) generated
+    pub only_a: u64,
+    pub only_b: u64,
+    /// The first [`DIFF_SAMPLE`] changed words, alphabetically
+    pub changed_words: Vec<String>,
+}
+
+/// Model name recorded for versions written by operators rather than the LLM.
+pub const CURATED_MODEL: &str = "curated";
+
+/// Changed words listed by name in a [`ModelDiff`].
+pub const DIFF_SAMPLE: usize = 100;
+
+/// Operator controls on an entry. Locked entries are curated and never
+/// regenerated by the model; deleted entries are hidden but keep their history.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct EntryFlags {
+    #[serde(default)]
+    pub locked: bool,
+    #[serde(default)]
+    pub deleted: bool,
+}
+
+/// The latest version of a word together with its operator flags.
+#[derive(Debug, Default)]
+pub struct CurrentEntry {
+    pub latest: Option<StoredVersion>,
+    pub flags: EntryFlags,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct EntryFile {
+    versions: Vec<StoredVersion>,
+    #[serde(default)]
+    flags: EntryFlags,
+    /// The key the file is stored under, recorded when its name is hashed
+    /// and so can't be decoded back to the word
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key: Option<String>,
+}
+
+impl EntryFile {
+    /// The word this file, named `stem`, holds.
+    fn word(&self, stem: &str) -> String {
+        self.key.clone().unwrap_or_else(|| word_from_stem(stem))
+    }
+}
+
+/// Longest file stem written as is; longer ones keep a readable prefix and a
+/// hash, staying well within the 255-byte file name limit once `.json.tmp`
+/// is added.
+const MAX_STEM: usize = 200;
+
+/// Bytes of a hashed stem's readable prefix.
+const STEM_PREFIX: usize = 120;
+
+/// File-backed persistence of word entries with full version history.
+///
+/// Each word lives in its own JSON file under `<dir>/entries/`, holding every
+/// version oldest first. Raw model outputs live under `<dir>/raw/`, one file
+/// per distinct output named by its hash. Writes are serialized through a
+/// single lock.
+pub struct EntryStore {
+    dir: PathBuf,
+    raw_dir: PathBuf,
+    write_lock: Mutex<()>,
+    case_policy: CasePolicy,
+    pretty: bool,
+}
+
+impl EntryStore {
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let raw_dir = dir.as_ref().join("raw");
+        let dir = dir.as_ref().join("entries");
+        for dir in [&dir, &raw_dir] {
+            fs::create_dir_all(dir).with_context(|| format!("create data dir {:?}", dir))?;
+        }
+        Ok(Self {
+            dir,
+            raw_dir,
+            write_lock: Mutex::new(()),
+            case_policy: CasePolicy::default(),
+            pretty: false,
+        })
+    }
+
+    /// Name entry files by `policy`'s key, matching the cache.
+    pub fn with_case_policy(mut self, policy: CasePolicy) -> Self {
+        self.case_policy = policy;
+        self
+    }
+
+    /// Write entry files indented, one value per line, so a copy of the data
+    /// dir kept in git diffs line by line.
+    pub fn with_pretty_files(mut self, enabled: bool) -> Self {
+        self.pretty = enabled;
+        self
+    }
+
+    /// Append a new version for the word, returning its version number.
+    pub fn append(
+        &self,
+        word: &str,
+        entry: &Value,
+        model: &str,
+        schema_version: &str,
+    ) -> Result<u32> {
+        self.append_generated(word, entry, model, schema_version, None, None)
+    }
+
+    /// [`append`](Self::append), recording the context sentence the entry was
+    /// generated for and the hash of the raw output it was validated from.
+    pub fn append_generated(
+        &self,
+        word: &str,
+        entry: &Value,
+        model: &str,
+        schema_version: &str,
+        context: Option<&str>,
+        raw_output: Option<&str>,
+    ) -> Result<u32> {
+        let _guard = self.write_lock.lock();
+        let mut file = self.read_file(word)?;
+        let version = file.versions.last().map(|v| v.version + 1).unwrap_or(1);
+        file.versions.push(StoredVersion {
+            version,
+            model: model.to_string(),
+            schema_version: schema_version.to_string(),
+            created_at: unix_now(),
+            context: context.map(str::to_string),
+            content_hash: Some(content_hash(entry)),
+            warnings: Vec::new(),
+            raw_output: raw_output.map(str::to_string),
+            entry: entry.clone(),
+        });
+        self.write_file(word, &mut file)?;
+        Ok(version)
+    }
+
+    pub fn latest(&self, word: &str) -> Result<Option<StoredVersion>> {
+        Ok(self.read_file(word)?.versions.pop())
+    }
+
+    pub fn current(&self, word: &str) -> Result<CurrentEntry> {
+        let mut file = self.read_file(word)?;
+        Ok(CurrentEntry {
+            latest: file.versions.pop(),
+            flags: file.flags,
+        })
+    }
+
+    /// Update the operator flags for a word, returning the new flags.
+    pub fn update_flags(
+        &self,
+        word: &str,
+        update: impl FnOnce(&mut EntryFlags),
+    ) -> Result<EntryFlags> {
+        let _guard = self.write_lock.lock();
+        let mut file = self.read_file(word)?;
+        update(&mut file.flags);
+        self.write_file(word, &mut file)?;
+        Ok(file.flags)
+    }
+
+    /// Attach quality warnings to a stored version. Returns false if the
+    /// version no longer exists.
+    pub fn add_warnings(&self, word: &str, version: u32, warnings: &[String]) -> Result<bool> {
+        let _guard = self.write_lock.lock();
+        let mut file = self.read_file(word)?;
+        let Some(stored) = file.versions.iter_mut().find(|v| v.version == version) else {
+            return Ok(false);
+        };
+        stored.warnings.extend_from_slice(warnings);
+        self.write_file(word, &mut file)?;
+        Ok(true)
+    }
+
+    pub fn version(&self, word: &str, version: u32) -> Result<Option<StoredVersion>> {
+        Ok(self
+            .read_file(word)?
+            .versions
+            .into_iter()
+            .find(|v| v.version == version))
+    }
+
+    pub fn history(&self, word: &str) -> Result<Vec<VersionSummary>> {
+        Ok(self
+            .read_file(word)?
+            .versions
+            .into_iter()
+            .map(|v| VersionSummary {
+                content_hash: v.hash(),
+                version: v.version,
+                model: v.model,
+                schema_version: v.schema_version,
+                created_at: v.created_at,
+                context: v.context,
+                warnings: v.warnings,
+                raw_output: v.raw_output,
+            })
+            .collect())
+    }
+
+    /// Compare each word's latest `model_a` version with its latest `model_b`
+    /// version by content hash. Identical content counts as unchanged however
+    /// far apart the two were generated, so a model upgrade can be signed off
+    /// on how many entries it actually rewrote.
+    pub fn diff_models(&self, model_a: &str, model_b: &str) -> Result<ModelDiff> {
+        let mut diff = ModelDiff {
+            model_a: model_a.to_string(),
+            model_b: model_b.to_string(),
+            ..ModelDiff::default()
+        };
+        let mut changed = BTreeSet::new();
+        let dir = fs::read_dir(&self.dir).with_context(|| format!("list {:?}", self.dir))?;
+        for item in dir {
+            let path = item.with_context(|| format!("list {:?}", self.dir))?.path();
+            let Some(stem) = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.strip_suffix(".json"))
+            else {
+                continue;
+            };
+            let bytes = fs::read(&path).with_context(|| format!("read {:?}", path))?;
+            let file: EntryFile =
+                serde_json::from_slice(&bytes).with_context(|| format!("parse {:?}", path))?;
+            let latest_by = |model: &str| file.versions.iter().rev().find(|v| v.model == model);
+            match (latest_by(model_a), latest_by(model_b)) {
+                (Some(a), Some(b)) => {
+                    diff.compared += 1;
+                    if a.hash() == b.hash() {
+                        diff.unchanged += 1;
+                    } else {
+                        diff.changed += 1;
+                        changed.insert(file.word(stem));
+                    }
+                }
+                (Some(_), None) => diff.only_a += 1,
+                (None, Some(_)) => diff.only_b += 1,
+                (None, None) => {}
+            }
+        }
+        diff.changed_words = changed.into_iter().take(DIFF_SAMPLE).collect();
+        Ok(diff)
+    }
+
+    /// Keep raw model output, returning its [`raw_hash`]. Outputs already
+    /// kept are not written again.
+    pub fn put_raw(&self, bytes: &[u8]) -> Result<String> {
+        let hash = raw_hash(bytes);
+        let path = self.raw_dir.join(&hash);
+        let _guard = self.write_lock.lock();
+        if !path.exists() {
+            let tmp = path.with_extension("tmp");
+            fs::write(&tmp, bytes).with_context(|| format!("write {:?}", tmp))?;
+            fs::rename(&tmp, &path).with_context(|| format!("rename {:?}", path))?;
+        }
+        Ok(hash)
+    }
+
+    /// Raw model output kept under `hash`; `None` for unknown or malformed hashes.
+    pub fn raw(&self, hash: &str) -> Result<Option<Vec<u8>>> {
+        let well_formed =
+            hash.len() == 64 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'));
+        if !well_formed {
+            return Ok(None);
+        }
+        let path = self.raw_dir.join(hash);
+        match fs::read(&path) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("read {:?}", path)),
+        }
+    }
+
+    /// Words due for regeneration: those whose latest version was created
+    /// before `cutoff` (Unix seconds) or by a model other than `model`, oldest
+    /// first. Locked and deleted words and operator-curated versions are left
+    /// out.
+    pub fn aging(&self, cutoff: Option<u64>, model: Option<&str>) -> Result<Vec<String>> {
+        let mut due = Vec::new();
+        let dir = fs::read_dir(&self.dir).with_context(|| format!("list {:?}", self.dir))?;
+        for item in dir {
+            let path = item.with_context(|| format!("list {:?}", self.dir))?.path();
+            let Some(stem) = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.strip_suffix(".json"))
+            else {
+                continue;
+            };
+            let bytes = fs::read(&path).with_context(|| format!("read {:?}", path))?;
+            let file: EntryFile =
+                serde_json::from_slice(&bytes).with_context(|| format!("parse {:?}", path))?;
+            let Some(latest) = file.versions.last() else {
+                continue;
+            };
+            if file.flags.locked || file.flags.deleted || latest.model == CURATED_MODEL {
+                continue;
+            }
+            let old = cutoff.is_some_and(|cutoff| latest.created_at < cutoff);
+            let superseded = model.is_some_and(|model| latest.model != model);
+            if old || superseded {
+                due.push((latest.created_at, file.word(stem)));
+            }
+        }
+        due.sort();
+        Ok(due.into_iter().map(|(_, word)| word).collect())
+    }
+
+    fn path_for_key(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", file_stem(key)))
+    }
+
+    /// The word's file, or the one it was stored in under another spelling
+    /// before spelling variants shared a key. That one is left in place; the
+    /// next write moves its versions and flags to the shared key.
+    fn read_file(&self, word: &str) -> Result<EntryFile> {
+        let key = self.case_policy.key(word);
+        if let Some(file) = read_at(&self.path_for_key(&key))? {
+            return Ok(file);
+        }
+        let spelling = self.case_policy.spelling_key(word);
+        let others = crate::variants::alternatives(&spelling);
+        for other in std::iter::once(spelling.as_str()).chain(others) {
+            if other == key {
+                continue;
+            }
+            if let Some(file) = read_at(&self.path_for_key(other))? {
+                return Ok(file);
+            }
+        }
+        Ok(EntryFile::default())
+    }
+
+    fn write_file(&self, word: &str, file: &mut EntryFile) -> Result<()> {
+        let key = self.case_policy.key(word);
+        let stem = file_stem(&key);
+        // A plain stem never holds `~`; it would be escaped
+        file.key = stem.contains('~').then_some(key);
+        let path = self.dir.join(format!("{}.json", stem));
+        // Write then rename so readers never observe a partial file
+        let tmp = path.with_extension("json.tmp");
+        let bytes = if self.pretty {
+            let mut bytes = serde_json::to_vec_pretty(file)?;
+            bytes.push(b'\n');
+            bytes
+        } else {
+            serde_json::to_vec(file)?
+        };
+        fs::write(&tmp, bytes).with_context(|| format!("write {:?}", tmp))?;
+        fs::rename(&tmp, &path).with_context(|| format!("rename {:?}", path))?;
+        Ok(())
+    }
+}
+
+/// The entry file at `path`, `None` when there is none.
+fn read_at(path: &Path) -> Result<Option<EntryFile>> {
+    match fs::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map(Some)
+            .with_context(|| format!("parse {:?}", path)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("read {:?}", path)),
+    }
+}
+
+/// Map a word to a filesystem-safe file stem, reversible unless it is longer
+/// than [`MAX_STEM`]: those keep the start of the encoding and end in a hash
+/// of the whole word.
+fn file_stem(word: &str) -> String {
+    let word = word.trim();
+    let mut out = String::with_capacity(word.len());
+    let mut prefix = 0;
+    for b in word.bytes() {
+        match b {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' => out.push(b as char),
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+        if out.len() <= STEM_PREFIX {
+            // Cut only between whole escapes
+            prefix = out.len();
+        }
+    }
+    if out.len() > MAX_STEM {
+        out.truncate(prefix);
+        out.push('~');
+        out.push_str(&raw_hash(word.as_bytes()));
+    }
+    out
+}
+
+/// Inverse of [`file_stem`] for stems that were not hashed.
+fn word_from_stem(stem: &str) -> String {
+    let bytes = stem.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| stem.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(b) => {
+                out.push(b);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Hex SHA-256 of the entry's canonical JSON, so key order and whitespace
+/// never make identical content look different.
+pub fn content_hash(entry: &Value) -> String {
+    raw_hash(crate::signing::canonical_json(entry).as_bytes())
+}
+
+/// Hex SHA-256 of raw model output, byte for byte.
+pub fn raw_hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn appends_versions_and_reads_history() {
+        let dir = std::env::temp_dir().join(format!("lingua-store-{}", std::process::id()));
+        let store = EntryStore::open(&dir).unwrap();
+
+        assert!(store.latest("naïve café").unwrap().is_none());
+        assert_eq!(
+            store
+                .append("naïve café", &json!({"v": 1}), "m1", "1")
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            store
+                .append("naïve café", &json!({"v": 2}), "m2", "1")
+                .unwrap(),
+            2
+        );
+
+        let history = store.history("naïve café").unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].model, "m1");
+        assert_eq!(
+            store.latest("naïve café").unwrap().unwrap().entry,
+            json!({"v": 2})
+        );
+        assert_eq!(
+            store.version("naïve café", 1).unwrap().unwrap().entry,
+            json!({"v": 1})
+        );
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn flags_survive_new_versions() {
+        let dir = std::env::temp_dir().join(format!("lingua-store-flags-{}", std::process::id()));
+        let store = EntryStore::open(&dir).unwrap();
+
+        store.update_flags("w", |f| f.locked = true).unwrap();
+        store.append("w", &json!({}), "curated", "1").unwrap();
+        let current = store.current("w").unwrap();
+        assert!(current.flags.locked);
+        assert!(!current.flags.deleted);
+        assert_eq!(current.latest.unwrap().version, 1);
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn entries_stored_under_a_variant_spelling_are_still_found() {
+        let dir = std::env::temp_dir().join(format!("lingua-store-variant-{}", std::process::id()));
+        let store = EntryStore::open(&dir).unwrap();
+        // As stored before "colour" and "color" shared the key "color"
+        store
+            .append("colour", &json!({"v": 1}), "curated", "1")
+            .unwrap();
+        store.update_flags("colour", |f| f.locked = true).unwrap();
+        fs::rename(store.dir.join("color.json"), store.dir.join("colour.json")).unwrap();
+
+        for spelling in ["colour", "color"] {
+            let current = store.current(spelling).unwrap();
+            assert!(current.flags.locked, "{}", spelling);
+            assert_eq!(current.latest.unwrap().entry, json!({"v": 1}));
+        }
+        // The next write moves it to the shared key, history and all
+        store
+            .append("color", &json!({"v": 2}), "curated", "1")
+            .unwrap();
+        assert!(store.dir.join("color.json").exists());
+        assert_eq!(store.history("colour").unwrap().len(), 2);
+        assert!(store.current("color").unwrap().flags.locked);
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn diffs_latest_versions_between_models_by_content() {
+        let dir = std::env::temp_dir().join(format!("lingua-store-diff-{}", std::process::id()));
+        let store = EntryStore::open(&dir).unwrap();
+
+        store
+            .append("same", &json!({"a": 1, "b": 2}), "m1", "1")
+            .unwrap();
+        store
+            .append("same", &json!({"b": 2, "a": 1}), "m2", "1")
+            .unwrap();
+        store.append("naïve", &json!({"v": 1}), "m1", "1").unwrap();
+        store.append("naïve", &json!({"v": 1}), "m2", "1").unwrap();
+        // Only the latest version per model counts
+        store.append("naïve", &json!({"v": 2}), "m2", "1").unwrap();
+        store.append("old", &json!({}), "m1", "1").unwrap();
+        store.append("new", &json!({}), "m2", "1").unwrap();
+
+        let diff = store.diff_models("m1", "m2").unwrap();
+        assert_eq!((diff.compared, diff.changed, diff.unchanged), (2, 1, 1));
+        assert_eq!((diff.only_a, diff.only_b), (1, 1));
+        assert_eq!(diff.changed_words, ["naïve"]);
+
+        let history = store.history("same").unwrap();
+        assert_eq!(history[0].content_hash, history[1].content_hash);
+        assert_eq!(history[0].content_hash.len(), 64);
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn aging_lists_old_and_superseded_words() {
+        let dir = std::env::temp_dir().join(format!("lingua-store-aging-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let store = EntryStore::open(&dir).unwrap();
+
+        store.append("old", &json!({}), "m1", "1").unwrap();
+        store.append("current", &json!({}), "m2", "1").unwrap();
+        store
+            .append("edited", &json!({}), CURATED_MODEL, "1")
+            .unwrap();
+        store.append("pinned", &json!({}), "m1", "1").unwrap();
+        store.update_flags("pinned", |f| f.locked = true).unwrap();
+
+        assert_eq!(store.aging(None, Some("m2")).unwrap(), ["old"]);
+        assert!(store.aging(Some(0), None).unwrap().is_empty());
+        let mut everything = store.aging(Some(u64::MAX), None).unwrap();
+        everything.sort();
+        assert_eq!(everything, ["current", "old"]);
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn long_non_ascii_words_get_short_file_names() {
+        let dir = std::env::temp_dir().join(format!("lingua-store-long-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let store = EntryStore::open(&dir).unwrap();
+        let word = "図書館情報学".repeat(20);
+        let other = format!("{}学", word);
+
+        store.append(&word, &json!({"v": 1}), "m1", "1").unwrap();
+        store.append(&other, &json!({"v": 2}), "m1", "1").unwrap();
+        store.append(&word, &json!({"v": 3}), "m2", "1").unwrap();
+        assert_eq!(store.latest(&word).unwrap().unwrap().entry, json!({"v": 3}));
+        assert_eq!(
+            store.latest(&other).unwrap().unwrap().entry,
+            json!({"v": 2})
+        );
+        for item in fs::read_dir(&store.dir).unwrap() {
+            let name = item.unwrap().file_name();
+            assert!(name.len() + ".tmp".len() <= 255, "{:?}", name);
+        }
+        // The word comes back from the file even though its name can't be decoded
+        assert_eq!(
+            store.diff_models("m1", "m2").unwrap().changed_words,
+            [word.as_str()]
+        );
+        let mut aging = store.aging(Some(u64::MAX), None).unwrap();
+        aging.sort();
+        assert_eq!(aging, [word, other]);
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn raw_outputs_are_kept_by_hash() {
+        let dir = std::env::temp_dir().join(format!("lingua-store-raw-{}", std::process::id()));
+        let store = EntryStore::open(&dir).unwrap();
+
+        let raw = br#"{"word": "run",}"#;
+        let hash = store.put_raw(raw).unwrap();
+        assert_eq!(hash, raw_hash(raw));
+        assert_eq!(store.put_raw(raw).unwrap(), hash);
+        assert_eq!(store.raw(&hash).unwrap().unwrap(), raw);
+        assert!(store.raw(&raw_hash(b"other")).unwrap().is_none());
+        assert!(store.raw("../entries/run.json").unwrap().is_none());
+
+        store
+            .append_generated("run", &json!({"word": "run"}), "m", "1", None, Some(&hash))
+            .unwrap();
+        assert_eq!(store.history("run").unwrap()[0].raw_output, Some(hash));
+
+        fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,315 @@
+use crate::health;
+use crate::model::{InferParams, LlmBackend};
+use crate::service::EntrySource;
+use anyhow::Result;
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use once_cell::sync::{Lazy, OnceCell};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Weight of the newest request in the rolling averages.
+const EWMA_ALPHA: f64 = 0.1;
+
+static HANDLE: OnceCell<PrometheusHandle> = OnceCell::new();
+static ROLLING: Lazy<Mutex<Rolling>> = Lazy::new(Default::default);
+static QUEUED: AtomicUsize = AtomicUsize::new(0);
+/// Entries served from the cache, the store and fresh generations, in that order.
+static SERVED: [AtomicU64; 3] = [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)];
+
+/// Install the process-wide Prometheus recorder behind `/metrics`.
+/// Calling it again is a no-op.
+pub fn install() -> Result<()> {
+    HANDLE.get_or_try_init(|| {
+        let handle = PrometheusBuilder::new().install_recorder()?;
+        metrics::describe_histogram!(
+            "lingua_prompt_tokens_per_second",
+            "Prompt evaluation speed of each inference"
+        );
+        metrics::describe_histogram!(
+            "lingua_decode_tokens_per_second",
+            "Token generation speed of each inference"
+        );
+        metrics::describe_histogram!(
+            "lingua_time_to_first_token_seconds",
+            metrics::Unit::Seconds,
+            "Time from an inference getting a slot to its first sampled token"
+        );
+        metrics::describe_histogram!(
+            "lingua_generation_seconds",
+            metrics::Unit::Seconds,
+            "Total time of each inference, from getting a slot to the last token"
+        );
+        metrics::describe_gauge!(
+            "lingua_inference_queue_depth",
+            "Inferences waiting for a free slot"
+        );
+        metrics::describe_counter!(
+            "lingua_json_repairs_total",
+            "Model outputs that only parsed after lenient JSON repair"
+        );
+        metrics::describe_counter!("lingua_retries_total", "Generations retried, by reason");
+        metrics::describe_counter!(
+            "lingua_sampling_successes_total",
+            "Generations accepted, by the sampling that produced them: configured, cooler, greedy or constrained"
+        );
+        metrics::describe_counter!(
+            "lingua_quality_warnings_total",
+            "Quality warnings recorded against generated entries, by check"
+        );
+        metrics::describe_gauge!(
+            "lingua_model_loaded",
+            "1 while the llama model is loaded, 0 while it is unloaded for being idle"
+        );
+        metrics::describe_counter!(
+            "lingua_schema_reloads_total",
+            "Word contract schema reloads, by outcome: changed, unchanged or failed"
+        );
+        metrics::describe_counter!(
+            "lingua_background_refreshes_total",
+            "Aging stored entries regenerated in the background, by outcome: refreshed, skipped or failed"
+        );
+        metrics::describe_counter!(
+            "lingua_panics_total",
+            "Panics caught, by source: handler or inference"
+        );
+        metrics::describe_counter!(
+            "lingua_entries_served_total",
+            "Word entries served, by source: cache, store or generated"
+        );
+        metrics::describe_gauge!(
+            "lingua_prompt_tokens_per_second_avg",
+            "Rolling average of prompt evaluation speed"
+        );
+        metrics::describe_gauge!(
+            "lingua_decode_tokens_per_second_avg",
+            "Rolling average of token generation speed"
+        );
+        anyhow::Ok(handle)
+    })?;
+    Ok(())
+}
+
+/// Prometheus text exposition, or `None` before [`install`].
+pub fn render() -> Option<String> {
+    HANDLE.get().map(PrometheusHandle::render)
+}
+
+/// Token counts and wall time of one inference's two phases.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Throughput {
+    pub prompt_tokens: usize,
+    pub prompt_time: Duration,
+    pub output_tokens: usize,
+    pub decode_time: Duration,
+    /// Slot acquired to first sampled token: prompt preparation and
+    /// evaluation plus one sampling step. `None` when nothing was sampled.
+    pub first_token: Option<Duration>,
+    /// Slot acquired to the last token.
+    pub total_time: Duration,
+}
+
+impl Throughput {
+    pub fn prompt_tps(&self) -> Option<f64> {
+        rate(self.prompt_tokens, self.prompt_time)
+    }
+
+    pub fn decode_tps(&self) -> Option<f64> {
+        rate(self.output_tokens, self.decode_time)
+    }
+}
+
+fn rate(tokens: usize, time: Duration) -> Option<f64> {
+    (tokens > 0 && !time.is_zero()).then(|| tokens as f64 / time.as_secs_f64())
+}
+
+/// Exponentially weighted averages across recent inferences.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Rolling {
+    pub prompt_tps: Option<f64>,
+    pub decode_tps: Option<f64>,
+    pub last: Option<Throughput>,
+}
+
+fn ewma(avg: Option<f64>, sample: Option<f64>) -> Option<f64> {
+    match (avg, sample) {
+        (Some(avg), Some(sample)) => Some(avg + EWMA_ALPHA * (sample - avg)),
+        (avg, sample) => sample.or(avg),
+    }
+}
+
+/// Export one inference's speeds and fold them into the rolling averages.
+pub fn record_throughput(t: Throughput) {
+    metrics::counter!("lingua_prompt_tokens_total").increment(t.prompt_tokens as u64);
+    metrics::counter!("lingua_generated_tokens_total").increment(t.output_tokens as u64);
+    if let Some(tps) = t.prompt_tps() {
+        metrics::histogram!("lingua_prompt_tokens_per_second").record(tps);
+    }
+    if let Some(tps) = t.decode_tps() {
+        metrics::histogram!("lingua_decode_tokens_per_second").record(tps);
+    }
+    if let Some(ttft) = t.first_token {
+        metrics::histogram!("lingua_time_to_first_token_seconds").record(ttft.as_secs_f64());
+    }
+    metrics::histogram!("lingua_generation_seconds").record(t.total_time.as_secs_f64());
+
+    let mut rolling = ROLLING.lock();
+    rolling.prompt_tps = ewma(rolling.prompt_tps, t.prompt_tps());
+    rolling.decode_tps = ewma(rolling.decode_tps, t.decode_tps());
+    rolling.last = Some(t);
+    if let Some(avg) = rolling.prompt_tps {
+        metrics::gauge!("lingua_prompt_tokens_per_second_avg").set(avg);
+    }
+    if let Some(avg) = rolling.decode_tps {
+        metrics::gauge!("lingua_decode_tokens_per_second_avg").set(avg);
+    }
+}
+
+pub fn rolling() -> Rolling {
+    *ROLLING.lock()
+}
+
+/// Counts an inference as queued for a slot until dropped.
+pub struct Queued(());
+
+impl Queued {
+    pub fn enter() -> Self {
+        let depth = QUEUED.fetch_add(1, Ordering::Relaxed) + 1;
+        metrics::gauge!("lingua_inference_queue_depth").set(depth as f64);
+        Queued(())
+    }
+}
+
+impl Drop for Queued {
+    fn drop(&mut self) {
+        let depth = QUEUED.fetch_sub(1, Ordering::Relaxed) - 1;
+        metrics::gauge!("lingua_inference_queue_depth").set(depth as f64);
+    }
+}
+
+/// Inferences currently waiting for a slot.
+pub fn queue_depth() -> usize {
+    QUEUED.load(Ordering::Relaxed)
+}
+
+/// Count one served entry by where it came from.
+pub fn record_served(source: EntrySource) {
+    let (index, label) = match source {
+        EntrySource::Cache => (0, "cache"),
+        EntrySource::Store => (1, "store"),
+        EntrySource::Generated => (2, "generated"),
+    };
+    SERVED[index].fetch_add(1, Ordering::Relaxed);
+    metrics::counter!("lingua_entries_served_total", "source" => label).increment(1);
+}
+
+/// Count one model output rescued by [`crate::lenient::repair`].
+pub fn record_json_repair() {
+    metrics::counter!("lingua_json_repairs_total").increment(1);
+}
+
+/// Count one generation retried for `reason`.
+pub fn record_retry(reason: &'static str) {
+    metrics::counter!("lingua_retries_total", "reason" => reason).increment(1);
+}
+
+/// Count one generation accepted on the `strategy` rung of the sampling ladder.
+pub fn record_sampling_success(strategy: &'static str) {
+    metrics::counter!("lingua_sampling_successes_total", "strategy" => strategy).increment(1);
+}
+
+/// Count one quality warning raised by `check` against a generated entry.
+pub fn record_quality_warning(check: &'static str) {
+    metrics::counter!("lingua_quality_warnings_total", "check" => check).increment(1);
+}
+
+/// Count one attempt to reload the word contract schema.
+pub fn record_model_loaded(loaded: bool) {
+    metrics::gauge!("lingua_model_loaded").set(if loaded { 1.0 } else { 0.0 });
+}
+
+pub fn record_schema_reload(outcome: &'static str) {
+    metrics::counter!("lingua_schema_reloads_total", "outcome" => outcome).increment(1);
+}
+
+/// Count one background refresh of an aging stored entry.
+pub fn record_background_refresh(outcome: &'static str) {
+    metrics::counter!("lingua_background_refreshes_total", "outcome" => outcome).increment(1);
+}
+
+/// Count one panic caught before it could take down a connection or worker.
+pub fn record_panic(source: &'static str) {
+    metrics::counter!("lingua_panics_total", "source" => source).increment(1);
+}
+
+/// Entries served since startup, by source.
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize)]
+pub struct Served {
+    pub cache: u64,
+    pub store: u64,
+    pub generated: u64,
+}
+
+impl Served {
+    /// Share of entries answered from the cache, `None` before the first.
+    pub fn cache_hit_rate(&self) -> Option<f64> {
+        let total = self.cache + self.store + self.generated;
+        (total > 0).then(|| self.cache as f64 / total as f64)
+    }
+}
+
+pub fn served() -> Served {
+    let [cache, store, generated] = SERVED.each_ref().map(|n| n.load(Ordering::Relaxed));
+    Served {
+        cache,
+        store,
+        generated,
+    }
+}
+
+/// Run one warmup inference, then a measured one, and return the measured
+/// speeds. `None` when the backend failed or does not report throughput.
+pub async fn benchmark(backend: &dyn LlmBackend, params: &InferParams) -> Option<Throughput> {
+    const BUDGET: Duration = Duration::from_secs(300);
+    // The first call pays for lazy allocation and weight upload; don't measure it
+    let warmup = health::run_canary(backend, params, BUDGET).await;
+    if !warmup.ok {
+        return None;
+    }
+    let before = rolling().last;
+    let measured = health::run_canary(backend, params, BUDGET).await;
+    let after = rolling().last;
+    (measured.ok && after != before).then_some(after).flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn speeds_are_exported_and_averaged() {
+        install().unwrap();
+        let sample = |output_tokens, decode_ms| Throughput {
+            prompt_tokens: 200,
+            prompt_time: Duration::from_millis(100),
+            output_tokens,
+            decode_time: Duration::from_millis(decode_ms),
+            first_token: Some(Duration::from_millis(120)),
+            total_time: Duration::from_millis(100 + decode_ms),
+        };
+        assert_eq!(sample(50, 1000).prompt_tps(), Some(2000.0));
+        assert_eq!(sample(0, 0).decode_tps(), None);
+
+        assert_eq!(ewma(None, Some(40.0)), Some(40.0));
+        assert_eq!(ewma(Some(40.0), Some(50.0)), Some(41.0));
+        assert_eq!(ewma(Some(40.0), None), Some(40.0));
+
+        record_throughput(sample(50, 1000));
+        assert_eq!(rolling().last, Some(sample(50, 1000)));
+        let text = render().unwrap();
+        assert!(text.contains("lingua_decode_tokens_per_second"));
+        assert!(text.contains("lingua_generated_tokens_total"));
+        assert!(text.contains("lingua_time_to_first_token_seconds"));
+        assert!(text.contains("lingua_generation_seconds"));
+    }
+}
@@ -0,0 +1,103 @@
+use crate::cache::WordCache;
+use crate::store::EntryStore;
+use crate::validate::Validator;
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Header naming the tenant a request is for, when its API key does not.
+pub const TENANT_HEADER: &str = "x-tenant";
+
+/// Directory under `DATA_DIR` holding one store per tenant.
+pub const TENANTS_DIR: &str = "tenants";
+
+/// One tenant's settings, from the `tenants` section of `PROFILES_FILE`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TenantConfig {
+    /// Validate this tenant's entries against this schema instead of the
+    /// server's; it must describe the same contract version
+    pub schema_file: Option<String>,
+}
+
+/// What one tenant keeps apart from the others: cached and stored entries,
+/// curated overrides among them, and the schema its entries are held to.
+pub struct Namespace {
+    pub name: String,
+    pub cache: Arc<WordCache>,
+    pub store: Option<Arc<EntryStore>>,
+    pub validator: Arc<Validator>,
+    /// Schema file `/admin/reload-schema` rereads for this tenant
+    pub schema_file: Option<Arc<str>>,
+}
+
+/// Every configured tenant by name. Requests naming none use the server's own
+/// cache and store, as they did before tenants existed.
+#[derive(Default)]
+pub struct Tenants {
+    by_name: BTreeMap<String, Arc<Namespace>>,
+}
+
+impl Tenants {
+    pub fn insert(&mut self, namespace: Namespace) {
+        self.by_name
+            .insert(namespace.name.clone(), Arc::new(namespace));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<Namespace>> {
+        self.by_name.get(name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_name.is_empty()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.by_name.keys().map(String::as_str)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<Namespace>> {
+        self.by_name.values()
+    }
+}
+
+/// Tenant names become directory names and header values: lowercase ASCII
+/// letters, digits, `-` and `_`, at most 64 of them.
+pub fn check_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'_');
+    if !valid {
+        bail!(
+            "tenant name '{}' must be 1-64 lowercase letters, digits, '-' or '_'",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// Where `tenant`'s entries are stored under `data_dir`.
+pub fn data_dir(data_dir: impl AsRef<Path>, tenant: &str) -> PathBuf {
+    data_dir.as_ref().join(TENANTS_DIR).join(tenant)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tenant_names_are_safe_as_directories() {
+        assert!(check_name("kids-app_2").is_ok());
+        for bad in ["", "Kids", "../x", "a/b", "a b", &"x".repeat(65)] {
+            assert!(check_name(bad).is_err(), "{:?}", bad);
+        }
+        assert_eq!(
+            data_dir("/data", "kids"),
+            PathBuf::from("/data/tenants/kids")
+        );
+    }
+}
@@ -1,7 +1,6 @@
 use anyhow::Context;
 use std::{fs, path::Path};
 
-#[allow(dead_code)]
 pub fn read_to_string<P: AsRef<Path>>(p: P) -> anyhow::Result<String> {
     fs::read_to_string(&p).with_context(|| format!("read file {:?}", p.as_ref()))
 }
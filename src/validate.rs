@@ -1,86 +1,587 @@
-use anyhow::{anyhow, Context, Result};
+use crate::cefr;
+use crate::config::CasePolicy;
+use crate::contract::{ContractProfile, ContractProfiles, DEFAULT_LANGUAGE};
+use crate::store::content_hash;
+use anyhow::Result;
+use jsonschema::paths::PathChunk;
 use jsonschema::{Draft, JSONSchema};
 use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashSet;
-use tracing::{debug, warn};
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::{debug, info, warn};
 
-#[derive(Debug, Clone)]
-pub enum ValidationErrorType {
-    SchemaValidation(String),
+/// Version of the embedded word contract schema, recorded with cached entries.
+pub const SCHEMA_VERSION: &str = "2";
+
+/// The embedded word contract schema.
+static SCHEMA_VALUE: Lazy<Value> = Lazy::new(|| {
+    serde_json::from_str(include_str!("../schema/word_contract.schema.json"))
+        .expect("valid schema JSON")
+});
+
+/// Field names a meaning may have, from the schema.
+pub fn meaning_fields() -> impl Iterator<Item = &'static str> {
+    SCHEMA_VALUE["properties"]["meanings"]["items"]["properties"]
+        .as_object()
+        .into_iter()
+        .flat_map(|fields| fields.keys().map(String::as_str))
+}
+
+/// Parts of speech an English meaning may have; other languages bring their
+/// own in a [`ContractProfile`].
+pub const PARTS_OF_SPEECH: [&str; 13] = [
+    "noun", "verb", "adjective", "adverb", "pronoun", "preposition",
+    "conjunction", "interjection", "article", "determiner", "numeral",
+    "participle", "gerund",
+];
+
+/// Key of the object a model emits instead of an entry when asked for a
+/// sense the word does not have.
+pub const SENSE_NOT_FOUND_KEY: &str = "senseNotFound";
+
+/// Languages every meaning must carry a translation for.
+pub const TRANSLATION_LANGS: [&str; 9] = ["es", "fr", "de", "zh", "ja", "it", "pt", "ru", "ar"];
+
+/// Why a model-produced entry was rejected by the [`Validator`].
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum ValidationError {
+    #[error("Schema validation failed: {}", summarize(.0))]
+    SchemaValidation(Vec<Violation>),
+    #[error("Missing required field: {0}")]
     MissingRequiredField(String),
+    #[error("Invalid value for {field}: {reason}")]
     InvalidFieldValue { field: String, reason: String },
+    #[error("Duplicate part of speech: {0}")]
     DuplicatePartOfSpeech(String),
+    #[error("At least one meaning is required")]
     InsufficientMeanings,
+    #[error("Invalid phonetic transcription: {0}")]
     InvalidPhonetic(String),
+    #[error("Malformed entry: {0}")]
+    Malformed(String),
+    #[error("Failed to compile JSON schema: {0}")]
+    SchemaUnavailable(String),
+    #[error("No {0} sense in the entry")]
+    MissingSense(String),
+}
+
+impl ValidationError {
+    /// Whether sampling the model again for the same word is worth it. Output
+    /// that is broken in shape tends to come out differently on another try;
+    /// a specific field being missing or wrong usually repeats.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::SchemaValidation(_)
+                | Self::InsufficientMeanings
+                | Self::InvalidPhonetic(_)
+                | Self::Malformed(_)
+                | Self::MissingSense(_)
+        )
+    }
+
+    /// Whether the entry is otherwise sound and only a named field needs fixing,
+    /// so a targeted follow-up could repair it without regenerating everything.
+    pub fn is_repairable(&self) -> bool {
+        matches!(
+            self,
+            Self::MissingRequiredField(_) | Self::InvalidFieldValue { .. } | Self::InvalidPhonetic(_)
+        )
+    }
+}
+
+/// One specific way an entry breaks the contract, addressable by JSON pointer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Violation {
+    /// JSON pointer to the offending value; empty for the whole entry
+    pub path: String,
+    /// The rule that failed, named after the JSON Schema keyword where one applies
+    pub keyword: String,
+    pub message: String,
 }
 
-impl std::fmt::Display for ValidationErrorType {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl Violation {
+    /// One JSON Schema failure, keyed by the keyword that rejected the value.
+    pub fn from_schema_error(error: jsonschema::ValidationError<'_>) -> Self {
+        Self {
+            path: error.instance_path.to_string(),
+            keyword: match error.schema_path.last() {
+                Some(PathChunk::Keyword(keyword)) => keyword.to_string(),
+                Some(PathChunk::Property(keyword)) => keyword.to_string(),
+                _ => "schema".to_string(),
+            },
+            message: error.to_string(),
+        }
+    }
+}
+
+impl ValidationError {
+    /// Every violation behind this error. Schema failures list each one; the
+    /// validator's own checks stop at the first problem and yield exactly one.
+    pub fn violations(&self) -> Vec<Violation> {
+        let one = |path: String, keyword: &str| {
+            vec![Violation {
+                path,
+                keyword: keyword.to_string(),
+                message: self.to_string(),
+            }]
+        };
         match self {
-            Self::SchemaValidation(msg) => write!(f, "Schema validation failed: {}", msg),
-            Self::MissingRequiredField(field) => write!(f, "Missing required field: {}", field),
-            Self::InvalidFieldValue { field, reason } => write!(f, "Invalid value for {}: {}", field, reason),
-            Self::DuplicatePartOfSpeech(pos) => write!(f, "Duplicate part of speech: {}", pos),
-            Self::InsufficientMeanings => write!(f, "At least one meaning is required"),
-            Self::InvalidPhonetic(reason) => write!(f, "Invalid phonetic transcription: {}", reason),
+            Self::SchemaValidation(violations) => violations.clone(),
+            Self::MissingRequiredField(field) => one(field_pointer(field), "required"),
+            Self::InvalidFieldValue { field, .. } => one(field_pointer(field), "invalid"),
+            Self::DuplicatePartOfSpeech(_) => one("/meanings".to_string(), "uniquePartOfSpeech"),
+            Self::InsufficientMeanings => one("/meanings".to_string(), "minItems"),
+            Self::InvalidPhonetic(_) => one("/phonetic".to_string(), "type"),
+            Self::Malformed(_) => one(String::new(), "type"),
+            Self::SchemaUnavailable(_) => one(String::new(), "schema"),
+            Self::MissingSense(_) => one("/meanings".to_string(), "partOfSpeech"),
+        }
+    }
+}
+
+/// JSON pointer for the field names the validator reports, such as
+/// `baseForm`, `definition in meaning 1` or `translation for 'ja' in meaning 0`.
+fn field_pointer(field: &str) -> String {
+    let Some((name, idx)) = field.rsplit_once(" in meaning ") else {
+        return format!("/{}", field);
+    };
+    match name.strip_prefix("translation for '").and_then(|rest| rest.strip_suffix('\'')) {
+        Some(lang) => format!("/meanings/{}/translations/{}", idx, lang),
+        None => format!("/meanings/{}/{}", idx, name),
+    }
+}
+
+/// Remove object keys `schema` does not list under `properties`, following
+/// `properties` and `items` down the tree. Objects whose schema declares no
+/// properties are left alone. Removed keys are collected as JSON pointers.
+fn strip_unknown_keys(schema: &Value, v: &mut Value, path: &str, stripped: &mut Vec<String>) {
+    match v {
+        Value::Object(obj) => {
+            let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+                return;
+            };
+            obj.retain(|key, _| {
+                let known = properties.contains_key(key);
+                if !known {
+                    stripped.push(format!("{}/{}", path, key));
+                }
+                known
+            });
+            for (key, child) in obj.iter_mut() {
+                strip_unknown_keys(&properties[key], child, &format!("{}/{}", path, key), stripped);
+            }
+        }
+        Value::Array(items) => {
+            let Some(item_schema) = schema.get("items") else {
+                return;
+            };
+            for (idx, item) in items.iter_mut().enumerate() {
+                strip_unknown_keys(item_schema, item, &format!("{}/{}", path, idx), stripped);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// How many values validation changed, added or removed between the model's
+/// output and the accepted entry, counted at the leaves. Meanings it reordered
+/// count once per value that moved.
+pub fn count_fixes(raw: &Value, fixed: &Value) -> usize {
+    match (raw, fixed) {
+        (Value::Object(a), Value::Object(b)) => {
+            let keys: HashSet<&String> = a.keys().chain(b.keys()).collect();
+            keys.into_iter()
+                .map(|key| match (a.get(key), b.get(key)) {
+                    (Some(x), Some(y)) => count_fixes(x, y),
+                    _ => 1,
+                })
+                .sum()
         }
+        (Value::Array(a), Value::Array(b)) if a.len() == b.len() => {
+            a.iter().zip(b).map(|(x, y)| count_fixes(x, y)).sum()
+        }
+        (a, b) => usize::from(a != b),
+    }
+}
+
+/// The first few violations in one line, for logs and error messages.
+fn summarize(violations: &[Violation]) -> String {
+    const SHOWN: usize = 5;
+    let mut out = violations
+        .iter()
+        .take(SHOWN)
+        .map(|v| format!("at {}: {}", if v.path.is_empty() { "/" } else { &v.path }, v.message))
+        .collect::<Vec<_>>()
+        .join("; ");
+    if violations.len() > SHOWN {
+        out.push_str(&format!(" (and {} more)", violations.len() - SHOWN));
+    }
+    out
+}
+
+/// Most meanings an entry may have, per the schema's `maxItems`.
+pub const MAX_MEANINGS: usize = 4;
+
+/// Keep only the first `max` meanings of `entry`, its highest ranked.
+pub fn limit_meanings(entry: &mut Value, max: usize) {
+    if let Some(meanings) = entry.get_mut("meanings").and_then(Value::as_array_mut) {
+        meanings.truncate(max);
     }
 }
 
-pub struct Validator;
+/// `entry` with only its `part_of_speech` meaning, ranked first; `None` if it has none.
+pub fn restrict_to_sense(entry: &Value, part_of_speech: &str) -> Option<Value> {
+    let mut meaning = entry["meanings"]
+        .as_array()?
+        .iter()
+        .find(|m| m["partOfSpeech"] == part_of_speech)?
+        .clone();
+    meaning["senseRank"] = Value::from(1);
+    let mut restricted = entry.clone();
+    restricted["meanings"] = Value::Array(vec![meaning]);
+    Some(restricted)
+}
+
+/// Lists within a meaning whose order carries no meaning.
+const UNORDERED_LISTS: [&str; 2] = ["synonyms", "antonyms"];
+
+/// Put `entry` in canonical form: object keys sorted at every level and the
+/// unordered lists (synonyms, antonyms) sorted, so a regeneration that says
+/// the same thing yields the same bytes. Meanings keep their rank order.
+pub fn canonicalize(entry: &mut Value) {
+    fn sort_keys(v: &mut Value) {
+        match v {
+            Value::Object(map) => {
+                map.sort_keys();
+                map.values_mut().for_each(sort_keys);
+            }
+            Value::Array(items) => items.iter_mut().for_each(sort_keys),
+            _ => {}
+        }
+    }
+    if let Some(meanings) = entry.get_mut("meanings").and_then(Value::as_array_mut) {
+        for meaning in meanings {
+            for key in UNORDERED_LISTS {
+                if let Some(Value::Array(items)) = meaning.get_mut(key) {
+                    items.sort_by(|a, b| a.as_str().cmp(&b.as_str()));
+                }
+            }
+        }
+    }
+    sort_keys(entry);
+}
+
+/// Whether model output is the "no such sense" answer rather than an entry.
+pub fn reports_missing_sense(v: &Value) -> bool {
+    v.get(SENSE_NOT_FOUND_KEY) == Some(&Value::Bool(true))
+}
+
+/// Translations missing from one meaning of an otherwise valid entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranslationGap {
+    /// Index into `meanings` as the model emitted them, before reordering
+    pub meaning: usize,
+    pub part_of_speech: String,
+    pub definition: String,
+    pub languages: Vec<&'static str>,
+}
+
+/// A compiled word contract, replaced whole when the schema is reloaded.
+struct Contract {
+    value: Value,
+    compiled: JSONSchema,
+    /// [`content_hash`] of `value`
+    hash: String,
+}
+
+impl Contract {
+    /// Compile `source` widened to what `profiles` allow; the hash stays that
+    /// of `source`, so reloading the same file is still a no-op.
+    fn compile(source: Value, profiles: &ContractProfiles) -> Result<Self, ValidationError> {
+        let hash = content_hash(&source);
+        let mut value = source;
+        profiles.widen_schema(&mut value);
+        let compiled = JSONSchema::options()
+            .with_draft(Draft::Draft202012)
+            .compile(&value)
+            .map_err(|e| ValidationError::SchemaUnavailable(e.to_string()))?;
+        Ok(Self { hash, value, compiled })
+    }
+}
+
+pub struct Validator {
+    case_policy: CasePolicy,
+    check_cefr: bool,
+    /// Hand back entries in [`canonicalize`]d form
+    canonical: bool,
+    /// Categories allowed per entry language
+    profiles: ContractProfiles,
+    /// The embedded schema ([`SCHEMA_VERSION`]) compiled once up front, until
+    /// [`reload`](Self::reload) swaps in another
+    contract: RwLock<Arc<Contract>>,
+}
 
 impl Validator {
     pub fn new(_schema_src: &str) -> Result<Self> {
-        Ok(Self)
+        let started = Instant::now();
+        let contract = Contract::compile(SCHEMA_VALUE.clone(), &ContractProfiles::default())?;
+        info!(
+            "Compiled word contract schema v{} in {:.2?}",
+            SCHEMA_VERSION,
+            started.elapsed()
+        );
+        Ok(Self {
+            case_policy: CasePolicy::default(),
+            check_cefr: false,
+            canonical: false,
+            profiles: ContractProfiles::default(),
+            contract: RwLock::new(Arc::new(contract)),
+        })
+    }
+
+    /// Validate against `schema_src` from now on, e.g. a contract tweaked to
+    /// allow a new optional field, without restarting. The schema is parsed and
+    /// compiled before anything changes, so a broken one leaves the current
+    /// contract in force; validations already running finish against the
+    /// schema they started with. Returns whether the schema changed.
+    ///
+    /// Entries keep being recorded as [`SCHEMA_VERSION`]: reloads are for
+    /// compatible changes, not for a new contract version.
+    pub fn reload(&self, schema_src: &str) -> Result<bool, ValidationError> {
+        let value: Value = serde_json::from_str(schema_src)
+            .map_err(|e| ValidationError::SchemaUnavailable(format!("schema is not valid JSON: {}", e)))?;
+        if !value.is_object() {
+            return Err(ValidationError::SchemaUnavailable("schema is not a JSON object".to_string()));
+        }
+        if content_hash(&value) == self.contract.read().hash {
+            return Ok(false);
+        }
+        let started = Instant::now();
+        let contract = Contract::compile(value, &self.profiles)?;
+        info!("Compiled reloaded word contract schema {} in {:.2?}", &contract.hash[..12], started.elapsed());
+        *self.contract.write() = Arc::new(contract);
+        Ok(true)
+    }
+
+    /// SHA-256 of the canonical JSON of the schema in force.
+    pub fn schema_hash(&self) -> String {
+        self.contract.read().hash.clone()
+    }
+
+    /// Decide the `word` field's casing by `policy` instead of always echoing the request.
+    pub fn with_case_policy(mut self, policy: CasePolicy) -> Self {
+        self.case_policy = policy;
+        self
+    }
+
+    /// Move `difficulty` into the band of the word's level in the embedded CEFR list.
+    pub fn with_cefr_check(mut self, enabled: bool) -> Self {
+        self.check_cefr = enabled;
+        self
+    }
+
+    /// Canonicalize every validated entry, so what is cached, stored, hashed
+    /// and served is the same for the same content.
+    pub fn with_canonical_output(mut self, enabled: bool) -> Self {
+        self.canonical = enabled;
+        self
+    }
+
+    /// Check parts of speech, difficulty and required meaning fields against
+    /// the profile of each entry's language instead of the English defaults.
+    ///
+    /// The schema in force is widened to accept their categories too.
+    pub fn with_contract_profiles(mut self, profiles: ContractProfiles) -> Self {
+        let current = self.contract.read().clone();
+        let mut contract = Contract::compile(current.value.clone(), &profiles)
+            .expect("widening a compiled schema's enums keeps it compilable");
+        contract.hash = current.hash.clone();
+        self.contract = RwLock::new(Arc::new(contract));
+        self.profiles = profiles;
+        self
+    }
+
+    pub fn case_policy(&self) -> CasePolicy {
+        self.case_policy
+    }
+
+    pub fn contract_profiles(&self) -> &ContractProfiles {
+        &self.profiles
+    }
+
+    /// [`validate_and_fix`](Self::validate_and_fix) for a sense-targeted request:
+    /// the entry must also have a `part_of_speech` meaning, which is all it keeps.
+    pub fn validate_sense(&self, v: Value, surface_word: &str, part_of_speech: &str) -> Result<Value, ValidationError> {
+        let entry = self.validate_and_fix(v, surface_word)?;
+        restrict_to_sense(&entry, part_of_speech)
+            .ok_or_else(|| ValidationError::MissingSense(part_of_speech.to_string()))
+    }
+
+    /// Validate a full entry, then hold it to `max_meanings`: extra senses a
+    /// model added anyway are dropped, lowest ranked first.
+    pub fn validate_senses(
+        &self,
+        v: Value,
+        surface_word: &str,
+        max_meanings: usize,
+    ) -> Result<Value, ValidationError> {
+        let mut entry = self.validate_and_fix(v, surface_word)?;
+        limit_meanings(&mut entry, max_meanings);
+        Ok(entry)
+    }
+
+    /// Set the `word` field for a request of `surface_word`, per the case policy.
+    /// Also used on cache hits, where the entry may have been generated for another casing.
+    pub fn fix_word(&self, v: &mut Value, surface_word: &str) {
+        if let Some(obj) = v.as_object_mut() {
+            let word = self.case_policy
+                .surface(obj.get("word").and_then(Value::as_str), surface_word)
+                .to_string();
+            obj.insert("word".to_string(), Value::String(word));
+        }
     }
 
     /// Enhanced validation with detailed error reporting and automatic fixes
-    pub fn validate_and_fix(&self, mut v: Value, surface_word: &str) -> Result<Value> {
+    pub fn validate_and_fix(&self, mut v: Value, surface_word: &str) -> Result<Value, ValidationError> {
         debug!("Starting validation for word: {}", surface_word);
 
         // Step 1: Basic structure fixes
-        self.fix_basic_structure(&mut v, surface_word)?;
+        let profile = self.fix_basic_structure(&mut v, surface_word)?;
 
         // Step 2: Validate and fix meanings structure
-        self.validate_and_fix_meanings(&mut v)?;
+        self.validate_and_fix_meanings(&mut v, profile)?;
 
-        // Step 3: Apply schema validation with detailed error reporting
-        self.apply_schema_validation(&v)?;
+        // One contract for the rest, even if a reload lands meanwhile
+        let contract = self.contract.read().clone();
 
+        // Step 3: Drop keys the contract does not define, at any depth
+        let mut stripped = Vec::new();
+        strip_unknown_keys(&contract.value, &mut v, "", &mut stripped);
+        if !stripped.is_empty() {
+            warn!("Stripped keys not in the schema: {:?}", stripped);
+        }
+
+        // Step 4: Apply schema validation with detailed error reporting
+        self.apply_schema_validation(&contract.compiled, &v)?;
+
+        if self.canonical {
+            canonicalize(&mut v);
+        }
         debug!("Validation completed successfully for word: {}", surface_word);
         Ok(v)
     }
 
-    /// Fix basic structural issues and ensure required top-level fields
-    fn fix_basic_structure(&self, v: &mut Value, surface_word: &str) -> Result<()> {
-        let obj = v.as_object_mut()
-            .ok_or_else(|| anyhow!("Expected JSON object at root"))?;
+    /// When the only thing wrong with `v` is missing translation keys, list
+    /// them per meaning so they can be filled in without regenerating the
+    /// entry. `None` when nothing is missing or something else is also wrong.
+    pub fn translation_gaps(&self, v: &Value, surface_word: &str) -> Option<Vec<TranslationGap>> {
+        let meanings = v.get("meanings")?.as_array()?;
+        let mut gaps = Vec::new();
+        let mut patched = v.clone();
+        for (idx, meaning) in meanings.iter().enumerate() {
+            let translations = meaning.get("translations");
+            if translations.is_some_and(|t| !t.is_object()) {
+                return None;
+            }
+            let languages: Vec<&'static str> = TRANSLATION_LANGS
+                .iter()
+                .copied()
+                .filter(|lang| translations.and_then(|t| t.get(*lang)).is_none())
+                .collect();
+            if languages.is_empty() {
+                continue;
+            }
+            let slot = patched["meanings"][idx]
+                .as_object_mut()?
+                .entry("translations")
+                .or_insert_with(|| Value::Object(Default::default()));
+            for lang in &languages {
+                slot[*lang] = Value::String(lang.to_string());
+            }
+            gaps.push(TranslationGap {
+                meaning: idx,
+                part_of_speech: meaning.get("partOfSpeech")?.as_str()?.to_string(),
+                definition: meaning.get("definition")?.as_str()?.to_string(),
+                languages,
+            });
+        }
+        // With placeholders in place the rest of the entry must pass as is
+        if gaps.is_empty() || self.validate_and_fix(patched, surface_word).is_err() {
+            return None;
+        }
+        Some(gaps)
+    }
 
+    /// Fix basic structural issues and ensure required top-level fields,
+    /// returning the contract profile of the entry's language
+    fn fix_basic_structure(&self, v: &mut Value, surface_word: &str) -> Result<&ContractProfile, ValidationError> {
         // Ensure word matches surface word
-        obj.insert("word".to_string(), Value::String(surface_word.to_string()));
+        self.fix_word(v, surface_word);
+
+        let obj = v.as_object_mut()
+            .ok_or_else(|| ValidationError::Malformed("expected JSON object at root".to_string()))?;
 
         // Validate required top-level fields exist
         let required_fields = ["baseForm", "phonetic", "difficulty", "language", "meanings"];
         for field in &required_fields {
             if !obj.contains_key(*field) {
-                return Err(anyhow!(ValidationErrorType::MissingRequiredField(field.to_string())));
+                return Err(ValidationError::MissingRequiredField(field.to_string()));
             }
         }
 
-        // Validate language is "english"
-        if let Some(lang) = obj.get("language").and_then(|l| l.as_str()) {
-            if lang != "english" {
-                warn!("Language was '{}', correcting to 'english'", lang);
-                obj.insert("language".to_string(), Value::String("english".to_string()));
+        // Pick the language's contract profile. With one profile configured,
+        // any other language is corrected to it; with several, a language
+        // without a profile must not pass on another language's categories.
+        let lang = obj.get("language").and_then(|l| l.as_str()).map(|l| l.trim().to_lowercase());
+        let profile = match (lang, self.profiles.default_profile()) {
+            (Some(lang), _) if self.profiles.get(&lang).is_some() => {
+                obj.insert("language".to_string(), Value::String(lang.clone()));
+                self.profiles.get(&lang).expect("checked above")
             }
-        }
+            (lang, Some(profile)) => {
+                if let Some(lang) = lang {
+                    warn!("Language was '{}', correcting to '{}'", lang, profile.language);
+                    obj.insert("language".to_string(), Value::String(profile.language.clone()));
+                }
+                profile
+            }
+            (Some(lang), None) => {
+                return Err(ValidationError::InvalidFieldValue {
+                    field: "language".to_string(),
+                    reason: format!(
+                        "'{}' has no contract profile (configured: {})",
+                        lang,
+                        self.profiles.languages().collect::<Vec<_>>().join(", ")
+                    ),
+                });
+            }
+            // Left for the schema to reject
+            (None, None) => self.profiles.get(DEFAULT_LANGUAGE).expect("the English profile is always loaded"),
+        };
 
         // Validate difficulty is one of the accepted values
         if let Some(diff) = obj.get("difficulty").and_then(|d| d.as_str()) {
-            if !["beginner", "intermediate", "advanced"].contains(&diff) {
-                warn!("Invalid difficulty '{}', setting to 'intermediate'", diff);
-                obj.insert("difficulty".to_string(), Value::String("intermediate".to_string()));
+            if !profile.allows_difficulty(diff) {
+                let fallback = profile.fallback_difficulty();
+                warn!("Invalid difficulty '{}', setting to '{}'", diff, fallback);
+                obj.insert("difficulty".to_string(), Value::String(fallback.to_string()));
+            }
+        }
+
+        // Cross-check difficulty against the CEFR list where the word is listed
+        if self.check_cefr && profile.language == DEFAULT_LANGUAGE {
+            let listed = obj.get("baseForm").and_then(|b| b.as_str()).and_then(cefr::listed);
+            if let Some(level) = listed {
+                let band = level.difficulty();
+                if obj.get("difficulty").and_then(|d| d.as_str()) != Some(band) {
+                    warn!("Difficulty disagrees with CEFR {}, setting to '{}'", level.as_str(), band);
+                    obj.insert("difficulty".to_string(), Value::String(band.to_string()));
+                }
             }
         }
 
@@ -98,56 +599,51 @@ impl Validator {
                 };
                 obj.insert("phonetic".to_string(), Value::String(normalized));
             } else {
-                return Err(anyhow!(ValidationErrorType::InvalidPhonetic(
+                return Err(ValidationError::InvalidPhonetic(
                     "phonetic must be a string".to_string()
-                )));
+                ));
             }
         }
 
-        Ok(())
+        Ok(profile)
     }
 
-    /// Validate and fix meanings array structure
-    fn validate_and_fix_meanings(&self, v: &mut Value) -> Result<()> {
+    /// Validate and fix meanings array structure against the language's profile
+    fn validate_and_fix_meanings(&self, v: &mut Value, profile: &ContractProfile) -> Result<(), ValidationError> {
         let meanings = v.get_mut("meanings").and_then(|m| m.as_array_mut())
-            .ok_or_else(|| anyhow!(ValidationErrorType::MissingRequiredField("meanings".to_string())))?;
+            .ok_or_else(|| ValidationError::MissingRequiredField("meanings".to_string()))?;
 
         if meanings.is_empty() {
-            return Err(anyhow!(ValidationErrorType::InsufficientMeanings));
+            return Err(ValidationError::InsufficientMeanings);
         }
 
         // Validate unique partOfSpeech across meanings
         let mut seen_pos = HashSet::new();
-        let valid_pos = [
-            "noun", "verb", "adjective", "adverb", "pronoun", "preposition",
-            "conjunction", "interjection", "article", "determiner", "numeral",
-            "participle", "gerund"
-        ];
 
         for (idx, meaning) in meanings.iter_mut().enumerate() {
             let meaning_obj = meaning.as_object_mut()
-                .ok_or_else(|| anyhow!("Meaning {} must be an object", idx))?;
+                .ok_or_else(|| ValidationError::Malformed(format!("meaning {} must be an object", idx)))?;
 
             // Validate and normalize partOfSpeech
             if let Some(pos) = meaning_obj.get("partOfSpeech").and_then(|p| p.as_str()) {
                 let pos_lower = pos.to_lowercase();
-                if !valid_pos.contains(&pos_lower.as_str()) {
-                    return Err(anyhow!(ValidationErrorType::InvalidFieldValue {
+                if !profile.allows_part_of_speech(&pos_lower) {
+                    return Err(ValidationError::InvalidFieldValue {
                         field: "partOfSpeech".to_string(),
-                        reason: format!("'{}' is not a valid part of speech", pos)
-                    }));
+                        reason: format!("'{}' is not a valid {} part of speech", pos, profile.language)
+                    });
                 }
 
                 if !seen_pos.insert(pos_lower.clone()) {
-                    return Err(anyhow!(ValidationErrorType::DuplicatePartOfSpeech(pos.to_string())));
+                    return Err(ValidationError::DuplicatePartOfSpeech(pos.to_string()));
                 }
 
                 // Normalize to lowercase
                 meaning_obj.insert("partOfSpeech".to_string(), Value::String(pos_lower));
             } else {
-                return Err(anyhow!(ValidationErrorType::MissingRequiredField(
+                return Err(ValidationError::MissingRequiredField(
                     format!("partOfSpeech in meaning {}", idx)
-                )));
+                ));
             }
 
             // Validate and fix synonyms/antonyms arrays
@@ -173,53 +669,84 @@ impl Validator {
             }
 
             // Validate required meaning fields
-            let required_meaning_fields = ["definition", "exampleSentence", "grammarTip", "translations"];
-            for field in &required_meaning_fields {
-                if !meaning_obj.contains_key(*field) {
-                    return Err(anyhow!(ValidationErrorType::MissingRequiredField(
+            for field in &profile.required_meaning_fields {
+                if !meaning_obj.contains_key(field) {
+                    return Err(ValidationError::MissingRequiredField(
                         format!("{} in meaning {}", field, idx)
-                    )));
+                    ));
                 }
             }
 
             // Validate translations object
             if let Some(translations) = meaning_obj.get("translations").and_then(|t| t.as_object()) {
-                let required_langs = ["es", "fr", "de", "zh", "ja", "it", "pt", "ru", "ar"];
-                for lang in &required_langs {
+                for lang in &TRANSLATION_LANGS {
                     if !translations.contains_key(*lang) {
-                        return Err(anyhow!(ValidationErrorType::MissingRequiredField(
+                        return Err(ValidationError::MissingRequiredField(
                             format!("translation for '{}' in meaning {}", lang, idx)
-                        )));
+                        ));
                     }
                 }
             }
         }
 
+        Self::order_by_sense_rank(meanings)?;
+
         Ok(())
     }
 
-    /// Apply JSON Schema validation with enhanced error reporting
-    fn apply_schema_validation(&self, v: &Value) -> Result<()> {
-        static SCHEMA_VALUE: Lazy<Value> = Lazy::new(|| {
-            serde_json::from_str(include_str!("../schema/word_contract.schema.json"))
-                .expect("valid schema JSON")
-        });
+    /// Ensure meanings run from most to least common sense. When the model emits
+    /// `senseRank`, sort by it; otherwise trust array order. Ranks are then renumbered
+    /// 1..n so clients can rely on `meanings[0]` being the primary sense.
+    fn order_by_sense_rank(meanings: &mut [Value]) -> Result<(), ValidationError> {
+        let mut ranks = Vec::with_capacity(meanings.len());
+        for (idx, meaning) in meanings.iter().enumerate() {
+            match meaning.get("senseRank") {
+                None => ranks.push(None),
+                Some(rank) => {
+                    let rank = rank.as_u64().filter(|r| *r >= 1).ok_or_else(|| {
+                        ValidationError::InvalidFieldValue {
+                            field: format!("senseRank in meaning {}", idx),
+                            reason: format!("'{}' is not a positive integer", rank),
+                        }
+                    })?;
+                    ranks.push(Some(rank));
+                }
+            }
+        }
 
-        let compiled: JSONSchema = JSONSchema::options()
-            .with_draft(Draft::Draft202012)
-            .compile(&SCHEMA_VALUE)
-            .context("Failed to compile JSON schema")?;
+        if ranks.iter().all(Option::is_some) {
+            let mut indexed: Vec<(u64, Value)> = ranks
+                .into_iter()
+                .flatten()
+                .zip(meanings.iter_mut().map(Value::take))
+                .collect();
+            if indexed.windows(2).any(|w| w[0].0 > w[1].0) {
+                debug!("Reordering meanings by senseRank");
+            }
+            // Stable sort keeps model order for tied ranks
+            indexed.sort_by_key(|(rank, _)| *rank);
+            for (slot, (_, meaning)) in meanings.iter_mut().zip(indexed) {
+                *slot = meaning;
+            }
+        } else if ranks.iter().any(Option::is_some) {
+            warn!("senseRank present on only some meanings; keeping array order");
+        }
+
+        for (idx, meaning) in meanings.iter_mut().enumerate() {
+            if let Some(obj) = meaning.as_object_mut() {
+                obj.insert("senseRank".to_string(), Value::from(idx as u64 + 1));
+            }
+        }
+        Ok(())
+    }
 
-        let validation_result = compiled.validate(v);
+    /// Apply JSON Schema validation with enhanced error reporting
+    fn apply_schema_validation(&self, schema: &JSONSchema, v: &Value) -> Result<(), ValidationError> {
+        let validation_result = schema.validate(v);
         if let Err(errors) = validation_result {
-            let error_messages: Vec<String> = errors
-                .take(5) // Limit to first 5 errors to avoid overwhelming output
-                .map(|error| format!("at {}: {:?}", error.instance_path, error.kind))
-                .collect();
+            let violations = errors.map(Violation::from_schema_error).collect();
 
-            return Err(anyhow!(ValidationErrorType::SchemaValidation(
-                error_messages.join("; ")
-            )));
+            return Err(ValidationError::SchemaValidation(violations));
         }
 
         Ok(())
@@ -271,6 +798,46 @@ mod tests {
         assert_eq!(ant, &vec![Value::String("opposite".into())]);
     }
 
+    #[test]
+    fn canonical_output_is_the_same_for_the_same_content() {
+        let canonical = Validator::new("").unwrap().with_canonical_output(true);
+        let mut v = base_json();
+        v["meanings"][0]["synonyms"] = serde_json::json!(["beta", "alpha"]);
+        let out = canonical.validate_and_fix(v.clone(), "ignored").unwrap();
+        let keys: Vec<&String> = out.as_object().unwrap().keys().collect();
+        assert_eq!(keys, ["baseForm", "difficulty", "language", "meanings", "phonetic", "word"]);
+        assert_eq!(out["meanings"][0]["synonyms"], serde_json::json!(["alpha", "beta"]));
+
+        // The model listing the same synonyms in another order changes nothing
+        v["meanings"][0]["synonyms"] = serde_json::json!(["alpha", "beta"]);
+        let again = canonical.validate_and_fix(v, "ignored").unwrap();
+        assert_eq!(again.to_string(), out.to_string());
+    }
+
+    #[test]
+    fn cefr_list_corrects_difficulty_when_enabled() {
+        let mut v = base_json();
+        v["baseForm"] = Value::String("ubiquitous".into());
+        let out = Validator::new("").unwrap().validate_and_fix(v.clone(), "ubiquitous").unwrap();
+        assert_eq!(out["difficulty"], "beginner");
+        let checked = Validator::new("").unwrap().with_cefr_check(true);
+        let out = checked.validate_and_fix(v, "ubiquitous").unwrap();
+        assert_eq!(out["difficulty"], "advanced");
+    }
+
+    #[test]
+    fn case_policy_decides_word_casing() {
+        let mut v = base_json();
+        v["word"] = Value::String("Polish".into());
+        let preserve = Validator::new("").unwrap().with_case_policy(CasePolicy::Preserve);
+        let out = preserve.validate_and_fix(v.clone(), "polish").unwrap();
+        assert_eq!(out["word"], "Polish");
+
+        let fold = Validator::new("").unwrap().with_case_policy(CasePolicy::Fold);
+        let out = fold.validate_and_fix(v, "polish").unwrap();
+        assert_eq!(out["word"], "polish");
+    }
+
     #[test]
     fn duplicate_pos_errors() {
         let mut v = base_json();
@@ -289,6 +856,181 @@ mod tests {
             }));
         }
         let res = Validator::new("").unwrap().validate_and_fix(v, "Surface");
-        assert!(res.is_err(), "expected error on duplicate partOfSpeech");
+        assert!(
+            matches!(res, Err(ValidationError::DuplicatePartOfSpeech(_))),
+            "expected error on duplicate partOfSpeech"
+        );
+    }
+
+    #[test]
+    fn missing_translation_is_repairable_not_retryable() {
+        let mut v = base_json();
+        v["meanings"][0]["translations"]
+            .as_object_mut()
+            .unwrap()
+            .remove("ja");
+        let err = Validator::new("")
+            .unwrap()
+            .validate_and_fix(v, "Surface")
+            .unwrap_err();
+        assert!(matches!(err, ValidationError::MissingRequiredField(_)));
+        assert!(err.is_repairable());
+        assert!(!err.is_retryable());
+        assert_eq!(err.violations()[0].path, "/meanings/0/translations/ja");
+    }
+
+    #[test]
+    fn fixes_are_counted_at_the_leaves() {
+        let raw = serde_json::json!({ "word": "Run", "phonetic": "rʌn", "meanings": [{ "synonyms": ["Dash", "dash"] }] });
+        let fixed = serde_json::json!({ "word": "run", "phonetic": "/rʌn/", "meanings": [{ "synonyms": ["dash"], "senseRank": 1 }] });
+        // word, phonetic, the synonyms array's length and the added senseRank
+        assert_eq!(count_fixes(&raw, &fixed), 4);
+        assert_eq!(count_fixes(&fixed, &fixed), 0);
+    }
+
+    #[test]
+    fn keys_outside_the_schema_are_stripped() {
+        let mut v = base_json();
+        v["etymology"] = Value::from("Old English");
+        v["meanings"][0]["register"] = Value::from("formal");
+        v["meanings"][0]["translations"]["ko"] = Value::from("x");
+        let out = Validator::new("")
+            .unwrap()
+            .validate_and_fix(v, "Surface")
+            .unwrap();
+        assert!(out.get("etymology").is_none());
+        assert!(out["meanings"][0].get("register").is_none());
+        assert!(out["meanings"][0]["translations"].get("ko").is_none());
+        assert_eq!(out["meanings"][0]["translations"]["ja"], "x");
+    }
+
+    #[test]
+    fn schema_failures_list_every_violation() {
+        let mut v = base_json();
+        v["meanings"][0]["exampleSentence"] = Value::from(5);
+        v["meanings"][0]["grammarTip"] = Value::from(7);
+        let err = Validator::new("")
+            .unwrap()
+            .validate_and_fix(v, "Surface")
+            .unwrap_err();
+        let violations = err.violations();
+        let paths: Vec<_> = violations.iter().map(|v| v.path.as_str()).collect();
+        assert!(paths.contains(&"/meanings/0/exampleSentence"), "{:?}", violations);
+        assert!(paths.contains(&"/meanings/0/grammarTip"), "{:?}", violations);
+        assert!(violations.iter().all(|v| v.keyword == "type"), "{:?}", violations);
+        assert!(err.to_string().contains("at /meanings/0/exampleSentence: 5 is not of type"));
+    }
+
+    #[test]
+    fn translation_gaps_only_when_nothing_else_is_wrong() {
+        let validator = Validator::new("").unwrap();
+        let mut v = base_json();
+        let translations = v["meanings"][0]["translations"].as_object_mut().unwrap();
+        translations.remove("ja");
+        translations.remove("ar");
+        let gaps = validator.translation_gaps(&v, "Surface").unwrap();
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(gaps[0].meaning, 0);
+        assert_eq!(gaps[0].languages, vec!["ja", "ar"]);
+
+        v["meanings"][0].as_object_mut().unwrap().remove("exampleSentence");
+        assert!(validator.translation_gaps(&v, "Surface").is_none());
+        assert!(validator.translation_gaps(&base_json(), "Surface").is_none());
+    }
+
+    #[test]
+    fn meanings_sorted_by_sense_rank() {
+        let mut v = base_json();
+        let mut verb = v["meanings"][0].clone();
+        verb["partOfSpeech"] = Value::String("verb".into());
+        verb["senseRank"] = Value::from(1);
+        v["meanings"][0]["senseRank"] = Value::from(2);
+        v["meanings"].as_array_mut().unwrap().push(verb);
+
+        let out = Validator::new("")
+            .unwrap()
+            .validate_and_fix(v, "Surface")
+            .unwrap();
+        assert_eq!(out["meanings"][0]["partOfSpeech"], "verb");
+        assert_eq!(out["meanings"][0]["senseRank"], 1);
+        assert_eq!(out["meanings"][1]["partOfSpeech"], "noun");
+        assert_eq!(out["meanings"][1]["senseRank"], 2);
+    }
+
+    #[test]
+    fn missing_sense_rank_assigned_from_order() {
+        let out = Validator::new("")
+            .unwrap()
+            .validate_and_fix(base_json(), "Surface")
+            .unwrap();
+        assert_eq!(out["meanings"][0]["senseRank"], 1);
+    }
+
+    #[test]
+    fn reload_swaps_the_contract_and_keeps_it_on_errors() {
+        let validator = Validator::new("").unwrap();
+        let mut v = base_json();
+        v["meanings"][0]["register"] = Value::String("formal".into());
+        let out = validator.validate_and_fix(v.clone(), "Surface").unwrap();
+        assert!(out["meanings"][0].get("register").is_none());
+
+        let mut schema = SCHEMA_VALUE.clone();
+        schema["properties"]["meanings"]["items"]["properties"]["register"] = serde_json::json!({ "type": "string" });
+        let before = validator.schema_hash();
+        assert!(validator.reload(&schema.to_string()).unwrap());
+        assert!(!validator.reload(&schema.to_string()).unwrap());
+        assert_ne!(validator.schema_hash(), before);
+        let out = validator.validate_and_fix(v.clone(), "Surface").unwrap();
+        assert_eq!(out["meanings"][0]["register"], "formal");
+
+        let reloaded = validator.schema_hash();
+        assert!(matches!(validator.reload("{ not json"), Err(ValidationError::SchemaUnavailable(_))));
+        assert!(matches!(
+            validator.reload(r#"{"type": "no-such-type"}"#),
+            Err(ValidationError::SchemaUnavailable(_))
+        ));
+        assert_eq!(validator.schema_hash(), reloaded);
+    }
+
+    #[test]
+    fn entries_are_checked_against_their_language_profile() {
+        let profiles = ContractProfiles::from_json(
+            r#"{ "german": {
+                "parts_of_speech": ["noun", "verb", "article"],
+                "difficulties": ["a1", "a2", "b1"],
+                "required_meaning_fields": ["definition", "exampleSentence", "translations"]
+            } }"#,
+        )
+        .unwrap();
+        // The embedded schema needs no editing to accept German entries
+        let validator = Validator::new("").unwrap().with_contract_profiles(profiles);
+        assert_eq!(validator.schema_hash(), Validator::new("").unwrap().schema_hash());
+
+        let mut v = base_json();
+        v["language"] = Value::String("German".into());
+        v["difficulty"] = Value::String("a2".into());
+        v["meanings"][0].as_object_mut().unwrap().remove("grammarTip");
+        let out = validator.validate_and_fix(v.clone(), "Haus").unwrap();
+        assert_eq!(out["language"], "german");
+        assert_eq!(out["difficulty"], "a2");
+
+        // English meanings still need their grammar tip
+        let mut tipless = v.clone();
+        tipless["language"] = Value::String("english".into());
+        assert!(validator.validate_and_fix(tipless, "running").is_err());
+        v["meanings"][0]["grammarTip"] = Value::String("Capitalized, as all nouns.".into());
+
+        // An English-only category is not accepted for German
+        v["meanings"][0]["partOfSpeech"] = Value::String("gerund".into());
+        let err = validator.validate_and_fix(v.clone(), "Haus").unwrap_err();
+        assert!(err.to_string().contains("german part of speech"), "{}", err);
+        let mut english = v.clone();
+        english["language"] = Value::String("english".into());
+        assert!(validator.validate_and_fix(english, "running").is_ok());
+
+        // With several languages configured, one without a profile is rejected
+        v["language"] = Value::String("french".into());
+        let err = validator.validate_and_fix(v, "maison").unwrap_err();
+        assert!(err.to_string().contains("no contract profile"), "{}", err);
     }
 }
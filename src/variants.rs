@@ -0,0 +1,72 @@
+use once_cell::sync::Lazy;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Regional spelling groups from the embedded table, canonical spelling first.
+static GROUPS: Lazy<Vec<Vec<&'static str>>> = Lazy::new(|| {
+    include_str!("../data/spelling_variants.tsv")
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(|line| {
+            line.split('\t')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>()
+        })
+        .filter(|group| group.len() > 1)
+        .collect()
+});
+
+/// Group index of every spelling in the table.
+static INDEX: Lazy<HashMap<&'static str, usize>> = Lazy::new(|| {
+    GROUPS
+        .iter()
+        .enumerate()
+        .flat_map(|(i, group)| group.iter().map(move |spelling| (*spelling, i)))
+        .collect()
+});
+
+/// The spelling `word` is cached and stored under, if it is a listed variant.
+/// Matching is exact: the table is lowercase, so under the distinct case
+/// policy "Colour" stays its own word.
+pub fn canonical(word: &str) -> Option<&'static str> {
+    INDEX.get(word).map(|&i| GROUPS[i][0])
+}
+
+/// The other spellings of `word`, canonical first.
+pub fn alternatives(word: &str) -> Vec<&'static str> {
+    let word = word.trim().to_lowercase();
+    match INDEX.get(word.as_str()) {
+        Some(&i) => GROUPS[i].iter().copied().filter(|s| *s != word).collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Add `alternativeSpellings` to an entry on its way out, when its word has any.
+pub fn apply(mut entry: Value) -> Value {
+    let alternatives = entry["word"].as_str().map(alternatives).unwrap_or_default();
+    if let (false, Some(obj)) = (alternatives.is_empty(), entry.as_object_mut()) {
+        obj.insert("alternativeSpellings".to_string(), alternatives.into());
+    }
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn variants_resolve_to_one_spelling_and_list_the_others() {
+        assert_eq!(canonical("colour"), Some("color"));
+        assert_eq!(canonical("color"), Some("color"));
+        assert_eq!(canonical("Colour"), None);
+        assert_eq!(canonical("tree"), None);
+        assert_eq!(alternatives("Colour"), ["color"]);
+        assert_eq!(alternatives("color"), ["colour"]);
+
+        let entry = apply(json!({ "word": "colour" }));
+        assert_eq!(entry["alternativeSpellings"], json!(["color"]));
+        assert_eq!(apply(json!({ "word": "tree" })), json!({ "word": "tree" }));
+    }
+}
@@ -1,9 +1,28 @@
+use axum::extract::{Path, Query};
 use axum::{body::Body, http, response::Response, Router};
-use lingua_fast::api::routes;
-use lingua_fast::model::{InferParams, LlmBackend, PromptParts};
-use lingua_fast::validate::Validator;
+use lingua_fast::api::{self, router, AppState, PresentationQuery, SenseQuery, Tenanted, WordReq};
+use lingua_fast::cache::WordCache;
+use lingua_fast::config::CefrMode;
+use lingua_fast::daily::DailyWords;
+use lingua_fast::extract::ValidJson;
+use lingua_fast::fewshot::FewShotLibrary;
+use lingua_fast::health::Readiness;
+use lingua_fast::lists::WordLists;
+use lingua_fast::model::mock::MockBackend;
+use lingua_fast::model::readonly::ReadOnlyBackend;
+use lingua_fast::model::upstream::UpstreamBackend;
+use lingua_fast::model::{
+    prompt, BackendError, Degenerate, InferParams, LlmBackend, PromptParts, PromptTask, Token,
+};
+use lingua_fast::profile::Profiles;
+use lingua_fast::quota::UsageTracker;
+use lingua_fast::signing::{self, EntrySigner};
+use lingua_fast::store::EntryStore;
+use lingua_fast::tenant::{Namespace, Tenants};
+use lingua_fast::validate::{Validator, SCHEMA_VERSION};
 use serde_json::{json, Value};
 use std::sync::Arc;
+use std::time::Duration;
 use tower::util::ServiceExt;
 
 #[derive(Clone)]
@@ -12,11 +31,59 @@ struct FakeBackend;
 #[async_trait::async_trait]
 impl LlmBackend for FakeBackend {
     async fn infer_json(&self, _prompt: PromptParts, _p: &InferParams) -> anyhow::Result<Vec<u8>> {
+        // Focused repairs get every requested language back
+        if let PromptTask::Translations { languages, .. } = &_prompt.task {
+            let out: serde_json::Map<String, Value> = languages
+                .iter()
+                .map(|l| (l.clone(), Value::String(format!("repaired-{}", l))))
+                .collect();
+            return Ok(serde_json::to_vec(&out)?);
+        }
+        // Field regeneration rewrites strings and echoes anything else unchanged
+        if let PromptTask::Field { path, entry } = &_prompt.task {
+            let pointer = lingua_fast::patch::field_pointer(path).unwrap();
+            let value = match entry.pointer(&pointer) {
+                Some(Value::String(_)) => Value::String(format!("Regenerated {}.", path)),
+                other => other.cloned().unwrap_or(Value::Null),
+            };
+            return Ok(serde_json::to_vec(&json!({ "value": value }))?);
+        }
+        // Back-translations stay on sense except for "drifted"
+        if let PromptTask::BackTranslation { items } = &_prompt.task {
+            let english = if _prompt.user_word == "drifted" {
+                "pineapple"
+            } else {
+                "alpha"
+            };
+            let out: serde_json::Map<String, Value> = items
+                .iter()
+                .map(|(lang, _)| (lang.clone(), json!([english])))
+                .collect();
+            return Ok(serde_json::to_vec(&out)?);
+        }
+        // Raw prompts come back as prose, the way an unconstrained model answers
+        if let PromptTask::Raw { prompt } = &_prompt.task {
+            return Ok(format!("Sure! Here is my answer to: {}", prompt).into_bytes());
+        }
         // Simulate a backend error for specific input to exercise error handling
         if _prompt.user_word == "fail" {
             anyhow::bail!("backend failure for test word");
         }
-        let out = serde_json::json!({
+        if _prompt.user_word == "busy" {
+            return Err(BackendError::QueueTimeout(Duration::from_millis(50)).into());
+        }
+        // Loops until retried with repetition penalties raised
+        if _prompt.user_word == "looping" && _p.presence_penalty == 0.0 {
+            return Err(Degenerate("the same 3-token sequence repeated 16 times".into()).into());
+        }
+        if _prompt.user_word == "kaboom" {
+            panic!("simulated crash in the bindings");
+        }
+        // Missing required fields is a non-retryable validation failure
+        if _prompt.user_word == "gibberish" {
+            return Ok(br#"{"word":"gibberish"}"#.to_vec());
+        }
+        let mut out = serde_json::json!({
             "word": _prompt.user_word,
             "baseForm": _prompt.user_word.to_lowercase(),
             "phonetic": "tɛst",
@@ -27,7 +94,8 @@ impl LlmBackend for FakeBackend {
                     "partOfSpeech": "noun",
                     "definition": "This is a long enough definition to satisfy schema.",
                     "exampleSentence": "A valid example sentence.",
-                    "grammarTip": "A short useful tip.",
+                    // Lets tests observe which system prompt reached the backend
+                    "grammarTip": _prompt.system.strip_prefix("TIP:").unwrap_or("A short useful tip."),
                     "synonyms": ["Alpha", "alpha", "BETA"],
                     "antonyms": ["Opposite", "opposite"],
                     "translations": {
@@ -37,12 +105,90 @@ impl LlmBackend for FakeBackend {
                 }
             ]
         });
+        // Family answers mix in the headword, a duplicate and an unrelated word
+        if let PromptTask::Family = &_prompt.task {
+            let members = json!([
+                { "word": "decision", "partOfSpeech": "noun" },
+                { "word": "Decisive", "partOfSpeech": "adjective" },
+                { "word": "decision", "partOfSpeech": "noun" },
+                { "word": _prompt.user_word, "partOfSpeech": "verb" },
+                { "word": "resolve", "partOfSpeech": "verb" },
+            ]);
+            return Ok(serde_json::to_vec(
+                &json!({ "word": "x", "members": members }),
+            )?);
+        }
+        // Pronunciation of "decide"; "unmarked" leaves out the syllable boundaries
+        if let PromptTask::Pronunciation = &_prompt.task {
+            let ipa = if _prompt.user_word == "unmarked" {
+                "/ʌnmɑɹkt/"
+            } else {
+                "/dɪˈsaɪd/"
+            };
+            return Ok(serde_json::to_vec(&json!({
+                "word": _prompt.user_word,
+                "ipa": ipa,
+                "syllables": ["de", "cide"],
+                "stress": 2,
+                "minimalPairs": [
+                    { "word": "deride", "ipa": "/dɪˈɹaɪd/" },
+                    { "word": "beside", "ipa": "/bɪˈsaɪd/" }
+                ]
+            }))?);
+        }
+        // Extra examples: one keeper per requested sentence, plus a repeat of the
+        // entry's own example, one without the word and one for a missing sense
+        if let PromptTask::Examples { count, .. } = &_prompt.task {
+            let word = &_prompt.user_word;
+            let mut examples = vec![
+                json!({ "partOfSpeech": "noun", "sentence": "A valid example sentence." }),
+                json!({ "partOfSpeech": "noun", "sentence": "Nothing to see here." }),
+                json!({ "partOfSpeech": "verb", "sentence": format!("To {} is a verb.", word) }),
+            ];
+            examples.extend((1..=*count).map(|i| {
+                json!({ "partOfSpeech": "noun", "sentence": format!("New sentence {} with {}.", i, word) })
+            }));
+            return Ok(serde_json::to_vec(&json!({ "examples": examples }))?);
+        }
+        // Sense requests: every word lacks an interjection sense
+        if let PromptTask::Sense { part_of_speech } = &_prompt.task {
+            if part_of_speech == "interjection" {
+                return Ok(br#"{"senseNotFound": true}"#.to_vec());
+            }
+            out["meanings"][0]["partOfSpeech"] = Value::String(part_of_speech.clone());
+        }
+        // Otherwise valid, but only some translations: exercises the repair path
+        if _prompt.user_word == "untranslated" {
+            let translations = out["meanings"][0]["translations"].as_object_mut().unwrap();
+            translations.remove("ja");
+            translations.remove("ar");
+        }
         Ok(serde_json::to_vec(&out)?)
     }
+
+    // One token per whitespace-separated word
+    fn tokenize(&self, text: &str) -> Option<anyhow::Result<Vec<Token>>> {
+        let tokens = text
+            .split_whitespace()
+            .enumerate()
+            .map(|(id, word)| Token {
+                id: id as i32,
+                text: word.to_string(),
+            })
+            .collect();
+        Some(Ok(tokens))
+    }
 }
 
 fn test_router() -> Router {
-    let backend = FakeBackend;
+    router_with_store(None)
+}
+
+fn router_with_store(store: Option<Arc<EntryStore>>) -> Router {
+    router(test_state(store))
+}
+
+fn test_state(store: Option<Arc<EntryStore>>) -> AppState {
     let validator =
         Arc::new(Validator::new(include_str!("../schema/word_contract.schema.json")).unwrap());
     let params = InferParams {
@@ -51,8 +197,68 @@ fn test_router() -> Router {
         top_p: 0.9,
         min_p: 0.05,
         repeat_penalty: 1.1,
+        ..InferParams::default()
     };
-    routes(backend, validator, params)
+    AppState {
+        backend: Arc::new(FakeBackend),
+        validator,
+        params,
+        cache: Arc::new(
+            WordCache::new(100, None, false).with_negative_caching(Duration::from_secs(60), 2),
+        ),
+        store,
+        admin_token: Some(Arc::from(ADMIN_TOKEN)),
+        readiness: Arc::new(Readiness::default()),
+        system_prompt: Arc::from(prompt::DEFAULT_SYSTEM),
+        allow_system_prompt_override: false,
+        few_shot: Arc::new(FewShotLibrary::default()),
+        few_shot_count: 2,
+        few_shot_from_cache: false,
+        back_translation_check: false,
+        word_time_budget: None,
+        schema_file: None,
+        batch_concurrency: 4,
+        batch_failure_threshold: 1.0,
+        batch_retry_budget: 0,
+        max_word_chars: 100,
+        profiles: Arc::new(Profiles::default()),
+        usage: Arc::new(UsageTracker::default()),
+        cefr: CefrMode::Off,
+        daily_words: Arc::new(DailyWords::default()),
+        signer: None,
+        tenants: Arc::new(Tenants::default()),
+        trust_tenant_header: false,
+        tenant: None,
+        lists: Arc::new(WordLists::default()),
+        scheduler: None,
+    }
+}
+
+const ADMIN_TOKEN: &str = "test-admin-token";
+
+fn post_json(uri: &str, body: Value) -> http::Request<Body> {
+    http::Request::builder()
+        .method(http::Method::POST)
+        .uri(uri)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(serde_json::to_vec(&body).unwrap()))
+        .unwrap()
+}
+
+/// `req` carrying the admin token, as curator endpoints require.
+fn as_admin(mut req: http::Request<Body>) -> http::Request<Body> {
+    req.headers_mut().insert(
+        http::header::AUTHORIZATION,
+        format!("Bearer {ADMIN_TOKEN}").parse().unwrap(),
+    );
+    req
+}
+
+async fn body_json(res: Response) -> Value {
+    let bytes = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&bytes).unwrap()
 }
 
 #[tokio::test]
@@ -88,11 +294,15 @@ async fn batch_mixed_results() {
         .unwrap();
 
     let res: Response = app.clone().oneshot(req).await.unwrap();
-    assert_eq!(res.status(), http::StatusCode::OK);
+    assert_eq!(res.status(), http::StatusCode::MULTI_STATUS);
     let bytes = axum::body::to_bytes(res.into_body(), usize::MAX)
         .await
         .unwrap();
-    let arr: Vec<Value> = serde_json::from_slice(&bytes).unwrap();
+    let v: Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(v["summary"]["succeeded"], 2);
+    assert_eq!(v["summary"]["failed"], 1);
+    assert!(v["summary"]["duration_ms"].is_u64());
+    let arr = v["results"].as_array().unwrap();
     assert_eq!(arr.len(), 3);
     assert_eq!(arr[0]["word"], "ok1");
     assert!(arr[0]["ok"].as_bool().unwrap());
@@ -100,11 +310,123 @@ async fn batch_mixed_results() {
 
     // The fake backend returns an error for "fail"
     assert!(!arr[1]["ok"].as_bool().unwrap());
+    assert_eq!(arr[1]["code"], "INFERENCE_ERROR");
 
     assert_eq!(arr[2]["word"], "ok2");
     assert!(arr[2]["ok"].as_bool().unwrap());
 }
 
+#[tokio::test]
+async fn batch_map_format_keys_entries_by_word() {
+    let res = test_router()
+        .oneshot(post_json(
+            "/v1/words?result_format=map",
+            json!({"words": ["ok1", "fail", "ok1", "ok2"]}),
+        ))
+        .await
+        .unwrap();
+    let v = body_json(res).await;
+    assert_eq!(v["results"].as_array().unwrap().len(), 4);
+    let by_word = v["by_word"].as_object().unwrap();
+    assert_eq!(by_word.keys().collect::<Vec<_>>(), vec!["ok1", "ok2"]);
+    assert_eq!(by_word["ok2"]["word"], "ok2");
+
+    let res = test_router()
+        .oneshot(post_json("/v1/words", json!({"words": ["ok1"]})))
+        .await
+        .unwrap();
+    assert!(body_json(res).await.get("by_word").is_none());
+}
+
+#[tokio::test]
+async fn batch_concurrency_requested_per_call() {
+    let res = test_router()
+        .oneshot(post_json(
+            "/v1/words",
+            json!({"words": ["a"], "concurrency": 0}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::BAD_REQUEST);
+
+    let state = AppState {
+        backend: Arc::new(MockBackend::new(Duration::from_millis(50))),
+        ..test_state(None)
+    };
+    let started = std::time::Instant::now();
+    let res = router(state)
+        .oneshot(post_json(
+            "/v1/words",
+            json!({"words": ["one", "two", "three", "four"], "concurrency": 1}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    // One at a time: four sequential 50 ms inferences
+    assert!(started.elapsed() >= Duration::from_millis(200));
+}
+
+#[tokio::test]
+async fn batch_status_reflects_failure_rate() {
+    let app = test_router();
+    let res = app
+        .clone()
+        .oneshot(post_json("/v1/words", json!({"words": ["ok1", "ok2"]})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+
+    let res = app
+        .oneshot(post_json("/v1/words", json!({"words": ["fail", "fail"]})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::BAD_GATEWAY);
+    assert_eq!(body_json(res).await["summary"]["failed"], 2);
+
+    // A lower threshold trips on partial failure
+    let state = AppState {
+        batch_failure_threshold: 0.5,
+        ..test_state(None)
+    };
+    let res = router(state)
+        .oneshot(post_json("/v1/words", json!({"words": ["ok1", "fail"]})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::BAD_GATEWAY);
+}
+
+#[tokio::test]
+async fn batch_fails_fast_once_its_retry_budget_is_spent() {
+    let state = AppState {
+        batch_retry_budget: 2,
+        ..test_state(None)
+    };
+    let res = router(state)
+        .oneshot(post_json(
+            "/v1/words",
+            json!({"words": ["fail", "fail", "ok1", "fail"], "concurrency": 1}),
+        ))
+        .await
+        .unwrap();
+    let v = body_json(res).await;
+    // The first word spends the budget; the rest of the batch needing the
+    // model is skipped without a single attempt
+    assert!(v["results"][0]["error"]
+        .as_str()
+        .unwrap()
+        .contains("after 3 attempts"));
+    assert_eq!(v["results"][0]["attempts"], 3);
+    assert!(v["results"][1].get("attempts").is_none());
+    for i in 1..4 {
+        assert_eq!(v["results"][i]["code"], "INFERENCE_ERROR");
+        assert!(v["results"][i]["error"]
+            .as_str()
+            .unwrap()
+            .contains("retry budget"));
+    }
+    assert_eq!(v["summary"]["retry_budget_exhausted"], true);
+}
+
 #[tokio::test]
 async fn single_word_backend_error() {
     let app = test_router();
@@ -119,6 +441,156 @@ async fn single_word_backend_error() {
     let res: Response = app.oneshot(req).await.unwrap();
     // Backend inference failures are treated as temporary and mapped to 503
     assert_eq!(res.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+    let v = body_json(res).await;
+    assert_eq!(v["attempts"], 3);
+    assert_eq!(
+        v["retry_reasons"],
+        json!(["backend_error", "backend_error"])
+    );
+}
+
+#[tokio::test]
+async fn pos_restricts_the_entry_to_one_sense() {
+    let app = test_router();
+
+    // The full entry already has a noun sense
+    let res = app
+        .clone()
+        .oneshot(post_json("/v1/word?pos=noun", json!({"word": "bark"})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    let v = body_json(res).await;
+    assert_eq!(v["meanings"].as_array().unwrap().len(), 1);
+    assert_eq!(v["meanings"][0]["partOfSpeech"], "noun");
+
+    // It has no verb sense, so that one is generated on its own
+    let res = app
+        .clone()
+        .oneshot(post_json(
+            "/v1/word",
+            json!({"word": "bark", "pos": "Verb"}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    let v = body_json(res).await;
+    assert_eq!(v["meanings"][0]["partOfSpeech"], "verb");
+    assert_eq!(v["meanings"][0]["senseRank"], 1);
+
+    let res = app
+        .clone()
+        .oneshot(post_json(
+            "/v1/word?pos=interjection",
+            json!({"word": "bark"}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::NOT_FOUND);
+    assert_eq!(body_json(res).await["code"], "SENSE_NOT_FOUND");
+
+    let res = app
+        .oneshot(post_json("/v1/word?pos=banana", json!({"word": "bark"})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn max_meanings_and_granularity_limit_the_senses() {
+    let app = test_router();
+
+    let res = app
+        .clone()
+        .oneshot(post_json(
+            "/v1/word",
+            json!({"word": "bark", "max_meanings": 1}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    let v = body_json(res).await;
+    assert_eq!(v["meanings"].as_array().unwrap().len(), 1);
+    assert_eq!(v["meanings"][0]["senseRank"], 1);
+
+    let res = app
+        .clone()
+        .oneshot(post_json(
+            "/v1/word",
+            json!({"word": "bark", "granularity": "coarse", "max_meanings": 1}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    let v = body_json(res).await;
+    assert_eq!(v["word"], "bark");
+    assert_eq!(v["meanings"].as_array().unwrap().len(), 1);
+
+    for body in [
+        json!({"word": "bark", "max_meanings": 5}),
+        json!({"word": "bark", "granularity": "medium"}),
+    ] {
+        let res = app
+            .clone()
+            .oneshot(post_json("/v1/word", body))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+    }
+}
+
+#[tokio::test]
+async fn family_lists_related_words_sharing_the_stem() {
+    let app = test_router();
+    let res = app
+        .clone()
+        .oneshot(post_json("/v1/family", json!({"word": "decide"})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    let v = body_json(res).await;
+    assert_eq!(v["word"], "decide");
+    assert_eq!(
+        v["members"],
+        json!([
+            { "word": "decision", "partOfSpeech": "noun" },
+            { "word": "decisive", "partOfSpeech": "adjective" },
+        ])
+    );
+
+    let res = app
+        .oneshot(post_json("/v1/family", json!({"word": "12345"})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn pronunciation_checks_syllables_against_the_ipa() {
+    let app = test_router();
+    let res = app
+        .clone()
+        .oneshot(post_json("/v1/pronunciation", json!({"word": "decide"})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    let v = body_json(res).await;
+    assert_eq!(v["syllables"], json!(["de", "cide"]));
+    assert_eq!(v["stress"], 2);
+    assert_eq!(v["minimalPairs"].as_array().unwrap().len(), 2);
+
+    let res = app
+        .oneshot(post_json("/v1/pronunciation", json!({"word": "unmarked"})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+    let v = body_json(res).await;
+    assert_eq!(v["code"], "VALIDATION_ERROR");
+    assert!(v["details"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|d| d["path"] == "/ipa"));
 }
 
 #[tokio::test]
@@ -136,3 +608,1876 @@ async fn single_word_bad_request() {
     let res: Response = app.oneshot(req).await.unwrap();
     assert_eq!(res.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
 }
+
+#[tokio::test]
+async fn admin_cache_requires_token() {
+    let app = test_router();
+    let res = app
+        .oneshot(post_json("/admin/cache/purge", json!({})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn admin_raw_returns_unvalidated_output() {
+    let app = test_router();
+    let body = json!({
+        "prompt": "Describe \"cat\" as JSON.",
+        "max_tokens": 32,
+        "presence_penalty": 0.5
+    });
+    let res = app
+        .clone()
+        .oneshot(post_json("/admin/raw", body.clone()))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::UNAUTHORIZED);
+
+    let mut req = post_json("/admin/raw", body);
+    req.headers_mut().insert(
+        http::header::AUTHORIZATION,
+        format!("Bearer {ADMIN_TOKEN}").parse().unwrap(),
+    );
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    let v = body_json(res).await;
+    assert_eq!(
+        v["output"],
+        "Sure! Here is my answer to: Describe \"cat\" as JSON."
+    );
+    assert_eq!(v["output_is_json"], false);
+    assert_eq!(v["params"]["max_tokens"], 32);
+    assert_eq!(v["params"]["presence_penalty"], 0.5);
+    assert_eq!(v["params"]["repeat_last_n"], 64);
+    assert!(v["timing"]["elapsed_ms"].is_u64());
+    assert_eq!(v["timing"]["prompt_tokens"], 4);
+}
+
+#[tokio::test]
+async fn admin_cache_delete_and_purge() {
+    let app = test_router();
+    let res = app
+        .clone()
+        .oneshot(post_json("/v1/word", json!({"word":"cached"})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+
+    let delete = |word: &str| {
+        http::Request::builder()
+            .method(http::Method::DELETE)
+            .uri(format!("/admin/cache/{word}"))
+            .header(http::header::AUTHORIZATION, format!("Bearer {ADMIN_TOKEN}"))
+            .body(Body::empty())
+            .unwrap()
+    };
+    let res = app.clone().oneshot(delete("cached")).await.unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    assert_eq!(body_json(res).await["purged"], 1);
+
+    let res = app.clone().oneshot(delete("cached")).await.unwrap();
+    assert_eq!(res.status(), http::StatusCode::NOT_FOUND);
+
+    app.clone()
+        .oneshot(post_json("/v1/word", json!({"word":"again"})))
+        .await
+        .unwrap();
+    let mut req = post_json("/admin/cache/purge", json!({"model": "unknown"}));
+    req.headers_mut().insert(
+        http::header::AUTHORIZATION,
+        format!("Bearer {ADMIN_TOKEN}").parse().unwrap(),
+    );
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    let v = body_json(res).await;
+    assert_eq!(v["purged"], 1);
+    assert_eq!(v["remaining"], 0);
+}
+
+#[tokio::test]
+async fn repeated_failures_are_negatively_cached() {
+    let app = test_router();
+    for _ in 0..2 {
+        let res = app
+            .clone()
+            .oneshot(post_json("/v1/word", json!({"word":"gibberish"})))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+        let v = body_json(res).await;
+        assert_eq!(v["error_type"], "validation_error");
+        assert_eq!(v["details"][0]["path"], "/baseForm");
+        assert_eq!(v["details"][0]["keyword"], "required");
+        assert_eq!(v["attempts"], 1);
+        assert_eq!(v["retry_reasons"], json!([]));
+    }
+
+    let res = app
+        .oneshot(post_json("/v1/word", json!({"word":"gibberish"})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+    let v = body_json(res).await;
+    assert_eq!(v["error_type"], "not_a_word");
+    assert_eq!(v["code"], "NOT_A_WORD");
+    assert_eq!(v["numeric_code"], 1002);
+    assert_eq!(v["retry_suggested"], false);
+}
+
+#[tokio::test]
+async fn missing_translations_are_repaired_not_regenerated() {
+    let res = test_router()
+        .oneshot(post_json("/v1/word", json!({"word":"untranslated"})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    let v = body_json(res).await;
+    let translations = &v["meanings"][0]["translations"];
+    assert_eq!(translations["ja"], "repaired-ja");
+    assert_eq!(translations["ar"], "repaired-ar");
+    assert_eq!(translations["es"], "x");
+}
+
+#[tokio::test]
+async fn regenerates_only_requested_fields() {
+    let app = test_router();
+    let res = app
+        .clone()
+        .oneshot(post_json("/v1/word", json!({"word":"curate"})))
+        .await
+        .unwrap();
+    let before = body_json(res).await;
+
+    let req = || {
+        post_json(
+            "/v1/word/curate/fields",
+            json!({"fields": ["meanings[0].exampleSentence"]}),
+        )
+    };
+    let res = app.clone().oneshot(req()).await.unwrap();
+    assert_eq!(res.status(), http::StatusCode::UNAUTHORIZED);
+    let res = app.clone().oneshot(as_admin(req())).await.unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    let v = body_json(res).await;
+    assert_eq!(
+        v["entry"]["meanings"][0]["exampleSentence"],
+        "Regenerated meanings[0].exampleSentence."
+    );
+    assert_eq!(
+        v["entry"]["meanings"][0]["definition"],
+        before["meanings"][0]["definition"]
+    );
+    assert_eq!(v["patch"].as_array().unwrap().len(), 1);
+
+    for (uri, fields, status) in [
+        (
+            "/v1/word/curate/fields",
+            json!(["word"]),
+            http::StatusCode::BAD_REQUEST,
+        ),
+        (
+            "/v1/word/curate/fields",
+            json!(["meanings[3].definition"]),
+            http::StatusCode::BAD_REQUEST,
+        ),
+        (
+            "/v1/word/unseen/fields",
+            json!(["phonetic"]),
+            http::StatusCode::NOT_FOUND,
+        ),
+    ] {
+        let res = app
+            .clone()
+            .oneshot(as_admin(post_json(uri, json!({ "fields": fields }))))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), status, "{} {}", uri, fields);
+    }
+}
+
+#[tokio::test]
+async fn full_queue_fails_fast_with_retry_hint() {
+    let started = std::time::Instant::now();
+    let res = test_router()
+        .oneshot(post_json("/v1/word", json!({"word":"busy"})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+    let v = body_json(res).await;
+    assert_eq!(v["code"], "INFERENCE_ERROR");
+    assert_eq!(v["retry_suggested"], true);
+    // No internal retries with their backoff sleeps
+    assert!(started.elapsed() < Duration::from_millis(400));
+}
+
+#[tokio::test]
+async fn looping_generation_is_retried_against_repetition() {
+    let started = std::time::Instant::now();
+    let res = test_router()
+        .oneshot(post_json("/v1/word", json!({"word":"looping"})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    assert_eq!(res.headers()["x-attempts"], "2");
+    assert_eq!(res.headers()["x-retry-reasons"], "degenerate");
+    assert_eq!(body_json(res).await["word"], "looping");
+    // Retried at once, without the backoff sleep
+    assert!(started.elapsed() < Duration::from_millis(400));
+}
+
+#[tokio::test]
+async fn panic_becomes_internal_error_response() {
+    let res = test_router()
+        .oneshot(post_json("/v1/word", json!({"word":"kaboom"})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+    let v = body_json(res).await;
+    assert_eq!(v["code"], "INTERNAL_ERROR");
+}
+
+#[tokio::test]
+async fn tokenize_counts_and_optionally_lists_tokens() {
+    let app = test_router();
+    let res = app
+        .clone()
+        .oneshot(post_json(
+            "/v1/tokenize",
+            json!({"text": "how many tokens"}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    let v = body_json(res).await;
+    assert_eq!(v["count"], 3);
+    assert!(v.get("tokens").is_none());
+
+    let res = app
+        .oneshot(post_json(
+            "/v1/tokenize",
+            json!({"text": "two tokens", "with_tokens": true}),
+        ))
+        .await
+        .unwrap();
+    let v = body_json(res).await;
+    assert_eq!(
+        v["tokens"],
+        json!([{"id": 0, "text": "two"}, {"id": 1, "text": "tokens"}])
+    );
+
+    // Backends without a local tokenizer say so rather than guessing
+    let state = AppState {
+        backend: Arc::new(MockBackend::default()),
+        ..test_state(None)
+    };
+    let res = router(state)
+        .oneshot(post_json("/v1/tokenize", json!({"text": "anything"})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::NOT_IMPLEMENTED);
+    assert_eq!(body_json(res).await["code"], "NOT_SUPPORTED");
+}
+
+#[tokio::test]
+async fn chat_completions_facade_wraps_word_analysis() {
+    let app = test_router();
+    let res = app
+        .clone()
+        .oneshot(post_json(
+            "/v1/chat/completions",
+            json!({
+                "model": "anything",
+                "temperature": 0.2,
+                "messages": [
+                    {"role": "system", "content": "Ignored; the server's prompt applies."},
+                    {"role": "user", "content": " swim "}
+                ]
+            }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    let v = body_json(res).await;
+    assert_eq!(v["object"], "chat.completion");
+    assert_eq!(v["choices"][0]["message"]["role"], "assistant");
+    assert_eq!(v["choices"][0]["finish_reason"], "stop");
+    let content = v["choices"][0]["message"]["content"].as_str().unwrap();
+    let entry: Value = serde_json::from_str(content).unwrap();
+    assert_eq!(entry["word"], "swim");
+    assert!(v["usage"]["total_tokens"].is_u64());
+
+    // Failures use OpenAI's error envelope with the service's status codes
+    let res = app
+        .clone()
+        .oneshot(post_json(
+            "/v1/chat/completions",
+            json!({"messages": [{"role": "user", "content": "12345"}]}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+    let v = body_json(res).await;
+    assert_eq!(v["error"]["type"], "invalid_request_error");
+    assert_eq!(v["error"]["code"], "NOT_A_WORD");
+
+    let res = app
+        .oneshot(post_json(
+            "/v1/chat/completions",
+            json!({"messages": [{"role": "system", "content": "no user turn"}]}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn chat_completions_accept_the_key_as_a_bearer_token() {
+    let profiles = Profiles::from_json(
+        r#"{
+            "profiles": { "trial": { "requests_per_minute": 5 } },
+            "keys": { "sdk-key": "trial" }
+        }"#,
+    )
+    .unwrap();
+    let app = router(AppState {
+        profiles: Arc::new(profiles),
+        ..test_state(None)
+    });
+    let bearer = |uri: &str, token: &str| {
+        let mut req = post_json(
+            uri,
+            json!({"messages": [{"role": "user", "content": "swim"}], "word": "swim"}),
+        );
+        req.headers_mut().insert(
+            http::header::AUTHORIZATION,
+            format!("Bearer {}", token).parse().unwrap(),
+        );
+        req
+    };
+
+    let res = app
+        .clone()
+        .oneshot(bearer("/v1/chat/completions", "sdk-key"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    assert_eq!(res.headers()["x-ratelimit-remaining"], "4");
+
+    let res = app
+        .clone()
+        .oneshot(bearer("/v1/chat/completions", "wrong-key"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::UNAUTHORIZED);
+
+    // Elsewhere a bearer token is not an API key
+    let res = app.oneshot(bearer("/v1/word", "sdk-key")).await.unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    assert!(res.headers().get("x-ratelimit-remaining").is_none());
+}
+
+#[tokio::test]
+async fn api_key_profiles_shape_responses() {
+    let profiles = Profiles::from_json(
+        r#"{
+            "profiles": { "cards": { "temp": 0.1, "languages": ["fr", "es"] } },
+            "keys": { "cards-key": "cards" }
+        }"#,
+    )
+    .unwrap();
+    let app = router(AppState {
+        profiles: Arc::new(profiles),
+        ..test_state(None)
+    });
+    let keyed = |key: &str| {
+        let mut req = post_json("/v1/word", json!({"word": "apple"}));
+        req.headers_mut().insert("x-api-key", key.parse().unwrap());
+        req
+    };
+
+    let res = app.clone().oneshot(keyed("cards-key")).await.unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    let v = body_json(res).await;
+    let translations = v["meanings"][0]["translations"].as_object().unwrap();
+    assert_eq!(translations.keys().collect::<Vec<_>>(), vec!["fr", "es"]);
+
+    let res = app.clone().oneshot(keyed("stolen-key")).await.unwrap();
+    assert_eq!(res.status(), http::StatusCode::UNAUTHORIZED);
+    assert_eq!(body_json(res).await["code"], "UNAUTHORIZED");
+
+    // Keyless requests keep the server defaults
+    let res = app
+        .oneshot(post_json("/v1/word", json!({"word": "apple"})))
+        .await
+        .unwrap();
+    let v = body_json(res).await;
+    assert_eq!(
+        v["meanings"][0]["translations"].as_object().unwrap().len(),
+        9
+    );
+}
+
+#[tokio::test]
+async fn api_key_limits_report_usage_and_reject_when_spent() {
+    let profiles = Profiles::from_json(
+        r#"{
+            "profiles": {
+                "trial": { "requests_per_minute": 2 },
+                "metered": { "tokens_per_day": 10 }
+            },
+            "keys": { "trial-key": "trial", "metered-key": "metered" }
+        }"#,
+    )
+    .unwrap();
+    let app = router(AppState {
+        profiles: Arc::new(profiles),
+        ..test_state(None)
+    });
+    let keyed = |key: &str, word: &str| {
+        let mut req = post_json("/v1/word", json!({ "word": word }));
+        req.headers_mut().insert("x-api-key", key.parse().unwrap());
+        req
+    };
+
+    let res = app
+        .clone()
+        .oneshot(keyed("trial-key", "apple"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    assert_eq!(res.headers()["x-ratelimit-remaining"], "1");
+    assert!(res.headers().get("x-quota-remaining-tokens").is_none());
+    app.clone()
+        .oneshot(keyed("trial-key", "apple"))
+        .await
+        .unwrap();
+    let res = app
+        .clone()
+        .oneshot(keyed("trial-key", "apple"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::TOO_MANY_REQUESTS);
+    assert!(res.headers().contains_key(http::header::RETRY_AFTER));
+    assert_eq!(res.headers()["x-ratelimit-remaining"], "0");
+    let v = body_json(res).await;
+    assert_eq!(v["code"], "RATE_LIMITED");
+    assert_eq!(v["retry_suggested"], true);
+
+    // Generating an entry spends the quota; the next request is refused
+    let res = app
+        .clone()
+        .oneshot(keyed("metered-key", "pear"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    assert_eq!(res.headers()["x-quota-remaining-tokens"], "0");
+    let res = app
+        .clone()
+        .oneshot(keyed("metered-key", "plum"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::PAYMENT_REQUIRED);
+    assert_eq!(body_json(res).await["code"], "QUOTA_EXCEEDED");
+
+    // Keyless callers carry no limits and see no usage headers
+    let res = app
+        .oneshot(post_json("/v1/word", json!({"word": "apple"})))
+        .await
+        .unwrap();
+    assert!(res.headers().get("x-ratelimit-remaining").is_none());
+}
+
+#[tokio::test]
+async fn regeneration_is_metered_like_generation() {
+    let profiles = Profiles::from_json(
+        r#"{
+            "profiles": { "metered": { "tokens_per_day": 10 } },
+            "keys": { "metered-key": "metered" }
+        }"#,
+    )
+    .unwrap();
+    let app = router(AppState {
+        profiles: Arc::new(profiles),
+        ..test_state(None)
+    });
+    let keyed = |key: &str, uri: &str, body: Value| {
+        let mut req = post_json(uri, body);
+        req.headers_mut().insert("x-api-key", key.parse().unwrap());
+        req
+    };
+
+    let res = app
+        .clone()
+        .oneshot(as_admin(keyed(
+            "unknown-key",
+            "/v1/word/pear/regenerate",
+            json!({}),
+        )))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::UNAUTHORIZED);
+
+    let res = app
+        .clone()
+        .oneshot(as_admin(keyed(
+            "metered-key",
+            "/v1/word/pear/regenerate",
+            json!({}),
+        )))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    assert_eq!(res.headers()["x-quota-remaining-tokens"], "0");
+    let res = app
+        .clone()
+        .oneshot(as_admin(keyed(
+            "metered-key",
+            "/v1/word/pear/fields",
+            json!({ "fields": ["phonetic"] }),
+        )))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::PAYMENT_REQUIRED);
+
+    let res = app
+        .oneshot(as_admin(keyed(
+            "unknown-key",
+            "/v1/word/pear/fields",
+            json!({ "fields": ["phonetic"] }),
+        )))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn non_word_inputs_rejected_before_inference() {
+    let app = test_router();
+    for input in [
+        "12345",
+        "1234x",
+        "https://example.com",
+        "fn main() {}",
+        "It ran. Then it stopped.",
+        "Stop! Go",
+        "aaaaaa",
+    ] {
+        let res = app
+            .clone()
+            .oneshot(post_json("/v1/word", json!({ "word": input })))
+            .await
+            .unwrap();
+        assert_eq!(
+            res.status(),
+            http::StatusCode::UNPROCESSABLE_ENTITY,
+            "{input}"
+        );
+        assert_eq!(body_json(res).await["error_type"], "not_a_word", "{input}");
+    }
+
+    // Multi-word expressions, hyphenated or apostrophised words, terms with
+    // digits and abbreviations still pass
+    for input in [
+        "give up",
+        "well-being",
+        "o'clock",
+        "naïve",
+        "3D",
+        "4K",
+        "2nd",
+        "St. Louis",
+        "e.g. something",
+    ] {
+        let res = app
+            .clone()
+            .oneshot(post_json("/v1/word", json!({ "word": input })))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), http::StatusCode::OK, "{input}");
+    }
+}
+
+#[tokio::test]
+async fn accept_language_orders_translations() {
+    let app = test_router();
+    let mut req = post_json("/v1/word", json!({"word":"hello"}));
+    req.headers_mut().insert(
+        http::header::ACCEPT_LANGUAGE,
+        "de-CH, ja;q=0.9, *;q=0.5".parse().unwrap(),
+    );
+    let v = body_json(app.clone().oneshot(req).await.unwrap()).await;
+    let meaning = &v["meanings"][0];
+    let keys: Vec<&String> = meaning["translations"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .collect();
+    assert_eq!(keys[..2], ["de", "ja"]);
+    assert_eq!(keys.len(), 9);
+    assert_eq!(meaning["primaryTranslation"], "x");
+
+    let mut req = post_json("/v1/word?translations=accepted", json!({"word":"hello"}));
+    req.headers_mut()
+        .insert(http::header::ACCEPT_LANGUAGE, "fr".parse().unwrap());
+    let v = body_json(app.oneshot(req).await.unwrap()).await;
+    let translations = v["meanings"][0]["translations"].as_object().unwrap();
+    assert_eq!(translations.keys().collect::<Vec<_>>(), ["fr"]);
+}
+
+#[tokio::test]
+async fn fields_parameter_projects_response() {
+    let app = test_router();
+    let res = app
+        .oneshot(post_json(
+            "/v1/word?fields=word,phonetic,meanings.definition",
+            json!({"word":"slim"}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    let v = body_json(res).await;
+    assert_eq!(
+        v.as_object().unwrap().keys().collect::<Vec<_>>(),
+        ["word", "phonetic", "meanings"]
+    );
+    let meaning = v["meanings"][0].as_object().unwrap();
+    assert_eq!(meaning.keys().collect::<Vec<_>>(), ["definition"]);
+}
+
+#[tokio::test]
+async fn regenerate_returns_patch_against_cached_entry() {
+    let app = test_router();
+    let res = app
+        .clone()
+        .oneshot(as_admin(post_json("/v1/word/fresh/regenerate", json!({}))))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    let v = body_json(res).await;
+    assert!(v["previous"].is_null());
+    assert_eq!(v["patch"][0]["op"], "add");
+    assert_eq!(v["patch"][0]["path"], "");
+
+    // The fake backend is deterministic, so a second regeneration changes nothing
+    let res = app
+        .oneshot(as_admin(post_json("/v1/word/fresh/regenerate", json!({}))))
+        .await
+        .unwrap();
+    let v = body_json(res).await;
+    assert_eq!(v["previous"]["model"], "unknown");
+    assert_eq!(v["patch"], json!([]));
+    assert_eq!(v["entry"]["word"], "fresh");
+}
+
+#[tokio::test]
+async fn regenerate_needs_the_admin_token_and_a_word() {
+    let app = test_router();
+    let res = app
+        .clone()
+        .oneshot(post_json("/v1/word/fresh/regenerate", json!({})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::UNAUTHORIZED);
+    // Near misses, a prefix of the token included, are rejected too
+    for token in ["test-admin-tokem", "test-admin", "test-admin-token-2"] {
+        let mut req = post_json("/v1/word/fresh/regenerate", json!({}));
+        req.headers_mut().insert(
+            http::header::AUTHORIZATION,
+            format!("Bearer {token}").parse().unwrap(),
+        );
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), http::StatusCode::UNAUTHORIZED, "{}", token);
+    }
+
+    let res = app
+        .clone()
+        .oneshot(as_admin(post_json("/v1/word/12345/regenerate", json!({}))))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body_json(res).await["error_type"], "not_a_word");
+
+    let long = "a".repeat(101);
+    let res = app
+        .oneshot(as_admin(post_json(
+            &format!("/v1/word/{long}/regenerate"),
+            json!({}),
+        )))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn batch_shares_cache_and_persistence_with_single_word() {
+    let dir = std::env::temp_dir().join(format!("lingua-api-batch-store-{}", std::process::id()));
+    let app = router_with_store(Some(Arc::new(EntryStore::open(&dir).unwrap())));
+
+    let res = app
+        .clone()
+        .oneshot(post_json(
+            "/v1/words",
+            json!({"words": ["stored", "stored"]}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+
+    // Batch results are persisted exactly like single-word results
+    let res = app
+        .clone()
+        .oneshot(
+            http::Request::builder()
+                .uri("/v1/word/stored/history")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    assert!(!body_json(res).await["versions"]
+        .as_array()
+        .unwrap()
+        .is_empty());
+
+    // And input screening applies per item
+    let v = body_json(
+        app.oneshot(post_json("/v1/words", json!({"words": ["12345", ""]})))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(v["results"][0]["code"], "NOT_A_WORD");
+    assert_eq!(v["results"][1]["code"], "INVALID_INPUT");
+
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[tokio::test]
+async fn context_hint_regenerates_and_is_recorded_in_history() {
+    let dir = std::env::temp_dir().join(format!("lingua-api-context-{}", std::process::id()));
+    let app = router_with_store(Some(Arc::new(EntryStore::open(&dir).unwrap())));
+
+    app.clone()
+        .oneshot(post_json("/v1/word", json!({"word": "bass"})))
+        .await
+        .unwrap();
+    let res = app
+        .clone()
+        .oneshot(post_json(
+            "/v1/word",
+            json!({"word": "bass", "context": "We caught a bass off the pier."}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+
+    // The hinted request bypassed the cache and left a second, annotated version
+    let res = app
+        .clone()
+        .oneshot(
+            http::Request::builder()
+                .uri("/v1/word/bass/history")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let v = body_json(res).await;
+    let versions = v["versions"].as_array().unwrap();
+    assert_eq!(versions.len(), 2);
+    assert!(versions[0].get("context").is_none());
+    assert_eq!(versions[1]["context"], "We caught a bass off the pier.");
+
+    let long = "x".repeat(501);
+    let res = app
+        .oneshot(post_json(
+            "/v1/word",
+            json!({"word": "bass", "context": long}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[tokio::test]
+async fn raw_outputs_are_kept_for_accepted_and_rejected_answers() {
+    let dir = std::env::temp_dir().join(format!("lingua-api-raw-{}", std::process::id()));
+    let app = router_with_store(Some(Arc::new(EntryStore::open(&dir).unwrap())));
+    let get = |uri: &str, token: Option<&str>| {
+        let mut req = http::Request::builder().uri(uri);
+        if let Some(token) = token {
+            req = req.header(http::header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        req.body(Body::empty()).unwrap()
+    };
+
+    app.clone()
+        .oneshot(post_json("/v1/word", json!({"word": "traced"})))
+        .await
+        .unwrap();
+    let v = body_json(
+        app.clone()
+            .oneshot(get("/v1/word/traced/history/1", None))
+            .await
+            .unwrap(),
+    )
+    .await;
+    let hash = v["raw_output"].as_str().unwrap().to_string();
+    let res = app
+        .clone()
+        .oneshot(get(&format!("/admin/raw/{hash}"), Some(ADMIN_TOKEN)))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    // Byte for byte what the model said, before the validator's fixes
+    let raw = body_json(res).await;
+    assert_eq!(raw["phonetic"], "tɛst");
+    assert_eq!(
+        raw["meanings"][0]["synonyms"],
+        json!(["Alpha", "alpha", "BETA"])
+    );
+
+    // A field regeneration keeps its own raw output
+    let res = app
+        .clone()
+        .oneshot(as_admin(post_json(
+            "/v1/word/traced/fields",
+            json!({"fields": ["phonetic"]}),
+        )))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    let v = body_json(
+        app.clone()
+            .oneshot(get("/v1/word/traced/history/2", None))
+            .await
+            .unwrap(),
+    )
+    .await;
+    let hash = v["raw_output"].as_str().unwrap().to_string();
+    let res = app
+        .clone()
+        .oneshot(get(&format!("/admin/raw/{hash}"), Some(ADMIN_TOKEN)))
+        .await
+        .unwrap();
+    assert_eq!(
+        body_json(res).await,
+        json!({"value": "Regenerated phonetic."})
+    );
+
+    let res = app
+        .clone()
+        .oneshot(post_json("/v1/word", json!({"word": "gibberish"})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+    let v = body_json(res).await;
+    let hash = v["raw_outputs"][0].as_str().unwrap().to_string();
+    let res = app
+        .clone()
+        .oneshot(get(&format!("/admin/raw/{hash}"), None))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::UNAUTHORIZED);
+    let res = app
+        .clone()
+        .oneshot(get(&format!("/admin/raw/{hash}"), Some(ADMIN_TOKEN)))
+        .await
+        .unwrap();
+    assert_eq!(body_json(res).await, json!({"word": "gibberish"}));
+
+    let res = app
+        .oneshot(get(
+            &format!("/admin/raw/{}", "0".repeat(64)),
+            Some(ADMIN_TOKEN),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::NOT_FOUND);
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[tokio::test]
+async fn history_lists_versions_and_supports_rollback() {
+    let dir = std::env::temp_dir().join(format!("lingua-api-history-{}", std::process::id()));
+    let app = router_with_store(Some(Arc::new(EntryStore::open(&dir).unwrap())));
+    let get = |uri: &str| {
+        http::Request::builder()
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let res = app
+        .clone()
+        .oneshot(get("/v1/word/kept/history"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::NOT_FOUND);
+
+    app.clone()
+        .oneshot(post_json("/v1/word", json!({"word":"kept"})))
+        .await
+        .unwrap();
+    let v = body_json(
+        app.clone()
+            .oneshot(as_admin(post_json("/v1/word/kept/regenerate", json!({}))))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(v["version"], 2);
+
+    let v = body_json(
+        app.clone()
+            .oneshot(get("/v1/word/kept/history"))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(v["versions"].as_array().unwrap().len(), 2);
+    assert!(v["versions"][0].get("entry").is_none());
+
+    let v = body_json(
+        app.clone()
+            .oneshot(get("/v1/word/kept/history/1"))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(v["entry"]["word"], "kept");
+
+    let mut req = post_json("/admin/entries/kept/rollback/1", json!({}));
+    req.headers_mut().insert(
+        http::header::AUTHORIZATION,
+        format!("Bearer {ADMIN_TOKEN}").parse().unwrap(),
+    );
+    let v = body_json(app.oneshot(req).await.unwrap()).await;
+    assert_eq!(v["restored_from"], 1);
+    assert_eq!(v["version"], 3);
+
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[tokio::test]
+async fn tenants_keep_their_own_entries_and_quotas() {
+    let dir = std::env::temp_dir().join(format!("lingua-api-tenants-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let base = test_state(Some(Arc::new(EntryStore::open(&dir).unwrap())));
+    let mut tenants = Tenants::default();
+    for name in ["kids", "pro"] {
+        tenants.insert(Namespace {
+            name: name.to_string(),
+            cache: Arc::new(WordCache::new(100, None, false)),
+            store: Some(Arc::new(
+                EntryStore::open(lingua_fast::tenant::data_dir(&dir, name)).unwrap(),
+            )),
+            validator: base.validator.clone(),
+            schema_file: None,
+        });
+    }
+    let profiles = Profiles::from_json(
+        r#"{
+            "profiles": { "kids": { "tenant": "kids", "requests_per_minute": 10 } },
+            "keys": { "kids-key": "kids" },
+            "tenants": { "kids": {}, "pro": {} }
+        }"#,
+    )
+    .unwrap();
+    let tenants = Arc::new(tenants);
+    let app = router(AppState {
+        profiles: Arc::new(profiles),
+        tenants: tenants.clone(),
+        ..base.clone()
+    });
+    let request = |uri: &str, key: Option<&str>, tenant: Option<&str>| {
+        let mut req = post_json(uri, json!({"word": "apple"}));
+        if let Some(key) = key {
+            req.headers_mut().insert("x-api-key", key.parse().unwrap());
+        }
+        if let Some(tenant) = tenant {
+            req.headers_mut()
+                .insert("x-tenant", tenant.parse().unwrap());
+        }
+        req
+    };
+
+    let res = app
+        .clone()
+        .oneshot(request("/v1/word", Some("kids-key"), None))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    assert_eq!(res.headers()["x-ratelimit-remaining"], "9");
+    let kids = tenants.get("kids").unwrap();
+    assert!(kids.cache.get("apple").is_some());
+    assert!(kids
+        .store
+        .as_ref()
+        .unwrap()
+        .latest("apple")
+        .unwrap()
+        .is_some());
+    assert!(base.cache.get("apple").is_none());
+    assert!(base
+        .store
+        .as_ref()
+        .unwrap()
+        .latest("apple")
+        .unwrap()
+        .is_none());
+
+    // Only a bound key, the admin token or a trusted gateway picks a tenant
+    let res = app
+        .clone()
+        .oneshot(request("/v1/word", None, Some("pro")))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::FORBIDDEN);
+    assert!(tenants.get("pro").unwrap().cache.get("apple").is_none());
+    let behind_gateway = router(AppState {
+        trust_tenant_header: true,
+        tenants: tenants.clone(),
+        ..base.clone()
+    });
+    let res = behind_gateway
+        .clone()
+        .oneshot(request("/v1/word", None, Some("pro")))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    assert!(tenants.get("pro").unwrap().cache.get("apple").is_some());
+
+    // History is read from the tenant's own store
+    let history = |tenant: &str| {
+        http::Request::builder()
+            .uri("/v1/word/apple/history")
+            .header("x-tenant", tenant)
+            .body(Body::empty())
+            .unwrap()
+    };
+    let res = app.clone().oneshot(history("kids")).await.unwrap();
+    assert_eq!(res.status(), http::StatusCode::FORBIDDEN);
+    let v = body_json(
+        app.clone()
+            .oneshot(as_admin(history("kids")))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(v["versions"].as_array().unwrap().len(), 1);
+
+    let res = app
+        .clone()
+        .oneshot(request("/v1/word", Some("kids-key"), Some("pro")))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::FORBIDDEN);
+    let res = behind_gateway
+        .oneshot(request("/v1/word", None, Some("nope")))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::BAD_REQUEST);
+    assert_eq!(body_json(res).await["code"], "INVALID_INPUT");
+
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[tokio::test]
+async fn without_a_model_only_stored_entries_are_served() {
+    let dir = std::env::temp_dir().join(format!("lingua-api-no-model-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let store = Arc::new(EntryStore::open(&dir).unwrap());
+    store
+        .append(
+            "kept",
+            &MockBackend::entry_for("kept"),
+            "mock",
+            SCHEMA_VERSION,
+        )
+        .unwrap();
+    let app = router(AppState {
+        backend: Arc::new(ReadOnlyBackend),
+        ..test_state(Some(store))
+    });
+
+    let res = app
+        .clone()
+        .oneshot(post_json("/v1/word", json!({"word": "kept"})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    assert_eq!(body_json(res).await["word"], "kept");
+
+    // Misses never count towards the negative cache's threshold
+    for _ in 0..3 {
+        let res = app
+            .clone()
+            .oneshot(post_json("/v1/word", json!({"word": "missing"})))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), http::StatusCode::NOT_FOUND);
+        let v = body_json(res).await;
+        assert_eq!(v["code"], "NOT_CACHED");
+        assert_eq!(v["retry_suggested"], false);
+    }
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[tokio::test]
+async fn edge_instances_fetch_misses_from_their_upstream() {
+    let dir = std::env::temp_dir().join(format!("lingua-api-upstream-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let central = Arc::new(EntryStore::open(dir.join("central")).unwrap());
+    central
+        .append(
+            "kept",
+            &MockBackend::entry_for("kept"),
+            "mock",
+            SCHEMA_VERSION,
+        )
+        .unwrap();
+    let upstream = router(AppState {
+        backend: Arc::new(ReadOnlyBackend),
+        ..test_state(Some(central))
+    });
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, upstream).await });
+
+    let edge_store = Arc::new(EntryStore::open(dir.join("edge")).unwrap());
+    let backend = UpstreamBackend::new(&url, None, Duration::from_secs(10)).unwrap();
+    let app = router(AppState {
+        backend: Arc::new(backend),
+        ..test_state(Some(edge_store.clone()))
+    });
+
+    let res = app
+        .clone()
+        .oneshot(post_json("/v1/word", json!({"word": "kept"})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    assert_eq!(body_json(res).await["word"], "kept");
+    let stored = edge_store.latest("kept").unwrap().unwrap();
+    assert_eq!(stored.model, format!("upstream:{}", url));
+
+    // The upstream's verdict is passed on, not retried
+    let res = app
+        .oneshot(post_json("/v1/word", json!({"word": "missing"})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::NOT_FOUND);
+    assert_eq!(body_json(res).await["code"], "NOT_CACHED");
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[tokio::test]
+async fn word_lists_are_built_then_exported_with_their_entries() {
+    let app = router(test_state(None));
+    let send = |req: http::Request<Body>, key: &str| {
+        let mut req = req;
+        req.headers_mut().insert("x-api-key", key.parse().unwrap());
+        app.clone().oneshot(req)
+    };
+    let get = |uri: String| {
+        http::Request::builder()
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let res = send(post_json("/v1/lists", json!({"name": "Week 1"})), "k1")
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::CREATED);
+    let id = body_json(res).await["id"].as_str().unwrap().to_string();
+
+    let words = json!({"words": ["run", "walk", "run", "asdfghjkl"]});
+    let res = send(post_json(&format!("/v1/lists/{id}/words"), words), "k1")
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    assert_eq!(
+        body_json(res).await["words"],
+        json!(["run", "walk", "asdfghjkl"])
+    );
+
+    let res = send(get(format!("/v1/lists/{id}")), "k1").await.unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    let bundle = body_json(res).await;
+    assert_eq!(bundle["name"], "Week 1");
+    assert_eq!(bundle["summary"]["succeeded"], 3);
+    assert_eq!(bundle["results"][1]["word"], "walk");
+    assert_eq!(bundle["results"][1]["data"]["word"], "walk");
+
+    // Lists belong to the key that made them
+    let res = send(get(format!("/v1/lists/{id}")), "k2").await.unwrap();
+    assert_eq!(res.status(), http::StatusCode::NOT_FOUND);
+    let res = send(get("/v1/lists/missing".to_string()), "k1")
+        .await
+        .unwrap();
+    assert_eq!(body_json(res).await["code"], "NOT_FOUND");
+}
+
+#[tokio::test]
+async fn back_translation_check_records_quality_warnings() {
+    let dir = std::env::temp_dir().join(format!("lingua-api-backtr-{}", std::process::id()));
+    let mut state = test_state(Some(Arc::new(EntryStore::open(&dir).unwrap())));
+    state.back_translation_check = true;
+    let app = router(state);
+    let history = |word: &str| {
+        let req = http::Request::builder()
+            .uri(format!("/v1/word/{word}/history"))
+            .body(Body::empty())
+            .unwrap();
+        let app = app.clone();
+        async move { body_json(app.oneshot(req).await.unwrap()).await }
+    };
+
+    for word in ["steady", "drifted"] {
+        let res = app
+            .clone()
+            .oneshot(post_json("/v1/word", json!({ "word": word })))
+            .await
+            .unwrap();
+        // The check never holds up or alters the response
+        assert_eq!(res.status(), http::StatusCode::OK);
+    }
+
+    // The check runs in the background
+    let mut warnings = Value::Null;
+    for _ in 0..100 {
+        warnings = history("drifted").await["versions"][0]["warnings"].clone();
+        if !warnings.is_null() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let warnings = warnings.as_array().expect("warnings recorded");
+    assert_eq!(warnings.len(), 2);
+    assert!(warnings[0].as_str().unwrap().contains("\"pineapple\""));
+    assert!(history("steady").await["versions"][0]
+        .get("warnings")
+        .is_none());
+
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[tokio::test]
+async fn history_without_persistence_is_not_implemented() {
+    let req = http::Request::builder()
+        .uri("/v1/word/any/history")
+        .body(Body::empty())
+        .unwrap();
+    let res = test_router().oneshot(req).await.unwrap();
+    assert_eq!(res.status(), http::StatusCode::NOT_IMPLEMENTED);
+}
+
+#[tokio::test]
+async fn dashboard_page_is_public_and_its_stats_need_the_admin_token() {
+    let app = test_router();
+    app.clone()
+        .oneshot(post_json("/v1/word", json!({"word":"dashboard"})))
+        .await
+        .unwrap();
+    let res = app
+        .clone()
+        .oneshot(post_json("/v1/word", json!({"word":""})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::BAD_REQUEST);
+
+    let get = |uri: &str, auth: bool| {
+        let mut req = http::Request::builder().uri(uri);
+        if auth {
+            req = req.header(http::header::AUTHORIZATION, format!("Bearer {ADMIN_TOKEN}"));
+        }
+        req.body(Body::empty()).unwrap()
+    };
+    let res = app
+        .clone()
+        .oneshot(get("/admin/dashboard", false))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    assert!(res.headers()[http::header::CONTENT_TYPE]
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+
+    let res = app
+        .clone()
+        .oneshot(get("/admin/dashboard/stats", false))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::UNAUTHORIZED);
+
+    // Other tests share the process-wide counters, so only lower bounds hold
+    let v = body_json(
+        app.clone()
+            .oneshot(get("/admin/dashboard/stats", true))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(v["requests_per_sec_5m"].as_f64().unwrap() > 0.0);
+    assert!(v["served"]["generated"].as_u64().unwrap() >= 1);
+    assert!(v["latency_ms"]["p50"].is_u64());
+    assert!(v["recent_errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|e| e["path"] == "/v1/word" && e["status"] == 400));
+
+    let res = app
+        .clone()
+        .oneshot(get("/admin/stats", false))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::UNAUTHORIZED);
+    let v = body_json(app.oneshot(get("/admin/stats", true)).await.unwrap()).await;
+    assert!(v["uptime_secs"].is_u64());
+    assert!(v.as_object().unwrap().contains_key("rss_bytes"));
+    assert!(v["cache_entries"].is_u64());
+    // The fake backend runs no model in-process
+    assert!(v["backend"].is_null());
+}
+
+#[tokio::test]
+async fn admin_diff_counts_entries_changed_between_models() {
+    let dir = std::env::temp_dir().join(format!("lingua-api-diff-{}", std::process::id()));
+    let store = Arc::new(EntryStore::open(&dir).unwrap());
+    store.append("kept", &json!({"a": 1}), "old", "1").unwrap();
+    store.append("kept", &json!({"a": 1}), "new", "1").unwrap();
+    store.append("moved", &json!({"a": 1}), "old", "1").unwrap();
+    store.append("moved", &json!({"a": 2}), "new", "1").unwrap();
+    let app = router_with_store(Some(store));
+    let req = |auth: bool| {
+        let mut req = http::Request::builder().uri("/admin/diff?model_a=old&model_b=new");
+        if auth {
+            req = req.header(http::header::AUTHORIZATION, format!("Bearer {ADMIN_TOKEN}"));
+        }
+        req.body(Body::empty()).unwrap()
+    };
+
+    let res = app.clone().oneshot(req(false)).await.unwrap();
+    assert_eq!(res.status(), http::StatusCode::UNAUTHORIZED);
+
+    let v = body_json(app.oneshot(req(true)).await.unwrap()).await;
+    assert_eq!(v["compared"], 2);
+    assert_eq!(v["changed"], 1);
+    assert_eq!(v["unchanged"], 1);
+    assert_eq!(v["changed_words"], json!(["moved"]));
+
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[tokio::test]
+async fn curated_entries_are_locked_and_soft_deletable() {
+    let dir = std::env::temp_dir().join(format!("lingua-api-curated-{}", std::process::id()));
+    let app = router_with_store(Some(Arc::new(EntryStore::open(&dir).unwrap())));
+    let edit = |body: Value| {
+        http::Request::builder()
+            .method(http::Method::PATCH)
+            .uri("/admin/entries/curated")
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .header(http::header::AUTHORIZATION, format!("Bearer {ADMIN_TOKEN}"))
+            .body(Body::from(serde_json::to_vec(&body).unwrap()))
+            .unwrap()
+    };
+
+    app.clone()
+        .oneshot(post_json("/v1/word", json!({"word":"curated"})))
+        .await
+        .unwrap();
+    let res = app
+        .clone()
+        .oneshot(edit(
+            json!({"locked": true, "entry": {"phonetic": "/kjʊəˈreɪtɪd/"}}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    let v = body_json(res).await;
+    assert_eq!(v["locked"], true);
+    assert_eq!(v["version"], 2);
+
+    let v = body_json(
+        app.clone()
+            .oneshot(post_json("/v1/word", json!({"word":"curated"})))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(v["phonetic"], "/kjʊəˈreɪtɪd/");
+
+    let res = app
+        .clone()
+        .oneshot(as_admin(post_json(
+            "/v1/word/curated/regenerate",
+            json!({}),
+        )))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::CONFLICT);
+
+    app.clone()
+        .oneshot(edit(json!({"deleted": true})))
+        .await
+        .unwrap();
+    let res = app
+        .oneshot(post_json("/v1/word", json!({"word":"curated"})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::NOT_FOUND);
+
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[tokio::test]
+async fn malformed_bodies_get_error_responses_with_field_details() {
+    let app = test_router();
+    let res = app
+        .clone()
+        .oneshot(post_json(
+            "/v1/words",
+            json!({"words": ["ok", 7], "concurrency": "x"}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+    let v = body_json(res).await;
+    assert_eq!(v["code"], "INVALID_INPUT");
+    let details = v["details"].as_array().unwrap();
+    let paths: Vec<_> = details
+        .iter()
+        .map(|d| d["path"].as_str().unwrap())
+        .collect();
+    assert!(paths.contains(&"/words/1"));
+    assert!(paths.contains(&"/concurrency"));
+
+    let res = app
+        .clone()
+        .oneshot(post_json("/v1/word", json!({"text": "swim"})))
+        .await
+        .unwrap();
+    let v = body_json(res).await;
+    assert_eq!(v["details"][0]["keyword"], "required");
+
+    // Broken syntax and a missing content type keep the same JSON shape
+    let broken = http::Request::builder()
+        .method(http::Method::POST)
+        .uri("/v1/word")
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(Body::from("{\"word\": "))
+        .unwrap();
+    let res = app.clone().oneshot(broken).await.unwrap();
+    assert_eq!(res.status(), http::StatusCode::BAD_REQUEST);
+    assert_eq!(body_json(res).await["code"], "INVALID_INPUT");
+
+    let untyped = http::Request::builder()
+        .method(http::Method::POST)
+        .uri("/v1/word")
+        .body(Body::from(r#"{"word": "swim"}"#))
+        .unwrap();
+    let res = app.oneshot(untyped).await.unwrap();
+    assert_eq!(res.status(), http::StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert!(body_json(res).await["error"]
+        .as_str()
+        .unwrap()
+        .contains("Content-Type"));
+}
+
+#[tokio::test]
+async fn handlers_can_be_called_directly() {
+    let state = test_state(None);
+
+    let res = api::analyze_word(
+        Tenanted(state.clone()),
+        http::HeaderMap::new(),
+        Query(PresentationQuery::default()),
+        Query(SenseQuery::default()),
+        ValidJson(WordReq {
+            word: "direct".to_string(),
+            system_prompt: None,
+            context: None,
+            pos: None,
+            max_meanings: None,
+            granularity: None,
+        }),
+    )
+    .await;
+    assert_eq!(res.status(), http::StatusCode::OK);
+    assert_eq!(body_json(res).await["word"], "direct");
+    assert!(state.cache.get("direct").is_some());
+
+    let res = api::word_history(Tenanted(state), Path("direct".to_string())).await;
+    assert_eq!(res.status(), http::StatusCode::NOT_IMPLEMENTED);
+}
+
+#[tokio::test]
+async fn health_endpoints_report_readiness() {
+    let mut state = test_state(None);
+    let get = |uri: &str| {
+        http::Request::builder()
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let res = router(state.clone())
+        .oneshot(get("/healthz"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    let res = router(state.clone()).oneshot(get("/readyz")).await.unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+
+    // With a canary configured but not yet passed the instance is unready
+    state.readiness = Arc::new(Readiness::with_canary());
+    let res = router(state).oneshot(get("/readyz")).await.unwrap();
+    assert_eq!(res.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body_json(res).await["ready"], false);
+}
+
+#[tokio::test]
+async fn system_prompt_override_requires_permission() {
+    let body = json!({"word": "tone", "system_prompt": "TIP:Be playful."});
+
+    let res = test_router()
+        .oneshot(post_json("/v1/word", body.clone()))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::FORBIDDEN);
+    assert_eq!(body_json(res).await["code"], "FORBIDDEN");
+
+    let mut state = test_state(None);
+    state.allow_system_prompt_override = true;
+    let res = router(state.clone())
+        .oneshot(post_json("/v1/word", body))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    let v = body_json(res).await;
+    assert_eq!(v["meanings"][0]["grammarTip"], "Be playful.");
+    // Custom-prompt entries never land in the shared cache
+    assert!(state.cache.get("tone").is_none());
+}
+
+#[tokio::test]
+async fn cefr_mode_adds_or_replaces_the_level() {
+    let augment = router(AppState {
+        cefr: CefrMode::Augment,
+        ..test_state(None)
+    });
+    let res = augment
+        .oneshot(post_json("/v1/word", json!({"word": "decide"})))
+        .await
+        .unwrap();
+    let v = body_json(res).await;
+    assert_eq!(v["difficulty"], "beginner");
+    assert_eq!(v["cefr"], "A2");
+
+    let replace = router(AppState {
+        cefr: CefrMode::Replace,
+        ..test_state(None)
+    });
+    let res = replace
+        .oneshot(post_json("/v1/word", json!({"word": "zyzzyva"})))
+        .await
+        .unwrap();
+    let v = body_json(res).await;
+    assert_eq!(v["difficulty"], "A1");
+    assert!(v.get("cefr").is_none());
+}
+
+#[tokio::test]
+async fn spelling_variants_share_an_entry() {
+    let state = test_state(None);
+    let app = router(state.clone());
+    let res = app
+        .clone()
+        .oneshot(post_json("/v1/word", json!({"word": "colour"})))
+        .await
+        .unwrap();
+    let v = body_json(res).await;
+    assert_eq!(v["word"], "colour");
+    assert_eq!(v["alternativeSpellings"], json!(["color"]));
+    assert!(state.cache.get("color").is_some());
+
+    let res = app
+        .oneshot(post_json("/v1/word", json!({"word": "color"})))
+        .await
+        .unwrap();
+    let v = body_json(res).await;
+    assert_eq!(v["word"], "color");
+    assert_eq!(v["alternativeSpellings"], json!(["colour"]));
+    assert_eq!(state.cache.entry_count(), 1);
+}
+
+#[tokio::test]
+async fn meta_reports_provenance_on_request() {
+    let app = test_router();
+    let res = app
+        .clone()
+        .oneshot(post_json("/v1/word?meta=true", json!({"word": "run"})))
+        .await
+        .unwrap();
+    let v = body_json(res).await;
+    let meta = &v["_meta"];
+    assert_eq!(meta["source"], "generated");
+    assert_eq!(meta["schemaVersion"], SCHEMA_VERSION);
+    assert!(meta["model"].is_string(), "{}", meta);
+    assert!(meta["generatedAt"].as_u64().unwrap() > 0);
+    assert!(meta["validatorFixes"].is_u64(), "{}", meta);
+
+    let res = app
+        .clone()
+        .oneshot(post_json(
+            "/v1/word?meta=true&fields=word",
+            json!({"word": "run"}),
+        ))
+        .await
+        .unwrap();
+    let v = body_json(res).await;
+    assert_eq!(v["_meta"]["source"], "cache");
+    assert!(v["_meta"].get("validatorFixes").is_none());
+    assert!(v.get("meanings").is_none());
+
+    let res = app
+        .oneshot(post_json("/v1/word", json!({"word": "run"})))
+        .await
+        .unwrap();
+    assert!(body_json(res).await.get("_meta").is_none());
+}
+
+#[tokio::test]
+async fn word_of_the_day_is_fixed_per_date() {
+    let app = test_router();
+    let get = |uri: &str| {
+        http::Request::builder()
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap()
+    };
+    let res = app
+        .clone()
+        .oneshot(get("/v1/word-of-the-day?date=2024-02-29"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    let first = body_json(res).await;
+    assert_eq!(first["date"], "2024-02-29");
+    assert_eq!(first["entry"]["word"], first["word"]);
+
+    let res = app
+        .clone()
+        .oneshot(get("/v1/word-of-the-day?date=2024-02-29"))
+        .await
+        .unwrap();
+    assert_eq!(body_json(res).await["word"], first["word"]);
+    let res = app
+        .clone()
+        .oneshot(get("/v1/word-of-the-day?date=2024-03-01"))
+        .await
+        .unwrap();
+    assert_ne!(body_json(res).await["word"], first["word"]);
+
+    let res = app
+        .oneshot(get("/v1/word-of-the-day?date=2023-02-29"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::BAD_REQUEST);
+    assert_eq!(body_json(res).await["code"], "INVALID_INPUT");
+}
+
+#[tokio::test]
+async fn examples_extend_an_analyzed_entry() {
+    let app = test_router();
+    let res = app
+        .clone()
+        .oneshot(post_json("/v1/word/decide/examples", json!({})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::NOT_FOUND);
+
+    let res = app
+        .clone()
+        .oneshot(post_json("/v1/word", json!({"word": "decide"})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    let res = app
+        .clone()
+        .oneshot(post_json("/v1/word/decide/examples?count=2", json!({})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    let v = body_json(res).await;
+    assert_eq!(v["word"], "decide");
+    assert_eq!(
+        v["examples"],
+        json!([
+            { "partOfSpeech": "noun", "sentence": "New sentence 1 with decide." },
+            { "partOfSpeech": "noun", "sentence": "New sentence 2 with decide." }
+        ])
+    );
+
+    let res = app
+        .oneshot(post_json("/v1/word/decide/examples?count=9", json!({})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn annotate_marks_content_words_with_difficulty() {
+    let text = "The cat's decision failed: fail!";
+    let res = test_router()
+        .oneshot(post_json(
+            "/v1/text/annotate",
+            json!({ "text": text, "inline": true }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    let v = body_json(res).await;
+    assert_eq!(v["text"], text);
+    let spans = v["spans"].as_array().unwrap();
+    let words: Vec<&str> = spans.iter().map(|s| s["word"].as_str().unwrap()).collect();
+    assert_eq!(words, ["cat", "decision", "failed", "fail"]);
+    assert_eq!(spans[0]["start"], 4);
+    assert_eq!(spans[0]["end"], 9);
+    assert_eq!(spans[1]["difficulty"], "beginner");
+    assert_eq!(spans[3]["code"], "INFERENCE_ERROR");
+    assert!(spans[3].get("difficulty").is_none());
+    assert_eq!(v["entries"]["decision"]["word"], "decision");
+    assert!(v["entries"].get("fail").is_none());
+}
+
+#[tokio::test]
+async fn signed_entries_verify_against_the_published_key() {
+    let res = test_router()
+        .oneshot(
+            http::Request::get("/v1/signing-key")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::NOT_FOUND);
+
+    let app = router(AppState {
+        signer: Some(Arc::new(EntrySigner::from_seed(
+            &[3; 32],
+            Some("k1".into()),
+        ))),
+        ..test_state(None)
+    });
+    let res = app
+        .clone()
+        .oneshot(
+            http::Request::get("/v1/signing-key")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let key = body_json(res).await;
+    assert_eq!(key["keyId"], "k1");
+    let public_key = key["publicKey"].as_str().unwrap().to_string();
+
+    let res = app
+        .clone()
+        .oneshot(post_json("/v1/word", json!({"word": "signed"})))
+        .await
+        .unwrap();
+    assert_eq!(res.headers()["x-signature-key-id"], "k1");
+    let signature = res.headers()["x-signature"].to_str().unwrap().to_string();
+    let entry = body_json(res).await;
+    assert!(signing::verify(&public_key, &entry, &signature));
+
+    let res = app
+        .oneshot(post_json("/v1/words", json!({"words": ["signed", ""]})))
+        .await
+        .unwrap();
+    assert_eq!(res.headers()["x-signature-key-id"], "k1");
+    let batch = body_json(res).await;
+    let item = &batch["results"][0];
+    assert!(signing::verify(
+        &public_key,
+        &item["data"],
+        item["signature"].as_str().unwrap()
+    ));
+    assert!(batch["results"][1].get("signature").is_none());
+}
+
+#[tokio::test]
+async fn reload_schema_swaps_the_contract_without_restarting() {
+    let admin = |uri: &str| {
+        let mut req = post_json(uri, json!({}));
+        req.headers_mut().insert(
+            http::header::AUTHORIZATION,
+            format!("Bearer {ADMIN_TOKEN}").parse().unwrap(),
+        );
+        req
+    };
+    let res = test_router()
+        .oneshot(admin("/admin/reload-schema"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::NOT_IMPLEMENTED);
+
+    let dir = std::env::temp_dir().join(format!("lingua-api-schema-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("schema.json");
+    let mut schema: Value =
+        serde_json::from_str(include_str!("../schema/word_contract.schema.json")).unwrap();
+    schema["properties"]["etymology"] = json!({ "type": "string" });
+    schema["required"]
+        .as_array_mut()
+        .unwrap()
+        .push(json!("etymology"));
+    std::fs::write(&path, schema.to_string()).unwrap();
+    let app = router(AppState {
+        schema_file: Some(Arc::from(path.to_str().unwrap())),
+        ..test_state(None)
+    });
+
+    let res = app
+        .clone()
+        .oneshot(admin("/admin/reload-schema"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::OK);
+    let v = body_json(res).await;
+    assert_eq!(v["changed"], true);
+    assert!(v["schema_hash"].is_string());
+    // The fake backend's entries lack the newly required field
+    let res = app
+        .clone()
+        .oneshot(post_json("/v1/word", json!({"word": "test"})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+
+    // A broken file is refused and the reloaded contract stays
+    std::fs::write(&path, "{ \"properties\": ").unwrap();
+    let res = app
+        .clone()
+        .oneshot(admin("/admin/reload-schema"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+    let res = app
+        .oneshot(post_json("/v1/word", json!({"word": "tested"})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+    std::fs::remove_dir_all(&dir).ok();
+}
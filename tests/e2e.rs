@@ -0,0 +1,283 @@
+//! End-to-end test: the real server binary with real llama.cpp inference on a
+//! tiny model, driven over HTTP.
+//!
+//! `cargo test --features e2e --test e2e -- --nocapture`
+//!
+//! The model comes from `E2E_MODEL_PATH` or is downloaded once from
+//! `E2E_MODEL_URL` (default: SmolLM2-135M-Instruct Q4_K_M, about 100MB) into
+//! Cargo's target tmp dir. A model this small often breaks the word contract,
+//! so generation results are checked for shape, not content: every answer
+//! must be a schema-valid entry or a well-formed error, and the server must
+//! survive all of them.
+#![cfg(feature = "e2e")]
+
+use serde_json::{json, Value};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+const DEFAULT_MODEL_URL: &str =
+    "https://huggingface.co/bartowski/SmolLM2-135M-Instruct-GGUF/resolve/main/SmolLM2-135M-Instruct-Q4_K_M.gguf";
+const ADMIN_TOKEN: &str = "e2e-admin-token";
+/// Model load plus warmup on a slow CI machine
+const BOOT_TIMEOUT: Duration = Duration::from_secs(180);
+
+async fn model_path() -> PathBuf {
+    if let Ok(path) = std::env::var("E2E_MODEL_PATH") {
+        return PathBuf::from(path);
+    }
+    let url = std::env::var("E2E_MODEL_URL").unwrap_or_else(|_| DEFAULT_MODEL_URL.to_string());
+    let name = url.rsplit('/').next().unwrap_or("model.gguf");
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR"))
+        .join("e2e-models")
+        .join(name);
+    if path.exists() {
+        return path;
+    }
+    eprintln!("downloading {} ...", url);
+    let res = reqwest::get(&url).await.expect("download model");
+    assert!(
+        res.status().is_success(),
+        "download {}: {}",
+        url,
+        res.status()
+    );
+    let bytes = res.bytes().await.expect("download model body");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    // Written under a temporary name so an interrupted download is not reused
+    let partial = path.with_extension("part");
+    std::fs::write(&partial, &bytes).unwrap();
+    std::fs::rename(&partial, &path).unwrap();
+    path
+}
+
+/// The server binary, killed when dropped.
+struct Server {
+    child: Child,
+    base: String,
+    log: PathBuf,
+}
+
+impl Server {
+    async fn start(model: &PathBuf) -> Self {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        // Away from the repo so its .env is not picked up
+        let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("e2e-{}", port));
+        std::fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("server.log");
+        let child = Command::new(env!("CARGO_BIN_EXE_lingua-fast"))
+            .current_dir(&dir)
+            .env("BIND_ADDR", format!("127.0.0.1:{}", port))
+            .env("MODEL_PATH", model)
+            .env("N_CTX", "4096")
+            .env("N_GPU_LAYERS", "0")
+            .env("THREADS", "4")
+            .env("INFER_CONCURRENCY", "2")
+            .env("MAX_TOKENS", "1024")
+            .env("ADMIN_TOKEN", ADMIN_TOKEN)
+            .env("STARTUP_BENCHMARK", "false")
+            .env("RUST_LOG", "info")
+            .stdout(Stdio::null())
+            .stderr(std::fs::File::create(&log).unwrap())
+            .spawn()
+            .expect("start server");
+        let mut server = Self {
+            child,
+            base: format!("http://127.0.0.1:{}", port),
+            log,
+        };
+        server.wait_ready().await;
+        server
+    }
+
+    async fn wait_ready(&mut self) {
+        let started = Instant::now();
+        loop {
+            if let Some(status) = self.child.try_wait().unwrap() {
+                panic!(
+                    "server exited with {} during startup\n{}",
+                    status,
+                    self.log_tail()
+                );
+            }
+            if let Ok(res) = reqwest::get(format!("{}/readyz", self.base)).await {
+                if res.status().is_success() {
+                    return;
+                }
+            }
+            assert!(
+                started.elapsed() < BOOT_TIMEOUT,
+                "server not ready after {:?}\n{}",
+                BOOT_TIMEOUT,
+                self.log_tail()
+            );
+            tokio::time::sleep(Duration::from_millis(250)).await;
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base, path)
+    }
+
+    fn assert_alive(&mut self) {
+        if let Some(status) = self.child.try_wait().unwrap() {
+            panic!("server died with {}\n{}", status, self.log_tail());
+        }
+    }
+
+    fn log_tail(&self) -> String {
+        let log = std::fs::read_to_string(&self.log).unwrap_or_default();
+        let lines: Vec<&str> = log.lines().collect();
+        lines[lines.len().saturating_sub(40)..].join("\n")
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+/// A generated entry, or the API's error shape with a generation error code.
+fn assert_entry_or_generation_error(status: reqwest::StatusCode, body: &Value) {
+    if status.is_success() {
+        assert!(body["word"].is_string(), "{}", body);
+        assert!(!body["meanings"].as_array().unwrap().is_empty(), "{}", body);
+        for meaning in body["meanings"].as_array().unwrap() {
+            assert!(meaning["translations"].is_object(), "{}", meaning);
+        }
+        return;
+    }
+    assert!(
+        [422, 502, 503, 504].contains(&status.as_u16()),
+        "unexpected status {}: {}",
+        status,
+        body
+    );
+    assert!(body["code"].is_string(), "{}", body);
+    assert!(body["message"].is_string(), "{}", body);
+    assert_ne!(body["code"], "INTERNAL_ERROR", "{}", body);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn server_answers_over_http_with_real_inference() {
+    let model = model_path().await;
+    let mut server = Server::start(&model).await;
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(300))
+        .build()
+        .unwrap();
+
+    // One word, generated for real
+    let res = http
+        .post(server.url("/v1/word"))
+        .json(&json!({ "word": "water" }))
+        .send()
+        .await
+        .unwrap();
+    let status = res.status();
+    let body: Value = res.json().await.unwrap();
+    assert_entry_or_generation_error(status, &body);
+    server.assert_alive();
+
+    // A batch: every word answered one way or the other
+    let res = http
+        .post(server.url("/v1/words"))
+        .json(&json!({ "words": ["run", "house"] }))
+        .send()
+        .await
+        .unwrap();
+    assert!(
+        [200, 207, 502].contains(&res.status().as_u16()),
+        "{}",
+        res.status()
+    );
+    let body: Value = res.json().await.unwrap();
+    let results = body["results"].as_array().expect("batch results");
+    assert_eq!(results.len(), 2, "{}", body);
+    for item in results {
+        assert!(item["ok"].is_boolean(), "{}", item);
+    }
+    server.assert_alive();
+
+    // Streaming is refused in the OpenAI shape; the non-streaming call generates
+    let res = http
+        .post(server.url("/v1/chat/completions"))
+        .json(&json!({ "messages": [{ "role": "user", "content": "tree" }], "stream": true }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 400);
+    let body: Value = res.json().await.unwrap();
+    assert!(body["error"]["message"].is_string(), "{}", body);
+    let res = http
+        .post(server.url("/v1/chat/completions"))
+        .json(&json!({ "messages": [{ "role": "user", "content": "tree" }] }))
+        .send()
+        .await
+        .unwrap();
+    let status = res.status();
+    let body: Value = res.json().await.unwrap();
+    if status.is_success() {
+        let content = body["choices"][0]["message"]["content"].as_str().unwrap();
+        serde_json::from_str::<Value>(content).expect("entry JSON as message content");
+    } else {
+        assert!(body["error"]["message"].is_string(), "{}", body);
+    }
+
+    // The raw model output, unvalidated
+    let res = http
+        .post(server.url("/admin/raw"))
+        .bearer_auth(ADMIN_TOKEN)
+        .json(&json!({ "prompt": "Say hello.", "max_tokens": 16 }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 200);
+    let body: Value = res.json().await.unwrap();
+    assert!(body["output"].is_string(), "{}", body);
+
+    // Error paths never reach the model
+    let rejected = [
+        (json!({ "word": "" }), 400),
+        (json!({ "word": "https://example.com/page" }), 422),
+        (json!({ "word": 42 }), 422),
+    ];
+    for (body, expected) in rejected {
+        let res = http
+            .post(server.url("/v1/word"))
+            .json(&body)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status().as_u16(), expected, "{}", body);
+        let error: Value = res.json().await.unwrap();
+        assert!(error["code"].is_string(), "{}", error);
+    }
+    let res = http
+        .post(server.url("/v1/word"))
+        .header("content-type", "application/json")
+        .body("{\"word\": ")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), 400);
+
+    // Inference really ran
+    let metrics = http
+        .get(server.url("/metrics"))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(metrics.contains("lingua_"), "{}", metrics);
+    server.assert_alive();
+}
@@ -1,5 +1,6 @@
 //! Integration test for real llama.cpp inference.
 //! Requires MODEL_PATH env var pointing to a local GGUF.
+#![cfg(feature = "llama")]
 
 #[tokio::test]
 async fn real_inference_produces_json() -> anyhow::Result<()> {
@@ -9,7 +10,9 @@ async fn real_inference_produces_json() -> anyhow::Result<()> {
         eprintln!("skipping real inference test (set RUN_LLAMA_TESTS=1 to enable)");
         return Ok(());
     }
-    use lingua_fast::model::{llama::LlamaBackend, InferParams, LlmBackend, PromptParts};
+    use lingua_fast::config::NumaMode;
+    use lingua_fast::model::llama::{LlamaBackend, LlamaSettings};
+    use lingua_fast::model::{InferParams, LlmBackend, PromptParts, PromptTask};
     use std::{env, fs, path::PathBuf};
     use walkdir::WalkDir;
 
@@ -38,25 +41,38 @@ async fn real_inference_produces_json() -> anyhow::Result<()> {
     // Configure for better JSON generation with Metal acceleration on macOS
     let n_gpu_layers = if cfg!(target_os = "macos") { 28 } else { 0 };
 
-    // The LlamaBackend::new signature was updated to require two additional parameters.
-    // Provide sensible defaults here so the integration test continues to work:
-    // - n_threads: number of worker threads to use (use 4 as a conservative default)
-    // - n_batch: batch size for token sampling (use 8 as a conservative default)
-    //
-    // Adjust these values if the backend's new parameters have a different meaning.
-    let n_threads = 4;
-    let n_batch = 8;
-    let backend = LlamaBackend::new(model_path, 4096, 1024, n_gpu_layers, n_threads, n_batch)?;
+    let backend = LlamaBackend::new(LlamaSettings {
+        model_path,
+        n_ctx: 4096,
+        n_batch: 1024,
+        n_ubatch: 0,
+        n_gpu_layers,
+        device: None,
+        // Conservative thread count so the test behaves on small CI machines
+        threads: 4,
+        threads_batch: 0,
+        numa: NumaMode::Disabled,
+        infer_concurrency: 8,
+        max_queue_wait: None,
+        token_tracer: None,
+        preset: None,
+        idle_unload: None,
+    })?;
     let params = InferParams {
         max_tokens: 1024, // Increased for comprehensive linguistic analysis
         temp: 0.4,
         top_p: 0.9,
         min_p: 0.05,
         repeat_penalty: 1.1,
+        ..InferParams::default()
     };
     let prompt = PromptParts {
         system: "You are a linguistic annotator.".to_string(),
         user_word: "communicated".to_string(),
+        examples: Vec::new(),
+        context: None,
+        contract: Default::default(),
+        task: PromptTask::Entry,
     };
 
     let bytes = backend.infer_json(prompt, &params).await?;
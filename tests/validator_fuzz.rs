@@ -0,0 +1,271 @@
+//! Property tests for `Validator::validate_and_fix`: whatever a model emits,
+//! the validator must not panic, and it either returns an entry the schema
+//! accepts or a typed `ValidationError` saying what is wrong.
+//!
+//! Cases start from the seeds in `tests/fuzz_seeds/` and from a known-good
+//! entry, then get fields removed, retyped or replaced with junk. The seeds
+//! are synthetic, written by hand in the `RECORD_DIR` format to mimic the
+//! ways small models get an entry wrong; no real captures are checked in yet.
+//! Drop real `RECORD_DIR` outputs that fail validation in beside them as
+//! they turn up.
+
+use jsonschema::{Draft, JSONSchema};
+use lingua_fast::record::Recording;
+use lingua_fast::validate::{ValidationError, Validator};
+use once_cell::sync::Lazy;
+use proptest::prelude::*;
+use proptest::sample::Index;
+use serde_json::{json, Map, Value};
+use std::path::Path;
+
+const SCHEMA_SRC: &str = include_str!("../schema/word_contract.schema.json");
+
+static SCHEMA: Lazy<JSONSchema> = Lazy::new(|| {
+    let schema: Value = serde_json::from_str(SCHEMA_SRC).unwrap();
+    JSONSchema::options()
+        .with_draft(Draft::Draft202012)
+        .compile(&schema)
+        .unwrap()
+});
+
+static VALIDATOR: Lazy<Validator> = Lazy::new(|| Validator::new(SCHEMA_SRC).unwrap());
+
+/// `(word, output)` of every seed recording.
+static SEEDS: Lazy<Vec<(String, Value)>> = Lazy::new(|| {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fuzz_seeds");
+    let mut paths: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+    paths
+        .iter()
+        .map(|path| {
+            let recording: Recording =
+                serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap();
+            let output = serde_json::from_str(&recording.output)
+                .unwrap_or_else(|e| panic!("{:?}: output is not JSON: {}", path, e));
+            (recording.word, output)
+        })
+        .collect()
+});
+
+fn valid_entry() -> Value {
+    let translations: Map<String, Value> = ["es", "fr", "de", "zh", "ja", "it", "pt", "ru", "ar"]
+        .iter()
+        .map(|lang| (lang.to_string(), json!(format!("test ({})", lang))))
+        .collect();
+    json!({
+        "word": "test",
+        "baseForm": "test",
+        "phonetic": "/tɛst/",
+        "difficulty": "beginner",
+        "language": "english",
+        "meanings": [{
+            "senseRank": 1,
+            "partOfSpeech": "noun",
+            "definition": "A procedure intended to establish the quality of something.",
+            "exampleSentence": "The test took an hour.",
+            "grammarTip": "Countable noun.",
+            "synonyms": ["trial"],
+            "antonyms": [],
+            "translations": translations,
+        }],
+    })
+}
+
+/// The property every case must hold.
+fn check(v: Value, word: &str) -> Result<(), TestCaseError> {
+    match VALIDATOR.validate_and_fix(v, word) {
+        Ok(fixed) => prop_assert!(
+            SCHEMA.is_valid(&fixed),
+            "accepted an entry the schema rejects: {}",
+            fixed
+        ),
+        Err(e) => {
+            prop_assert!(!matches!(e, ValidationError::SchemaUnavailable(_)), "{}", e);
+            prop_assert!(!e.violations().is_empty(), "no violations for: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Strings a model (or its tokenizer) might produce, odd ones included:
+/// combining marks, zero-width and bidi controls, NULs, astral characters.
+fn weird_string() -> impl Strategy<Value = String> {
+    prop_oneof![
+        "[a-z ]{0,12}",
+        "\\PC{0,24}",
+        "[\u{0}\u{7f}\u{200b}\u{200d}\u{202e}\u{feff}\u{0301}\u{1f4b0}a-zа-я中/ˈ]{0,16}",
+        ".{0,48}",
+    ]
+}
+
+/// Keys from the contract, so junk lands in places the validator looks.
+fn key() -> impl Strategy<Value = String> {
+    const KNOWN: [&str; 17] = [
+        "word",
+        "baseForm",
+        "phonetic",
+        "difficulty",
+        "language",
+        "meanings",
+        "senseRank",
+        "partOfSpeech",
+        "definition",
+        "exampleSentence",
+        "grammarTip",
+        "synonyms",
+        "antonyms",
+        "translations",
+        "es",
+        "ja",
+        "senseNotFound",
+    ];
+    prop_oneof![
+        3 => prop::sample::select(&KNOWN[..]).prop_map(str::to_string),
+        1 => weird_string(),
+    ]
+}
+
+/// Arbitrary JSON, nested up to eight levels.
+fn junk() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::from),
+        any::<i64>().prop_map(Value::from),
+        any::<f64>()
+            .prop_filter_map("JSON has no NaN or infinity", |f| {
+                serde_json::Number::from_f64(f)
+            })
+            .prop_map(Value::Number),
+        weird_string().prop_map(Value::from),
+    ];
+    leaf.prop_recursive(8, 64, 6, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..6).prop_map(Value::Array),
+            prop::collection::vec((key(), inner), 0..6)
+                .prop_map(|pairs| Value::Object(pairs.into_iter().collect())),
+        ]
+    })
+}
+
+#[derive(Debug, Clone)]
+enum Mutation {
+    Remove,
+    Replace(Value),
+    /// Wrap in an array, as models do with objects they half remember
+    Wrap,
+    /// Bury under many levels of arrays and objects
+    Bury(usize),
+}
+
+fn mutation() -> impl Strategy<Value = Mutation> {
+    prop_oneof![
+        2 => Just(Mutation::Remove),
+        4 => junk().prop_map(Mutation::Replace),
+        1 => Just(Mutation::Wrap),
+        1 => (1usize..200).prop_map(Mutation::Bury),
+    ]
+}
+
+/// JSON pointers to every value in `v`, the root included.
+fn pointers(v: &Value, at: String, out: &mut Vec<String>) {
+    match v {
+        Value::Object(obj) => {
+            for (key, child) in obj {
+                let escaped = key.replace('~', "~0").replace('/', "~1");
+                pointers(child, format!("{}/{}", at, escaped), out);
+            }
+        }
+        Value::Array(items) => {
+            for (idx, child) in items.iter().enumerate() {
+                pointers(child, format!("{}/{}", at, idx), out);
+            }
+        }
+        _ => {}
+    }
+    out.push(at);
+}
+
+fn apply(v: &mut Value, target: &Index, mutation: Mutation) {
+    let mut all = Vec::new();
+    pointers(v, String::new(), &mut all);
+    let pointer = target.get(&all).clone();
+    if let Mutation::Remove = mutation {
+        let Some((parent, last)) = pointer.rsplit_once('/') else {
+            *v = Value::Null;
+            return;
+        };
+        let last = last.replace("~1", "/").replace("~0", "~");
+        match v.pointer_mut(parent) {
+            Some(Value::Object(obj)) => {
+                obj.remove(&last);
+            }
+            Some(Value::Array(items)) => {
+                if let Ok(idx) = last.parse::<usize>() {
+                    items.remove(idx);
+                }
+            }
+            _ => {}
+        }
+        return;
+    }
+    let Some(slot) = v.pointer_mut(&pointer) else {
+        return;
+    };
+    *slot = match mutation {
+        Mutation::Remove => unreachable!(),
+        Mutation::Replace(junk) => junk,
+        Mutation::Wrap => json!([slot.take()]),
+        Mutation::Bury(depth) => (0..depth).fold(slot.take(), |inner, level| {
+            if level % 2 == 0 {
+                json!([inner])
+            } else {
+                json!({ "meanings": inner })
+            }
+        }),
+    };
+}
+
+/// A seed recording or the known-good entry, with the word it was made for.
+fn base() -> impl Strategy<Value = (String, Value)> {
+    prop_oneof![
+        1 => Just(("test".to_string(), valid_entry())),
+        3 => prop::sample::select(SEEDS.clone()),
+    ]
+}
+
+#[test]
+fn seeds_are_rejected_or_repaired_without_panicking() {
+    assert!(!SEEDS.is_empty());
+    for (word, output) in SEEDS.iter() {
+        check(output.clone(), word).unwrap();
+    }
+}
+
+#[test]
+fn known_good_entry_passes() {
+    let fixed = VALIDATOR.validate_and_fix(valid_entry(), "test").unwrap();
+    assert!(SCHEMA.is_valid(&fixed));
+}
+
+proptest! {
+    #[test]
+    fn arbitrary_json_never_panics(v in junk(), word in weird_string()) {
+        check(v, &word)?;
+    }
+
+    #[test]
+    fn mutated_entries_never_panic(
+        (word, mut v) in base(),
+        mutations in prop::collection::vec((any::<Index>(), mutation()), 1..5),
+        odd_word in prop::option::weighted(0.2, weird_string()),
+    ) {
+        for (target, mutation) in mutations {
+            apply(&mut v, &target, mutation);
+        }
+        check(v, odd_word.as_deref().unwrap_or(&word))?;
+    }
+}
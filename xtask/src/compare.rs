@@ -0,0 +1,378 @@
+//! `compare`: run one word list through the server once per model and print
+//! the results side by side.
+
+use anyhow::{bail, Context};
+use hdrhistogram::Histogram;
+use reqwest::Client;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Longest a server may take to load its model and start listening.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(600);
+
+const USAGE: &str =
+    "usage: cargo xtask compare --models a.gguf,b.gguf --words list.txt [--clients N] [--bin PATH]";
+
+struct Options {
+    models: Vec<PathBuf>,
+    words: Vec<String>,
+    clients: usize,
+    bin: Option<PathBuf>,
+}
+
+/// One model's results over the word list.
+struct Run {
+    model: String,
+    ok: usize,
+    failed: usize,
+    /// Failures by kind: the error code, refined for contract violations by
+    /// the rule and field that failed
+    failures: BTreeMap<String, usize>,
+    latency: Histogram<u64>,
+    elapsed: Duration,
+    /// The server's median decode speed, from `/metrics`
+    decode_tps: Option<f64>,
+}
+
+pub async fn run(args: &[String]) -> anyhow::Result<()> {
+    let opts = parse(args)?;
+    let bin = match &opts.bin {
+        Some(bin) => bin.clone(),
+        None => build_server()?,
+    };
+    let client = Client::builder()
+        .pool_idle_timeout(Duration::from_secs(10))
+        .build()?;
+    println!(
+        "comparing {} models on {} words, {} client(s)",
+        opts.models.len(),
+        opts.words.len(),
+        opts.clients
+    );
+
+    let mut runs = Vec::with_capacity(opts.models.len());
+    for model in &opts.models {
+        let name = model.file_stem().map_or_else(
+            || model.display().to_string(),
+            |s| s.to_string_lossy().into_owned(),
+        );
+        println!("\n== {} ==", name);
+        let mut server = Server::start(&bin, model)?;
+        let loading = Instant::now();
+        server.wait_ready(&client).await?;
+        println!("model loaded in {:.1?}", loading.elapsed());
+        runs.push(run_words(&client, &server.base, &opts, name).await?);
+    }
+    print_table(&runs);
+    Ok(())
+}
+
+fn parse(args: &[String]) -> anyhow::Result<Options> {
+    let mut models = Vec::new();
+    let mut words_file = None;
+    let mut clients = 1;
+    let mut bin = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .with_context(|| format!("{arg} needs a value\n{USAGE}"))
+        };
+        match arg.as_str() {
+            "--models" => {
+                models = value()?
+                    .split(',')
+                    .filter(|m| !m.is_empty())
+                    .map(PathBuf::from)
+                    .collect()
+            }
+            "--words" => words_file = Some(value()?.clone()),
+            "--clients" => clients = value()?.parse::<usize>()?.max(1),
+            "--bin" => bin = Some(PathBuf::from(value()?)),
+            other => bail!("unknown argument {other}\n{USAGE}"),
+        }
+    }
+    if models.is_empty() {
+        bail!("no models given\n{USAGE}");
+    }
+    for model in &mut models {
+        *model = model
+            .canonicalize()
+            .with_context(|| format!("model {}", model.display()))?;
+    }
+    let words_file = words_file.with_context(|| format!("no word list given\n{USAGE}"))?;
+    let words: Vec<String> = std::fs::read_to_string(&words_file)
+        .with_context(|| format!("read {words_file}"))?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect();
+    if words.is_empty() {
+        bail!("{words_file} lists no words");
+    }
+    Ok(Options {
+        models,
+        words,
+        clients,
+        bin,
+    })
+}
+
+/// Build the release server binary and return its path.
+fn build_server() -> anyhow::Result<PathBuf> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .context("xtask has no parent directory")?;
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    println!("building lingua-fast (release)...");
+    let status = Command::new(cargo)
+        .args(["build", "--release", "--bin", "lingua-fast"])
+        .current_dir(root)
+        .status()
+        .context("run cargo build")?;
+    if !status.success() {
+        bail!("cargo build failed");
+    }
+    Ok(root.join("target/release/lingua-fast"))
+}
+
+/// A server process serving one model, killed on drop.
+struct Server {
+    child: Child,
+    base: String,
+    data_dir: PathBuf,
+}
+
+impl Server {
+    fn start(bin: &Path, model: &Path) -> anyhow::Result<Self> {
+        // Let the OS pick a free port, then hand it to the server
+        let port = TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+        // Everything else comes from the environment and .env, so every model
+        // runs under the same settings. No cache and a fresh data dir, so no
+        // model is credited with entries another generated
+        let data_dir = std::env::temp_dir().join(format!("lingua-compare-{port}"));
+        let child = Command::new(bin)
+            .env("BACKEND", "llama")
+            .env("MODEL_PATH", model)
+            .env("BIND_ADDR", format!("127.0.0.1:{port}"))
+            .env("CACHE_CAPACITY", "0")
+            .env("DATA_DIR", &data_dir)
+            .env("CANARY_INTERVAL_SECS", "0")
+            .env("STARTUP_BENCHMARK", "false")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("start {}", bin.display()))?;
+        Ok(Self {
+            child,
+            base: format!("http://127.0.0.1:{port}"),
+            data_dir,
+        })
+    }
+
+    /// Wait until the server answers `/healthz`, failing if it exits first.
+    async fn wait_ready(&mut self, client: &Client) -> anyhow::Result<()> {
+        let started = Instant::now();
+        loop {
+            if let Some(status) = self.child.try_wait()? {
+                bail!("server exited during startup ({status}); run it by hand to see why");
+            }
+            let health = client.get(format!("{}/healthz", self.base)).send().await;
+            if health.is_ok_and(|r| r.status().is_success()) {
+                return Ok(());
+            }
+            if started.elapsed() > STARTUP_TIMEOUT {
+                bail!("server did not start within {:?}", STARTUP_TIMEOUT);
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.child.kill().ok();
+        self.child.wait().ok();
+        std::fs::remove_dir_all(&self.data_dir).ok();
+    }
+}
+
+async fn run_words(
+    client: &Client,
+    base: &str,
+    opts: &Options,
+    model: String,
+) -> anyhow::Result<Run> {
+    let url = format!("{base}/v1/word");
+    let started = Instant::now();
+    let mut tasks = Vec::with_capacity(opts.clients);
+    for chunk in opts.words.chunks(opts.words.len().div_ceil(opts.clients)) {
+        let client = client.clone();
+        let url = url.clone();
+        let words = chunk.to_vec();
+        tasks.push(tokio::spawn(async move {
+            let mut outcomes = Vec::with_capacity(words.len());
+            for word in words {
+                let t0 = Instant::now();
+                let outcome = match client
+                    .post(&url)
+                    .json(&serde_json::json!({"word": word}))
+                    .send()
+                    .await
+                {
+                    Ok(res) if res.status().is_success() => Ok(()),
+                    Ok(res) => Err(failure_kind(&res.json().await.unwrap_or(Value::Null))),
+                    Err(e) if e.is_timeout() => Err("TIMEOUT".to_string()),
+                    Err(_) => Err("CONNECTION".to_string()),
+                };
+                outcomes.push((t0.elapsed(), outcome));
+            }
+            outcomes
+        }));
+    }
+
+    let mut run = Run {
+        model,
+        ok: 0,
+        failed: 0,
+        failures: BTreeMap::new(),
+        latency: Histogram::new(3)?,
+        elapsed: Duration::ZERO,
+        decode_tps: None,
+    };
+    for task in tasks {
+        for (latency, outcome) in task.await? {
+            run.latency.record(latency.as_millis() as u64).ok();
+            match outcome {
+                Ok(()) => run.ok += 1,
+                Err(kind) => {
+                    run.failed += 1;
+                    *run.failures.entry(kind).or_default() += 1;
+                }
+            }
+        }
+    }
+    run.elapsed = started.elapsed();
+    run.decode_tps = decode_tps(client, base).await;
+    println!(
+        "{} ok, {} failed in {:.1?}",
+        run.ok, run.failed, run.elapsed
+    );
+    Ok(run)
+}
+
+/// What went wrong, from an error response: its code, and for contract
+/// violations the first failed rule and where, with array indices dropped so
+/// the same mistake in different meanings counts once.
+fn failure_kind(body: &Value) -> String {
+    let code = body["code"].as_str().unwrap_or("UNKNOWN");
+    let Some(violation) = body["details"].as_array().and_then(|d| d.first()) else {
+        return code.to_string();
+    };
+    let path: Vec<&str> = violation["path"]
+        .as_str()
+        .unwrap_or("")
+        .split('/')
+        .map(|part| {
+            if !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()) {
+                "*"
+            } else {
+                part
+            }
+        })
+        .collect();
+    format!(
+        "{}: {} {}",
+        code,
+        violation["keyword"].as_str().unwrap_or("?"),
+        path.join("/")
+    )
+}
+
+/// Median decode tokens/sec the server measured, from its `/metrics`.
+async fn decode_tps(client: &Client, base: &str) -> Option<f64> {
+    let text = client
+        .get(format!("{base}/metrics"))
+        .send()
+        .await
+        .ok()?
+        .text()
+        .await
+        .ok()?;
+    text.lines()
+        .find_map(|line| line.strip_prefix("lingua_decode_tokens_per_second{quantile=\"0.5\"} "))
+        .and_then(|v| v.parse().ok())
+        .filter(|v: &f64| v.is_finite())
+}
+
+fn print_table(runs: &[Run]) {
+    let mut rows: Vec<(String, Vec<String>)> = vec![
+        row("words", runs, |r| (r.ok + r.failed).to_string()),
+        row("success", runs, |r| {
+            format!(
+                "{:.1}%",
+                100.0 * r.ok as f64 / (r.ok + r.failed).max(1) as f64
+            )
+        }),
+        row("latency p50 (ms)", runs, |r| {
+            r.latency.value_at_quantile(0.50).to_string()
+        }),
+        row("latency p95 (ms)", runs, |r| {
+            r.latency.value_at_quantile(0.95).to_string()
+        }),
+        row("latency max (ms)", runs, |r| r.latency.max().to_string()),
+        row("words/min", runs, |r| {
+            format!(
+                "{:.1}",
+                (r.ok + r.failed) as f64 * 60.0 / r.elapsed.as_secs_f64().max(1e-9)
+            )
+        }),
+        row("decode tok/s p50", runs, |r| {
+            r.decode_tps
+                .map_or_else(|| "-".to_string(), |t| format!("{t:.1}"))
+        }),
+    ];
+    let mut kinds: Vec<&String> = runs.iter().flat_map(|r| r.failures.keys()).collect();
+    kinds.sort();
+    kinds.dedup();
+    for kind in kinds {
+        rows.push(row(kind, runs, |r| {
+            r.failures.get(kind).copied().unwrap_or(0).to_string()
+        }));
+    }
+
+    let label_width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    let widths: Vec<usize> = runs
+        .iter()
+        .enumerate()
+        .map(|(i, r)| {
+            rows.iter()
+                .map(|(_, cells)| cells[i].len())
+                .chain([r.model.len()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    println!();
+    print!("{:label_width$}", "");
+    for (r, width) in runs.iter().zip(&widths) {
+        print!("  {:>width$}", r.model);
+    }
+    println!();
+    for (label, cells) in &rows {
+        print!("{label:label_width$}");
+        for (cell, width) in cells.iter().zip(&widths) {
+            print!("  {cell:>width$}");
+        }
+        println!();
+    }
+}
+
+fn row(label: &str, runs: &[Run], cell: impl Fn(&Run) -> String) -> (String, Vec<String>) {
+    (label.to_string(), runs.iter().map(cell).collect())
+}
@@ -3,13 +3,24 @@ use rand::{seq::SliceRandom, thread_rng};
 use reqwest::Client;
 use std::time::{Duration, Instant};
 
+mod compare;
+mod soak;
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|a| a == "compare") {
+        return compare::run(&args[1..]).await;
+    }
+    if args.first().is_some_and(|a| a == "soak") {
+        return soak::run(&args[1..]).await;
+    }
     let url = std::env::args()
         .nth(1)
         .unwrap_or_else(|| "http://127.0.0.1:8080/v1/word".to_string());
-    let clients = 8usize; // concurrent
-    let total = 200usize; // total requests
+    // Optional: concurrent clients and total requests, for comparing server settings
+    let clients: usize = std::env::args().nth(2).map_or(Ok(8), |s| s.parse())?;
+    let total: usize = std::env::args().nth(3).map_or(Ok(200), |s| s.parse())?;
     let words = vec![
         "communicated",
         "running",
@@ -27,6 +38,8 @@ async fn main() -> anyhow::Result<()> {
         .pool_idle_timeout(Duration::from_secs(10))
         .build()?;
     let mut hist = Histogram::<u64>::new(3)?;
+    // Time to the first body byte: the client-side TTFT once responses stream
+    let mut first_byte = Histogram::<u64>::new(3)?;
     let mut errors = 0usize;
 
     let start = Instant::now();
@@ -49,10 +62,15 @@ async fn main() -> anyhow::Result<()> {
                     .json(&serde_json::json!({"word": w}))
                     .send()
                     .await;
-                let dur = t0.elapsed();
                 match res {
-                    Ok(r) if r.status().is_success() => {
-                        latencies.push(dur);
+                    Ok(mut r) if r.status().is_success() => {
+                        let Ok(Some(_)) = r.chunk().await else {
+                            errs += 1;
+                            continue;
+                        };
+                        let ttfb = t0.elapsed();
+                        while let Ok(Some(_)) = r.chunk().await {}
+                        latencies.push((ttfb, t0.elapsed()));
                     }
                     _ => errs += 1,
                 }
@@ -63,16 +81,50 @@ async fn main() -> anyhow::Result<()> {
 
     for t in tasks {
         let (ls, e) = t.await?;
-        for d in ls {
+        for (ttfb, d) in ls {
+            first_byte.record(ttfb.as_millis() as u64).ok();
             hist.record(d.as_millis() as u64).ok();
         }
         errors += e;
     }
 
-    println!("ran {} reqs in {:?}", total, start.elapsed());
+    let elapsed = start.elapsed();
+    println!("ran {} reqs in {:?}", total, elapsed);
+    println!(
+        "throughput: {:.2} req/s",
+        hist.len() as f64 / elapsed.as_secs_f64()
+    );
     println!("errors: {}", errors);
     println!("p50: {} ms", hist.value_at_quantile(0.50));
     println!("p95: {} ms", hist.value_at_quantile(0.95));
     println!("p99: {} ms", hist.value_at_quantile(0.99));
+    println!(
+        "first byte p50/p95/p99: {} / {} / {} ms",
+        first_byte.value_at_quantile(0.50),
+        first_byte.value_at_quantile(0.95),
+        first_byte.value_at_quantile(0.99)
+    );
+    print_server_ttft(&client, &url).await;
     Ok(())
 }
+
+/// Print the server's own time-to-first-token quantiles from `/metrics`,
+/// which exclude HTTP overhead and queueing for an inference slot.
+async fn print_server_ttft(client: &Client, url: &str) {
+    let base = url.split("/v1/").next().unwrap_or(url);
+    let Ok(res) = client.get(format!("{base}/metrics")).send().await else {
+        return;
+    };
+    let Ok(text) = res.text().await else { return };
+    for line in text.lines() {
+        let Some(rest) = line.strip_prefix("lingua_time_to_first_token_seconds{quantile=\"") else {
+            continue;
+        };
+        let Some((quantile, seconds)) = rest.split_once("\"} ") else {
+            continue;
+        };
+        if let Ok(seconds) = seconds.parse::<f64>() {
+            println!("server ttft q{}: {:.0} ms", quantile, seconds * 1000.0);
+        }
+    }
+}
@@ -0,0 +1,271 @@
+//! `soak`: hold a steady request rate for hours while sampling the server's
+//! memory from `/admin/stats`, and flag memory that keeps growing.
+
+use anyhow::{bail, Context};
+use reqwest::Client;
+use serde_json::Value;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Samples taken before memory is expected to have settled (model pages
+/// faulted in, buffers at their working size); excluded from growth checks.
+const WARMUP_SAMPLES: usize = 5;
+/// Consecutive non-decreasing samples that count as monotonic growth...
+const GROWTH_WINDOW: usize = 10;
+/// ...when they add up to at least this much.
+const MIN_GROWTH_MB: f64 = 16.0;
+
+const USAGE: &str = "usage: cargo xtask soak [--url URL] [--hours H] [--rate REQ_PER_SEC] \
+                     [--clients N] [--sample-secs S] [--token ADMIN_TOKEN] [--words FILE]";
+
+const WORDS: [&str; 10] = [
+    "communicated",
+    "running",
+    "happier",
+    "analysis",
+    "swiftly",
+    "astonishing",
+    "children",
+    "better",
+    "understand",
+    "synthesis",
+];
+
+struct Options {
+    base: String,
+    duration: Duration,
+    rate: f64,
+    clients: usize,
+    sample_every: Duration,
+    token: Option<String>,
+    words: Vec<String>,
+}
+
+/// One memory reading, in MB.
+struct Sample {
+    at: Duration,
+    rss_mb: Option<f64>,
+    gpu_mb: Option<f64>,
+}
+
+pub async fn run(args: &[String]) -> anyhow::Result<()> {
+    let opts = parse(args)?;
+    let client = Client::builder()
+        .pool_idle_timeout(Duration::from_secs(10))
+        .timeout(Duration::from_secs(300))
+        .build()?;
+    // Fail fast on a wrong URL or token rather than an hour in
+    sample(&client, &opts, Duration::ZERO)
+        .await
+        .context("read /admin/stats")?;
+
+    let ok = Arc::new(AtomicU64::new(0));
+    let errors = Arc::new(AtomicU64::new(0));
+    let started = Instant::now();
+    let deadline = started + opts.duration;
+    let period = Duration::from_secs_f64(opts.clients as f64 / opts.rate);
+    let clients = opts.clients;
+    for client_idx in 0..clients {
+        let client = client.clone();
+        let url = format!("{}/v1/word", opts.base);
+        let words = opts.words.clone();
+        let (ok, errors) = (ok.clone(), errors.clone());
+        tokio::spawn(async move {
+            // Stagger clients so requests arrive evenly, not in bursts
+            tokio::time::sleep(period.mul_f64(client_idx as f64 / clients as f64)).await;
+            let mut ticks = tokio::time::interval(period);
+            ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            for word in words.iter().cycle().skip(client_idx) {
+                ticks.tick().await;
+                if Instant::now() >= deadline {
+                    break;
+                }
+                let res = client
+                    .post(&url)
+                    .json(&serde_json::json!({ "word": word }))
+                    .send()
+                    .await;
+                match res {
+                    Ok(r) if r.status().is_success() => ok.fetch_add(1, Ordering::Relaxed),
+                    _ => errors.fetch_add(1, Ordering::Relaxed),
+                };
+            }
+        });
+    }
+
+    println!(
+        "soaking {} for {:.1?} at {} req/s from {} clients; sampling every {:?}",
+        opts.base, opts.duration, opts.rate, opts.clients, opts.sample_every
+    );
+    println!(
+        "{:>9}  {:>9}  {:>7}  {:>9}  {:>9}",
+        "elapsed", "ok", "errors", "rss MB", "gpu MB"
+    );
+    let mut samples = Vec::new();
+    let mut alerts = 0;
+    let mut ticks = tokio::time::interval(opts.sample_every);
+    ticks.tick().await;
+    while Instant::now() < deadline {
+        ticks.tick().await;
+        let at = started.elapsed();
+        let s = match sample(&client, &opts, at).await {
+            Ok(s) => s,
+            Err(e) => {
+                println!("{:>8.0}s  stats unavailable: {:#}", at.as_secs_f64(), e);
+                continue;
+            }
+        };
+        println!(
+            "{:>8.0}s  {:>9}  {:>7}  {:>9}  {:>9}",
+            at.as_secs_f64(),
+            ok.load(Ordering::Relaxed),
+            errors.load(Ordering::Relaxed),
+            mb(s.rss_mb),
+            mb(s.gpu_mb)
+        );
+        samples.push(s);
+        for (name, series) in [("RSS", rss(&samples)), ("GPU memory", gpu(&samples))] {
+            if let Some(growth) = growing(&series) {
+                alerts += 1;
+                println!(
+                    "ALERT: {} grew for {} samples in a row, +{:.0} MB; possible leak",
+                    name, GROWTH_WINDOW, growth
+                );
+            }
+        }
+    }
+
+    println!(
+        "\n{} ok, {} errors over {:.1?}",
+        ok.load(Ordering::Relaxed),
+        errors.load(Ordering::Relaxed),
+        started.elapsed()
+    );
+    for (name, series) in [("RSS", rss(&samples)), ("GPU memory", gpu(&samples))] {
+        let settled: Vec<(f64, f64)> = series.into_iter().skip(WARMUP_SAMPLES).collect();
+        if let (Some(first), Some(last)) = (settled.first(), settled.last()) {
+            println!(
+                "{}: {:.0} -> {:.0} MB after warmup, trend {:+.1} MB/hour",
+                name,
+                first.1,
+                last.1,
+                slope(&settled) * 3600.0
+            );
+        }
+    }
+    if alerts > 0 {
+        bail!("{alerts} memory growth alert(s)");
+    }
+    Ok(())
+}
+
+fn parse(args: &[String]) -> anyhow::Result<Options> {
+    let mut opts = Options {
+        base: "http://127.0.0.1:8080".to_string(),
+        duration: Duration::from_secs(4 * 3600),
+        rate: 1.0,
+        clients: 4,
+        sample_every: Duration::from_secs(60),
+        token: std::env::var("ADMIN_TOKEN").ok(),
+        words: WORDS.map(str::to_string).to_vec(),
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args
+            .next()
+            .with_context(|| format!("{arg} needs a value\n{USAGE}"))?;
+        match arg.as_str() {
+            "--url" => opts.base = value.trim_end_matches('/').to_string(),
+            "--hours" => opts.duration = Duration::from_secs_f64(value.parse::<f64>()? * 3600.0),
+            "--rate" => opts.rate = value.parse()?,
+            "--clients" => opts.clients = value.parse::<usize>()?.max(1),
+            "--sample-secs" => {
+                opts.sample_every = Duration::from_secs(value.parse::<u64>()?.max(1))
+            }
+            "--token" => opts.token = Some(value.clone()),
+            "--words" => {
+                opts.words = std::fs::read_to_string(value)
+                    .with_context(|| format!("read {value}"))?
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(str::to_string)
+                    .collect()
+            }
+            other => bail!("unknown argument {other}\n{USAGE}"),
+        }
+    }
+    if opts.rate.is_nan() || opts.rate <= 0.0 {
+        bail!("--rate must be positive");
+    }
+    if opts.words.is_empty() {
+        bail!("no words to send");
+    }
+    Ok(opts)
+}
+
+async fn sample(client: &Client, opts: &Options, at: Duration) -> anyhow::Result<Sample> {
+    let mut req = client.get(format!("{}/admin/stats", opts.base));
+    if let Some(token) = &opts.token {
+        req = req.bearer_auth(token);
+    }
+    let res = req.send().await?;
+    if !res.status().is_success() {
+        bail!("HTTP {}", res.status());
+    }
+    let stats: Value = res.json().await?;
+    Ok(Sample {
+        at,
+        rss_mb: stats["rss_bytes"].as_f64().map(|b| b / (1024.0 * 1024.0)),
+        gpu_mb: stats["gpu_memory_mb"].as_f64(),
+    })
+}
+
+fn rss(samples: &[Sample]) -> Vec<(f64, f64)> {
+    series(samples, |s| s.rss_mb)
+}
+
+fn gpu(samples: &[Sample]) -> Vec<(f64, f64)> {
+    series(samples, |s| s.gpu_mb)
+}
+
+/// `(seconds, MB)` points of one measurement, skipping missing readings.
+fn series(samples: &[Sample], value: impl Fn(&Sample) -> Option<f64>) -> Vec<(f64, f64)> {
+    samples
+        .iter()
+        .filter_map(|s| Some((s.at.as_secs_f64(), value(s)?)))
+        .collect()
+}
+
+/// The growth over the latest window when it never went down and grew
+/// enough to matter. Only reported the moment the window completes, so a
+/// long climb alerts once per window rather than on every sample.
+fn growing(series: &[(f64, f64)]) -> Option<f64> {
+    let settled = series.get(WARMUP_SAMPLES..)?;
+    if settled.len() < GROWTH_WINDOW || settled.len() % GROWTH_WINDOW != 0 {
+        return None;
+    }
+    let window = &settled[settled.len() - GROWTH_WINDOW..];
+    let monotonic = window.windows(2).all(|pair| pair[1].1 >= pair[0].1);
+    let growth = window[GROWTH_WINDOW - 1].1 - window[0].1;
+    (monotonic && growth >= MIN_GROWTH_MB).then_some(growth)
+}
+
+/// Least-squares slope of `points`, in MB per second.
+fn slope(points: &[(f64, f64)]) -> f64 {
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let cov: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+    let var: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+    if var > 0.0 {
+        cov / var
+    } else {
+        0.0
+    }
+}
+
+fn mb(value: Option<f64>) -> String {
+    value.map_or_else(|| "-".to_string(), |v| format!("{v:.0}"))
+}